@@ -12,10 +12,35 @@ pub const D50: XYZ = XYZ {
     z: 0.8249,
 };
 
+/// The D65 white of ITU-R BT.709 and BT.2100, from its (0.3127, 0.3290) chromaticity.
+pub const D65: XYZ = XYZ {
+    x: 0.3127 / 0.3290,
+    y: 1.0,
+    z: (1.0 - 0.3127 - 0.3290) / 0.3290,
+};
+
+/// The black point of the perceptual and saturation intents on ICC v4 profiles.
+pub const PERCEPTUAL_BLACK: XYZ = XYZ {
+    x: 0.00336,
+    y: 0.0034731,
+    z: 0.00287,
+};
+
 pub const MAX_CHANNELS: usize = 16;
 pub const MAX_INPUT_DIMENSIONS: usize = 15;
 pub const MAX_TYPES_IN_PLUGIN: usize = 20;
+pub const MAX_TABLE_TAG: usize = 100;
+
+/// Iteration cap used by [`Pipeline::eval_reverse_f32`](crate::types::Pipeline::eval_reverse_f32).
+pub const INVERSION_MAX_ITERATIONS: usize = 30;
 
 pub const PTR_ALIGNMENT: usize = size_of::<usize>();
 
 pub(crate) const MAX_STAGE_CHANNELS: usize = 128;
+pub(crate) const MATRIX_DET_TOLERANCE: f64 = 0.0001;
+
+/// Largest XYZ value representable in the 16 bits encoding.
+pub(crate) const MAX_ENCODEABLE_XYZ: f64 = 1.0 + 32767.0 / 32768.0;
+
+pub(crate) const MINUS_INF: f32 = -1e22;
+pub(crate) const PLUS_INF: f32 = 1e22;
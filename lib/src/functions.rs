@@ -1,6 +1,6 @@
 use std::mem::size_of;
 
-use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use chrono::{DateTime, Datelike, LocalResult, TimeZone, Timelike, Utc};
 
 use crate::{
     types::DateTimeNumber, S15Fixed16Number, U16Fixed16Number, U8Fixed8Number, PTR_ALIGNMENT,
@@ -11,7 +11,7 @@ pub fn u8_fixed8_number_to_f64(fixed8: U8Fixed8Number) -> f64 {
     let lsb = fixed8 & 0xFF;
     let msb = fixed8 >> 8;
 
-    msb as f64 + (lsb as f64 / 255.0)
+    msb as f64 + (lsb as f64 / 256.0)
 }
 
 #[inline]
@@ -61,8 +61,9 @@ pub fn encode_date_time(source: DateTime<Utc>) -> DateTimeNumber {
     }
 }
 
+/// Decodes a date as stored in profiles, which is [`LocalResult::None`] if it isn't a valid date.
 #[inline]
-pub fn decode_date_time(source: DateTimeNumber) -> DateTime<Utc> {
+pub fn decode_date_time(source: DateTimeNumber) -> LocalResult<DateTime<Utc>> {
     let utc = Utc;
     utc.with_ymd_and_hms(
         source.year.to_be() as i32,
@@ -72,7 +73,70 @@ pub fn decode_date_time(source: DateTimeNumber) -> DateTime<Utc> {
         source.minutes.to_be() as u32,
         source.seconds.to_be() as u32,
     )
-    .unwrap()
+}
+
+/// Converts an IEEE 754 binary16 value to single precision. Every half value, including
+/// denormals, infinities and NaN payloads, is represented exactly.
+#[inline]
+pub fn half_to_float(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let exp = ((h >> 10) & 0x1f) as u32;
+    let man = (h & 0x03ff) as u32;
+
+    let bits = match (exp, man) {
+        // Signed zero
+        (0, 0) => sign,
+        // Denormal, normalize it as single precision has a wider exponent range
+        (0, _) => {
+            let shift = man.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | (((man << shift) & 0x03ff) << 13)
+        }
+        // Infinity or NaN, keeping the payload
+        (0x1f, _) => sign | 0x7f80_0000 | (man << 13),
+        _ => sign | ((exp + 112) << 23) | (man << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+/// Converts a single precision value to IEEE 754 binary16, rounding to nearest even.
+/// Values too large for a half become infinity, and NaNs stay NaN.
+#[inline]
+pub fn float_to_half(f: f32) -> u16 {
+    let bits = f.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let man = bits & 0x007f_ffff;
+
+    if exp == 0xff {
+        if man == 0 {
+            return sign | 0x7c00;
+        }
+        // Keep the upper bits of the payload, but make sure it is still a NaN
+        return sign | 0x7e00 | (man >> 13) as u16;
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let (half, rest, halfway) = if exp <= 0 {
+        // Too small even for a denormal, rounds to zero
+        if exp < -10 {
+            return sign;
+        }
+
+        let man = man | 0x0080_0000;
+        let shift = (14 - exp) as u32;
+        (man >> shift, man & ((1 << shift) - 1), 1 << (shift - 1))
+    } else {
+        (((exp as u32) << 10) | (man >> 13), man & 0x1fff, 0x1000)
+    };
+
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    let round = rest > halfway || (rest == halfway && half & 1 == 1);
+    sign | (half + round as u32) as u16
 }
 
 #[inline]
@@ -138,6 +202,13 @@ pub fn quick_floor(val: f64) -> i32 {
     unsafe_block!("Accessing part of a union for performing quick_floor" => i.halves[0] >> 16)
 }
 
+/// The value of node `i` in a grid of `max_samples` nodes, spread over the 16 bit domain.
+#[inline]
+pub fn quantize_val(i: f64, max_samples: usize) -> u16 {
+    let x = (i * 65535.0) / (max_samples - 1) as f64;
+    quick_saturate_word(x)
+}
+
 #[inline]
 pub fn quick_floor_word(d: f64) -> u16 {
     (quick_floor(d - 32767.0) + 32767) as u16
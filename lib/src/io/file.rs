@@ -128,41 +128,45 @@ impl IoHandler for File {
     }
 
     fn read(&mut self, buffer: &mut [u8], size: usize, count: usize) -> Result<usize> {
-        let n_read = match self
-            .file
-            .lock()
-            .unwrap()
-            .read(&mut buffer[..(size * count)])
-        {
-            Ok(len) => len / size,
-            Err(error) => {
-                return err!(
-                    self.context_id,
-                    Error,
-                    File,
-                    "A read error occured: {}",
-                    error;
-                    io =>
-                    error
-                )
+        let len = size * count;
+        if len == 0 {
+            return Ok(count);
+        }
+
+        let mut n_read = 0usize;
+        while n_read < len {
+            match self.file.lock().unwrap().read(&mut buffer[n_read..len]) {
+                Ok(0) => break,
+                Ok(read) => n_read += read,
+                Err(error) => {
+                    return err!(
+                        self.context_id,
+                        Error,
+                        File,
+                        "A read error occured: {}",
+                        error;
+                        io =>
+                        error
+                    )
+                }
             }
-        } / size;
+        }
 
-        if n_read != count {
+        if n_read != len {
             return err!(
                 self.context_id,
                 Error,
                 File,
                 "Read error. Got {} bytes, block should be {} bytes",
-                n_read * size,
-                count * size;
+                n_read,
+                len;
                 io =>
                 UnexpectedEof,
                 "Didn't read enough bytes"
             );
         }
 
-        Ok(n_read)
+        Ok(count)
     }
 
     fn seek(&mut self, offset: usize) -> Result<()> {
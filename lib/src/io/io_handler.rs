@@ -3,7 +3,7 @@ use std::{io::Result, mem::size_of};
 use crate::{
     align_long, f64_to_s15_fixed16_number, f64_to_u16_fixed16_number, s15_fixed16_number_to_f64,
     state::Context,
-    types::{Signature, XYZ},
+    types::{DateTimeNumber, Signature, XYZ},
     u16_fixed16_number_to_f64, S15Fixed16Number,
 };
 
@@ -123,6 +123,18 @@ impl dyn IoHandler + '_ {
         })
     }
 
+    pub fn read_date_time_number(&mut self) -> Result<DateTimeNumber> {
+        // DateTimeNumber keeps its fields in big-endian order
+        Ok(DateTimeNumber {
+            year: self.read_u16()?.to_be(),
+            month: self.read_u16()?.to_be(),
+            day: self.read_u16()?.to_be(),
+            hours: self.read_u16()?.to_be(),
+            minutes: self.read_u16()?.to_be(),
+            seconds: self.read_u16()?.to_be(),
+        })
+    }
+
     pub fn write_u8(&mut self, n: u8) -> Result<()> {
         let tmp = [n];
         Ok(self.write(size_of::<u8>(), &tmp)?)
@@ -178,6 +190,17 @@ impl dyn IoHandler + '_ {
         Ok(())
     }
 
+    pub fn write_date_time_number(&mut self, date: DateTimeNumber) -> Result<()> {
+        self.write_u16(u16::from_be(date.year))?;
+        self.write_u16(u16::from_be(date.month))?;
+        self.write_u16(u16::from_be(date.day))?;
+        self.write_u16(u16::from_be(date.hours))?;
+        self.write_u16(u16::from_be(date.minutes))?;
+        self.write_u16(u16::from_be(date.seconds))?;
+
+        Ok(())
+    }
+
     pub fn read_type_base(&mut self) -> Result<Signature> {
         let result = Signature(self.read_u32()?);
         _ = Signature(self.read_u32()?);
@@ -3,6 +3,7 @@ mod io_handler;
 mod mem;
 mod null;
 
+pub use file::{File, FileLike};
 pub use io_handler::IoHandler;
 pub use mem::FileMem;
 pub use null::FileNull;
@@ -1,4 +1,7 @@
-use std::{io::Result, sync::Arc};
+use std::{
+    io::Result,
+    sync::{Arc, Mutex},
+};
 
 use crate::state::Context;
 
@@ -21,8 +24,8 @@ impl FileNull {
         }
     }
 
-    pub fn open(context_id: &Context) -> Arc<dyn IoHandler> {
-        Arc::new(FileNull::new(&context_id))
+    pub fn open(context_id: &Context) -> Arc<Mutex<dyn IoHandler>> {
+        Arc::new(Mutex::new(FileNull::new(context_id)))
     }
 }
 impl IoHandler for FileNull {
@@ -1,4 +1,4 @@
-use crate::state::ParametricCurve;
+use crate::{state::ParametricCurve, MATRIX_DET_TOLERANCE, PLUS_INF};
 
 use super::Plugin;
 
@@ -21,8 +21,312 @@ pub(crate) const DEFAULT_PARAMETRIC_CURVE: ParametricCurve = ParametricCurve {
     eval: default_parametric_curve_evaluator,
 };
 
-pub(crate) fn default_parametric_curve_evaluator(_type: i32, _params: &[f64], _r: f64) -> f64 {
-    0.0
+fn sigmoid_base(k: f64, t: f64) -> f64 {
+    (1.0 / (1.0 + (-k * t).exp())) - 0.5
+}
+
+fn inverted_sigmoid_base(k: f64, t: f64) -> f64 {
+    -((1.0 / (t + 0.5)) - 1.0).ln() / k
+}
+
+fn sigmoid_factory(k: f64, t: f64) -> f64 {
+    let correction = 0.5 / sigmoid_base(k, 1.0);
+
+    correction * sigmoid_base(k, 2.0 * t - 1.0) + 0.5
+}
+
+fn inverse_sigmoid_factory(k: f64, t: f64) -> f64 {
+    let correction = 0.5 / sigmoid_base(k, 1.0);
+
+    (inverted_sigmoid_base(k, (t - 0.5) / correction) + 1.0) / 2.0
+}
+
+/// Evaluates the built-in parametric curves. Negative types evaluate the inverse of their
+/// positive counterpart.
+pub(crate) fn default_parametric_curve_evaluator(r#type: i32, params: &[f64], r: f64) -> f64 {
+    match r#type {
+        // Y = X ^ Gamma
+        1 => {
+            if r < 0.0 {
+                if (params[0] - 1.0).abs() < MATRIX_DET_TOLERANCE {
+                    r
+                } else {
+                    0.0
+                }
+            } else {
+                r.powf(params[0])
+            }
+        }
+
+        // Type 1 reversed: X = Y ^ 1/Gamma
+        -1 => {
+            if r < 0.0 {
+                if (params[0] - 1.0).abs() < MATRIX_DET_TOLERANCE {
+                    r
+                } else {
+                    0.0
+                }
+            } else if params[0].abs() < MATRIX_DET_TOLERANCE {
+                PLUS_INF as f64
+            } else {
+                r.powf(1.0 / params[0])
+            }
+        }
+
+        // CIE 122-1966
+        // Y = (aX + b) ^ Gamma | X >= -b/a
+        // Y = 0                | else
+        2 => {
+            if params[1].abs() < MATRIX_DET_TOLERANCE {
+                return 0.0;
+            }
+
+            let disc = -params[2] / params[1];
+            if r >= disc {
+                let e = params[1] * r + params[2];
+
+                if e > 0.0 {
+                    e.powf(params[0])
+                } else {
+                    0.0
+                }
+            } else {
+                0.0
+            }
+        }
+
+        // Type 2 reversed
+        // X = (Y ^ 1/Gamma - b) / a
+        -2 => {
+            if params[0].abs() < MATRIX_DET_TOLERANCE || params[1].abs() < MATRIX_DET_TOLERANCE {
+                return 0.0;
+            }
+
+            let val = if r < 0.0 {
+                0.0
+            } else {
+                (r.powf(1.0 / params[0]) - params[2]) / params[1]
+            };
+
+            if val < 0.0 {
+                0.0
+            } else {
+                val
+            }
+        }
+
+        // IEC 61966-3
+        // Y = (aX + b) ^ Gamma + c | X >= -b/a
+        // Y = c                    | else
+        3 => {
+            if params[1].abs() < MATRIX_DET_TOLERANCE {
+                return 0.0;
+            }
+
+            let disc = (-params[2] / params[1]).max(0.0);
+            if r >= disc {
+                let e = params[1] * r + params[2];
+
+                if e > 0.0 {
+                    e.powf(params[0]) + params[3]
+                } else {
+                    0.0
+                }
+            } else {
+                params[3]
+            }
+        }
+
+        // Type 3 reversed
+        // X = ((Y - c) ^ 1/Gamma - b) / a | Y >= c
+        // X = -b/a                        | Y < c
+        -3 => {
+            if params[0].abs() < MATRIX_DET_TOLERANCE || params[1].abs() < MATRIX_DET_TOLERANCE {
+                return 0.0;
+            }
+
+            if r >= params[3] {
+                let e = r - params[3];
+
+                if e > 0.0 {
+                    (e.powf(1.0 / params[0]) - params[2]) / params[1]
+                } else {
+                    0.0
+                }
+            } else {
+                -params[2] / params[1]
+            }
+        }
+
+        // IEC 61966-2.1 (sRGB)
+        // Y = (aX + b) ^ Gamma | X >= d
+        // Y = cX               | X < d
+        4 => {
+            if r >= params[4] {
+                let e = params[1] * r + params[2];
+
+                if e > 0.0 {
+                    e.powf(params[0])
+                } else {
+                    0.0
+                }
+            } else {
+                r * params[3]
+            }
+        }
+
+        // Type 4 reversed
+        // X = (Y ^ 1/Gamma - b) / a | Y >= (ad + b) ^ Gamma
+        // X = Y / c                 | Y < (ad + b) ^ Gamma
+        -4 => {
+            let e = params[1] * params[4] + params[2];
+            let disc = if e < 0.0 { 0.0 } else { e.powf(params[0]) };
+
+            if r >= disc {
+                if params[0].abs() < MATRIX_DET_TOLERANCE || params[1].abs() < MATRIX_DET_TOLERANCE
+                {
+                    0.0
+                } else {
+                    (r.powf(1.0 / params[0]) - params[2]) / params[1]
+                }
+            } else if params[3].abs() < MATRIX_DET_TOLERANCE {
+                0.0
+            } else {
+                r / params[3]
+            }
+        }
+
+        // Y = (aX + b) ^ Gamma + e | X >= d
+        // Y = cX + f               | X < d
+        5 => {
+            if r >= params[4] {
+                let e = params[1] * r + params[2];
+
+                if e > 0.0 {
+                    e.powf(params[0]) + params[5]
+                } else {
+                    params[5]
+                }
+            } else {
+                r * params[3] + params[6]
+            }
+        }
+
+        // Type 5 reversed
+        // X = ((Y - e) ^ 1/Gamma - b) / a | Y >= cd + f
+        // X = (Y - f) / c                 | else
+        -5 => {
+            let disc = params[3] * params[4] + params[6];
+
+            if r >= disc {
+                let e = r - params[5];
+
+                if e < 0.0
+                    || params[0].abs() < MATRIX_DET_TOLERANCE
+                    || params[1].abs() < MATRIX_DET_TOLERANCE
+                {
+                    0.0
+                } else {
+                    (e.powf(1.0 / params[0]) - params[2]) / params[1]
+                }
+            } else if params[3].abs() < MATRIX_DET_TOLERANCE {
+                0.0
+            } else {
+                (r - params[6]) / params[3]
+            }
+        }
+
+        // Types 6, 7 and 8 come from the segmented curves of the ICC floating point addendum.
+        // Type 6 is type 5 without d.
+
+        // Y = (aX + b) ^ Gamma + c
+        6 => {
+            let e = params[1] * r + params[2];
+
+            // On gamma 1.0, don't clamp
+            if params[0] == 1.0 {
+                e + params[3]
+            } else if e < 0.0 {
+                params[3]
+            } else {
+                e.powf(params[0]) + params[3]
+            }
+        }
+
+        // X = ((Y - c) ^ 1/Gamma - b) / a
+        -6 => {
+            if params[0].abs() < MATRIX_DET_TOLERANCE || params[1].abs() < MATRIX_DET_TOLERANCE {
+                return 0.0;
+            }
+
+            let e = r - params[3];
+            if e < 0.0 {
+                0.0
+            } else {
+                (e.powf(1.0 / params[0]) - params[2]) / params[1]
+            }
+        }
+
+        // Y = a * log10(b * X ^ Gamma + c) + d
+        7 => {
+            let e = params[2] * r.powf(params[0]) + params[3];
+
+            if e <= 0.0 {
+                params[4]
+            } else {
+                params[1] * e.log10() + params[4]
+            }
+        }
+
+        // X = ((10 ^ ((Y - d) / a) - c) / b) ^ 1/Gamma
+        -7 => {
+            if params[0].abs() < MATRIX_DET_TOLERANCE
+                || params[1].abs() < MATRIX_DET_TOLERANCE
+                || params[2].abs() < MATRIX_DET_TOLERANCE
+            {
+                return 0.0;
+            }
+
+            ((10f64.powf((r - params[4]) / params[1]) - params[3]) / params[2])
+                .powf(1.0 / params[0])
+        }
+
+        // Y = a * b ^ (cX + d) + e
+        8 => params[0] * params[1].powf(params[2] * r + params[3]) + params[4],
+
+        // X = (log((Y - e) / a) / log(b) - d) / c
+        -8 => {
+            let disc = r - params[4];
+
+            if disc < 0.0
+                || params[0].abs() < MATRIX_DET_TOLERANCE
+                || params[2].abs() < MATRIX_DET_TOLERANCE
+            {
+                0.0
+            } else {
+                ((disc / params[0]).ln() / params[1].ln() - params[3]) / params[2]
+            }
+        }
+
+        // S-shaped: Y = (1 - (1 - X) ^ 1/Gamma) ^ 1/Gamma
+        108 => {
+            if params[0].abs() < MATRIX_DET_TOLERANCE {
+                0.0
+            } else {
+                (1.0 - (1.0 - r).powf(1.0 / params[0])).powf(1.0 / params[0])
+            }
+        }
+
+        // X = 1 - (1 - Y ^ Gamma) ^ Gamma
+        -108 => 1.0 - (1.0 - r.powf(params[0])).powf(params[0]),
+
+        // Sigmoidal, with slope k
+        109 => sigmoid_factory(params[0], r),
+        -109 => inverse_sigmoid_factory(params[0], r),
+
+        // Unsupported parametric curve, should never get here
+        _ => 0.0,
+    }
 }
 
 pub(crate) const DEFAULT_CURVE_DEFS: &[CurveDef] = &[
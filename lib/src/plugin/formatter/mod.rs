@@ -0,0 +1,503 @@
+use crate::{
+    float_to_half, half_to_float, quick_saturate_word,
+    types::{
+        bytes_sh, channels_sh, colorspace_sh, doswap_sh, endian16_sh, extra_sh, flavor_sh,
+        float_sh, optimized_sh, pixel_type, planar_sh, premul_sh, swapfirst_sh, Format, Transform,
+    },
+    MAX_ENCODEABLE_XYZ,
+};
+
+use super::Plugin;
+
+pub type FormatterIn16 =
+    for<'a> fn(cargo: &Transform, values: &mut [u16], buffer: &'a [u8], stride: u32) -> &'a [u8];
+pub type FormatterInFloat =
+    for<'a> fn(cargo: &Transform, values: &mut [f32], buffer: &'a [u8], stride: u32) -> &'a [u8];
+
+pub type FormatterOut16 = for<'a> fn(
+    cargo: &Transform,
+    values: &[u16],
+    buffer: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8];
+pub type FormatterOutFloat = for<'a> fn(
+    cargo: &Transform,
+    values: &[f32],
+    buffer: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8];
+
+pub type FormatterInFactory = fn(r#type: u32, flags: u32) -> FormatterIn;
+pub type FormatterOutFactory = fn(r#type: u32, flags: u32) -> FormatterOut;
+
+pub struct FormatterPlugin {
+    pub base: Plugin,
+    pub in_factory: FormatterInFactory,
+    pub out_factory: FormatterOutFactory,
+}
+
+pub enum FormatterIn {
+    F32(Option<FormatterInFloat>),
+    U16(Option<FormatterIn16>),
+}
+
+impl FormatterIn {
+    pub const fn is_f32(&self) -> bool {
+        matches!(*self, Self::F32(_))
+    }
+    pub const fn is_u16(&self) -> bool {
+        matches!(*self, Self::U16(_))
+    }
+    pub fn is_f32_and(self, f: impl FnOnce(Option<FormatterInFloat>) -> bool) -> bool {
+        match self {
+            Self::U16(_) => false,
+            Self::F32(x) => f(x),
+        }
+    }
+    pub fn is_u16_and(self, f: impl FnOnce(Option<FormatterIn16>) -> bool) -> bool {
+        match self {
+            Self::U16(x) => f(x),
+            Self::F32(_) => false,
+        }
+    }
+}
+
+pub enum FormatterOut {
+    F32(Option<FormatterOutFloat>),
+    U16(Option<FormatterOut16>),
+}
+
+impl FormatterOut {
+    pub const fn is_f32(&self) -> bool {
+        matches!(*self, Self::F32(_))
+    }
+    pub const fn is_u16(&self) -> bool {
+        matches!(*self, Self::U16(_))
+    }
+    pub fn is_f32_and(self, f: impl FnOnce(Option<FormatterOutFloat>) -> bool) -> bool {
+        match self {
+            Self::U16(_) => false,
+            Self::F32(x) => f(x),
+        }
+    }
+    pub fn is_u16_and(self, f: impl FnOnce(Option<FormatterOut16>) -> bool) -> bool {
+        match self {
+            Self::U16(x) => f(x),
+            Self::F32(_) => false,
+        }
+    }
+}
+
+pub(crate) fn default_input_formatter_factory(r#type: u32, flags: u32) -> FormatterIn {
+    let r#type = r#type & !optimized_sh(1);
+
+    if flags & pack_flags::FLOAT != 0 {
+        FormatterIn::F32(
+            INPUT_FORMATTERS_FLOAT
+                .iter()
+                .find(|(fmt, mask, _)| r#type & !mask == *fmt)
+                .map(|(_, _, frm)| *frm),
+        )
+    } else {
+        FormatterIn::U16(
+            INPUT_FORMATTERS_16
+                .iter()
+                .find(|(fmt, mask, _)| r#type & !mask == *fmt)
+                .map(|(_, _, frm)| *frm),
+        )
+    }
+}
+
+pub(crate) fn default_output_formatter_factory(r#type: u32, flags: u32) -> FormatterOut {
+    let r#type = r#type & !optimized_sh(1);
+
+    if flags & pack_flags::FLOAT != 0 {
+        FormatterOut::F32(
+            OUTPUT_FORMATTERS_FLOAT
+                .iter()
+                .find(|(fmt, mask, _)| r#type & !mask == *fmt)
+                .map(|(_, _, frm)| *frm),
+        )
+    } else {
+        FormatterOut::U16(
+            OUTPUT_FORMATTERS_16
+                .iter()
+                .find(|(fmt, mask, _)| r#type & !mask == *fmt)
+                .map(|(_, _, frm)| *frm),
+        )
+    }
+}
+
+pub(crate) const DEFAULT_FORMATTER_FACTORIES: (
+    &'static FormatterInFactory,
+    &'static FormatterOutFactory,
+) = (
+    &(default_input_formatter_factory as fn(u32, u32) -> FormatterIn),
+    &(default_output_formatter_factory as fn(u32, u32) -> FormatterOut),
+);
+
+const ANY_SPACE: u32 = colorspace_sh(31);
+const ANY_CHANNELS: u32 = channels_sh(15);
+const ANY_EXTRA: u32 = extra_sh(7);
+const ANY_PLANAR: u32 = planar_sh(1);
+const ANY_ENDIAN: u32 = endian16_sh(1);
+const ANY_SWAP: u32 = doswap_sh(1);
+const ANY_SWAP_FIRST: u32 = swapfirst_sh(1);
+const ANY_FLAVOR: u32 = flavor_sh(1);
+const ANY_PREMUL: u32 = premul_sh(1);
+
+/// Any layout of a given sample size.
+const ANY_LAYOUT: u32 = ANY_SPACE
+    | ANY_CHANNELS
+    | ANY_EXTRA
+    | ANY_PLANAR
+    | ANY_SWAP
+    | ANY_SWAP_FIRST
+    | ANY_FLAVOR
+    | ANY_PREMUL;
+
+const FLOAT_16: u32 = float_sh(1) | bytes_sh(2);
+const FLOAT_32: u32 = float_sh(1) | bytes_sh(4);
+// Doubles don't fit in the bytes field
+const FLOAT_64: u32 = float_sh(1) | bytes_sh(0);
+const BYTES_8: u32 = bytes_sh(1);
+const BYTES_16: u32 = bytes_sh(2);
+
+// Tables are searched in order, the first entry where `type & !mask == entry type` wins.
+static INPUT_FORMATTERS_16: &[(u32, u32, FormatterIn16)] = &[
+    (
+        Format::LAB_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_lab_double_to_16,
+    ),
+    (
+        Format::XYZ_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_xyz_double_to_16,
+    ),
+    (
+        Format::LAB_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_lab_float_to_16,
+    ),
+    (
+        Format::XYZ_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_xyz_float_to_16,
+    ),
+    (FLOAT_64, ANY_LAYOUT, unroll_doubles_to_16),
+    (FLOAT_32, ANY_LAYOUT, unroll_floats_to_16),
+    (FLOAT_16, ANY_LAYOUT | ANY_ENDIAN, unroll_half_to_16),
+    (Format::LAB_V2_8.0, 0, unroll_lab_v2_bytes),
+    (Format::ALAB_V2_8.0, 0, unroll_lab_v2_bytes),
+    (Format::LAB_V2_16.0, 0, unroll_lab_v2_words),
+    (BYTES_8, ANY_LAYOUT, unroll_bytes),
+    (BYTES_16, ANY_LAYOUT | ANY_ENDIAN, unroll_words),
+];
+
+static INPUT_FORMATTERS_FLOAT: &[(u32, u32, FormatterInFloat)] = &[
+    (
+        Format::LAB_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_lab_double_to_float,
+    ),
+    (
+        Format::LAB_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_lab_float_to_float,
+    ),
+    (
+        Format::XYZ_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_xyz_double_to_float,
+    ),
+    (
+        Format::XYZ_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        unroll_xyz_float_to_float,
+    ),
+    (FLOAT_32, ANY_LAYOUT, unroll_floats_to_float),
+    (FLOAT_64, ANY_LAYOUT, unroll_doubles_to_float),
+    (FLOAT_16, ANY_LAYOUT | ANY_ENDIAN, unroll_half_to_float),
+    (Format::LAB_V2_8.0, 0, unroll_lab_v2_bytes_to_float),
+    (Format::ALAB_V2_8.0, 0, unroll_lab_v2_bytes_to_float),
+    (Format::LAB_V2_16.0, 0, unroll_lab_v2_words_to_float),
+    (BYTES_8, ANY_LAYOUT, unroll_bytes_to_float),
+    (BYTES_16, ANY_LAYOUT | ANY_ENDIAN, unroll_words_to_float),
+];
+
+static OUTPUT_FORMATTERS_16: &[(u32, u32, FormatterOut16)] = &[
+    (
+        Format::LAB_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_lab_double_from_16,
+    ),
+    (
+        Format::XYZ_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_xyz_double_from_16,
+    ),
+    (
+        Format::LAB_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_lab_float_from_16,
+    ),
+    (
+        Format::XYZ_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_xyz_float_from_16,
+    ),
+    (FLOAT_64, ANY_LAYOUT, pack_doubles_from_16),
+    (FLOAT_32, ANY_LAYOUT, pack_floats_from_16),
+    (FLOAT_16, ANY_LAYOUT | ANY_ENDIAN, pack_half_from_16),
+    (Format::LAB_V2_8.0, 0, pack_lab_v2_bytes),
+    (Format::ALAB_V2_8.0, 0, pack_lab_v2_bytes),
+    (Format::LAB_V2_16.0, 0, pack_lab_v2_words),
+    (BYTES_8, ANY_LAYOUT, pack_bytes),
+    (BYTES_16, ANY_LAYOUT | ANY_ENDIAN, pack_words),
+];
+
+static OUTPUT_FORMATTERS_FLOAT: &[(u32, u32, FormatterOutFloat)] = &[
+    (
+        Format::LAB_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_lab_float_from_float,
+    ),
+    (
+        Format::XYZ_FLT.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_xyz_float_from_float,
+    ),
+    (
+        Format::LAB_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_lab_double_from_float,
+    ),
+    (
+        Format::XYZ_DBL.bits(),
+        ANY_PLANAR | ANY_EXTRA,
+        pack_xyz_double_from_float,
+    ),
+    (FLOAT_32, ANY_LAYOUT, pack_floats_from_float),
+    (FLOAT_64, ANY_LAYOUT, pack_doubles_from_float),
+    (FLOAT_16, ANY_LAYOUT | ANY_ENDIAN, pack_half_from_float),
+    (Format::LAB_V2_8.0, 0, pack_lab_v2_bytes_from_float),
+    (Format::ALAB_V2_8.0, 0, pack_lab_v2_bytes_from_float),
+    (Format::LAB_V2_16.0, 0, pack_lab_v2_words_from_float),
+    (BYTES_8, ANY_LAYOUT, pack_bytes_from_float),
+    (BYTES_16, ANY_LAYOUT | ANY_ENDIAN, pack_words_from_float),
+];
+
+/// Size in bytes of one sample. Doubles are stored with a size of 0.
+pub(crate) fn sample_size(format: Format) -> usize {
+    match format.bytes() {
+        0 => 8,
+        bytes => bytes as usize,
+    }
+}
+
+/// Ink spaces are expressed in 0..100% when using floating point.
+fn is_ink_space(format: Format) -> bool {
+    matches!(
+        format.colorspace() as u32,
+        pixel_type::CMY | pixel_type::CMYK | pixel_type::MCH5..=pixel_type::MCH15
+    )
+}
+
+/// Iterates the color channels of a pixel as pairs of the byte offset of the sample and the
+/// index of the channel it holds. `stride` is the size of a plane in bytes.
+fn channel_layout(format: Format, stride: u32) -> impl Iterator<Item = (usize, usize)> {
+    let n_chans = format.channels() as usize;
+    let extra = format.extra() as usize;
+    let do_swap = format.doswap();
+    let swap_first = format.swapfirst();
+
+    // Extra channels come before the colorants when only one of the swaps is set
+    let start = if do_swap ^ swap_first { extra } else { 0 };
+    let step = if format.planar() {
+        stride as usize
+    } else {
+        sample_size(format)
+    };
+
+    (0..n_chans).map(move |i| {
+        let mut index = if do_swap { n_chans - i - 1 } else { i };
+        if extra == 0 && swap_first {
+            // The first sample belongs to the last channel
+            index = (index + n_chans - 1) % n_chans;
+        }
+
+        ((start + i) * step, index)
+    })
+}
+
+/// Number of bytes from the start of a pixel to the start of the next one.
+fn pixel_advance(format: Format) -> usize {
+    if format.planar() {
+        sample_size(format)
+    } else {
+        (format.channels() as usize + format.extra() as usize) * sample_size(format)
+    }
+}
+
+/// Alpha of a premultiplied pixel in the 0..1 range, or `None` if the colorants of `format`
+/// aren't premultiplied. The alpha is the first extra channel, and is never reversed. Output
+/// formatters read it from the destination buffer, so transforms with premultiplied outputs
+/// must copy the alpha there before packing.
+fn read_alpha(format: Format, buffer: &[u8], stride: u32) -> Option<f64> {
+    if !format.premul() || format.extra() == 0 {
+        return None;
+    }
+
+    let step = if format.planar() {
+        stride as usize
+    } else {
+        sample_size(format)
+    };
+    let pos = if format.doswap() ^ format.swapfirst() {
+        0
+    } else {
+        format.channels() as usize * step
+    };
+
+    let alpha = read_sample(format, buffer, pos);
+
+    // Floating point ink spaces hold every channel in 0..100%
+    Some(if format.float() && is_ink_space(format) {
+        alpha / 100.0
+    } else {
+        alpha
+    })
+}
+
+/// Reads the sample at `pos` as stored by `format`, integers are normalized to 0..1.
+pub(crate) fn read_sample(format: Format, buffer: &[u8], pos: usize) -> f64 {
+    match (format.float(), format.bytes()) {
+        (false, 1) => buffer[pos] as f64 / 255.0,
+        (false, _) => {
+            let v = read_u16(buffer, pos);
+            let v = if format.endian16() { v.swap_bytes() } else { v };
+            v as f64 / 65535.0
+        }
+        (true, 2) => {
+            let v = read_u16(buffer, pos);
+            let v = if format.endian16() { v.swap_bytes() } else { v };
+            half_to_float(v) as f64
+        }
+        (true, 4) => read_f32(buffer, pos) as f64,
+        (true, _) => read_f64(buffer, pos),
+    }
+}
+
+/// Writes a sample at `pos` as stored by `format`, the inverse of [`read_sample`].
+pub(crate) fn write_sample(format: Format, buffer: &mut [u8], pos: usize, v: f64) {
+    match (format.float(), format.bytes()) {
+        (false, 1) => buffer[pos] = quick_saturate_byte(v * 255.0),
+        (false, _) => {
+            let v = quick_saturate_word(v * 65535.0);
+            write_u16(
+                buffer,
+                pos,
+                if format.endian16() { v.swap_bytes() } else { v },
+            )
+        }
+        (true, 2) => {
+            let v = float_to_half(v as f32);
+            write_u16(
+                buffer,
+                pos,
+                if format.endian16() { v.swap_bytes() } else { v },
+            )
+        }
+        (true, 4) => write_f32(buffer, pos, v as f32),
+        (true, _) => write_f64(buffer, pos, v),
+    }
+}
+
+/// Alpha of a premultiplied pixel in the 0..0xffff range.
+fn read_alpha_16(format: Format, buffer: &[u8], stride: u32) -> Option<u16> {
+    read_alpha(format, buffer, stride).map(|alpha| quick_saturate_word(alpha * 65535.0))
+}
+
+/// Undoes the premultiplication of a sample. Colorants of fully transparent pixels are zero.
+fn unpremultiply_16(v: u16, alpha: u16) -> u16 {
+    if alpha == 0 {
+        return 0;
+    }
+
+    ((v as u32 * 0xffff + alpha as u32 / 2) / alpha as u32).min(0xffff) as u16
+}
+
+fn premultiply_16(v: u16, alpha: u16) -> u16 {
+    ((v as u32 * alpha as u32 + 0x7fff) / 0xffff) as u16
+}
+
+/// Undoes the premultiplication of a floating point sample. Colorants of fully transparent
+/// pixels are zero.
+fn unpremultiply_float(v: f64, alpha: f64) -> f64 {
+    if alpha <= 0.0 {
+        0.0
+    } else {
+        v / alpha
+    }
+}
+
+fn read_u16(buffer: &[u8], pos: usize) -> u16 {
+    u16::from_ne_bytes([buffer[pos], buffer[pos + 1]])
+}
+
+fn read_f32(buffer: &[u8], pos: usize) -> f32 {
+    f32::from_ne_bytes(buffer[pos..pos + 4].try_into().unwrap())
+}
+
+fn read_f64(buffer: &[u8], pos: usize) -> f64 {
+    f64::from_ne_bytes(buffer[pos..pos + 8].try_into().unwrap())
+}
+
+fn write_u16(buffer: &mut [u8], pos: usize, value: u16) {
+    buffer[pos..pos + 2].copy_from_slice(&value.to_ne_bytes())
+}
+
+fn write_f32(buffer: &mut [u8], pos: usize, value: f32) {
+    buffer[pos..pos + 4].copy_from_slice(&value.to_ne_bytes())
+}
+
+fn write_f64(buffer: &mut [u8], pos: usize, value: f64) {
+    buffer[pos..pos + 8].copy_from_slice(&value.to_ne_bytes())
+}
+
+fn quick_saturate_byte(d: f64) -> u8 {
+    let d = d + 0.5;
+    if d <= 0.0 {
+        return 0;
+    }
+    if d >= 255.0 {
+        return 0xff;
+    }
+
+    d.floor() as u8
+}
+
+fn lab_v2_to_v4(x: u16) -> u16 {
+    let a = ((x as u32) << 8 | x as u32) >> 8;
+
+    a.min(0xffff) as u16
+}
+
+fn lab_v4_to_v2(x: u16) -> u16 {
+    ((((x as u32) << 8) + 0x80) / 257) as u16
+}
+
+pub mod pack_flags {
+    pub const BITS_16: u32 = 0x0000;
+    pub const FLOAT: u32 = 0x0001;
+}
+
+mod pack;
+mod unpack;
+
+use pack::*;
+use unpack::*;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,447 @@
+use crate::{
+    float_to_half, from_16_to_8, quick_saturate_word,
+    types::{Format, Lab, Transform, XYZ},
+};
+
+use super::{
+    channel_layout, is_ink_space, lab_v4_to_v2, pixel_advance, premultiply_16, quick_saturate_byte,
+    read_alpha, read_alpha_16, write_f32, write_f64, write_u16, MAX_ENCODEABLE_XYZ,
+};
+
+// 16 bits
+
+pub(super) fn pack_bytes<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let reverse = format.flavor();
+    let alpha = read_alpha_16(format, output, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = values[index];
+        if reverse {
+            v = 0xffff - v;
+        }
+        if let Some(alpha) = alpha {
+            v = premultiply_16(v, alpha);
+        }
+        output[pos] = from_16_to_8(v);
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+pub(super) fn pack_words<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let reverse = format.flavor();
+    let swap_endian = format.endian16();
+    let alpha = read_alpha_16(format, output, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = values[index];
+        if reverse {
+            v = 0xffff - v;
+        }
+        if let Some(alpha) = alpha {
+            v = premultiply_16(v, alpha);
+        }
+        if swap_endian {
+            v = v.swap_bytes();
+        }
+        write_u16(output, pos, v);
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+/// Scales a 16 bits value to the range of a floating point sample.
+fn word_to_float_sample(info: &Transform, v: u16) -> f64 {
+    let format = info.get_output_format();
+    let maximum = if is_ink_space(format) { 100.0 } else { 1.0 };
+
+    let v = v as f64 / 65535.0 * maximum;
+    if format.flavor() {
+        maximum - v
+    } else {
+        v
+    }
+}
+
+pub(super) fn pack_floats_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_f32(
+            output,
+            pos,
+            (word_to_float_sample(info, values[index]) * alpha) as f32,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+pub(super) fn pack_doubles_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_f64(
+            output,
+            pos,
+            word_to_float_sample(info, values[index]) * alpha,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+/// Writes a half float sample.
+fn write_half(format: Format, output: &mut [u8], pos: usize, v: f32) {
+    let mut v = float_to_half(v);
+    if format.endian16() {
+        v = v.swap_bytes();
+    }
+
+    write_u16(output, pos, v);
+}
+
+pub(super) fn pack_half_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_half(
+            format,
+            output,
+            pos,
+            (word_to_float_sample(info, values[index]) * alpha) as f32,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+/// Writes the three components of a colorimetric pixel.
+fn write_pcs(info: &Transform, output: &mut [u8], stride: u32, is_double: bool, pcs: [f64; 3]) {
+    for (pos, index) in channel_layout(info.get_output_format(), stride) {
+        if is_double {
+            write_f64(output, pos, pcs[index]);
+        } else {
+            write_f32(output, pos, pcs[index] as f32);
+        }
+    }
+}
+
+fn values_to_lab(values: &[u16]) -> [f64; 3] {
+    Lab::decode(&[values[0], values[1], values[2]]).into()
+}
+
+fn values_to_xyz(values: &[u16]) -> [f64; 3] {
+    XYZ::decode(&[values[0], values[1], values[2]]).into()
+}
+
+pub(super) fn pack_lab_double_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, true, values_to_lab(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_lab_float_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, false, values_to_lab(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_xyz_double_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, true, values_to_xyz(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_xyz_float_from_16<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, false, values_to_xyz(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_lab_v2_bytes<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let lab = [
+        lab_v4_to_v2(values[0]),
+        lab_v4_to_v2(values[1]),
+        lab_v4_to_v2(values[2]),
+    ];
+
+    pack_bytes(info, &lab, output, stride)
+}
+
+pub(super) fn pack_lab_v2_words<'a>(
+    info: &Transform,
+    values: &[u16],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let lab = [
+        lab_v4_to_v2(values[0]),
+        lab_v4_to_v2(values[1]),
+        lab_v4_to_v2(values[2]),
+    ];
+
+    pack_words(info, &lab, output, stride)
+}
+
+// Floating point
+
+pub(super) fn pack_bytes_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let v = values[index] as f64;
+        let v = if format.flavor() { 1.0 - v } else { v } * alpha;
+        output[pos] = quick_saturate_byte(v * 255.0);
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+pub(super) fn pack_words_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let v = values[index] as f64;
+        let v = if format.flavor() { 1.0 - v } else { v } * alpha;
+        let mut v = quick_saturate_word(v * 65535.0);
+        if format.endian16() {
+            v = v.swap_bytes();
+        }
+        write_u16(output, pos, v);
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+/// Scales a normalized value to the range of a floating point sample.
+fn float_to_float_sample(info: &Transform, v: f32) -> f64 {
+    let format = info.get_output_format();
+    let maximum = if is_ink_space(format) { 100.0 } else { 1.0 };
+
+    let v = v as f64 * maximum;
+    if format.flavor() {
+        maximum - v
+    } else {
+        v
+    }
+}
+
+pub(super) fn pack_floats_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_f32(
+            output,
+            pos,
+            (float_to_float_sample(info, values[index]) * alpha) as f32,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+pub(super) fn pack_doubles_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_f64(
+            output,
+            pos,
+            float_to_float_sample(info, values[index]) * alpha,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+pub(super) fn pack_half_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    let format = info.get_output_format();
+    let alpha = read_alpha(format, output, stride).unwrap_or(1.0);
+
+    for (pos, index) in channel_layout(format, stride) {
+        write_half(
+            format,
+            output,
+            pos,
+            (float_to_float_sample(info, values[index]) * alpha) as f32,
+        );
+    }
+
+    &mut output[pixel_advance(format)..]
+}
+
+/// Lab value of the 0..1 range used by float pipelines.
+fn float_to_lab(values: &[f32]) -> [f64; 3] {
+    [
+        values[0] as f64 * 100.0,
+        values[1] as f64 * 255.0 - 128.0,
+        values[2] as f64 * 255.0 - 128.0,
+    ]
+}
+
+fn float_to_xyz(values: &[f32]) -> [f64; 3] {
+    [
+        values[0] as f64 * MAX_ENCODEABLE_XYZ,
+        values[1] as f64 * MAX_ENCODEABLE_XYZ,
+        values[2] as f64 * MAX_ENCODEABLE_XYZ,
+    ]
+}
+
+pub(super) fn pack_lab_float_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, false, float_to_lab(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_lab_double_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, true, float_to_lab(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_xyz_float_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, false, float_to_xyz(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_xyz_double_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    write_pcs(info, output, stride, true, float_to_xyz(values));
+
+    &mut output[pixel_advance(info.get_output_format())..]
+}
+
+pub(super) fn pack_lab_v2_bytes_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    pack_lab_v2_bytes(
+        info,
+        &Lab::from(float_to_lab(values)).encode(),
+        output,
+        stride,
+    )
+}
+
+pub(super) fn pack_lab_v2_words_from_float<'a>(
+    info: &Transform,
+    values: &[f32],
+    output: &'a mut [u8],
+    stride: u32,
+) -> &'a mut [u8] {
+    pack_lab_v2_words(
+        info,
+        &Lab::from(float_to_lab(values)).encode(),
+        output,
+        stride,
+    )
+}
@@ -0,0 +1,458 @@
+use crate::{
+    state::DEFAULT_CONTEXT,
+    types::{pixel_type, transform_flags, Format, Pipeline, Transform},
+    Result, MAX_CHANNELS,
+};
+
+use super::{pack_flags, FormatterIn, FormatterOut};
+
+macro_rules! formats {
+    ($($name:ident),* $(,)?) => {
+        &[$((stringify!($name), Format::$name)),*]
+    };
+}
+
+#[rustfmt::skip]
+static ALL_FORMATS: &[(&str, Format)] = formats![
+    GRAY_8, GRAY_8_REV, GRAY_16, GRAY_16_REV, GRAY_16_SE, GRAYA_8, GRAYA_16, GRAYA_16_SE,
+    GRAYA_8_PLANAR, GRAYA_16_PLANAR, RGB_8, RGB_8_PLANAR, BGR_8, BGR_8_PLANAR, RGB_16,
+    RGB_16_PLANAR, RGB_16_SE, BGR_16, BGR_16_PLANAR, BGR_16_SE, RGBA_8, RGBA_8_PLANAR, RGBA_16,
+    RGBA_16_PLANAR, RGBA_16_SE, ARGB_8, ARGB_8_PLANAR, ARGB_16, ABGR_8, ABGR_8_PLANAR, ABGR_16,
+    ABGR_16_PLANAR, ABGR_16_SE, BGRA_8, BGRA_8_PLANAR, BGRA_16, BGRA_16_SE, CMY_8, CMY_8_PLANAR,
+    CMY_16, CMY_16_PLANAR, CMY_16_SE, CMYK_8, CMYKA_8, CMYK_8_REV, YUVK_8, CMYK_8_PLANAR,
+    CMYK_16, CMYK_16_REV, YUVK_16, CMYK_16_PLANAR, CMYK_16_SE, KYMC_8, KYMC_16, KYMC_16_SE,
+    KCMY_8, KCMY_8_REV, KCMY_16, KCMY_16_REV, KCMY_16_SE, CMYK5_8, CMYK5_16, CMYK5_16_SE,
+    KYMC5_8, KYMC5_16, KYMC5_16_SE, CMYK6_8, CMYK6_8_PLANAR, CMYK6_16, CMYK6_16_PLANAR,
+    CMYK6_16_SE, CMYK7_8, CMYK7_16, CMYK7_16_SE, KYMC7_8, KYMC7_16, KYMC7_16_SE, CMYK8_8,
+    CMYK8_16, CMYK8_16_SE, KYMC8_8, KYMC8_16, KYMC8_16_SE, CMYK9_8, CMYK9_16, CMYK9_16_SE,
+    KYMC9_8, KYMC9_16, KYMC9_16_SE, CMYK10_8, CMYK10_16, CMYK10_16_SE, KYMC10_8, KYMC10_16,
+    KYMC10_16_SE, CMYK11_8, CMYK11_16, CMYK11_16_SE, KYMC11_8, KYMC11_16, KYMC11_16_SE,
+    CMYK12_8, CMYK12_16, CMYK12_16_SE, KYMC12_8, KYMC12_16, KYMC12_16_SE, XYZ_16, LAB_8,
+    LAB_V2_8, ALAB_8, ALAB_V2_8, LAB_16, LAB_V2_16, YXY_16, YCB_CR_8, YCB_CR_8_PLANAR,
+    YCB_CR_16, YCB_CR_16_PLANAR, YCB_CR_16_SE, YUV_8, YUV_8_PLANAR, YUV_16, YUV_16_PLANAR,
+    YUV_16_SE, HLS_8, HLS_8_PLANAR, HLS_16, HLS_16_PLANAR, HLS_16_SE, HSV_8, HSV_8_PLANAR,
+    HSV_16, HSV_16_PLANAR, HSV_16_SE, NAMED_COLOR_INDEX, XYZ_FLT, LAB_FLT, LAB_A_FLT, GRAY_FLT,
+    GRAYA_FLT, RGB_FLT, RGBA_FLT, ARGB_FLT, BGR_FLT, BGRA_FLT, ABGR_FLT, CMYK_FLT, XYZ_DBL,
+    LAB_DBL, GRAY_DBL, RGB_DBL, BGR_DBL, CMYK_DBL,
+];
+
+/// Size of a plane in planar test buffers.
+const PLANE: u32 = 128;
+
+fn transform(format: Format) -> Transform {
+    let n_chans = format.channels() as usize;
+    let lut = Pipeline::new(&DEFAULT_CONTEXT, n_chans, n_chans).unwrap();
+
+    // Premultiplied outputs are only allowed when the alpha is copied
+    let flags = if format.premul() {
+        transform_flags::NULLTRANSFORM | transform_flags::COPY_ALPHA
+    } else {
+        transform_flags::NULLTRANSFORM
+    };
+
+    Transform::new(&DEFAULT_CONTEXT, lut, 0, format, format, flags).unwrap()
+}
+
+/// Lab v2 8 bits can't hold every v4 value.
+fn is_lossy(format: Format) -> bool {
+    format == Format::LAB_V2_8 || format == Format::ALAB_V2_8
+}
+
+#[test]
+fn formatters_16_round_trip() -> Result<()> {
+    for (name, format) in ALL_FORMATS {
+        let info = transform(*format);
+        let flags = pack_flags::BITS_16;
+
+        let (unroll, pack) = match (
+            DEFAULT_CONTEXT.get_formatter_in(format.bits(), flags),
+            DEFAULT_CONTEXT.get_formatter_out(format.bits(), flags),
+        ) {
+            (FormatterIn::U16(Some(unroll)), FormatterOut::U16(Some(pack))) => (unroll, pack),
+            _ => panic!("No 16 bits formatters for {}", name),
+        };
+
+        let n_chans = format.channels() as usize;
+        let max = if is_lossy(*format) { 0x101 } else { 0 };
+
+        for j in 0..5 {
+            // Values surviving the trip through 8 bits
+            let values = (0..n_chans)
+                .map(|i| ((i + j + 1) * 257) as u16)
+                .collect::<Vec<_>>();
+            let mut buffer = [0u8; 2048];
+
+            let rest = pack(&info, &values, &mut buffer, PLANE).len();
+            let pixel_size = 2048 - rest;
+
+            let mut result = [0u16; MAX_CHANNELS];
+            let rest = unroll(&info, &mut result, &buffer, PLANE).len();
+
+            assert_eq!(
+                2048 - rest,
+                pixel_size,
+                "{}: Unroll and pack sizes differ",
+                name
+            );
+            for i in 0..n_chans {
+                assert!(
+                    values[i].abs_diff(result[i]) <= max,
+                    "{}: Channel {} should be {}, but is {}",
+                    name,
+                    i,
+                    values[i],
+                    result[i]
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn formatters_float_round_trip() -> Result<()> {
+    for (name, format) in ALL_FORMATS {
+        let info = transform(*format);
+        let flags = pack_flags::FLOAT;
+
+        let (unroll, pack) = match (
+            DEFAULT_CONTEXT.get_formatter_in(format.bits(), flags),
+            DEFAULT_CONTEXT.get_formatter_out(format.bits(), flags),
+        ) {
+            (FormatterIn::F32(Some(unroll)), FormatterOut::F32(Some(pack))) => (unroll, pack),
+            _ => panic!("No float formatters for {}", name),
+        };
+
+        let n_chans = format.channels() as usize;
+        let max = if format.bytes() == 1 || format.colorspace() as u32 == pixel_type::LAB_V2 {
+            2.0 / 255.0
+        } else {
+            1e-5
+        };
+
+        for j in 0..5 {
+            let values = (0..n_chans)
+                .map(|i| (i + j + 1) as f32 / 20.0)
+                .collect::<Vec<_>>();
+            let mut buffer = [0u8; 2048];
+
+            pack(&info, &values, &mut buffer, PLANE);
+
+            let mut result = [0f32; MAX_CHANNELS];
+            unroll(&info, &mut result, &buffer, PLANE);
+
+            for i in 0..n_chans {
+                assert!(
+                    (values[i] - result[i]).abs() <= max,
+                    "{}: Channel {} should be {}, but is {}",
+                    name,
+                    i,
+                    values[i],
+                    result[i]
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[rustfmt::skip]
+static HALF_FORMATS: &[(&str, Format)] = formats![
+    GRAY_HALF_FLT, RGB_HALF_FLT, RGBA_HALF_FLT, CMYK_HALF_FLT, ARGB_HALF_FLT, BGR_HALF_FLT,
+    BGRA_HALF_FLT, ABGR_HALF_FLT,
+];
+
+#[test]
+fn formatters_half_round_trip() {
+    for (name, format) in HALF_FORMATS {
+        let info = transform(*format);
+
+        let (unroll_16, pack_16, unroll_float, pack_float) = match (
+            DEFAULT_CONTEXT.get_formatter_in(format.bits(), pack_flags::BITS_16),
+            DEFAULT_CONTEXT.get_formatter_out(format.bits(), pack_flags::BITS_16),
+            DEFAULT_CONTEXT.get_formatter_in(format.bits(), pack_flags::FLOAT),
+            DEFAULT_CONTEXT.get_formatter_out(format.bits(), pack_flags::FLOAT),
+        ) {
+            (
+                FormatterIn::U16(Some(a)),
+                FormatterOut::U16(Some(b)),
+                FormatterIn::F32(Some(c)),
+                FormatterOut::F32(Some(d)),
+            ) => (a, b, c, d),
+            _ => panic!("No half formatters for {}", name),
+        };
+
+        let n_chans = format.channels() as usize;
+        let mut buffer = [0u8; 2048];
+
+        // Halves have 11 bits of precision
+        let values = (0..n_chans)
+            .map(|i| ((i + 1) * 9000) as u16)
+            .collect::<Vec<_>>();
+        let rest = pack_16(&info, &values, &mut buffer, PLANE).len();
+        assert_eq!(2048 - rest, (n_chans + format.extra() as usize) * 2);
+
+        let mut result = [0u16; MAX_CHANNELS];
+        unroll_16(&info, &mut result, &buffer, PLANE);
+        for i in 0..n_chans {
+            assert!(
+                values[i].abs_diff(result[i]) <= 32,
+                "{}: Channel {} should be {}, but is {}",
+                name,
+                i,
+                values[i],
+                result[i]
+            );
+        }
+
+        // Values representable as halves go through unchanged
+        let values = (0..n_chans)
+            .map(|i| (i + 1) as f32 / 8.0)
+            .collect::<Vec<_>>();
+        pack_float(&info, &values, &mut buffer, PLANE);
+
+        let mut result = [0f32; MAX_CHANNELS];
+        unroll_float(&info, &mut result, &buffer, PLANE);
+        assert_eq!(values, result[..n_chans], "{}", name);
+    }
+}
+
+fn unroll_16(format: Format, buffer: &[u8], stride: u32) -> Vec<u16> {
+    let unroll = match DEFAULT_CONTEXT.get_formatter_in(format.bits(), pack_flags::BITS_16) {
+        FormatterIn::U16(Some(unroll)) => unroll,
+        _ => panic!("No formatter"),
+    };
+
+    let mut values = [0u16; MAX_CHANNELS];
+    unroll(&transform(format), &mut values, buffer, stride);
+
+    values[..format.channels() as usize].to_vec()
+}
+
+fn unroll_float(format: Format, buffer: &[u8]) -> Vec<f32> {
+    let unroll = match DEFAULT_CONTEXT.get_formatter_in(format.bits(), pack_flags::FLOAT) {
+        FormatterIn::F32(Some(unroll)) => unroll,
+        _ => panic!("No formatter"),
+    };
+
+    let mut values = [0f32; MAX_CHANNELS];
+    unroll(&transform(format), &mut values, buffer, 0);
+
+    values[..format.channels() as usize].to_vec()
+}
+
+#[test]
+fn formatters_follow_channel_order() {
+    let pixel = [0x11u8, 0x22, 0x33, 0x44];
+    let w = |v: u8| v as u16 * 257;
+
+    assert_eq!(
+        unroll_16(Format::RGB_8, &pixel, 0),
+        [w(0x11), w(0x22), w(0x33)]
+    );
+    assert_eq!(
+        unroll_16(Format::BGR_8, &pixel, 0),
+        [w(0x33), w(0x22), w(0x11)]
+    );
+    assert_eq!(
+        unroll_16(Format::RGBA_8, &pixel, 0),
+        [w(0x11), w(0x22), w(0x33)]
+    );
+    assert_eq!(
+        unroll_16(Format::ARGB_8, &pixel, 0),
+        [w(0x22), w(0x33), w(0x44)]
+    );
+    assert_eq!(
+        unroll_16(Format::ABGR_8, &pixel, 0),
+        [w(0x44), w(0x33), w(0x22)]
+    );
+    assert_eq!(
+        unroll_16(Format::BGRA_8, &pixel, 0),
+        [w(0x33), w(0x22), w(0x11)]
+    );
+    assert_eq!(
+        unroll_16(Format::KYMC_8, &pixel, 0),
+        [w(0x44), w(0x33), w(0x22), w(0x11)]
+    );
+    assert_eq!(
+        unroll_16(Format::KCMY_8, &pixel, 0),
+        [w(0x22), w(0x33), w(0x44), w(0x11)]
+    );
+    assert_eq!(
+        unroll_16(Format::CMYK_8_REV, &pixel, 0),
+        [w(0xEE), w(0xDD), w(0xCC), w(0xBB)]
+    );
+}
+
+#[test]
+fn formatters_handle_planes_and_endianness() {
+    let planar = [0x10u8, 0xFF, 0x20, 0xFF, 0x30, 0xFF];
+    assert_eq!(
+        unroll_16(Format::RGB_8_PLANAR, &planar, 2),
+        [0x1010, 0x2020, 0x3030]
+    );
+
+    let swapped = [0x12u8, 0x34];
+    assert_eq!(
+        unroll_16(Format::GRAY_16_SE, &swapped, 0),
+        [u16::from_be_bytes(swapped)]
+    );
+    assert_eq!(
+        unroll_16(Format::GRAY_16, &swapped, 0),
+        [u16::from_ne_bytes(swapped)]
+    );
+}
+
+#[test]
+fn formatters_scale_floats() {
+    let as_bytes = |values: &[f64]| {
+        values
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>()
+    };
+
+    // Ink spaces are in 0..100%
+    let cmyk = as_bytes(&[100.0, 50.0, 0.0, 25.0]);
+    assert_eq!(
+        unroll_16(Format::CMYK_DBL, &cmyk, 0),
+        [0xffff, 0x8000, 0, 0x4000]
+    );
+    assert_eq!(unroll_float(Format::CMYK_DBL, &cmyk), [1.0, 0.5, 0.0, 0.25]);
+
+    // Lab is encoded as ICC v4
+    let lab = as_bytes(&[100.0, 0.0, 0.0]);
+    assert_eq!(
+        unroll_16(Format::LAB_DBL, &lab, 0),
+        [0xffff, 0x8080, 0x8080]
+    );
+    assert_eq!(
+        unroll_float(Format::LAB_DBL, &lab),
+        [1.0, 128.0 / 255.0, 128.0 / 255.0]
+    );
+
+    // XYZ is encoded as 1.15 fixed point
+    let xyz = as_bytes(&[0.5, 1.0, 1.5]);
+    assert_eq!(
+        unroll_16(Format::XYZ_DBL, &xyz, 0),
+        [0x4000, 0x8000, 0xC000]
+    );
+}
+
+#[test]
+fn formatters_unpremultiply_alpha() {
+    let w = |v: u8| v as u16 * 257;
+
+    // Half opaque pixel, alpha last or first
+    let pixel = [0x40u8, 0x20, 0x00, 0x80];
+    assert_eq!(
+        unroll_16(Format::RGBA_8_PREMUL, &pixel, 0),
+        [0x8000, 0x4000, 0]
+    );
+    let pixel = [0x80u8, 0x40, 0x20, 0x00];
+    assert_eq!(
+        unroll_16(Format::ARGB_8_PREMUL, &pixel, 0),
+        [0x8000, 0x4000, 0]
+    );
+
+    // Opaque pixels are left as is, transparent ones become black
+    let pixel = [0x40u8, 0x20, 0x10, 0xff];
+    assert_eq!(
+        unroll_16(Format::RGBA_8_PREMUL, &pixel, 0),
+        [w(0x40), w(0x20), w(0x10)]
+    );
+    let pixel = [0x40u8, 0x20, 0x10, 0x00];
+    assert_eq!(unroll_16(Format::RGBA_8_PREMUL, &pixel, 0), [0, 0, 0]);
+
+    let pixel = [0.25f32, 0.1, 0.0, 0.5]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unroll_float(Format::RGBA_FLT_PREMUL, &pixel),
+        [0.5, 0.2, 0.0]
+    );
+}
+
+#[test]
+fn formatters_premultiply_alpha() {
+    let formats = [
+        Format::GRAYA_8_PREMUL,
+        Format::GRAYA_16_PREMUL,
+        Format::RGBA_8_PREMUL,
+        Format::RGBA_16_PREMUL,
+        Format::ARGB_8_PREMUL,
+        Format::ARGB_16_PREMUL,
+        Format::ABGR_8_PREMUL,
+        Format::ABGR_16_PREMUL,
+        Format::BGRA_8_PREMUL,
+        Format::BGRA_16_PREMUL,
+        Format::GRAYA_FLT_PREMUL,
+        Format::RGBA_FLT_PREMUL,
+        Format::ARGB_FLT_PREMUL,
+        Format::BGRA_FLT_PREMUL,
+        Format::ABGR_FLT_PREMUL,
+    ];
+
+    for format in formats {
+        let info = transform(format);
+        let (unroll, pack) = match (
+            DEFAULT_CONTEXT.get_formatter_in(format.bits(), pack_flags::FLOAT),
+            DEFAULT_CONTEXT.get_formatter_out(format.bits(), pack_flags::FLOAT),
+        ) {
+            (FormatterIn::F32(Some(unroll)), FormatterOut::F32(Some(pack))) => (unroll, pack),
+            _ => panic!("No premultiplied formatters for {:08x}", format.bits()),
+        };
+
+        let n_chans = format.channels() as usize;
+        let size = match format.bytes() {
+            1 => 1,
+            2 => 2,
+            _ => 4,
+        };
+        let alpha_pos = if format.doswap() ^ format.swapfirst() {
+            0
+        } else {
+            n_chans * size
+        };
+
+        for alpha in [0.0f32, 0.25, 1.0] {
+            // The alpha is expected to be in the output buffer already
+            let mut buffer = [0u8; 64];
+            match size {
+                1 => buffer[alpha_pos] = (alpha * 255.0) as u8,
+                2 => buffer[alpha_pos..alpha_pos + 2]
+                    .copy_from_slice(&((alpha * 65535.0) as u16).to_ne_bytes()),
+                _ => buffer[alpha_pos..alpha_pos + 4].copy_from_slice(&alpha.to_ne_bytes()),
+            }
+            let original = buffer;
+
+            let values = [0.5f32, 0.75, 1.0][..n_chans].to_vec();
+            pack(&info, &values, &mut buffer, 0);
+
+            // The alpha is left untouched
+            assert_eq!(
+                buffer[alpha_pos..alpha_pos + size],
+                original[alpha_pos..alpha_pos + size]
+            );
+
+            let mut result = [0f32; MAX_CHANNELS];
+            unroll(&info, &mut result, &buffer, 0);
+
+            for i in 0..n_chans {
+                let expected = if alpha == 0.0 { 0.0 } else { values[i] };
+                assert!(
+                    (expected - result[i]).abs() <= 0.02,
+                    "{:08x}: Channel {} with alpha {} should be {}, but is {}",
+                    format.bits(),
+                    i,
+                    alpha,
+                    expected,
+                    result[i]
+                );
+            }
+        }
+    }
+}
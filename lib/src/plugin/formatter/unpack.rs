@@ -0,0 +1,444 @@
+use crate::{
+    from_8_to_16, half_to_float, quick_saturate_word,
+    types::{Format, Lab, Transform, XYZ},
+};
+
+use super::{
+    channel_layout, is_ink_space, lab_v2_to_v4, pixel_advance, read_alpha, read_alpha_16, read_f32,
+    read_f64, read_u16, unpremultiply_16, unpremultiply_float, MAX_ENCODEABLE_XYZ,
+};
+
+// 16 bits
+
+pub(super) fn unroll_bytes<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let reverse = format.flavor();
+    let alpha = read_alpha_16(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = from_8_to_16(accum[pos]);
+        if let Some(alpha) = alpha {
+            v = unpremultiply_16(v, alpha);
+        }
+        values[index] = if reverse { 0xffff - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_words<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let reverse = format.flavor();
+    let swap_endian = format.endian16();
+    let alpha = read_alpha_16(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_u16(accum, pos);
+        if swap_endian {
+            v = v.swap_bytes();
+        }
+        if let Some(alpha) = alpha {
+            v = unpremultiply_16(v, alpha);
+        }
+        values[index] = if reverse { 0xffff - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_floats_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) {
+        655.35
+    } else {
+        65535.0
+    };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_f32(accum, pos) as f64;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = quick_saturate_word(v * maximum);
+        values[index] = if format.flavor() { 0xffff - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_doubles_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) {
+        655.35
+    } else {
+        65535.0
+    };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_f64(accum, pos);
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = quick_saturate_word(v * maximum);
+        values[index] = if format.flavor() { 0xffff - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+/// Reads a half float sample.
+fn read_half(format: Format, accum: &[u8], pos: usize) -> f32 {
+    let mut v = read_u16(accum, pos);
+    if format.endian16() {
+        v = v.swap_bytes();
+    }
+
+    half_to_float(v)
+}
+
+pub(super) fn unroll_half_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) {
+        655.35
+    } else {
+        65535.0
+    };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_half(format, accum, pos) as f64;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = quick_saturate_word(v * maximum);
+        values[index] = if format.flavor() { 0xffff - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+/// Reads the three components of a colorimetric pixel as doubles.
+fn read_pcs(info: &Transform, accum: &[u8], stride: u32, is_double: bool) -> [f64; 3] {
+    let mut pcs = [0f64; 3];
+
+    for (pos, index) in channel_layout(info.get_input_format(), stride) {
+        pcs[index] = if is_double {
+            read_f64(accum, pos)
+        } else {
+            read_f32(accum, pos) as f64
+        };
+    }
+
+    pcs
+}
+
+pub(super) fn unroll_lab_double_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let lab = read_pcs(info, accum, stride, true);
+    values[..3].copy_from_slice(&Lab::from(lab).encode());
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_lab_float_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let lab = read_pcs(info, accum, stride, false);
+    values[..3].copy_from_slice(&Lab::from(lab).encode());
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_xyz_double_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let xyz = read_pcs(info, accum, stride, true);
+    values[..3].copy_from_slice(&XYZ::from(xyz).encode());
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_xyz_float_to_16<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let xyz = read_pcs(info, accum, stride, false);
+    values[..3].copy_from_slice(&XYZ::from(xyz).encode());
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_lab_v2_bytes<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let accum = unroll_bytes(info, values, accum, stride);
+    for v in values[..3].iter_mut() {
+        *v = lab_v2_to_v4(*v);
+    }
+
+    accum
+}
+
+pub(super) fn unroll_lab_v2_words<'a>(
+    info: &Transform,
+    values: &mut [u16],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let accum = unroll_words(info, values, accum, stride);
+    for v in values[..3].iter_mut() {
+        *v = lab_v2_to_v4(*v);
+    }
+
+    accum
+}
+
+// Floating point
+
+pub(super) fn unroll_bytes_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = accum[pos] as f64 / 255.0;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = v as f32;
+        values[index] = if format.flavor() { 1.0 - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_words_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_u16(accum, pos);
+        if format.endian16() {
+            v = v.swap_bytes();
+        }
+
+        let mut v = v as f64 / 65535.0;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = v as f32;
+        values[index] = if format.flavor() { 1.0 - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_floats_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) { 100.0 } else { 1.0 };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_f32(accum, pos) as f64;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = (v / maximum) as f32;
+        values[index] = if format.flavor() { 1.0 - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_doubles_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) { 100.0 } else { 1.0 };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_f64(accum, pos);
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = (v / maximum) as f32;
+        values[index] = if format.flavor() { 1.0 - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+pub(super) fn unroll_half_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let format = info.get_input_format();
+    let maximum = if is_ink_space(format) { 100.0 } else { 1.0 };
+    let alpha = read_alpha(format, accum, stride);
+
+    for (pos, index) in channel_layout(format, stride) {
+        let mut v = read_half(format, accum, pos) as f64;
+        if let Some(alpha) = alpha {
+            v = unpremultiply_float(v, alpha);
+        }
+
+        let v = (v / maximum) as f32;
+        values[index] = if format.flavor() { 1.0 - v } else { v };
+    }
+
+    &accum[pixel_advance(format)..]
+}
+
+/// Normalizes a Lab value to the 0..1 range used by float pipelines.
+fn lab_to_float(values: &mut [f32], lab: [f64; 3]) {
+    values[0] = (lab[0] / 100.0) as f32;
+    values[1] = ((lab[1] + 128.0) / 255.0) as f32;
+    values[2] = ((lab[2] + 128.0) / 255.0) as f32;
+}
+
+fn xyz_to_float(values: &mut [f32], xyz: [f64; 3]) {
+    for (v, xyz) in values.iter_mut().zip(xyz) {
+        *v = (xyz / MAX_ENCODEABLE_XYZ) as f32;
+    }
+}
+
+pub(super) fn unroll_lab_double_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    lab_to_float(values, read_pcs(info, accum, stride, true));
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_lab_float_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    lab_to_float(values, read_pcs(info, accum, stride, false));
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_xyz_double_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    xyz_to_float(values, read_pcs(info, accum, stride, true));
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_xyz_float_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    xyz_to_float(values, read_pcs(info, accum, stride, false));
+
+    &accum[pixel_advance(info.get_input_format())..]
+}
+
+pub(super) fn unroll_lab_v2_bytes_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let mut lab = [0u16; 3];
+    let accum = unroll_lab_v2_bytes(info, &mut lab, accum, stride);
+    lab_to_float(values, Lab::decode(&lab).into());
+
+    accum
+}
+
+pub(super) fn unroll_lab_v2_words_to_float<'a>(
+    info: &Transform,
+    values: &mut [f32],
+    accum: &'a [u8],
+    stride: u32,
+) -> &'a [u8] {
+    let mut lab = [0u16; 3];
+    let accum = unroll_lab_v2_words(info, &mut lab, accum, stride);
+    lab_to_float(values, Lab::decode(&lab).into());
+
+    accum
+}
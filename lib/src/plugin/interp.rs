@@ -78,8 +78,9 @@ pub(crate) fn default_interpolators_factory(
 
 #[inline]
 fn linear_interp_u16(a: S15Fixed16Number, l: S15Fixed16Number, h: S15Fixed16Number) -> u16 {
-    let dif = (h - l) as u32 * a as u32 + 0x8000;
-    let dif = (dif >> 16) + l as u32;
+    // Descending intervals rely on unsigned wraparound
+    let dif = ((h - l) as u32).wrapping_mul(a as u32).wrapping_add(0x8000);
+    let dif = (dif >> 16).wrapping_add(l as u32);
     dif as u16
 }
 
@@ -204,7 +205,7 @@ fn eval_1_input_f32(value: &[f32], output: &mut [f32], p: &InterpParams<f32>) {
             let y0 = lut_table[cell0 as usize + out_chan];
             let y1 = lut_table[cell1 as usize + out_chan];
 
-            output[0] = linear_interp_f32(rest, y0, y1);
+            output[out_chan] = linear_interp_f32(rest, y0, y1);
         }
     }
 }
@@ -491,28 +492,13 @@ fn tetrahedral_interp_u16(input: &[u16], mut output: &mut [u16], p: &InterpParam
     let rz = fixed_rest_to_int(fz);
 
     let x0 = p.opta[2] as i32 * x0;
-    let mut x1 = (x0
-        + (if input[0] == 0xffff {
-            0
-        } else {
-            p.opta[2] as i32
-        })) as usize;
+    let mut x1 = if input[0] == 0xffff { 0 } else { p.opta[2] };
 
     let y0 = p.opta[1] as i32 * y0;
-    let mut y1 = (y0
-        + (if input[1] == 0xffff {
-            0
-        } else {
-            p.opta[1] as i32
-        })) as usize;
+    let mut y1 = if input[1] == 0xffff { 0 } else { p.opta[1] };
 
     let z0 = p.opta[0] as i32 * z0;
-    let mut z1 = (z0
-        + (if input[2] == 0xffff {
-            0
-        } else {
-            p.opta[0] as i32
-        })) as usize;
+    let mut z1 = if input[2] == 0xffff { 0 } else { p.opta[0] };
 
     let mut lut_table = &lut_table[((x0 + y0 + z0) as usize)..];
 
@@ -521,48 +507,60 @@ fn tetrahedral_interp_u16(input: &[u16], mut output: &mut [u16], p: &InterpParam
             y1 += x1;
             z1 += y1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c3 = c3 - c2;
                 let c2 = c2 - c1;
                 let c1 = c1 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         } else if rz >= rx {
             x1 += z1;
             y1 += x1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c2 = c2 - c1;
                 let c1 = c1 - c3;
                 let c3 = c3 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         } else {
             z1 += x1;
             y1 += z1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c2 = c2 - c3;
                 let c3 = c3 - c1;
                 let c1 = c1 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         }
@@ -571,48 +569,60 @@ fn tetrahedral_interp_u16(input: &[u16], mut output: &mut [u16], p: &InterpParam
             x1 += y1;
             z1 += x1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c3 = c3 - c1;
                 let c1 = c1 - c2;
                 let c2 = c2 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         } else if ry >= rz {
             z1 += y1;
             x1 += z1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c1 = c1 - c3;
                 let c3 = c3 - c2;
                 let c2 = c2 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         } else {
             y1 += z1;
             x1 += y1;
             for _ in 0..total_out {
-                let c1 = lut_table[x1];
-                let c2 = lut_table[y1];
-                let c3 = lut_table[z1];
-                let c0 = lut_table[0];
+                let c1 = lut_table[x1] as i32;
+                let c2 = lut_table[y1] as i32;
+                let c3 = lut_table[z1] as i32;
+                let c0 = lut_table[0] as i32;
                 lut_table = &lut_table[1..];
                 let c1 = c1 - c2;
                 let c2 = c2 - c3;
                 let c3 = c3 - c0;
-                let rest = rx.wrapping_mul(c1 as i32) + ry.wrapping_mul(c2 as i32) + rz.wrapping_mul(c3 as i32) + 0x8001;
-                output[0] = (c0 as i32 + ((rest + (rest >> 16)) >> 16)) as u16;
+                let rest = rx
+                    .wrapping_mul(c1)
+                    .wrapping_add(ry.wrapping_mul(c2))
+                    .wrapping_add(rz.wrapping_mul(c3))
+                    .wrapping_add(0x8001);
+                output[0] = c0.wrapping_add(rest.wrapping_add(rest >> 16) >> 16) as u16;
                 output = &mut output[1..];
             }
         }
@@ -633,7 +643,10 @@ macro_rules! lut_u16 {
 
                 let (c1, c2, c3) = _lut!(i32 => ($lut, out_chan, c0) => $({$($c_x_, $c_y_, $c_z_);*}),*);
 
-                let rest = c1 * $rx + c2 * $ry + c3 * $rz;
+                let rest = c1
+                    .wrapping_mul($rx)
+                    .wrapping_add(c2.wrapping_mul($ry))
+                    .wrapping_add(c3.wrapping_mul($rz));
                 $output[out_chan] = (c0 + round_fixed_to_int(to_fixed_domain(rest))) as u16;
             }
     };
@@ -1039,12 +1052,12 @@ macro_rules! eval_fns {
                 let rk = fixed_rest_to_int(fk);
 
                 let k0 = p16.opta[$nm] as i32 * k0;
-                let k1 = p16.opta[$nm] as i32 * (k0
+                let k1 = k0
                     + (if input[0] == 0xffff {
                         0
                     } else {
-                        1
-                    }));
+                        p16.opta[$nm] as i32
+                    });
 
                 let mut p1 = p16.clone();
                 p1.domain.copy_within(1..$n, 0);
@@ -1073,12 +1086,12 @@ macro_rules! eval_fns {
                 let rest = pk - k0 as f32;
 
                 let k0 = p.opta[$nm] as i32 * k0;
-                let k1 = p.opta[3] as i32 * (k0
+                let k1 = k0
                     + (if fclamp(input[0]) >= 1f32 {
                         0
                     } else {
-                        1
-                    }));
+                        p.opta[$nm] as i32
+                    });
 
                 let mut p1 = p.clone();
                 p1.domain.copy_within(1..$n, 0);
@@ -77,22 +77,22 @@ mod transform;
 
 pub use curves::{CurveDef, ParametricCurveEvaluator};
 pub use formatter::{
-    FormatterIn, FormatterIn16, FormatterInFactory, FormatterInFloat, FormatterOut, FormatterOut16,
-    FormatterOutFactory, FormatterOutFloat, FormatterPlugin,
+    pack_flags, FormatterIn, FormatterIn16, FormatterInFactory, FormatterInFloat, FormatterOut,
+    FormatterOut16, FormatterOutFactory, FormatterOutFloat, FormatterPlugin,
 };
-pub use interp::{InterpFnFactory, lerp_flags};
+pub use interp::{lerp_flags, InterpFnFactory};
 pub use optimization::OptimizationFn;
 pub use parallel::ParallelizationPlugin;
-pub use rendering_intent::IntentFn;
+pub use rendering_intent::{intent, link_profiles, IntentFn};
 pub use tag::TagDescriptor;
-pub use tag_type::{TagTypeHandler, TagTypePlugin};
+pub use tag_type::{TagTypeDupFn, TagTypeHandler, TagTypePlugin, TagTypeReadFn, TagTypeWriteFn};
 pub use transform::{
     Transform2Factory, Transform2FactoryResult, Transform2Fn, TransformFactory,
     TransformFactoryResult, TransformFn, TransformFunc, TransformPlugin,
 };
 
 pub(crate) use curves::DEFAULT_PARAMETRIC_CURVE;
-pub(crate) use formatter::DEFAULT_FORMATTER_FACTORIES;
+pub(crate) use formatter::{read_sample, sample_size, write_sample, DEFAULT_FORMATTER_FACTORIES};
 pub(crate) use interp::default_interpolators_factory;
 pub(crate) use optimization::DEFAULT_OPTIMIZATIONS;
 pub(crate) use rendering_intent::DEFAULT_INTENTS;
@@ -0,0 +1,399 @@
+use crate::{
+    quick_saturate_word, sig,
+    state::Context,
+    types::{reasonable_gridpoints_by_color_space, Pipeline, Profile, Stage, StageLoc, ToneCurve},
+    Result,
+};
+
+use super::{add_conversion, check_link_parameters, intent, link_icc_profiles, IDENTITY};
+
+/// Number of points of the curves relating the K of the input and the output.
+const K_TONE_POINTS: usize = 4096;
+
+/// Links CMYK to CMYK keeping pure K, CMY = 0, as K only ink. Every other color goes through
+/// the ICC intent the preserving intent is based on.
+#[allow(clippy::boxed_local)]
+pub(super) fn black_preserving_k_only_intents(
+    context_id: &Context,
+    n_profiles: usize,
+    intents: Box<[u32]>,
+    profiles: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    adaptation_states: Box<[f64]>,
+    flags: u32,
+) -> Result<Pipeline> {
+    check_link_parameters(
+        context_id,
+        n_profiles,
+        &intents,
+        &profiles,
+        &bpc,
+        &adaptation_states,
+    )?;
+
+    preserve_black(
+        context_id,
+        &intents[..n_profiles],
+        &profiles[..n_profiles],
+        &bpc[..n_profiles],
+        &adaptation_states[..n_profiles],
+        flags,
+        false,
+    )
+}
+
+/// Links CMYK to CMYK keeping the K plane: the output K is computed from the input K alone, and
+/// the CMY is searched to match the colorimetry of the ICC intent the preserving intent is based
+/// on. The total area coverage of the last profile is honored.
+#[allow(clippy::boxed_local)]
+pub(super) fn black_preserving_k_plane_intents(
+    context_id: &Context,
+    n_profiles: usize,
+    intents: Box<[u32]>,
+    profiles: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    adaptation_states: Box<[f64]>,
+    flags: u32,
+) -> Result<Pipeline> {
+    check_link_parameters(
+        context_id,
+        n_profiles,
+        &intents,
+        &profiles,
+        &bpc,
+        &adaptation_states,
+    )?;
+
+    preserve_black(
+        context_id,
+        &intents[..n_profiles],
+        &profiles[..n_profiles],
+        &bpc[..n_profiles],
+        &adaptation_states[..n_profiles],
+        flags,
+        true,
+    )
+}
+
+/// The ICC intent a black preserving intent is based on.
+fn translate_non_icc_intents(value: u32) -> u32 {
+    match value {
+        intent::PRESERVE_K_ONLY_PERCEPTUAL | intent::PRESERVE_K_PLANE_PERCEPTUAL => {
+            intent::PERCEPTUAL
+        }
+        intent::PRESERVE_K_ONLY_RELATIVE_COLORIMETRIC
+        | intent::PRESERVE_K_PLANE_RELATIVE_COLORIMETRIC => intent::RELATIVE_COLORIMETRIC,
+        intent::PRESERVE_K_ONLY_SATURATION | intent::PRESERVE_K_PLANE_SATURATION => {
+            intent::SATURATION
+        }
+        // Rest of intents pass through
+        _ => value,
+    }
+}
+
+/// The work behind both black preserving intents. The chain is sampled into a CMYK CLUT by the
+/// K only or the K plane sampler.
+fn preserve_black(
+    context_id: &Context,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+    flags: u32,
+    k_plane: bool,
+) -> Result<Pipeline> {
+    let icc_intents = intents
+        .iter()
+        .map(|value| translate_non_icc_intents(*value))
+        .collect::<Vec<_>>();
+
+    // Trim all CMYK devicelinks at the end, they are appended as is
+    let mut last = profiles.len() - 1;
+    while last > 1
+        && profiles[last].get_color_space() == sig::colorspace::CMYK
+        && profiles[last].get_device_class() == sig::class::LINK
+    {
+        last -= 1;
+    }
+    let n_preserving = last + 1;
+    let last_profile = &profiles[last];
+
+    // Check for non-cmyk profiles
+    if profiles[0].get_color_space() != sig::colorspace::CMYK
+        || !(last_profile.get_color_space() == sig::colorspace::CMYK
+            || last_profile.get_device_class() == sig::class::OUTPUT)
+    {
+        let (result, _) = link_icc_profiles(
+            context_id,
+            &icc_intents,
+            profiles,
+            bpc,
+            adaptation_states,
+            flags,
+        )?;
+
+        return Ok(result);
+    }
+
+    // The normal transform, used for every color not needing preservation
+    let (cmyk2cmyk, _) = link_icc_profiles(
+        context_id,
+        &icc_intents[..n_preserving],
+        &profiles[..n_preserving],
+        &bpc[..n_preserving],
+        &adaptation_states[..n_preserving],
+        flags,
+    )?;
+
+    // The relationship between the input and output K
+    let k_tone = build_k_tone_curve(
+        context_id,
+        K_TONE_POINTS,
+        &icc_intents[..n_preserving],
+        &profiles[..n_preserving],
+        &bpc[..n_preserving],
+        &adaptation_states[..n_preserving],
+        flags,
+    )?;
+
+    let n_grid_points = reasonable_gridpoints_by_color_space(sig::colorspace::CMYK, flags);
+    let mut clut = Stage::alloc_clut_16bit(context_id, n_grid_points, 4, 4, None)?;
+
+    // We cannot afford pre/post linearization this time
+    if k_plane {
+        sample_k_plane(context_id, &mut clut, &cmyk2cmyk, &k_tone, last_profile)?;
+    } else {
+        sample_k_only(&mut clut, &cmyk2cmyk, &k_tone)?;
+    }
+
+    // This is the one and only MPE in this LUT
+    let mut result = Pipeline::new(context_id, 4, 4)?;
+    result.insert_stage(StageLoc::AtBegin, clut)?;
+
+    // Insert possible devicelinks at the end
+    for (profile, value) in profiles.iter().zip(icc_intents.iter()).skip(n_preserving) {
+        result.cat(&profile.read_devicelink_lut(*value)?)?;
+    }
+
+    Ok(result)
+}
+
+/// Keeps pure K as K only, everything else goes through `cmyk2cmyk`.
+fn sample_k_only(clut: &mut Stage, cmyk2cmyk: &Pipeline, k_tone: &ToneCurve) -> Result<()> {
+    clut.sample_clut_16bit(
+        |r#in, out| {
+            // If going across black only, keep black only
+            if r#in[..3] == [0, 0, 0] {
+                // TAC does not apply because it is black ink!
+                out[..3].fill(0);
+                out[3] = k_tone.eval_u16(r#in[3]);
+            } else {
+                // Keep normal transform for other colors
+                cmyk2cmyk.eval_16(r#in, out);
+            }
+
+            true
+        },
+        0,
+    )
+}
+
+/// Keeps the K plane as the K tone curve says, looking for the CMY that reproduces the
+/// colorimetry of `cmyk2cmyk` in `last_profile`.
+fn sample_k_plane(
+    context_id: &Context,
+    clut: &mut Stage,
+    cmyk2cmyk: &Pipeline,
+    k_tone: &ToneCurve,
+    last_profile: &Profile,
+) -> Result<()> {
+    // The last profile is assumed to be responsible of the black generation, so its input
+    // table is searched in inverse order. Lab is in the 0..1 range.
+    let cmyk2lab = chain_to_lab(
+        context_id,
+        &[intent::RELATIVE_COLORIMETRIC],
+        std::slice::from_ref(last_profile),
+        &[false],
+        &[1.0],
+        0,
+    )?;
+
+    let max_tac = last_profile.detect_tac() / 100.0;
+
+    clut.sample_clut_16bit(
+        |r#in, out| {
+            // Convert from 16 bits to floating point
+            let mut inf = [0f32; 4];
+            for (inf, value) in inf.iter_mut().zip(r#in.iter()) {
+                *inf = *value as f32 / 65535.0;
+            }
+
+            // Get the K across the tone curve
+            let mut lab_k = [0f32; 4];
+            lab_k[3] = k_tone.eval_f32(inf[3]);
+
+            // If going across black only, keep black only
+            if r#in[..3] == [0, 0, 0] {
+                out[..3].fill(0);
+                out[3] = quick_saturate_word(lab_k[3] as f64 * 65535.0);
+                return true;
+            }
+
+            // Try the original transform
+            let mut outf = [0f32; 4];
+            cmyk2cmyk.eval_f32(&inf, &mut outf);
+
+            // Store a copy of the floating point result into 16-bit
+            for (out, outf) in out.iter_mut().zip(outf.iter()) {
+                *out = quick_saturate_word(*outf as f64 * 65535.0);
+            }
+
+            // Maybe K is already ok (mostly on K=0)
+            if (outf[3] - lab_k[3]).abs() < (3.0 / 65535.0) {
+                return true;
+            }
+
+            // K differs, so obtain the Lab of the output CMYK. After that we have Lab + K
+            cmyk2lab.eval_f32(&outf, &mut lab_k[..3]);
+
+            // Obtain the corresponding CMY using reverse interpolation, K is fixed in lab_k[3]
+            let hint = outf;
+            if cmyk2lab
+                .eval_reverse_f32(&lab_k, &mut outf, Some(&hint))
+                .is_err()
+            {
+                // Cannot find a suitable value, so use the colorimetric transform, which is
+                // already stored in out
+                return true;
+            }
+
+            // Make sure to pass through K (which now is fixed)
+            outf[3] = lab_k[3];
+
+            // Apply TAC if needed
+            let sum_cmy = outf[0] as f64 + outf[1] as f64 + outf[2] as f64;
+            let sum_cmyk = sum_cmy + outf[3] as f64;
+
+            let ratio = if sum_cmyk > max_tac {
+                (1.0 - ((sum_cmyk - max_tac) / sum_cmy)).max(0.0)
+            } else {
+                1.0
+            };
+
+            out[0] = quick_saturate_word(outf[0] as f64 * ratio * 65535.0);
+            out[1] = quick_saturate_word(outf[1] as f64 * ratio * 65535.0);
+            out[2] = quick_saturate_word(outf[2] as f64 * ratio * 65535.0);
+            out[3] = quick_saturate_word(outf[3] as f64 * 65535.0);
+
+            true
+        },
+        0,
+    )
+}
+
+/// Builds the curve relating the K of the first profile to the K of the last one, through
+/// their L*. Both ends have to be CMYK and the last one has to be an output profile.
+fn build_k_tone_curve(
+    context_id: &Context,
+    n_points: usize,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+    flags: u32,
+) -> Result<ToneCurve> {
+    let n = profiles.len();
+
+    // Make sure CMYK -> CMYK
+    if profiles[0].get_color_space() != sig::colorspace::CMYK
+        || profiles[n - 1].get_color_space() != sig::colorspace::CMYK
+    {
+        return err!(context_id, Error, ColorspaceCheck, "Black preserving intents need CMYK to CMYK"; str => "Black preserving intents need CMYK to CMYK");
+    }
+
+    // Make sure last is an output profile
+    if profiles[n - 1].get_device_class() != sig::class::OUTPUT {
+        return err!(context_id, Error, ColorspaceCheck, "Black preserving intents need an output profile last"; str => "Black preserving intents need an output profile last");
+    }
+
+    // Create individual curves. BPC works also as each K to L* is computed as a BPC curve
+    let r#in = compute_k_to_lstar(
+        context_id,
+        n_points,
+        &intents[..n - 1],
+        &profiles[..n - 1],
+        &bpc[..n - 1],
+        &adaptation_states[..n - 1],
+        flags,
+    )?;
+    let out = compute_k_to_lstar(
+        context_id,
+        n_points,
+        &intents[n - 1..],
+        &profiles[n - 1..],
+        &bpc[n - 1..],
+        &adaptation_states[n - 1..],
+        flags,
+    )?;
+
+    // Build the relationship. This effectively limits the maximum K to that of the output
+    // profile
+    let k_tone = ToneCurve::join(context_id, &r#in, &out, n_points)?;
+
+    // Make sure it is monotonic
+    if !k_tone.is_monotonic() {
+        return err!(context_id, Error, Range, "K tone curve is not monotonic"; str => "K tone curve is not monotonic");
+    }
+
+    Ok(k_tone)
+}
+
+/// Samples the L* of pure K through a CMYK chain. L* is negated, 1 - L*, so the curve goes up
+/// with K.
+fn compute_k_to_lstar(
+    context_id: &Context,
+    n_points: usize,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+    flags: u32,
+) -> Result<ToneCurve> {
+    let lut = chain_to_lab(context_id, intents, profiles, bpc, adaptation_states, flags)?;
+
+    let mut lab = [0f32; 3];
+    let sampled_points = (0..n_points)
+        .map(|i| {
+            let cmyk = [0.0, 0.0, 0.0, i as f32 / (n_points - 1) as f32];
+            lut.eval_f32(&cmyk, &mut lab);
+
+            1.0 - lab[0]
+        })
+        .collect::<Vec<_>>();
+
+    ToneCurve::build_tabulated_f32(context_id, &sampled_points)
+}
+
+/// Links the profiles, converting the result to Lab in the 0..1 range if it ends in the XYZ
+/// PCS.
+fn chain_to_lab(
+    context_id: &Context,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+    flags: u32,
+) -> Result<Pipeline> {
+    let (mut lut, color_space) =
+        link_icc_profiles(context_id, intents, profiles, bpc, adaptation_states, flags)?;
+
+    add_conversion(
+        &mut lut,
+        color_space,
+        sig::colorspace::LAB,
+        &IDENTITY,
+        &[0.0; 3],
+    )?;
+
+    Ok(lut)
+}
@@ -0,0 +1,501 @@
+use crate::{
+    sig,
+    state::{Context, Intent},
+    types::{
+        adaptation_matrix, cone_matrix, temp_from_white_point, white_point_from_temp, Mat3,
+        Pipeline, Profile, Signature, Stage, StageLoc, Vec3, XYZ,
+    },
+    Result, D50, MAX_ENCODEABLE_XYZ,
+};
+
+pub type IntentFn = fn(
+    context_id: &Context,
+    n_profiles: usize,
+    intents: Box<[u32]>,
+    profiles: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    adaptation_states: Box<[f64]>,
+    flags: u32,
+) -> Result<Pipeline>;
+
+/// Maximum number of profiles [`link_profiles`] accepts.
+const MAX_PROFILES: usize = 255;
+
+/// Links `profiles` into a single pipeline, going through the PCS between each of them. Each
+/// profile has its own intent, black point compensation and adaptation state.
+///
+/// The handler of the first intent links the whole chain. That prevents mixing custom intents
+/// in a chain, but a custom intent preserving something like primaries couldn't make sense of
+/// the other steps anyway.
+pub fn link_profiles(
+    context_id: &Context,
+    intents: Box<[u32]>,
+    profiles: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    adaptation_states: Box<[f64]>,
+    flags: u32,
+) -> Result<Pipeline> {
+    let n_profiles = profiles.len();
+
+    // Make sure a reasonable number of profiles is provided
+    if n_profiles == 0 || n_profiles > MAX_PROFILES {
+        return err!(context_id, Error, Range, "Couldn't link '{}' profiles", n_profiles; str => "Wrong number of profiles to link");
+    }
+    if intents.len() < n_profiles || bpc.len() < n_profiles || adaptation_states.len() < n_profiles
+    {
+        return err!(context_id, Error, Range, "Linking {} profiles needs as many intents, black point compensations and adaptation states", n_profiles; str => "Missing parameters to link profiles");
+    }
+
+    // BPC does not apply to devicelink profiles, nor to absolute colorimetric, and applies
+    // always on V4 perceptual and saturation
+    let mut bpc = bpc;
+    for ((value, profile), bpc) in intents.iter().zip(profiles.iter()).zip(bpc.iter_mut()) {
+        if *value == intent::ABSOLUTE_COLORIMETRIC {
+            *bpc = false;
+        }
+
+        if (*value == intent::PERCEPTUAL || *value == intent::SATURATION)
+            && profile.get_encoded_icc_version() >= 0x4000000
+        {
+            *bpc = true;
+        }
+    }
+
+    let handler = match context_id.get_intent(intents[0]) {
+        Some(handler) => handler.r#fn,
+        None => {
+            return err!(context_id, Error, Range, "Unsupported intent '{}'", intents[0]; str => "Unsupported intent")
+        }
+    };
+
+    handler(
+        context_id,
+        n_profiles,
+        intents,
+        profiles,
+        bpc,
+        adaptation_states,
+        flags,
+    )
+}
+
+/// Links the profiles as the ICC specifies: device LUTs through the PCS, converting between
+/// Lab and XYZ where the profiles disagree. Absolute colorimetric scales by the media white
+/// points.
+#[allow(clippy::boxed_local)]
+fn default_icc_intents(
+    context_id: &Context,
+    n_profiles: usize,
+    intents: Box<[u32]>,
+    profiles: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    adaptation_states: Box<[f64]>,
+    flags: u32,
+) -> Result<Pipeline> {
+    check_link_parameters(
+        context_id,
+        n_profiles,
+        &intents,
+        &profiles,
+        &bpc,
+        &adaptation_states,
+    )?;
+
+    let (result, _) = link_icc_profiles(
+        context_id,
+        &intents[..n_profiles],
+        &profiles[..n_profiles],
+        &bpc[..n_profiles],
+        &adaptation_states[..n_profiles],
+        flags,
+    )?;
+
+    Ok(result)
+}
+
+/// Makes sure there are at least `n_profiles` of every parameter an [`IntentFn`] gets.
+fn check_link_parameters(
+    context_id: &Context,
+    n_profiles: usize,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+) -> Result<()> {
+    // For safety
+    if n_profiles == 0 {
+        return err!(context_id, Error, Range, "Couldn't link '0' profiles"; str => "Wrong number of profiles to link");
+    }
+    if intents.len() < n_profiles
+        || profiles.len() < n_profiles
+        || bpc.len() < n_profiles
+        || adaptation_states.len() < n_profiles
+    {
+        return err!(context_id, Error, Range, "Linking {} profiles needs as many intents, black point compensations and adaptation states", n_profiles; str => "Missing parameters to link profiles");
+    }
+
+    Ok(())
+}
+
+/// The work behind [`default_icc_intents`], on every profile given. Also returns the color
+/// space the resulting pipeline ends in.
+fn link_icc_profiles(
+    context_id: &Context,
+    intents: &[u32],
+    profiles: &[Profile],
+    bpc: &[bool],
+    adaptation_states: &[f64],
+    _flags: u32,
+) -> Result<(Pipeline, Signature)> {
+    let n_profiles = profiles.len();
+
+    // 0 as channel count means 'undefined'
+    let mut result = Pipeline::new(context_id, 0, 0)?;
+    let mut current_color_space = profiles[0].get_color_space();
+
+    for i in 0..n_profiles {
+        let profile = &profiles[i];
+        let class = profile.get_device_class();
+        let is_device_link = class == sig::class::LINK || class == sig::class::ABSTRACT;
+
+        // First profile is used as input unless devicelink or abstract. Else use profile in the
+        // input direction if current space is not PCS
+        let is_input = if i == 0 && !is_device_link {
+            true
+        } else {
+            current_color_space != sig::colorspace::XYZ
+                && current_color_space != sig::colorspace::LAB
+        };
+
+        let intent = intents[i];
+
+        let (color_space_in, color_space_out) = if is_input || is_device_link {
+            (profile.get_color_space(), profile.get_pcs())
+        } else {
+            (profile.get_pcs(), profile.get_color_space())
+        };
+
+        if !color_space_is_compatible(color_space_in, current_color_space) {
+            return err!(context_id, Error, ColorspaceCheck, "ColorSpace mismatch"; str => "ColorSpace mismatch");
+        }
+
+        let lut = if is_device_link {
+            // Settings don't apply to device links, only to abstract profiles after the first
+            let lut = profile.read_devicelink_lut(intent)?;
+
+            let (m, off) = if class == sig::class::ABSTRACT && i > 0 {
+                compute_conversion(i, profiles, intent, bpc[i], adaptation_states[i])?
+            } else {
+                (IDENTITY, [0.0; 3])
+            };
+
+            add_conversion(&mut result, current_color_space, color_space_in, &m, &off)?;
+            lut
+        } else if is_input {
+            // Input direction means non-pcs connection, so proceed like devicelinks
+            profile.read_input_lut(intent)?
+        } else {
+            // Output direction means PCS connection. Intent may apply here
+            let lut = profile.read_output_lut(intent)?;
+
+            let (m, off) = compute_conversion(i, profiles, intent, bpc[i], adaptation_states[i])?;
+            add_conversion(&mut result, current_color_space, color_space_in, &m, &off)?;
+            lut
+        };
+
+        result.cat(&lut)?;
+        current_color_space = color_space_out;
+    }
+
+    Ok((result, current_color_space))
+}
+
+#[rustfmt::skip]
+const IDENTITY: [f64; 9] = [
+    1.0, 0.0, 0.0,
+    0.0, 1.0, 0.0,
+    0.0, 0.0, 1.0,
+];
+
+/// The matrix and offset to apply on the XYZ PCS between `profiles[i - 1]` and `profiles[i]`.
+fn compute_conversion(
+    i: usize,
+    profiles: &[Profile],
+    intent: u32,
+    bpc: bool,
+    adaptation_state: f64,
+) -> Result<([f64; 9], [f64; 3])> {
+    let mut m = IDENTITY;
+    let mut off = [0.0; 3];
+
+    if intent == intent::ABSOLUTE_COLORIMETRIC {
+        let white_point_in = profiles[i - 1].read_media_white_point();
+        let chad_in = profiles[i - 1].read_chad()?;
+        let white_point_out = profiles[i].read_media_white_point();
+        let chad_out = profiles[i].read_chad()?;
+
+        m = match compute_absolute_intent(
+            adaptation_state,
+            &white_point_in,
+            &chad_in,
+            &white_point_out,
+            &chad_out,
+        ) {
+            Some(m) => m.to_array(),
+            None => {
+                return err!(profiles[i].context_id(), Error, Range, "Unable to compute the absolute colorimetric adaptation"; str => "Unable to compute the absolute colorimetric adaptation")
+            }
+        };
+    } else if bpc {
+        // Rest of intents may apply BPC. Undetectable black points are taken as zero
+        let black_point_in = profiles[i - 1]
+            .detect_black_point(intent, 0)
+            .unwrap_or_default();
+        let black_point_out = profiles[i]
+            .detect_destination_black_point(intent, 0)
+            .unwrap_or_default();
+
+        // If black points are equal, then do nothing
+        if black_point_in != black_point_out {
+            (m, off) = compute_black_point_compensation(&black_point_in, &black_point_out);
+        }
+    }
+
+    // XYZ is encoded normalized to 0..1 by dividing by MAX_ENCODEABLE_XYZ, so the offset has to
+    // be in that encoding as well
+    Ok((m, off.map(|off| off / MAX_ENCODEABLE_XYZ)))
+}
+
+/// The absolute colorimetric conversion between two media whites, for an observer adapted by
+/// `adaptation_state`. A fully adapted observer only needs the media whites matched, a non
+/// adapted one has the chromatic adaptations of both profiles undone, and anything in between
+/// gets an adaptation to D50 from the mix of both illuminant temperatures.
+fn compute_absolute_intent(
+    adaptation_state: f64,
+    white_point_in: &XYZ,
+    chad_in: &Mat3,
+    white_point_out: &XYZ,
+    chad_out: &Mat3,
+) -> Option<Mat3> {
+    let scale = Mat3::diagonal(
+        white_point_in.x / white_point_out.x,
+        white_point_in.y / white_point_out.y,
+        white_point_in.z / white_point_out.z,
+    );
+
+    if adaptation_state == 1.0 {
+        // Observer is fully adapted. Keep chromatic adaptation. That is the standard V4
+        // behaviour
+        return Some(scale);
+    }
+
+    if adaptation_state == 0.0 {
+        // Observer is not adapted, undo the chromatic adaptation
+        return Some(*chad_out * scale * chad_in.inverse()?);
+    }
+
+    // m1 holds CHAD from input white to D50 times abs. col. scaling
+    let m1 = chad_in.inverse()? * scale;
+
+    let temp_src = chad_to_temp(chad_in)?;
+    let temp_dest = chad_to_temp(chad_out)?;
+
+    if scale.is_identity() && (temp_src - temp_dest).abs() < 0.01 {
+        return Some(Mat3::IDENTITY);
+    }
+
+    let temp = (1.0 - adaptation_state) * temp_dest + adaptation_state * temp_src;
+
+    // Get a CHAD from whatever output temperature to D50. This replaces output CHAD
+    let white = XYZ::from(white_point_from_temp(temp).ok()?);
+    let mixed_chad = adaptation_matrix(&cone_matrix::BRADFORD, &white, &D50).ok()?;
+
+    Some(m1 * mixed_chad)
+}
+
+/// The temperature of the illuminant `chad` adapts from.
+fn chad_to_temp(chad: &Mat3) -> Option<f64> {
+    let white = XYZ::from(chad.inverse()? * Vec3::from(D50));
+
+    temp_from_white_point(&white.into()).ok()
+}
+
+/// The scaling taking `black_point_in` to `black_point_out` while keeping D50 in place. Each
+/// channel is scaled as `a * x + b`, where
+///
+/// `a = (bp_out - D50) / (bp_in - D50)`
+///
+/// `b = -D50 * (bp_out - bp_in) / (bp_in - D50)`
+fn compute_black_point_compensation(
+    black_point_in: &XYZ,
+    black_point_out: &XYZ,
+) -> ([f64; 9], [f64; 3]) {
+    let tx = black_point_in.x - D50.x;
+    let ty = black_point_in.y - D50.y;
+    let tz = black_point_in.z - D50.z;
+
+    let ax = (black_point_out.x - D50.x) / tx;
+    let ay = (black_point_out.y - D50.y) / ty;
+    let az = (black_point_out.z - D50.z) / tz;
+
+    let bx = -D50.x * (black_point_out.x - black_point_in.x) / tx;
+    let by = -D50.y * (black_point_out.y - black_point_in.y) / ty;
+    let bz = -D50.z * (black_point_out.z - black_point_in.z) / tz;
+
+    #[rustfmt::skip]
+    let m = [
+        ax, 0.0, 0.0,
+        0.0, ay, 0.0,
+        0.0, 0.0, az,
+    ];
+
+    (m, [bx, by, bz])
+}
+
+/// Adds the stages going from `in_pcs` to `out_pcs` through the conversion `m` and `off`.
+fn add_conversion(
+    result: &mut Pipeline,
+    in_pcs: Signature,
+    out_pcs: Signature,
+    m: &[f64; 9],
+    off: &[f64; 3],
+) -> Result<()> {
+    let ctx = result.context_id().clone();
+    let matrix = || Stage::alloc_matrix(&ctx, 3, 3, m, Some(off));
+
+    match (in_pcs, out_pcs) {
+        (sig::colorspace::XYZ, sig::colorspace::XYZ) => {
+            if !is_empty_layer(m, off) {
+                result.insert_stage(StageLoc::AtEnd, matrix()?)?;
+            }
+        }
+        (sig::colorspace::XYZ, sig::colorspace::LAB) => {
+            if !is_empty_layer(m, off) {
+                result.insert_stage(StageLoc::AtEnd, matrix()?)?;
+            }
+            result.insert_stage(StageLoc::AtEnd, Stage::alloc_xyz_to_lab(&ctx)?)?;
+        }
+        (sig::colorspace::LAB, sig::colorspace::XYZ) => {
+            result.insert_stage(StageLoc::AtEnd, Stage::alloc_lab_to_xyz(&ctx)?)?;
+            if !is_empty_layer(m, off) {
+                result.insert_stage(StageLoc::AtEnd, matrix()?)?;
+            }
+        }
+        (sig::colorspace::LAB, sig::colorspace::LAB) => {
+            if !is_empty_layer(m, off) {
+                result.insert_stage(StageLoc::AtEnd, Stage::alloc_lab_to_xyz(&ctx)?)?;
+                result.insert_stage(StageLoc::AtEnd, matrix()?)?;
+                result.insert_stage(StageLoc::AtEnd, Stage::alloc_xyz_to_lab(&ctx)?)?;
+            }
+        }
+        // On colorspaces other than PCS, check for same space
+        (sig::colorspace::XYZ | sig::colorspace::LAB, _) => {
+            return err!(ctx, Error, ColorspaceCheck, "ColorSpace mismatch"; str => "ColorSpace mismatch")
+        }
+        _ if in_pcs != out_pcs => {
+            return err!(ctx, Error, ColorspaceCheck, "ColorSpace mismatch"; str => "ColorSpace mismatch")
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Whether the conversion is close enough to identity to be skipped.
+fn is_empty_layer(m: &[f64; 9], off: &[f64; 3]) -> bool {
+    let diff = m
+        .iter()
+        .zip(IDENTITY.iter())
+        .map(|(m, i)| (m - i).abs())
+        .chain(off.iter().map(|off| off.abs()))
+        .sum::<f64>();
+
+    diff < 0.002
+}
+
+/// Lab and XYZ can be computed from each other, and CMYK may go by its 4 color name.
+fn color_space_is_compatible(a: Signature, b: Signature) -> bool {
+    use sig::colorspace::{CMYK, COLOR4, LAB, XYZ};
+
+    a == b
+        || matches!(
+            (a, b),
+            (COLOR4, CMYK) | (CMYK, COLOR4) | (XYZ, LAB) | (LAB, XYZ)
+        )
+}
+
+pub(crate) const DEFAULT_INTENTS: &[Intent] = &[
+    Intent {
+        value: intent::PERCEPTUAL,
+        desc: "Perceptual",
+        r#fn: default_icc_intents,
+    },
+    Intent {
+        value: intent::RELATIVE_COLORIMETRIC,
+        desc: "Relative colorimetric",
+        r#fn: default_icc_intents,
+    },
+    Intent {
+        value: intent::SATURATION,
+        desc: "Saturation",
+        r#fn: default_icc_intents,
+    },
+    Intent {
+        value: intent::ABSOLUTE_COLORIMETRIC,
+        desc: "Absolute colorimetric",
+        r#fn: default_icc_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_ONLY_PERCEPTUAL,
+        desc: "Perceptual preserving black ink",
+        r#fn: black_preserving_k_only_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_ONLY_RELATIVE_COLORIMETRIC,
+        desc: "Relative colorimetric preserving black ink",
+        r#fn: black_preserving_k_only_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_ONLY_SATURATION,
+        desc: "Saturation preserving black ink",
+        r#fn: black_preserving_k_only_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_PLANE_PERCEPTUAL,
+        desc: "Perceptual preserving black plane",
+        r#fn: black_preserving_k_plane_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_PLANE_RELATIVE_COLORIMETRIC,
+        desc: "Relative colorimetric preserving black plane",
+        r#fn: black_preserving_k_plane_intents,
+    },
+    Intent {
+        value: intent::PRESERVE_K_PLANE_SATURATION,
+        desc: "Saturation preserving black plane",
+        r#fn: black_preserving_k_plane_intents,
+    },
+];
+
+/// The rendering intents defined by the ICC, followed by the built-in ones preserving black on
+/// CMYK to CMYK transforms.
+pub mod intent {
+    pub const PERCEPTUAL: u32 = 0;
+    pub const RELATIVE_COLORIMETRIC: u32 = 1;
+    pub const SATURATION: u32 = 2;
+    pub const ABSOLUTE_COLORIMETRIC: u32 = 3;
+
+    /// Pure K stays K only ink.
+    pub const PRESERVE_K_ONLY_PERCEPTUAL: u32 = 10;
+    pub const PRESERVE_K_ONLY_RELATIVE_COLORIMETRIC: u32 = 11;
+    pub const PRESERVE_K_ONLY_SATURATION: u32 = 12;
+
+    /// The whole K plane is kept, and CMY is adjusted to match the colorimetry.
+    pub const PRESERVE_K_PLANE_PERCEPTUAL: u32 = 13;
+    pub const PRESERVE_K_PLANE_RELATIVE_COLORIMETRIC: u32 = 14;
+    pub const PRESERVE_K_PLANE_SATURATION: u32 = 15;
+}
+
+mod black_preserving;
+
+use black_preserving::{black_preserving_k_only_intents, black_preserving_k_plane_intents};
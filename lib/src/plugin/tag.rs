@@ -1,6 +1,11 @@
 use std::any::Any;
 
-use crate::{state::Tag, types::Signature, MAX_TYPES_IN_PLUGIN};
+use crate::{
+    sig,
+    state::Tag,
+    types::{Signature, ToneCurve},
+    MAX_TYPES_IN_PLUGIN,
+};
 
 use super::Plugin;
 
@@ -11,4 +16,179 @@ pub struct TagDescriptor {
     pub supported_types: &'static [Signature],
 }
 
-pub(crate) const DEFAULT_TAGS: &[Tag] = &[];
+const TRC_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: Some(decide_curve_type),
+    supported_types: &[sig::types::CURVE, sig::types::PARAMETRIC_CURVE],
+};
+
+const XYZ_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: None,
+    supported_types: &[sig::types::XYZ],
+};
+
+const CHAD_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 9,
+    decide_type: None,
+    supported_types: &[sig::types::S15_FIXED16_ARRAY],
+};
+
+const VIEWING_CONDITIONS_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: None,
+    supported_types: &[sig::types::VIEWING_CONDITIONS],
+};
+
+const A_TO_B_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: Some(decide_lut_type_a_to_b),
+    supported_types: &[sig::types::LUT16, sig::types::LUT_A_TO_B, sig::types::LUT8],
+};
+
+const B_TO_A_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: Some(decide_lut_type_b_to_a),
+    supported_types: &[sig::types::LUT16, sig::types::LUT_B_TO_A, sig::types::LUT8],
+};
+
+const MPE_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: None,
+    supported_types: &[sig::types::MULTI_PROCESS_ELEMENT],
+};
+
+pub(crate) const DEFAULT_TAGS: &[Tag] = &[
+    Tag {
+        sig: sig::tags::A_TO_B0,
+        desc: &A_TO_B_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::A_TO_B1,
+        desc: &A_TO_B_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::A_TO_B2,
+        desc: &A_TO_B_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_A0,
+        desc: &B_TO_A_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_A1,
+        desc: &B_TO_A_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_A2,
+        desc: &B_TO_A_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::D_TO_B0,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::D_TO_B1,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::D_TO_B2,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::D_TO_B3,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_D0,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_D1,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_D2,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::B_TO_D3,
+        desc: &MPE_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::RED_COLORANT,
+        desc: &XYZ_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::GREEN_COLORANT,
+        desc: &XYZ_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::BLUE_COLORANT,
+        desc: &XYZ_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::MEDIA_WHITE_POINT,
+        desc: &XYZ_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::CHROMATIC_ADAPTATION,
+        desc: &CHAD_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::RED_TRC,
+        desc: &TRC_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::GREEN_TRC,
+        desc: &TRC_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::BLUE_TRC,
+        desc: &TRC_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::GRAY_TRC,
+        desc: &TRC_DESCRIPTOR,
+    },
+    Tag {
+        sig: sig::tags::VIEWING_CONDITIONS,
+        desc: &VIEWING_CONDITIONS_DESCRIPTOR,
+    },
+];
+
+/// Only v4 profiles may use parametric curves, and only for the single segment, non inverted
+/// ICC functions.
+fn decide_curve_type(icc_version: f64, data: &Box<dyn Any>) -> Signature {
+    let curve = match data.downcast_ref::<ToneCurve>() {
+        Some(curve) => curve,
+        None => return sig::types::CURVE,
+    };
+
+    if icc_version < 4.0 {
+        return sig::types::CURVE;
+    }
+    if !(1..=5).contains(&curve.get_parametric_type()) {
+        return sig::types::CURVE;
+    }
+
+    sig::types::PARAMETRIC_CURVE
+}
+
+/// v2 profiles only know about 16 bits LUTs.
+fn decide_lut_type_a_to_b(icc_version: f64, _data: &Box<dyn Any>) -> Signature {
+    if icc_version < 4.0 {
+        sig::types::LUT16
+    } else {
+        sig::types::LUT_A_TO_B
+    }
+}
+
+/// v2 profiles only know about 16 bits LUTs.
+fn decide_lut_type_b_to_a(icc_version: f64, _data: &Box<dyn Any>) -> Signature {
+    if icc_version < 4.0 {
+        sig::types::LUT16
+    } else {
+        sig::types::LUT_B_TO_A
+    }
+}
@@ -0,0 +1,81 @@
+use std::any::Any;
+
+use crate::{
+    f64_to_u8_fixed8_number, io::IoHandler, sig, types::ToneCurve, u8_fixed8_number_to_f64, Result,
+};
+
+use super::TagTypeHandler;
+
+/// Tables longer than this are rejected as corrupt.
+const MAX_ENTRIES: usize = 0x7FFF;
+
+pub(crate) const CURVE_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::CURVE,
+    read: read_curve,
+    write: write_curve,
+    dup: dup_curve,
+};
+
+fn read_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+    let count = io.read_u32().map_err(|_| "Read error")? as usize;
+
+    let curve = match count {
+        // Linear
+        0 => ToneCurve::build_gamma(&ctx, 1.0)?,
+        // Specified as the exponent of a gamma function
+        1 => {
+            let gamma = u8_fixed8_number_to_f64(io.read_u16().map_err(|_| "Read error")?);
+            ToneCurve::build_gamma(&ctx, gamma)?
+        }
+        _ => {
+            if count > MAX_ENTRIES {
+                return err!(ctx, Error, CorruptionDetected, "Too many entries in curve: {}", count; str => "Too many entries in curve");
+            }
+
+            let mut values = vec![0u16; count];
+            io.read_u16_slice(&mut values).map_err(|_| "Read error")?;
+            ToneCurve::build_tabulated_16(&ctx, &values)?
+        }
+    };
+
+    *n_items = 1;
+    Ok(Box::new(curve))
+}
+
+fn write_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let curve = match data.downcast_ref::<ToneCurve>() {
+        Some(curve) => curve,
+        None => return Err("Wrong data type"),
+    };
+
+    // Single gamma, preserve the number
+    if curve.get_parametric_type() == 1 {
+        let gamma = f64_to_u8_fixed8_number(curve.segments()[0].params[0]);
+
+        io.write_u32(1).map_err(|_| "Write error")?;
+        return io.write_u16(gamma).map_err(|_| "Write error");
+    }
+
+    let table = curve.get_estimated_table();
+    io.write_u32(table.len() as u32)
+        .map_err(|_| "Write error")?;
+    io.write_u16_slice(table).map_err(|_| "Write error")
+}
+
+fn dup_curve(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<ToneCurve>() {
+        Some(curve) => Ok(Box::new(curve.clone())),
+        None => Err("Wrong data type"),
+    }
+}
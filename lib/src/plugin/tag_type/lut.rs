@@ -0,0 +1,457 @@
+use std::any::Any;
+
+use crate::{
+    from_16_to_8, from_8_to_16,
+    io::IoHandler,
+    quantize_val, sig,
+    state::Context,
+    types::{
+        cube_size, Mat3, Pipeline, Stage, StageCLutData, StageLoc, StageMatrixData, ToneCurve,
+    },
+    Result, MAX_CHANNELS,
+};
+
+use super::{check_table_size, TagTypeHandler};
+
+/// Tables longer than this are rejected as corrupt.
+const MAX_ENTRIES: usize = 0x7FFF;
+
+pub(crate) const LUT8_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::LUT8,
+    read: read_lut8,
+    write: write_lut8,
+    dup: dup_lut,
+};
+
+pub(crate) const LUT16_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::LUT16,
+    read: read_lut16,
+    write: write_lut16,
+    dup: dup_lut,
+};
+
+/// The stages a lut8 or lut16 can hold, in evaluation order. Any of them may be missing.
+struct LutStages<'a> {
+    matrix: Option<&'a StageMatrixData>,
+    pre: Option<&'a [ToneCurve]>,
+    clut: Option<&'a StageCLutData<u16>>,
+    post: Option<&'a [ToneCurve]>,
+}
+
+/// The channel counts and grid points of a lut8 or lut16. The pipeline counts follow the last
+/// stage inserted, so the tables have to be read with these.
+struct LutHeader {
+    in_chans: usize,
+    out_chans: usize,
+    clut_points: usize,
+}
+
+/// Reads the header shared by both LUT types and creates the pipeline, with the matrix stage if
+/// it does something.
+fn read_lut_header(io: &mut dyn IoHandler) -> Result<(Pipeline, LutHeader)> {
+    let ctx = io.context_id().clone();
+
+    let in_chans = io.read_u8().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u8().map_err(|_| "Read error")? as usize;
+    let clut_points = io.read_u8().map_err(|_| "Read error")? as usize;
+    io.read_u8().map_err(|_| "Read error")?; // Padding
+
+    if clut_points == 1 {
+        // Impossible value, 0 for no CLUT and then 2 at least
+        return err!(ctx, Error, CorruptionDetected, "Wrong number of CLUT points: 1"; str => "Wrong number of CLUT points");
+    }
+    if in_chans == 0 || in_chans > MAX_CHANNELS || out_chans == 0 || out_chans > MAX_CHANNELS {
+        return err!(ctx, Error, CorruptionDetected, "Wrong number of LUT channels ({} in, {} out)", in_chans, out_chans; str => "Wrong number of LUT channels");
+    }
+
+    let mut matrix = [0f64; 9];
+    for value in matrix.iter_mut() {
+        *value = io.read_s15_fixed16_number().map_err(|_| "Read error")?;
+    }
+
+    let mut lut = Pipeline::new(&ctx, in_chans, out_chans)?;
+
+    // Only operates if not identity...
+    let mat3 = Mat3::new([
+        [matrix[0], matrix[1], matrix[2]],
+        [matrix[3], matrix[4], matrix[5]],
+        [matrix[6], matrix[7], matrix[8]],
+    ]);
+    if in_chans == 3 && !mat3.is_identity() {
+        lut.insert_stage(
+            StageLoc::AtEnd,
+            Stage::alloc_matrix(&ctx, 3, 3, &matrix, None)?,
+        )?;
+    }
+
+    Ok((
+        lut,
+        LutHeader {
+            in_chans,
+            out_chans,
+            clut_points,
+        },
+    ))
+}
+
+/// Adds a CLUT stage from the table as read, unless there is no CLUT at all.
+fn add_clut(lut: &mut Pipeline, header: &LutHeader, table: &[u16]) -> Result<()> {
+    if table.is_empty() {
+        return Ok(());
+    }
+
+    let clut = Stage::alloc_clut_16bit(
+        lut.context_id(),
+        header.clut_points,
+        header.in_chans,
+        header.out_chans,
+        Some(table),
+    )?;
+    lut.insert_stage(StageLoc::AtEnd, clut)
+}
+
+/// Number of values in the CLUT, which has to fit before `end`, the end of the tag. Zero if there
+/// is no CLUT.
+fn read_clut_size(
+    io: &mut dyn IoHandler,
+    header: &LutHeader,
+    value_size: usize,
+    end: usize,
+) -> Result<usize> {
+    if header.clut_points == 0 {
+        return Ok(0);
+    }
+
+    let n_values = cube_size(&[header.clut_points; MAX_CHANNELS], header.in_chans)
+        .and_then(|n| n.checked_mul(header.out_chans));
+    check_table_size(io, n_values, value_size, end)
+}
+
+fn read_lut8(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let end = io.tell().map_err(|_| "Read error")? + tag_size;
+    let (mut lut, header) = read_lut_header(io)?;
+
+    // Get input tables
+    let pre = read_8bit_tables(io, header.in_chans)?;
+    lut.insert_stage(StageLoc::AtEnd, pre)?;
+
+    // Get 3D CLUT
+    let size = read_clut_size(io, &header, 1, end)?;
+    let mut temp = vec![0u8; size];
+    if size > 0 && io.read(&mut temp, size, 1).map_err(|_| "Read error")? != 1 {
+        return Err("Read error");
+    }
+    let table = temp.iter().map(|v| from_8_to_16(*v)).collect::<Vec<_>>();
+    add_clut(&mut lut, &header, &table)?;
+
+    // Get output tables
+    let post = read_8bit_tables(io, header.out_chans)?;
+    lut.insert_stage(StageLoc::AtEnd, post)?;
+
+    *n_items = 1;
+    Ok(Box::new(lut))
+}
+
+fn write_lut8(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let lut = match data.downcast_ref::<Pipeline>() {
+        Some(lut) => lut,
+        None => return Err("Wrong data type"),
+    };
+    let stages = split_lut(lut, "LUT8")?;
+
+    write_lut_header(io, lut, &stages)?;
+
+    // Prelinearization table
+    write_8bit_tables(io, lut.get_input_channels(), stages.pre)?;
+
+    // The 3D CLUT
+    if let Some(clut) = stages.clut {
+        for value in clut.tab.iter() {
+            io.write_u8(from_16_to_8(*value))
+                .map_err(|_| "Write error")?;
+        }
+    }
+
+    // The postlinearization table
+    write_8bit_tables(io, lut.get_output_channels(), stages.post)
+}
+
+fn read_lut16(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+    let end = io.tell().map_err(|_| "Read error")? + tag_size;
+    let (mut lut, header) = read_lut_header(io)?;
+
+    let in_entries = io.read_u16().map_err(|_| "Read error")? as usize;
+    let out_entries = io.read_u16().map_err(|_| "Read error")? as usize;
+
+    if in_entries > MAX_ENTRIES || out_entries > MAX_ENTRIES {
+        return err!(ctx, Error, CorruptionDetected, "Too many entries in LUT tables"; str => "Too many entries in LUT tables");
+    }
+
+    // Get input tables
+    if let Some(pre) = read_16bit_tables(io, header.in_chans, in_entries)? {
+        lut.insert_stage(StageLoc::AtEnd, pre)?;
+    }
+
+    // Get 3D CLUT
+    let size = read_clut_size(io, &header, 2, end)?;
+    let mut table = vec![0u16; size];
+    io.read_u16_slice(&mut table).map_err(|_| "Read error")?;
+    add_clut(&mut lut, &header, &table)?;
+
+    // Get output tables
+    if let Some(post) = read_16bit_tables(io, header.out_chans, out_entries)? {
+        lut.insert_stage(StageLoc::AtEnd, post)?;
+    }
+
+    *n_items = 1;
+    Ok(Box::new(lut))
+}
+
+fn write_lut16(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let lut = match data.downcast_ref::<Pipeline>() {
+        Some(lut) => lut,
+        None => return Err("Wrong data type"),
+    };
+    let stages = split_lut(lut, "LUT16")?;
+
+    write_lut_header(io, lut, &stages)?;
+
+    let pre = tables_16bit(lut.context_id(), lut.get_input_channels(), stages.pre)?;
+    let post = tables_16bit(lut.context_id(), lut.get_output_channels(), stages.post)?;
+
+    io.write_u16((pre.len() / lut.get_input_channels()) as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16((post.len() / lut.get_output_channels()) as u16)
+        .map_err(|_| "Write error")?;
+
+    // Prelinearization table
+    io.write_u16_slice(&pre).map_err(|_| "Write error")?;
+
+    // The 3D CLUT
+    if let Some(clut) = stages.clut {
+        io.write_u16_slice(&clut.tab).map_err(|_| "Write error")?;
+    }
+
+    // The postlinearization table
+    io.write_u16_slice(&post).map_err(|_| "Write error")
+}
+
+fn dup_lut(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<Pipeline>() {
+        Some(lut) => Ok(Box::new(lut.duplicate())),
+        None => Err("Wrong data type"),
+    }
+}
+
+/// Disassembles `lut` into the stages a lut8 or lut16 can hold, failing on anything else.
+fn split_lut<'a>(lut: &'a Pipeline, name: &str) -> Result<LutStages<'a>> {
+    let ctx = lut.context_id();
+    let mut stages = lut.stages().peekable();
+    let mut result = LutStages {
+        matrix: None,
+        pre: None,
+        clut: None,
+        post: None,
+    };
+
+    if let Some(stage) = stages.next_if(|stage| stage.get_type() == sig::mpe_stage::MATRIX) {
+        if stage.get_input_channels() != 3 || stage.get_output_channels() != 3 {
+            return err!(ctx, Error, UnknownExtension, "Only 3x3 matrices can be saved as {}", name; str => "LUT is not suitable to be saved");
+        }
+
+        let matrix = stage.get_data().downcast_ref::<StageMatrixData>();
+        if matrix.is_some_and(|matrix| {
+            matrix
+                .offset
+                .as_ref()
+                .is_some_and(|offset| offset.iter().any(|v| *v != 0.0))
+        }) {
+            return err!(ctx, Error, UnknownExtension, "Matrix offsets can't be saved as {}", name; str => "LUT is not suitable to be saved");
+        }
+        result.matrix = matrix;
+    }
+    if let Some(stage) = stages.next_if(|stage| stage.get_type() == sig::mpe_stage::CURVE_SET) {
+        result.pre = stage.get_curves();
+    }
+    if let Some(stage) = stages.next_if(|stage| stage.get_type() == sig::mpe_stage::CLUT) {
+        let clut = match stage.get_data().downcast_ref::<StageCLutData<u16>>() {
+            Some(clut) => clut,
+            None => {
+                return err!(ctx, Error, UnknownExtension, "Only 16 bits CLUTs can be saved as {}", name; str => "LUT is not suitable to be saved")
+            }
+        };
+
+        // Lut8 and lut16 only allow the same CLUT points in all dimensions
+        let n_inputs = clut.params.n_inputs;
+        if clut.params.n_samples[..n_inputs]
+            .iter()
+            .any(|n| *n != clut.params.n_samples[0])
+        {
+            return err!(ctx, Error, UnknownExtension, "LUT with different samples per dimension not suitable to be saved as {}", name; str => "LUT is not suitable to be saved");
+        }
+        result.clut = Some(clut);
+    }
+    if let Some(stage) = stages.next_if(|stage| stage.get_type() == sig::mpe_stage::CURVE_SET) {
+        result.post = stage.get_curves();
+    }
+
+    // That should be all
+    if stages.next().is_some() {
+        return err!(ctx, Error, UnknownExtension, "LUT is not suitable to be saved as {}", name; str => "LUT is not suitable to be saved");
+    }
+
+    Ok(result)
+}
+
+/// Writes the channels, grid points and matrix shared by both LUT types.
+fn write_lut_header(io: &mut dyn IoHandler, lut: &Pipeline, stages: &LutStages) -> Result<()> {
+    let clut_points = stages.clut.map_or(0, |clut| clut.params.n_samples[0]);
+
+    io.write_u8(lut.get_input_channels() as u8)
+        .map_err(|_| "Write error")?;
+    io.write_u8(lut.get_output_channels() as u8)
+        .map_err(|_| "Write error")?;
+    io.write_u8(clut_points as u8).map_err(|_| "Write error")?;
+    io.write_u8(0).map_err(|_| "Write error")?; // Padding
+
+    let matrix = match stages.matrix {
+        Some(matrix) => matrix.double.as_slice(),
+        None => &Mat3::IDENTITY.to_array(),
+    };
+    for value in matrix {
+        io.write_s15_fixed16_number(*value)
+            .map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
+
+/// Reads one 256 entry table per channel.
+fn read_8bit_tables(io: &mut dyn IoHandler, n_chans: usize) -> Result<Stage> {
+    let ctx = io.context_id().clone();
+    let mut temp = [0u8; 256];
+    let mut tables = Vec::with_capacity(n_chans);
+
+    for _ in 0..n_chans {
+        if io.read(&mut temp, 256, 1).map_err(|_| "Read error")? != 1 {
+            return Err("Read error");
+        }
+
+        let values = temp.iter().map(|v| from_8_to_16(*v)).collect::<Vec<_>>();
+        tables.push(ToneCurve::build_tabulated_16(&ctx, &values)?);
+    }
+
+    Stage::alloc_tone_curves(&ctx, n_chans, Some(&tables))
+}
+
+/// Writes one 256 entry table per channel. Missing tables are written as identities.
+fn write_8bit_tables(
+    io: &mut dyn IoHandler,
+    n_chans: usize,
+    tables: Option<&[ToneCurve]>,
+) -> Result<()> {
+    for i in 0..n_chans {
+        let table = tables.map(|tables| tables[i].get_estimated_table());
+
+        match table {
+            // Usual case of identity curves
+            None | Some([0, 0xFFFF]) => {
+                for j in 0..=255u8 {
+                    io.write_u8(j).map_err(|_| "Write error")?;
+                }
+            }
+            Some(table) if table.len() == 256 => {
+                for value in table {
+                    io.write_u8(from_16_to_8(*value))
+                        .map_err(|_| "Write error")?;
+                }
+            }
+            Some(_) => {
+                return err!(io.context_id(), Error, Range, "LUT8 needs 256 entries on prelinearization"; str => "LUT8 needs 256 entries on prelinearization")
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads `n_entries` values per channel, if there are any tables at all.
+fn read_16bit_tables(
+    io: &mut dyn IoHandler,
+    n_chans: usize,
+    n_entries: usize,
+) -> Result<Option<Stage>> {
+    let ctx = io.context_id().clone();
+
+    // Maybe an empty table? (this is a lcms extension)
+    if n_entries == 0 {
+        return Ok(None);
+    }
+
+    // Check for malicious profiles
+    if n_entries < 2 {
+        return err!(ctx, Error, CorruptionDetected, "LUT tables need at least 2 entries"; str => "LUT tables need at least 2 entries");
+    }
+
+    let mut values = vec![0u16; n_entries];
+    let mut tables = Vec::with_capacity(n_chans);
+    for _ in 0..n_chans {
+        io.read_u16_slice(&mut values).map_err(|_| "Read error")?;
+        tables.push(ToneCurve::build_tabulated_16(&ctx, &values)?);
+    }
+
+    // Add the table (which may certainly be an identity, but this is up to the optimizer, not
+    // the reading code)
+    Ok(Some(Stage::alloc_tone_curves(
+        &ctx,
+        n_chans,
+        Some(&tables),
+    )?))
+}
+
+/// The tables of every channel, one after the other, all as long as the first one. Without
+/// tables, every channel gets a 2 entry identity.
+fn tables_16bit(ctx: &Context, n_chans: usize, tables: Option<&[ToneCurve]>) -> Result<Vec<u16>> {
+    let tables = match tables {
+        Some(tables) => tables,
+        None => return Ok([0, 0xFFFF].repeat(n_chans)),
+    };
+
+    let n_entries = tables[0].get_estimated_table_entries();
+    if n_entries > MAX_ENTRIES {
+        return err!(ctx, Error, Range, "Too many entries in LUT tables: {}", n_entries; str => "Too many entries in LUT tables");
+    }
+
+    let mut result = Vec::with_capacity(n_chans * n_entries);
+    for table in tables.iter().take(n_chans) {
+        if table.get_estimated_table_entries() == n_entries {
+            result.extend_from_slice(table.get_estimated_table());
+        } else {
+            result
+                .extend((0..n_entries).map(|j| table.eval_u16(quantize_val(j as f64, n_entries))));
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,495 @@
+use std::any::Any;
+
+use crate::{
+    from_8_to_16,
+    io::IoHandler,
+    sig::{
+        self,
+        mpe_stage::{CLUT, CURVE_SET, MATRIX},
+    },
+    types::{
+        cube_size, Pipeline, Signature, Stage, StageCLutData, StageLoc, StageMatrixData, ToneCurve,
+    },
+    Result, MAX_CHANNELS,
+};
+
+use super::{check_table_size, TagTypeHandler, CURVE_HANDLER, PARAMETRIC_CURVE_HANDLER};
+
+pub(crate) const LUT_A_TO_B_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::LUT_A_TO_B,
+    read: read_lut_a_to_b,
+    write: write_lut_a_to_b,
+    dup: dup_lut,
+};
+
+pub(crate) const LUT_B_TO_A_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::LUT_B_TO_A,
+    read: read_lut_b_to_a,
+    write: write_lut_b_to_a,
+    dup: dup_lut,
+};
+
+/// Size of the type base, which element offsets are relative to.
+const TAG_BASE_SIZE: usize = 8;
+
+/// The elements of a lutAtoB or lutBtoA, as found in the directory. Any of them may be missing.
+#[derive(Default)]
+struct Elements<'a> {
+    b: Option<&'a Stage>,
+    matrix: Option<&'a Stage>,
+    m: Option<&'a Stage>,
+    clut: Option<&'a Stage>,
+    a: Option<&'a Stage>,
+}
+
+/// Reads the channels and the element offsets, which are relative to the type base.
+fn read_header(io: &mut dyn IoHandler) -> Result<(Pipeline, [usize; 5])> {
+    let ctx = io.context_id().clone();
+
+    let in_chans = io.read_u8().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u8().map_err(|_| "Read error")? as usize;
+    io.read_u16().map_err(|_| "Read error")?; // Padding
+
+    // B, matrix, M, CLUT and A
+    let mut offsets = [0usize; 5];
+    for offset in offsets.iter_mut() {
+        *offset = io.read_u32().map_err(|_| "Read error")? as usize;
+    }
+
+    if in_chans == 0 || in_chans >= MAX_CHANNELS || out_chans == 0 || out_chans >= MAX_CHANNELS {
+        return err!(ctx, Error, CorruptionDetected, "Wrong number of LUT channels ({} in, {} out)", in_chans, out_chans; str => "Wrong number of LUT channels");
+    }
+
+    Ok((Pipeline::new(&ctx, in_chans, out_chans)?, offsets))
+}
+
+fn read_lut_a_to_b(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    // Get the base for all offsets
+    let base = io.tell().map_err(|_| "Read error")? - TAG_BASE_SIZE;
+    let end = base + TAG_BASE_SIZE + tag_size;
+    let (mut lut, [b, matrix, m, clut, a]) = read_header(io)?;
+    let in_chans = lut.get_input_channels();
+    let out_chans = lut.get_output_channels();
+
+    if a != 0 {
+        let stage = read_set_of_curves(handler, io, base + a, in_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if clut != 0 {
+        let stage = read_clut(io, base + clut, end, in_chans, out_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if m != 0 {
+        let stage = read_set_of_curves(handler, io, base + m, out_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if matrix != 0 {
+        let stage = read_matrix(io, base + matrix)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if b != 0 {
+        let stage = read_set_of_curves(handler, io, base + b, out_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+
+    *n_items = 1;
+    Ok(Box::new(lut))
+}
+
+fn write_lut_a_to_b(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let lut = match data.downcast_ref::<Pipeline>() {
+        Some(lut) => lut,
+        None => return Err("Wrong data type"),
+    };
+
+    let stages = lut.stages().collect::<Vec<_>>();
+    let elements = match (types(lut).as_slice(), stages.as_slice()) {
+        ([], []) => Elements::default(),
+        ([CURVE_SET], [b]) => Elements {
+            b: Some(b),
+            ..Default::default()
+        },
+        ([CURVE_SET, MATRIX, CURVE_SET], [m, matrix, b]) => Elements {
+            m: Some(m),
+            matrix: Some(matrix),
+            b: Some(b),
+            ..Default::default()
+        },
+        ([CURVE_SET, CLUT, CURVE_SET], [a, clut, b]) => Elements {
+            a: Some(a),
+            clut: Some(clut),
+            b: Some(b),
+            ..Default::default()
+        },
+        ([CURVE_SET, CLUT, CURVE_SET, MATRIX, CURVE_SET], [a, clut, m, matrix, b]) => Elements {
+            a: Some(a),
+            clut: Some(clut),
+            m: Some(m),
+            matrix: Some(matrix),
+            b: Some(b),
+        },
+        _ => {
+            return err!(lut.context_id(), Error, NotSuitable, "LUT is not suitable to be saved as LutAToB"; str => "LUT is not suitable to be saved as LutAToB")
+        }
+    };
+
+    write_elements(handler, io, lut, &elements)
+}
+
+fn read_lut_b_to_a(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    // Get the base for all offsets
+    let base = io.tell().map_err(|_| "Read error")? - TAG_BASE_SIZE;
+    let end = base + TAG_BASE_SIZE + tag_size;
+    let (mut lut, [b, matrix, m, clut, a]) = read_header(io)?;
+    let in_chans = lut.get_input_channels();
+    let out_chans = lut.get_output_channels();
+
+    if b != 0 {
+        let stage = read_set_of_curves(handler, io, base + b, in_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if matrix != 0 {
+        let stage = read_matrix(io, base + matrix)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if m != 0 {
+        let stage = read_set_of_curves(handler, io, base + m, in_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if clut != 0 {
+        let stage = read_clut(io, base + clut, end, in_chans, out_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+    if a != 0 {
+        let stage = read_set_of_curves(handler, io, base + a, out_chans)?;
+        lut.insert_stage(StageLoc::AtEnd, stage)?;
+    }
+
+    *n_items = 1;
+    Ok(Box::new(lut))
+}
+
+fn write_lut_b_to_a(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let lut = match data.downcast_ref::<Pipeline>() {
+        Some(lut) => lut,
+        None => return Err("Wrong data type"),
+    };
+
+    let stages = lut.stages().collect::<Vec<_>>();
+    let elements = match (types(lut).as_slice(), stages.as_slice()) {
+        ([], []) => Elements::default(),
+        ([CURVE_SET], [b]) => Elements {
+            b: Some(b),
+            ..Default::default()
+        },
+        ([CURVE_SET, MATRIX, CURVE_SET], [b, matrix, m]) => Elements {
+            b: Some(b),
+            matrix: Some(matrix),
+            m: Some(m),
+            ..Default::default()
+        },
+        ([CURVE_SET, CLUT, CURVE_SET], [b, clut, a]) => Elements {
+            b: Some(b),
+            clut: Some(clut),
+            a: Some(a),
+            ..Default::default()
+        },
+        ([CURVE_SET, MATRIX, CURVE_SET, CLUT, CURVE_SET], [b, matrix, m, clut, a]) => Elements {
+            b: Some(b),
+            matrix: Some(matrix),
+            m: Some(m),
+            clut: Some(clut),
+            a: Some(a),
+        },
+        _ => {
+            return err!(lut.context_id(), Error, NotSuitable, "LUT is not suitable to be saved as LutBToA"; str => "LUT is not suitable to be saved as LutBToA")
+        }
+    };
+
+    write_elements(handler, io, lut, &elements)
+}
+
+fn dup_lut(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<Pipeline>() {
+        Some(lut) => Ok(Box::new(lut.duplicate())),
+        None => Err("Wrong data type"),
+    }
+}
+
+fn types(lut: &Pipeline) -> Vec<Signature> {
+    lut.stages().map(|stage| stage.get_type()).collect()
+}
+
+/// Writes the header and the elements, in the same order for both directions, then goes back
+/// to fill in the directory.
+fn write_elements(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    lut: &Pipeline,
+    elements: &Elements,
+) -> Result<()> {
+    // Get the base for all offsets
+    let base = io.tell().map_err(|_| "Write error")? - TAG_BASE_SIZE;
+
+    io.write_u8(lut.get_input_channels() as u8)
+        .map_err(|_| "Write error")?;
+    io.write_u8(lut.get_output_channels() as u8)
+        .map_err(|_| "Write error")?;
+    io.write_u16(0).map_err(|_| "Write error")?; // Padding
+
+    // Keep directory to be filled later on
+    let directory_pos = io.tell().map_err(|_| "Write error")?;
+    for _ in 0..5 {
+        io.write_u32(0).map_err(|_| "Write error")?;
+    }
+
+    let offset = |io: &mut dyn IoHandler| -> Result<u32> {
+        Ok((io.tell().map_err(|_| "Write error")? - base) as u32)
+    };
+    let (mut offset_b, mut offset_mat, mut offset_m, mut offset_c, mut offset_a) = (0, 0, 0, 0, 0);
+
+    if let Some(a) = elements.a {
+        offset_a = offset(io)?;
+        write_set_of_curves(handler, io, a)?;
+    }
+    if let Some(clut) = elements.clut {
+        offset_c = offset(io)?;
+        write_clut(io, clut)?;
+    }
+    if let Some(m) = elements.m {
+        offset_m = offset(io)?;
+        write_set_of_curves(handler, io, m)?;
+    }
+    if let Some(matrix) = elements.matrix {
+        offset_mat = offset(io)?;
+        write_matrix(io, matrix)?;
+    }
+    if let Some(b) = elements.b {
+        offset_b = offset(io)?;
+        write_set_of_curves(handler, io, b)?;
+    }
+
+    let current_pos = io.tell().map_err(|_| "Write error")?;
+    io.seek(directory_pos).map_err(|_| "Write error")?;
+
+    for value in [offset_b, offset_mat, offset_m, offset_c, offset_a] {
+        io.write_u32(value).map_err(|_| "Write error")?;
+    }
+
+    io.seek(current_pos).map_err(|_| "Write error")
+}
+
+/// Reads a curv or para tag embedded in a set of curves.
+fn read_embedded_curve(handler: &TagTypeHandler, io: &mut dyn IoHandler) -> Result<ToneCurve> {
+    let ctx = io.context_id().clone();
+    let base_type = io.read_type_base().map_err(|_| "Read error")?;
+
+    let embedded = match base_type {
+        sig::types::CURVE => CURVE_HANDLER,
+        sig::types::PARAMETRIC_CURVE => PARAMETRIC_CURVE_HANDLER,
+        _ => {
+            return err!(ctx, Error, UnknownExtension, "Unknown curve type '{}'", base_type; str => "Unknown curve type")
+        }
+    };
+
+    let mut n_items = 0;
+    let curve = (embedded.read)(handler, io, &mut n_items, 0)?;
+    match curve.downcast::<ToneCurve>() {
+        Ok(curve) => Ok(*curve),
+        Err(_) => Err("Wrong data type"),
+    }
+}
+
+fn read_set_of_curves(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    offset: usize,
+    n_curves: usize,
+) -> Result<Stage> {
+    let ctx = io.context_id().clone();
+
+    if n_curves > MAX_CHANNELS {
+        return err!(ctx, Error, Range, "Too many curves in set: {}", n_curves; str => "Too many curves in set");
+    }
+
+    io.seek(offset).map_err(|_| "Read error")?;
+
+    let mut curves = Vec::with_capacity(n_curves);
+    for _ in 0..n_curves {
+        curves.push(read_embedded_curve(handler, io)?);
+        io.read_alignment().map_err(|_| "Read error")?;
+    }
+
+    Stage::alloc_tone_curves(&ctx, n_curves, Some(&curves))
+}
+
+/// Writes each curve as parametric if it is made of a single ICC function, as a table
+/// otherwise.
+fn write_set_of_curves(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    stage: &Stage,
+) -> Result<()> {
+    let curves = match stage.get_curves() {
+        Some(curves) => curves,
+        None => return Err("Wrong data type"),
+    };
+
+    for curve in curves {
+        let embedded = if (1..=5).contains(&curve.get_parametric_type()) {
+            PARAMETRIC_CURVE_HANDLER
+        } else {
+            CURVE_HANDLER
+        };
+
+        io.write_type_base(embedded.sig)
+            .map_err(|_| "Write error")?;
+        (embedded.write)(handler, io, curve, 1)?;
+        io.write_alignment().map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
+
+/// Reads the CLUT at `offset`, which has to fit before `end`, the end of the tag.
+fn read_clut(
+    io: &mut dyn IoHandler,
+    offset: usize,
+    end: usize,
+    in_chans: usize,
+    out_chans: usize,
+) -> Result<Stage> {
+    let ctx = io.context_id().clone();
+
+    io.seek(offset).map_err(|_| "Read error")?;
+
+    let mut grid_points = [0usize; MAX_CHANNELS];
+    for points in grid_points.iter_mut() {
+        *points = io.read_u8().map_err(|_| "Read error")? as usize;
+
+        if *points == 1 {
+            // Impossible value, 0 for no CLUT and then 2 at least
+            return err!(ctx, Error, CorruptionDetected, "Wrong number of CLUT points: 1"; str => "Wrong number of CLUT points");
+        }
+    }
+
+    let precision = io.read_u8().map_err(|_| "Read error")?;
+    for _ in 0..3 {
+        io.read_u8().map_err(|_| "Read error")?; // Padding
+    }
+
+    // Precision can be 1 or 2 bytes
+    if precision != 1 && precision != 2 {
+        return err!(ctx, Error, UnknownExtension, "Unknown precision of '{}'", precision; str => "Unknown CLUT precision");
+    }
+
+    let n_values = cube_size(&grid_points, in_chans).and_then(|n| n.checked_mul(out_chans));
+    check_table_size(io, n_values, precision as usize, end)?;
+
+    let mut clut = Stage::alloc_clut_16bit_granular(&ctx, &grid_points, in_chans, out_chans, None)?;
+    let tab = match clut.get_data_mut().downcast_mut::<StageCLutData<u16>>() {
+        Some(data) => &mut data.tab,
+        None => return Err("Wrong data type"),
+    };
+
+    if precision == 1 {
+        for value in tab.iter_mut() {
+            *value = from_8_to_16(io.read_u8().map_err(|_| "Read error")?);
+        }
+    } else {
+        io.read_u16_slice(tab).map_err(|_| "Read error")?;
+    }
+
+    Ok(clut)
+}
+
+/// Writes a 16 bits CLUT, float CLUTs can't be saved in these types.
+fn write_clut(io: &mut dyn IoHandler, stage: &Stage) -> Result<()> {
+    let clut = match stage.get_data().downcast_ref::<StageCLutData<u16>>() {
+        Some(clut) => clut,
+        None => {
+            return err!(stage.context_id(), Error, NotSuitable, "Cannot save floating point data, CLUT are 8 or 16 bit only"; str => "Cannot save floating point CLUTs")
+        }
+    };
+
+    let mut grid_points = [0u8; MAX_CHANNELS];
+    for (points, n) in grid_points
+        .iter_mut()
+        .zip(clut.params.n_samples.iter())
+        .take(clut.params.n_inputs)
+    {
+        *points = *n as u8;
+    }
+    io.write(MAX_CHANNELS, &grid_points)
+        .map_err(|_| "Write error")?;
+
+    io.write_u8(2).map_err(|_| "Write error")?; // Precision
+    for _ in 0..3 {
+        io.write_u8(0).map_err(|_| "Write error")?; // Padding
+    }
+
+    io.write_u16_slice(&clut.tab).map_err(|_| "Write error")?;
+    io.write_alignment().map_err(|_| "Write error")
+}
+
+fn read_matrix(io: &mut dyn IoHandler, offset: usize) -> Result<Stage> {
+    let ctx = io.context_id().clone();
+
+    io.seek(offset).map_err(|_| "Read error")?;
+
+    let mut matrix = [0f64; 9];
+    for value in matrix.iter_mut() {
+        *value = io.read_s15_fixed16_number().map_err(|_| "Read error")?;
+    }
+
+    let mut offset = [0f64; 3];
+    for value in offset.iter_mut() {
+        *value = io.read_s15_fixed16_number().map_err(|_| "Read error")?;
+    }
+
+    Stage::alloc_matrix(&ctx, 3, 3, &matrix, Some(&offset))
+}
+
+fn write_matrix(io: &mut dyn IoHandler, stage: &Stage) -> Result<()> {
+    let matrix = match stage.get_data().downcast_ref::<StageMatrixData>() {
+        Some(matrix) => matrix,
+        None => return Err("Wrong data type"),
+    };
+
+    if stage.get_input_channels() != 3 || stage.get_output_channels() != 3 {
+        return err!(stage.context_id(), Error, NotSuitable, "Only 3x3 matrices can be saved in LUT types"; str => "Only 3x3 matrices can be saved in LUT types");
+    }
+
+    for value in matrix.double.iter() {
+        io.write_s15_fixed16_number(*value)
+            .map_err(|_| "Write error")?;
+    }
+
+    let offset = matrix.offset.as_deref().unwrap_or(&[0.0; 3]);
+    for value in offset {
+        io.write_s15_fixed16_number(*value)
+            .map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
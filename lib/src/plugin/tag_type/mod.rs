@@ -4,15 +4,36 @@ use crate::{io::IoHandler, types::Signature, Result};
 
 use super::Plugin;
 
-#[derive(Clone, PartialEq, Eq)]
+pub type TagTypeReadFn = fn(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>>;
+pub type TagTypeWriteFn = fn(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    n_items: usize,
+) -> Result<()>;
+pub type TagTypeDupFn =
+    fn(handler: &TagTypeHandler, data: &dyn Any, n_items: usize) -> Result<Box<dyn Any>>;
+
+/// Serializes one tag type. The type base is read and written by the caller, the handler only
+/// deals with the contents that follow it.
+#[derive(Clone, Eq)]
 pub struct TagTypeHandler {
     pub sig: Signature,
-    pub read: fn(
-        handler: &TagTypeHandler,
-        io: &mut dyn IoHandler,
-        n_items: &mut usize,
-        tag_size: usize,
-    ) -> Result<Box<dyn Any>>,
+    pub read: TagTypeReadFn,
+    pub write: TagTypeWriteFn,
+    pub dup: TagTypeDupFn,
+}
+
+impl PartialEq for TagTypeHandler {
+    /// Handlers are told apart by the type they serialize, function addresses aren't unique.
+    fn eq(&self, other: &Self) -> bool {
+        self.sig == other.sig
+    }
 }
 
 pub struct TagTypePlugin {
@@ -20,5 +41,68 @@ pub struct TagTypePlugin {
     pub handler: TagTypeHandler,
 }
 
-pub(crate) const DEFAULT_TAG_TYPE_HANDLERS: &[TagTypeHandler] = &[];
-pub(crate) const DEFAULT_MPE_TYPE_HANDLERS: &[TagTypeHandler] = &[];
+pub(crate) const DEFAULT_TAG_TYPE_HANDLERS: &[TagTypeHandler] = &[
+    CURVE_HANDLER,
+    PARAMETRIC_CURVE_HANDLER,
+    XYZ_HANDLER,
+    S15_FIXED16_ARRAY_HANDLER,
+    VIEWING_CONDITIONS_HANDLER,
+    LUT8_HANDLER,
+    LUT16_HANDLER,
+    LUT_A_TO_B_HANDLER,
+    LUT_B_TO_A_HANDLER,
+    MULTI_PROCESS_ELEMENT_HANDLER,
+];
+pub(crate) const DEFAULT_MPE_TYPE_HANDLERS: &[TagTypeHandler] = &[
+    MPE_BACS_HANDLER,
+    MPE_EACS_HANDLER,
+    MPE_CURVE_SET_HANDLER,
+    MPE_MATRIX_HANDLER,
+    MPE_CLUT_HANDLER,
+];
+
+mod curve;
+mod lut;
+mod lut_ab;
+mod mpe;
+mod parametric_curve;
+mod s15_fixed16;
+mod segmented_curve;
+mod viewing_conditions;
+mod xyz;
+
+use curve::CURVE_HANDLER;
+use lut::{LUT16_HANDLER, LUT8_HANDLER};
+use lut_ab::{LUT_A_TO_B_HANDLER, LUT_B_TO_A_HANDLER};
+use mpe::{
+    MPE_BACS_HANDLER, MPE_CLUT_HANDLER, MPE_CURVE_SET_HANDLER, MPE_EACS_HANDLER,
+    MPE_MATRIX_HANDLER, MULTI_PROCESS_ELEMENT_HANDLER,
+};
+use parametric_curve::PARAMETRIC_CURVE_HANDLER;
+use s15_fixed16::S15_FIXED16_ARRAY_HANDLER;
+pub(crate) use segmented_curve::{read_segmented_curve, write_segmented_curve};
+use viewing_conditions::VIEWING_CONDITIONS_HANDLER;
+use xyz::XYZ_HANDLER;
+
+/// Checks that `n_values` values of `value_size` bytes fit between the current position and
+/// `end`, the end of the tag, so nothing is allocated for a table the tag can't hold. `None`
+/// stands for a count that already overflowed.
+fn check_table_size(
+    io: &mut dyn IoHandler,
+    n_values: Option<usize>,
+    value_size: usize,
+    end: usize,
+) -> Result<usize> {
+    let ctx = io.context_id().clone();
+    let left = end.saturating_sub(io.tell().map_err(|_| "Read error")?);
+
+    match n_values {
+        Some(n) if n.checked_mul(value_size).is_some_and(|size| size <= left) => Ok(n),
+        _ => {
+            err!(ctx, Error, CorruptionDetected, "Table doesn't fit in the {} bytes left in the tag", left; str => "Table doesn't fit in the tag")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,481 @@
+use std::any::Any;
+
+use crate::{
+    io::IoHandler,
+    sig,
+    types::{cube_size, Pipeline, Stage, StageCLutData, StageLoc, StageMatrixData},
+    Result, MAX_CHANNELS, MAX_INPUT_DIMENSIONS,
+};
+
+use super::{check_table_size, read_segmented_curve, write_segmented_curve, TagTypeHandler};
+
+pub(crate) const MULTI_PROCESS_ELEMENT_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::MULTI_PROCESS_ELEMENT,
+    read: read_mpe,
+    write: write_mpe,
+    dup: dup_mpe,
+};
+
+pub(crate) const MPE_CURVE_SET_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::mpe_stage::CURVE_SET,
+    read: read_mpe_curve,
+    write: write_mpe_curve,
+    dup: dup_stage,
+};
+
+pub(crate) const MPE_MATRIX_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::mpe_stage::MATRIX,
+    read: read_mpe_matrix,
+    write: write_mpe_matrix,
+    dup: dup_stage,
+};
+
+pub(crate) const MPE_CLUT_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::mpe_stage::CLUT,
+    read: read_mpe_clut,
+    write: write_mpe_clut,
+    dup: dup_stage,
+};
+
+/// Reserved by the ICC for future use, these elements are skipped when read.
+pub(crate) const MPE_BACS_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::mpe_stage::BACS,
+    read: read_acs,
+    write: write_acs,
+    dup: dup_acs,
+};
+
+/// Reserved by the ICC for future use, these elements are skipped when read.
+pub(crate) const MPE_EACS_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::mpe_stage::EACS,
+    read: read_acs,
+    write: write_acs,
+    dup: dup_acs,
+};
+
+/// Size of the type base and of the element headers, which offsets are relative to.
+const TAG_BASE_SIZE: usize = 8;
+
+/// Reads the offset and size of `count` elements, relative to `base`.
+fn read_position_table(
+    io: &mut dyn IoHandler,
+    count: usize,
+    base: usize,
+) -> Result<Vec<(usize, usize)>> {
+    let ctx = io.context_id().clone();
+    let current_pos = io.tell().map_err(|_| "Read error")?;
+
+    // Verify there is enough space left to read at least two u32 items for count items
+    if io.reported_size().saturating_sub(current_pos) / 8 < count {
+        return err!(ctx, Error, CorruptionDetected, "Position table with {} elements doesn't fit in the tag", count; str => "Position table doesn't fit in the tag");
+    }
+
+    let mut table = Vec::with_capacity(count);
+    for _ in 0..count {
+        let offset = io.read_u32().map_err(|_| "Read error")? as usize;
+        let size = io.read_u32().map_err(|_| "Read error")? as usize;
+        table.push((base + offset, size));
+    }
+
+    Ok(table)
+}
+
+/// Writes a fake position table for `count` elements, to be filled in by
+/// [`write_position_table`]. Returns where it starts.
+fn reserve_position_table(io: &mut dyn IoHandler, count: usize) -> Result<usize> {
+    let directory_pos = io.tell().map_err(|_| "Write error")?;
+
+    for _ in 0..count {
+        io.write_u32(0).map_err(|_| "Write error")?; // Offset
+        io.write_u32(0).map_err(|_| "Write error")?; // Size
+    }
+
+    Ok(directory_pos)
+}
+
+/// Goes back to `directory_pos` to fill in the position table, then returns to the end.
+fn write_position_table(
+    io: &mut dyn IoHandler,
+    directory_pos: usize,
+    table: &[(usize, usize)],
+) -> Result<()> {
+    let current_pos = io.tell().map_err(|_| "Write error")?;
+    io.seek(directory_pos).map_err(|_| "Write error")?;
+
+    for (offset, size) in table {
+        io.write_u32(*offset as u32).map_err(|_| "Write error")?;
+        io.write_u32(*size as u32).map_err(|_| "Write error")?;
+    }
+
+    io.seek(current_pos).map_err(|_| "Write error")
+}
+
+fn read_mpe(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+
+    // Get actual position as a basis for element offsets
+    let base = io.tell().map_err(|_| "Read error")? - TAG_BASE_SIZE;
+    let end = base + TAG_BASE_SIZE + tag_size;
+
+    let in_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+
+    if in_chans == 0 || in_chans >= MAX_CHANNELS || out_chans == 0 || out_chans >= MAX_CHANNELS {
+        return err!(ctx, Error, CorruptionDetected, "Wrong number of multi process element channels ({} in, {} out)", in_chans, out_chans; str => "Wrong number of multi process element channels");
+    }
+
+    let mut lut = Pipeline::new(&ctx, in_chans, out_chans)?;
+
+    let count = io.read_u32().map_err(|_| "Read error")? as usize;
+    for (offset, size) in read_position_table(io, count, base)? {
+        io.seek(offset).map_err(|_| "Read error")?;
+
+        // Take signature for each element
+        let element_sig = io.read_signature().map_err(|_| "Read error")?;
+        io.read_u32().map_err(|_| "Read error")?; // Reserved
+
+        let element = match ctx.get_mpe_type_handler(element_sig) {
+            Some(element) => element,
+            None => {
+                return err!(ctx, Error, UnknownExtension, "Unknown MPE type '{}' found.", element_sig; str => "Unknown MPE type")
+            }
+        };
+
+        // Sizes are written without the element header, and can't go past the end of the tag
+        let element_size = size.min(end.saturating_sub(offset + TAG_BASE_SIZE));
+
+        // Elements that are not stages, like the reserved ones, are just ignored
+        let mut n = 0;
+        if let Ok(stage) = (element.read)(&element, io, &mut n, element_size)?.downcast::<Stage>() {
+            lut.insert_stage(StageLoc::AtEnd, *stage)?;
+        }
+    }
+
+    // Check channel count
+    if in_chans != lut.get_input_channels() || out_chans != lut.get_output_channels() {
+        return err!(ctx, Error, CorruptionDetected, "Multi process element channels don't match its elements"; str => "Multi process element channels don't match its elements");
+    }
+
+    *n_items = 1;
+    Ok(Box::new(lut))
+}
+
+fn write_mpe(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let lut = match data.downcast_ref::<Pipeline>() {
+        Some(lut) => lut,
+        None => return Err("Wrong data type"),
+    };
+    let ctx = lut.context_id();
+
+    let base = io.tell().map_err(|_| "Write error")? - TAG_BASE_SIZE;
+
+    // Write the head
+    io.write_u16(lut.get_input_channels() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(lut.get_output_channels() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u32(lut.stage_count() as u32)
+        .map_err(|_| "Write error")?;
+
+    let directory_pos = reserve_position_table(io, lut.stage_count())?;
+
+    // Write each single element. Keep track of the size as well.
+    let mut table = Vec::with_capacity(lut.stage_count());
+    for stage in lut.stages() {
+        let offset = io.tell().map_err(|_| "Write error")? - base;
+
+        let element = match ctx.get_mpe_type_handler(stage.get_type()) {
+            Some(element) => element,
+            None => {
+                return err!(ctx, Error, UnknownExtension, "Found unknown MPE type '{}'", stage.get_type(); str => "Unknown MPE type")
+            }
+        };
+
+        io.write_signature(stage.get_type())
+            .map_err(|_| "Write error")?;
+        io.write_u32(0).map_err(|_| "Write error")?; // Reserved
+
+        let before = io.tell().map_err(|_| "Write error")?;
+        (element.write)(&element, io, stage, 1)?;
+        io.write_alignment().map_err(|_| "Write error")?;
+
+        table.push((offset, io.tell().map_err(|_| "Write error")? - before));
+    }
+
+    write_position_table(io, directory_pos, &table)
+}
+
+fn dup_mpe(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<Pipeline>() {
+        Some(lut) => Ok(Box::new(lut.duplicate())),
+        None => Err("Wrong data type"),
+    }
+}
+
+fn dup_stage(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<Stage>() {
+        Some(stage) => Ok(Box::new(stage.duplicate())),
+        None => Err("Wrong data type"),
+    }
+}
+
+fn read_mpe_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+
+    // Get actual position as a basis for element offsets
+    let base = io.tell().map_err(|_| "Read error")? - TAG_BASE_SIZE;
+    let end = base + TAG_BASE_SIZE + tag_size;
+
+    let in_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+
+    if in_chans != out_chans {
+        return err!(ctx, Error, CorruptionDetected, "Curve set with {} inputs and {} outputs", in_chans, out_chans; str => "Curve set channels don't match");
+    }
+
+    let mut curves = Vec::with_capacity(in_chans);
+    for (offset, _size) in read_position_table(io, in_chans, base)? {
+        io.seek(offset).map_err(|_| "Read error")?;
+        curves.push(read_segmented_curve(io, end)?);
+    }
+
+    *n_items = 1;
+    Ok(Box::new(Stage::alloc_tone_curves(
+        &ctx,
+        in_chans,
+        Some(&curves),
+    )?))
+}
+
+fn write_mpe_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let curves = match data
+        .downcast_ref::<Stage>()
+        .and_then(|stage| stage.get_curves())
+    {
+        Some(curves) => curves,
+        None => return Err("Wrong data type"),
+    };
+
+    let base = io.tell().map_err(|_| "Write error")? - TAG_BASE_SIZE;
+
+    // Write the header. Since those are curves, input and output channels are the same
+    io.write_u16(curves.len() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(curves.len() as u16)
+        .map_err(|_| "Write error")?;
+
+    let directory_pos = reserve_position_table(io, curves.len())?;
+
+    let mut table = Vec::with_capacity(curves.len());
+    for curve in curves {
+        let before = io.tell().map_err(|_| "Write error")?;
+        write_segmented_curve(io, curve)?;
+
+        table.push((
+            before - base,
+            io.tell().map_err(|_| "Write error")? - before,
+        ));
+    }
+
+    write_position_table(io, directory_pos, &table)
+}
+
+fn read_mpe_matrix(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+
+    let in_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+
+    // Input and output chans may be ANY (up to 0xffff), but we choose to limit to 16 channels
+    // for now
+    if in_chans >= MAX_CHANNELS || out_chans >= MAX_CHANNELS {
+        return err!(ctx, Error, CorruptionDetected, "Too many channels in matrix ({} in, {} out)", in_chans, out_chans; str => "Too many channels in matrix");
+    }
+
+    let mut matrix = vec![0f64; in_chans * out_chans];
+    for value in matrix.iter_mut() {
+        *value = io.read_f32().map_err(|_| "Read error")? as f64;
+    }
+
+    let mut offset = vec![0f64; out_chans];
+    for value in offset.iter_mut() {
+        *value = io.read_f32().map_err(|_| "Read error")? as f64;
+    }
+
+    let stage = Stage::alloc_matrix(&ctx, out_chans, in_chans, &matrix, Some(&offset))?;
+
+    *n_items = 1;
+    Ok(Box::new(stage))
+}
+
+fn write_mpe_matrix(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let stage = match data.downcast_ref::<Stage>() {
+        Some(stage) => stage,
+        None => return Err("Wrong data type"),
+    };
+    let matrix = match stage.get_data().downcast_ref::<StageMatrixData>() {
+        Some(matrix) => matrix,
+        None => return Err("Wrong data type"),
+    };
+
+    io.write_u16(stage.get_input_channels() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(stage.get_output_channels() as u16)
+        .map_err(|_| "Write error")?;
+
+    for value in matrix.double.iter() {
+        io.write_f32(*value as f32).map_err(|_| "Write error")?;
+    }
+
+    for i in 0..stage.get_output_channels() {
+        let offset = matrix.offset.as_ref().map_or(0.0, |offset| offset[i]);
+        io.write_f32(offset as f32).map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
+
+fn read_mpe_clut(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+    let end = io.tell().map_err(|_| "Read error")? + tag_size;
+
+    let in_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+    let out_chans = io.read_u16().map_err(|_| "Read error")? as usize;
+
+    if in_chans == 0 || out_chans == 0 {
+        return err!(ctx, Error, CorruptionDetected, "CLUT with no channels"; str => "CLUT with no channels");
+    }
+    if in_chans > MAX_INPUT_DIMENSIONS {
+        return err!(ctx, Error, Range, "Too many input channels ({} channels, max={})", in_chans, MAX_INPUT_DIMENSIONS; str => "Too many input channels");
+    }
+
+    let mut dimensions = [0u8; 16];
+    if io.read(&mut dimensions, 1, 16).map_err(|_| "Read error")? != 16 {
+        return Err("Read error");
+    }
+
+    // Copy MAX_INPUT_DIMENSIONS at most
+    let mut grid_points = [0usize; MAX_INPUT_DIMENSIONS];
+    for (points, n) in grid_points.iter_mut().zip(dimensions).take(in_chans) {
+        if n == 1 {
+            // Impossible value, 0 for no CLUT and then 2 at least
+            return err!(ctx, Error, CorruptionDetected, "Wrong number of CLUT points: 1"; str => "Wrong number of CLUT points");
+        }
+
+        *points = n as usize;
+    }
+
+    let n_values = cube_size(&grid_points, in_chans).and_then(|n| n.checked_mul(out_chans));
+    check_table_size(io, n_values, 4, end)?;
+
+    let mut stage = Stage::alloc_clut_f32_granular(&ctx, &grid_points, in_chans, out_chans, None)?;
+    match stage.get_data_mut().downcast_mut::<StageCLutData<f32>>() {
+        Some(clut) => {
+            for value in clut.tab.iter_mut() {
+                *value = io.read_f32().map_err(|_| "Read error")?;
+            }
+        }
+        None => return Err("Wrong data type"),
+    }
+
+    *n_items = 1;
+    Ok(Box::new(stage))
+}
+
+fn write_mpe_clut(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let stage = match data.downcast_ref::<Stage>() {
+        Some(stage) => stage,
+        None => return Err("Wrong data type"),
+    };
+
+    // Only floats are supported in MPE
+    let clut = match stage.get_data().downcast_ref::<StageCLutData<f32>>() {
+        Some(clut) => clut,
+        None => {
+            return err!(stage.context_id(), Error, NotSuitable, "Only float CLUTs can be saved as multi process elements"; str => "Only float CLUTs can be saved as multi process elements")
+        }
+    };
+
+    io.write_u16(stage.get_input_channels() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(stage.get_output_channels() as u16)
+        .map_err(|_| "Write error")?;
+
+    let mut dimensions = [0u8; 16];
+    for (points, n) in dimensions
+        .iter_mut()
+        .zip(clut.params.n_samples)
+        .take(stage.get_input_channels())
+    {
+        *points = n as u8;
+    }
+    io.write(16, &dimensions).map_err(|_| "Write error")?;
+
+    for value in clut.tab.iter() {
+        io.write_f32(*value).map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
+
+fn read_acs(
+    _handler: &TagTypeHandler,
+    _io: &mut dyn IoHandler,
+    _n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    Ok(Box::new(()))
+}
+
+fn write_acs(
+    handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    _data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    err!(io.context_id(), Error, NotSuitable, "'{}' elements can't be written", handler.sig; str => "Reserved elements can't be written")
+}
+
+fn dup_acs(_handler: &TagTypeHandler, _data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    Ok(Box::new(()))
+}
@@ -0,0 +1,85 @@
+use std::any::Any;
+
+use crate::{io::IoHandler, sig, types::ToneCurve, Result};
+
+use super::TagTypeHandler;
+
+/// Parameters taken by each ICC function type, which are the lcms types minus one.
+const PARAMS_BY_TYPE: [usize; 5] = [1, 3, 4, 5, 7];
+
+pub(crate) const PARAMETRIC_CURVE_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::PARAMETRIC_CURVE,
+    read: read_parametric_curve,
+    write: write_parametric_curve,
+    dup: dup_parametric_curve,
+};
+
+fn read_parametric_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let ctx = io.context_id().clone();
+
+    let r#type = io.read_u16().map_err(|_| "Read error")? as usize;
+    io.read_u16().map_err(|_| "Read error")?; // Reserved
+
+    if r#type >= PARAMS_BY_TYPE.len() {
+        return err!(ctx, Error, UnknownExtension, "Unknown parametric curve type '{}'", r#type; str => "Unknown parametric curve type");
+    }
+
+    let mut params = [0f64; 10];
+    for param in params.iter_mut().take(PARAMS_BY_TYPE[r#type]) {
+        *param = io.read_s15_fixed16_number().map_err(|_| "Read error")?;
+    }
+
+    let curve = ToneCurve::build_parametric(&ctx, r#type as i32 + 1, &params)?;
+
+    *n_items = 1;
+    Ok(Box::new(curve))
+}
+
+fn write_parametric_curve(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    let curve = match data.downcast_ref::<ToneCurve>() {
+        Some(curve) => curve,
+        None => return Err("Wrong data type"),
+    };
+    let ctx = curve.context_id();
+
+    let r#type = curve.get_parametric_type();
+    if r#type < 1 {
+        return err!(ctx, Error, UnknownExtension, "Multisegment or Inverted parametric curves cannot be written"; str => "Multisegment or Inverted parametric curves cannot be written");
+    }
+    if r#type as usize > PARAMS_BY_TYPE.len() {
+        return err!(ctx, Error, UnknownExtension, "Unsupported parametric curve"; str => "Unsupported parametric curve");
+    }
+
+    let seg = &curve.segments()[0];
+    io.write_u16((r#type - 1) as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(0).map_err(|_| "Write error")?; // Reserved
+
+    for param in seg.params.iter().take(PARAMS_BY_TYPE[r#type as usize - 1]) {
+        io.write_s15_fixed16_number(*param)
+            .map_err(|_| "Write error")?;
+    }
+
+    Ok(())
+}
+
+fn dup_parametric_curve(
+    _handler: &TagTypeHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<ToneCurve>() {
+        Some(curve) => Ok(Box::new(curve.clone())),
+        None => Err("Wrong data type"),
+    }
+}
@@ -0,0 +1,57 @@
+use std::any::Any;
+
+use crate::{io::IoHandler, sig, Result};
+
+use super::TagTypeHandler;
+
+pub(crate) const S15_FIXED16_ARRAY_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::S15_FIXED16_ARRAY,
+    read: read_s15_fixed16_array,
+    write: write_s15_fixed16_array,
+    dup: dup_s15_fixed16_array,
+};
+
+/// The array fills the whole tag, 4 bytes per number.
+fn read_s15_fixed16_array(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let values = (0..tag_size / 4)
+        .map(|_| io.read_s15_fixed16_number())
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|_| "Read error")?;
+
+    *n_items = values.len();
+    Ok(Box::new(values))
+}
+
+fn write_s15_fixed16_array(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    match data.downcast_ref::<Vec<f64>>() {
+        Some(values) => {
+            for value in values {
+                io.write_s15_fixed16_number(*value)
+                    .map_err(|_| "Write error")?;
+            }
+            Ok(())
+        }
+        None => Err("Wrong data type"),
+    }
+}
+
+fn dup_s15_fixed16_array(
+    _handler: &TagTypeHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<Vec<f64>>() {
+        Some(values) => Ok(Box::new(values.clone())),
+        None => Err("Wrong data type"),
+    }
+}
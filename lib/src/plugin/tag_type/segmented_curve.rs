@@ -0,0 +1,152 @@
+use crate::{
+    io::IoHandler,
+    sig,
+    types::{CurveSegment, ToneCurve},
+    Result, MINUS_INF, PLUS_INF,
+};
+
+use super::check_table_size;
+
+/// Parameters taken by each formula segment type, which are the lcms types minus six.
+const PARAMS_BY_TYPE: [usize; 3] = [4, 5, 5];
+
+/// Reads a segmented curve element, as found in multi process element curve sets. Sampled
+/// segments have to fit before `end`, the end of the tag.
+pub(crate) fn read_segmented_curve(io: &mut dyn IoHandler, end: usize) -> Result<ToneCurve> {
+    let ctx = io.context_id().clone();
+
+    if io.read_signature().map_err(|_| "Read error")? != sig::curve_segment::SEGMENTED {
+        return err!(ctx, Error, UnknownExtension, "Segmented curve expected"; str => "Segmented curve expected");
+    }
+    io.read_u32().map_err(|_| "Read error")?; // Reserved
+    let n_segments = io.read_u16().map_err(|_| "Read error")? as usize;
+    io.read_u16().map_err(|_| "Read error")?; // Reserved
+
+    if n_segments < 1 {
+        return err!(ctx, Error, CorruptionDetected, "Segmented curve with no segments"; str => "Segmented curve with no segments");
+    }
+
+    // Read breakpoints
+    let mut segments = Vec::with_capacity(n_segments);
+    let mut prev_break = MINUS_INF;
+    for i in 0..n_segments {
+        let x1 = if i == n_segments - 1 {
+            PLUS_INF
+        } else {
+            io.read_f32().map_err(|_| "Read error")?
+        };
+
+        segments.push(CurveSegment {
+            x0: prev_break,
+            x1,
+            r#type: 0,
+            params: [0.0; 10],
+            sampled_points: Vec::new(),
+        });
+        prev_break = x1;
+    }
+
+    // Read segments
+    for seg in segments.iter_mut() {
+        let element_sig = io.read_signature().map_err(|_| "Read error")?;
+        io.read_u32().map_err(|_| "Read error")?; // Reserved
+
+        match element_sig {
+            sig::curve_segment::FORMULA => {
+                let r#type = io.read_u16().map_err(|_| "Read error")? as usize;
+                io.read_u16().map_err(|_| "Read error")?; // Reserved
+
+                if r#type >= PARAMS_BY_TYPE.len() {
+                    return err!(ctx, Error, UnknownExtension, "Unknown formula curve segment type '{}'", r#type; str => "Unknown formula curve segment type");
+                }
+
+                seg.r#type = r#type as i32 + 6;
+                for param in seg.params.iter_mut().take(PARAMS_BY_TYPE[r#type]) {
+                    *param = io.read_f32().map_err(|_| "Read error")? as f64;
+                }
+            }
+            sig::curve_segment::SAMPLED => {
+                let count = io.read_u32().map_err(|_| "Read error")? as usize;
+                let count = check_table_size(io, Some(count), 4, end)?;
+
+                // The first point is implicit in the previous segment, it is filled in later on
+                seg.sampled_points = vec![0f32; count + 1];
+                for point in seg.sampled_points.iter_mut().skip(1) {
+                    *point = io.read_f32().map_err(|_| "Read error")?;
+                }
+            }
+            _ => {
+                return err!(ctx, Error, UnknownExtension, "Unknown curve element type '{}' found.", element_sig; str => "Unknown curve element type")
+            }
+        }
+    }
+
+    let curve = ToneCurve::build_segmented(&ctx, &segments)?;
+
+    // Fill in the implicit points
+    let mut has_samples = false;
+    for seg in segments.iter_mut().filter(|seg| seg.r#type == 0) {
+        seg.sampled_points[0] = curve.eval_f32(seg.x0);
+        has_samples = true;
+    }
+
+    if !has_samples {
+        return Ok(curve);
+    }
+    ToneCurve::build_segmented(&ctx, &segments)
+}
+
+/// Writes a segmented curve element, as found in multi process element curve sets.
+pub(crate) fn write_segmented_curve(io: &mut dyn IoHandler, curve: &ToneCurve) -> Result<()> {
+    let segments = curve.segments();
+
+    if segments.is_empty() {
+        return err!(curve.context_id(), Error, Range, "Only segmented curves can be written as such"; str => "Only segmented curves can be written as such");
+    }
+
+    io.write_signature(sig::curve_segment::SEGMENTED)
+        .map_err(|_| "Write error")?;
+    io.write_u32(0).map_err(|_| "Write error")?; // Reserved
+    io.write_u16(segments.len() as u16)
+        .map_err(|_| "Write error")?;
+    io.write_u16(0).map_err(|_| "Write error")?; // Reserved
+
+    // Write the breakpoints
+    for seg in segments.iter().take(segments.len() - 1) {
+        io.write_f32(seg.x1).map_err(|_| "Write error")?;
+    }
+
+    // Write the segments
+    for seg in segments {
+        if seg.r#type == 0 {
+            // The first point is implicit in the ICC format, but not in our representation
+            io.write_signature(sig::curve_segment::SAMPLED)
+                .map_err(|_| "Write error")?;
+            io.write_u32(0).map_err(|_| "Write error")?; // Reserved
+            io.write_u32(seg.sampled_points.len() as u32 - 1)
+                .map_err(|_| "Write error")?;
+
+            for point in seg.sampled_points.iter().skip(1) {
+                io.write_f32(*point).map_err(|_| "Write error")?;
+            }
+        } else {
+            // Only types 6, 7 and 8 are allowed
+            let r#type = seg.r#type - 6;
+            if !(0..PARAMS_BY_TYPE.len() as i32).contains(&r#type) {
+                return err!(curve.context_id(), Error, UnknownExtension, "Unsupported formula curve segment type {}", seg.r#type; str => "Unsupported formula curve segment type");
+            }
+
+            io.write_signature(sig::curve_segment::FORMULA)
+                .map_err(|_| "Write error")?;
+            io.write_u32(0).map_err(|_| "Write error")?; // Reserved
+            io.write_u16(r#type as u16).map_err(|_| "Write error")?;
+            io.write_u16(0).map_err(|_| "Write error")?; // Reserved
+
+            for param in seg.params.iter().take(PARAMS_BY_TYPE[r#type as usize]) {
+                io.write_f32(*param as f32).map_err(|_| "Write error")?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,413 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    io::{FileMem, FileNull, IoHandler},
+    sig,
+    state::{Context, DEFAULT_CONTEXT},
+    types::{Pipeline, Profile, Signature},
+    Result,
+};
+
+use super::{
+    read_segmented_curve, write_segmented_curve, TagTypeHandler, DEFAULT_TAG_TYPE_HANDLERS,
+};
+
+/// Serialized contents, type base excluded, of a valid instance of every built-in tag type.
+static SAMPLES: &[(Signature, &[u8])] = &[
+    // Gamma 2.2 as u8Fixed8
+    (sig::types::CURVE, &[0, 0, 0, 1, 0x02, 0x33]),
+    // Three entry table
+    (
+        sig::types::CURVE,
+        &[0, 0, 0, 3, 0x00, 0x00, 0x80, 0x00, 0xFF, 0xFF],
+    ),
+    // Function type 0, gamma 2.2
+    (
+        sig::types::PARAMETRIC_CURVE,
+        &[0, 0, 0, 0, 0x00, 0x02, 0x33, 0x00],
+    ),
+    // Function type 3, sRGB
+    (
+        sig::types::PARAMETRIC_CURVE,
+        &[
+            0, 3, 0, 0, 0x00, 0x02, 0x66, 0x66, 0x00, 0x00, 0xF2, 0xA7, 0x00, 0x00, 0x0D, 0x59,
+            0x00, 0x00, 0x13, 0xD0, 0x00, 0x00, 0x0A, 0x5F,
+        ],
+    ),
+    // D50
+    (
+        sig::types::XYZ,
+        &[
+            0x00, 0x00, 0xF6, 0xD6, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xD3, 0x2D,
+        ],
+    ),
+    // Bradford D65 to D50, as found in sRGB profiles
+    (
+        sig::types::S15_FIXED16_ARRAY,
+        &[
+            0x00, 0x01, 0x0C, 0x42, 0x00, 0x00, 0x05, 0xDE, 0xFF, 0xFF, 0xF3, 0x25, 0x00, 0x00,
+            0x07, 0x93, 0x00, 0x00, 0xFD, 0x90, 0xFF, 0xFF, 0xFB, 0xA1, 0xFF, 0xFF, 0xFD, 0xA2,
+            0x00, 0x00, 0x03, 0xDC, 0x00, 0x00, 0xC0, 0x6E,
+        ],
+    ),
+    // D50 illuminant at 64 cd/m², with a 20% surround
+    (
+        sig::types::VIEWING_CONDITIONS,
+        &[
+            0x00, 0x3D, 0xB5, 0x74, 0x00, 0x40, 0x00, 0x00, 0x00, 0x34, 0xCB, 0x29, 0x00, 0x0C,
+            0x57, 0x7E, 0x00, 0x0C, 0xCC, 0xCD, 0x00, 0x0A, 0x8F, 0x08, 0x00, 0x00, 0x00, 0x01,
+        ],
+    ),
+    // Function type 4, with a negative offset
+    (
+        sig::types::PARAMETRIC_CURVE,
+        &[
+            0, 4, 0, 0, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x80, 0x00, 0x00, 0x00,
+            0x10, 0x00,
+        ],
+    ),
+];
+
+/// LUT based profiles written by lcms 2, see `testdata/generate.py`. Their tags are samples
+/// too, so the LUT types have to be written byte for byte as lcms writes them.
+static LUT16_PROFILE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/lut16.icc"));
+static LUT_AB_PROFILE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/lut_ab.icc"));
+
+fn be_u32(bytes: &[u8]) -> usize {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
+
+/// [`SAMPLES`] plus every tag of the lcms profiles.
+fn samples() -> Vec<(Signature, &'static [u8])> {
+    let mut samples = SAMPLES.to_vec();
+
+    for profile in [LUT16_PROFILE, LUT_AB_PROFILE] {
+        let count = be_u32(&profile[128..]);
+
+        for entry in profile[132..].chunks(12).take(count) {
+            let (offset, size) = (be_u32(&entry[4..]), be_u32(&entry[8..]));
+            let tag = &profile[offset..offset + size];
+
+            samples.push((Signature(be_u32(tag) as u32), &tag[8..]));
+        }
+    }
+
+    samples
+}
+
+fn read(ctx: &Context, handler: &TagTypeHandler, block: &[u8]) -> Result<(Box<dyn Any>, usize)> {
+    // Offsets inside some types are relative to the type base, so it has to be there
+    let tag = [&handler.sig.0.to_be_bytes(), &[0u8; 4][..], block].concat();
+    let io = FileMem::open_for_reading(ctx, &tag);
+    let mut io = io.lock().unwrap();
+    io.read_type_base().map_err(|_| "Read error")?;
+
+    let mut n_items = 0usize;
+    let data = (handler.read)(handler, &mut *io, &mut n_items, block.len())?;
+
+    Ok((data, n_items))
+}
+
+fn write(
+    ctx: &Context,
+    handler: &TagTypeHandler,
+    data: &dyn Any,
+    n_items: usize,
+) -> Result<Vec<u8>> {
+    let null = FileNull::open(ctx);
+    null.lock()
+        .unwrap()
+        .write_type_base(handler.sig)
+        .map_err(|_| "Write error")?;
+    (handler.write)(handler, &mut *null.lock().unwrap(), data, n_items)?;
+    let size = null.lock().unwrap().used_space();
+
+    let block: Arc<Mutex<Box<[u8]>>> = Arc::new(Mutex::new(vec![0u8; size].into_boxed_slice()));
+    let io = FileMem::open_for_writing(ctx, &block);
+    io.lock()
+        .unwrap()
+        .write_type_base(handler.sig)
+        .map_err(|_| "Write error")?;
+    (handler.write)(handler, &mut *io.lock().unwrap(), data, n_items)?;
+
+    let result = block.lock().unwrap()[8..].to_vec();
+    Ok(result)
+}
+
+/// Proves read -> write -> read is byte stable for `handler`, both for the data as read and for
+/// a duplicate of it.
+fn check_round_trip(ctx: &Context, handler: &TagTypeHandler, block: &[u8]) -> Result<()> {
+    let (data, n_items) = read(ctx, handler, block)?;
+
+    let written = write(ctx, handler, data.as_ref(), n_items)?;
+    if written != block {
+        return Err("Written data differs from the data read");
+    }
+
+    let copy = (handler.dup)(handler, data.as_ref(), n_items)?;
+    if write(ctx, handler, copy.as_ref(), n_items)? != block {
+        return Err("Duplicated data differs from the data read");
+    }
+
+    let (data, n_items) = read(ctx, handler, &written)?;
+    if write(ctx, handler, data.as_ref(), n_items)? != block {
+        return Err("Data is not stable across a second round trip");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn every_default_tag_type_has_a_sample() {
+    for handler in DEFAULT_TAG_TYPE_HANDLERS {
+        assert!(
+            samples().iter().any(|(sig, _)| *sig == handler.sig),
+            "No round trip sample for '{}'",
+            handler.sig
+        );
+    }
+}
+
+#[test]
+fn default_tag_types_round_trip() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    for (sig, block) in samples() {
+        let handler = match ctx.get_tag_type_handler(sig) {
+            Some(handler) => handler,
+            None => return Err("Sample for an unregistered type"),
+        };
+
+        check_round_trip(ctx, &handler, block)?;
+    }
+
+    Ok(())
+}
+
+static TEST_TYPE_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: Signature(0x74657374),
+    read: |_, io, n_items, tag_size| {
+        let mut values = vec![0u16; tag_size / 2];
+        io.read_u16_slice(&mut values).map_err(|_| "Read error")?;
+        *n_items = values.len();
+
+        Ok(Box::new(values))
+    },
+    write: |_, io, data, _| match data.downcast_ref::<Vec<u16>>() {
+        Some(values) => io.write_u16_slice(values).map_err(|_| "Write error"),
+        None => Err("Wrong data type"),
+    },
+    dup: |_, data, _| match data.downcast_ref::<Vec<u16>>() {
+        Some(values) => Ok(Box::new(values.clone())),
+        None => Err("Wrong data type"),
+    },
+};
+
+#[test]
+fn plugin_tag_type_round_trips() -> Result<()> {
+    check_round_trip(
+        &DEFAULT_CONTEXT,
+        &TEST_TYPE_HANDLER,
+        &[0, 1, 0xCA, 0xFE, 0xFF, 0xFF],
+    )
+}
+
+#[test]
+fn round_trip_detects_unstable_writers() {
+    let mut handler = TEST_TYPE_HANDLER.clone();
+    handler.write = |_, io, _, _| io.write_u16(0).map_err(|_| "Write error");
+
+    assert!(check_round_trip(&DEFAULT_CONTEXT, &handler, &[0, 1, 0xCA, 0xFE]).is_err());
+}
+
+#[test]
+fn segmented_curves_round_trip() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // Constant 0, sampled from 0 to 1, constant 1
+    #[rustfmt::skip]
+    let block: &[u8] = &[
+        0x63, 0x75, 0x72, 0x66, 0, 0, 0, 0, 0, 3, 0, 0, // 'curf', 3 segments
+        0x00, 0x00, 0x00, 0x00, 0x3F, 0x80, 0x00, 0x00, // Breakpoints 0 and 1
+        0x70, 0x61, 0x72, 0x66, 0, 0, 0, 0, 0, 0, 0, 0, // 'parf', type 0
+        0x3F, 0x80, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0x73, 0x61, 0x6D, 0x66, 0, 0, 0, 0, 0, 0, 0, 2, // 'samf', 2 points
+        0x3F, 0x00, 0x00, 0x00, 0x3F, 0x80, 0x00, 0x00,
+        0x70, 0x61, 0x72, 0x66, 0, 0, 0, 0, 0, 0, 0, 0, // 'parf', type 0
+        0x3F, 0x80, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x3F, 0x80, 0x00, 0x00,
+    ];
+
+    let io = FileMem::open_for_reading(ctx, block);
+    let curve = read_segmented_curve(&mut *io.lock().unwrap(), block.len())?;
+
+    // The implicit first sample comes from the previous segment
+    assert_eq!(curve.segments()[1].sampled_points, [0.0, 0.5, 1.0]);
+    assert_eq!(curve.eval_f32(-1.0), 0.0);
+    assert_eq!(curve.eval_f32(0.25), 0.25);
+    assert_eq!(curve.eval_f32(2.0), 1.0);
+
+    let null = FileNull::open(ctx);
+    write_segmented_curve(&mut *null.lock().unwrap(), &curve)?;
+    let size = null.lock().unwrap().used_space();
+
+    let written: Arc<Mutex<Box<[u8]>>> = Arc::new(Mutex::new(vec![0u8; size].into_boxed_slice()));
+    let io = FileMem::open_for_writing(ctx, &written);
+    write_segmented_curve(&mut *io.lock().unwrap(), &curve)?;
+
+    assert_eq!(&written.lock().unwrap()[..], block);
+    Ok(())
+}
+
+#[test]
+fn sampled_segments_larger_than_their_tag_are_rejected() {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    #[rustfmt::skip]
+    let block: &[u8] = &[
+        0x63, 0x75, 0x72, 0x66, 0, 0, 0, 0, 0, 1, 0, 0, // 'curf', 1 segment
+        0x73, 0x61, 0x6D, 0x66, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, // 'samf', 2^32 - 1 points
+        0x3F, 0x80, 0x00, 0x00,
+    ];
+
+    let io = FileMem::open_for_reading(ctx, block);
+    assert!(read_segmented_curve(&mut *io.lock().unwrap(), block.len()).is_err());
+}
+
+/// The outputs for each of the inputs of [`lut_tags_evaluate_as_in_lcms`].
+type Evaluations = [[f32; 3]; 5];
+
+/// What lcms evaluates the tags of the test profiles to, as printed by `testdata/generate.py`.
+#[rustfmt::skip]
+static LCMS_EVALUATIONS: &[(&[u8], Signature, Evaluations)] = &[
+    (LUT16_PROFILE, sig::tags::A_TO_B0, [
+        [0.234913, 0.500008, 0.707088],
+        [0.485435, 0.431312, 0.562997],
+        [0.354314, 0.469917, 0.629755],
+        [0.408209, 0.567636, 0.53547],
+        [0.818051, 0.500008, 0.522148],
+    ]),
+    (LUT16_PROFILE, sig::tags::A_TO_B1, [
+        [0.2, 0.439216, 0.501961],
+        [0.50486, 0.294453, 0.288853],
+        [0.368597, 0.374395, 0.360281],
+        [0.495735, 0.570153, 0.258091],
+        [0.8, 0.439216, 0.27451],
+    ]),
+    (LUT16_PROFILE, sig::tags::B_TO_A0, [
+        [0.0, 0.0, 0.0],
+        [0.039628, 0.329244, 0.788663],
+        [0.183948, 0.535958, 0.390005],
+        [0.713893, 0.518257, 0.718242],
+        [1.0, 1.0, 0.666667],
+    ]),
+    (LUT_AB_PROFILE, sig::tags::A_TO_B0, [
+        [0.140788, 0.540714, 0.58043],
+        [0.288073, 0.474682, 0.41039],
+        [0.166627, 0.522731, 0.51338],
+        [0.22614, 0.601745, 0.395474],
+        [0.642154, 0.523757, 0.416359],
+    ]),
+    (LUT_AB_PROFILE, sig::tags::B_TO_A0, [
+        [0.0, 0.056336, 0.0],
+        [0.190702, 0.342931, 0.656113],
+        [0.3941, 0.554147, 0.208565],
+        [0.816913, 0.555245, 0.603495],
+        [1.0, 0.982315, 0.606273],
+    ]),
+    (LUT_AB_PROFILE, sig::tags::D_TO_B0, [
+        [0.0, 0.011, 0.1],
+        [0.167244, 0.213151, 0.381501],
+        [0.159912, 0.321408, 0.126213],
+        [0.274823, 0.190397, 0.204872],
+        [0.86787, 1.1, 0.50755],
+    ]),
+];
+
+#[test]
+fn lut_tags_evaluate_as_in_lcms() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let inputs = [
+        [0.0, 0.0, 0.0],
+        [0.1, 0.5, 0.9],
+        [0.33, 0.66, 0.25],
+        [0.8, 0.2, 0.6],
+        [1.0, 1.0, 1.0],
+    ];
+
+    for (data, sig, expected) in LCMS_EVALUATIONS {
+        let profile = Profile::open_from_mem(ctx, data)?;
+        let lut = match profile.read_tag(*sig)?.downcast_ref::<Pipeline>() {
+            Some(lut) => lut,
+            None => return Err("Wrong data type"),
+        };
+
+        for (input, expected) in inputs.iter().zip(expected) {
+            let mut output = [0f32; 3];
+            lut.eval_f32(input, &mut output);
+
+            for (actual, expected) in output.iter().zip(expected) {
+                assert!(
+                    (actual - expected).abs() < 1e-5,
+                    "'{}' of {:?} is {:?}, lcms gives {:?}",
+                    sig,
+                    input,
+                    output,
+                    expected
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cluts_larger_than_their_tag_are_rejected() {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // 7 in, 3 out, 255 points, the CLUT alone would take over 2^58 bytes
+    #[rustfmt::skip]
+    let lut16: &[u8] = &[
+        7, 3, 255, 0,
+        0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // Identity matrix
+        0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+        0, 2, 0, 2, // Entries
+        0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF,
+        0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    // 3 in, 3 out, only a CLUT with 255 points of 2 bytes
+    #[rustfmt::skip]
+    let lut_a_to_b: &[u8] = &[
+        3, 3, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32, 0, 0, 0, 0, // Offsets
+        255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        2, 0, 0, 0, // Precision
+    ];
+
+    // 3 in, 3 out, one CLUT element with 255 points
+    #[rustfmt::skip]
+    let mpe: &[u8] = &[
+        0, 3, 0, 3, 0, 0, 0, 1,
+        0, 0, 0, 24, 0, 0, 0, 20, // Position table
+        0x63, 0x6C, 0x75, 0x74, 0, 0, 0, 0, // 'clut'
+        0, 3, 0, 3, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    for (sig, block) in [
+        (sig::types::LUT16, lut16),
+        (sig::types::LUT_A_TO_B, lut_a_to_b),
+        (sig::types::MULTI_PROCESS_ELEMENT, mpe),
+    ] {
+        let handler = ctx.get_tag_type_handler(sig).unwrap();
+        assert!(read(ctx, &handler, block).is_err(), "'{}' was read", sig);
+    }
+}
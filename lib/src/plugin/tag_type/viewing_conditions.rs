@@ -0,0 +1,58 @@
+use std::any::Any;
+
+use crate::{io::IoHandler, sig, types::IccViewingConditions, Result};
+
+use super::TagTypeHandler;
+
+pub(crate) const VIEWING_CONDITIONS_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::VIEWING_CONDITIONS,
+    read: read_viewing_conditions,
+    write: write_viewing_conditions,
+    dup: dup_viewing_conditions,
+};
+
+fn read_viewing_conditions(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let illuminant = io.read_xyz().map_err(|_| "Read error")?;
+    let surround = io.read_xyz().map_err(|_| "Read error")?;
+    let illuminant_type = io.read_u32().map_err(|_| "Read error")?;
+
+    *n_items = 1;
+    Ok(Box::new(IccViewingConditions {
+        illuminant,
+        surround,
+        illuminant_type,
+    }))
+}
+
+fn write_viewing_conditions(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    match data.downcast_ref::<IccViewingConditions>() {
+        Some(view) => {
+            io.write_xyz(view.illuminant).map_err(|_| "Write error")?;
+            io.write_xyz(view.surround).map_err(|_| "Write error")?;
+            io.write_u32(view.illuminant_type)
+                .map_err(|_| "Write error")
+        }
+        None => Err("Wrong data type"),
+    }
+}
+
+fn dup_viewing_conditions(
+    _handler: &TagTypeHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<IccViewingConditions>() {
+        Some(view) => Ok(Box::new(*view)),
+        None => Err("Wrong data type"),
+    }
+}
@@ -0,0 +1,43 @@
+use std::any::Any;
+
+use crate::{io::IoHandler, sig, types::XYZ, Result};
+
+use super::TagTypeHandler;
+
+pub(crate) const XYZ_HANDLER: TagTypeHandler = TagTypeHandler {
+    sig: sig::types::XYZ,
+    read: read_xyz,
+    write: write_xyz,
+    dup: dup_xyz,
+};
+
+fn read_xyz(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    let xyz = io.read_xyz().map_err(|_| "Read error")?;
+
+    *n_items = 1;
+    Ok(Box::new(xyz))
+}
+
+fn write_xyz(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    match data.downcast_ref::<XYZ>() {
+        Some(xyz) => io.write_xyz(*xyz).map_err(|_| "Write error"),
+        None => Err("Wrong data type"),
+    }
+}
+
+fn dup_xyz(_handler: &TagTypeHandler, data: &dyn Any, _n_items: usize) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<XYZ>() {
+        Some(xyz) => Ok(Box::new(*xyz)),
+        None => Err("Wrong data type"),
+    }
+}
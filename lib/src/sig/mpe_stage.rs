@@ -23,3 +23,11 @@ pub const FLOAT_PCS_2_LAB: Signature = Signature(0x6C326420);
 pub const XYZ_2_FLOAT_PCS: Signature = Signature(0x64327820);
 pub const FLOAT_PCS_2_XYZ: Signature = Signature(0x78326420);
 pub const CLIP_NEGATIVES: Signature = Signature(0x636c7020);
+
+// Perceptually uniform spaces
+pub const XYZ_2_OKLAB: Signature = Signature(0x78326F20);
+pub const OKLAB_2_XYZ: Signature = Signature(0x6F327820);
+pub const XYZ_2_JZAZBZ: Signature = Signature(0x78326A20);
+pub const JZAZBZ_2_XYZ: Signature = Signature(0x6A327820);
+pub const XYZ_2_ICTCP: Signature = Signature(0x78326920);
+pub const ICTCP_2_XYZ: Signature = Signature(0x69327820);
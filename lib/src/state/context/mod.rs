@@ -10,13 +10,16 @@ use once_cell::sync::Lazy;
 
 use crate::{
     plugin::{
-        default_interpolators_factory, FormatterInFactory, FormatterOutFactory, InterpFnFactory,
-        OptimizationFn, Plugin, TagTypeHandler, TransformFunc, DEFAULT_FORMATTER_FACTORIES,
-        DEFAULT_INTENTS, DEFAULT_MPE_TYPE_HANDLERS, DEFAULT_OPTIMIZATIONS,
-        DEFAULT_PARAMETRIC_CURVE, DEFAULT_TAGS, DEFAULT_TAG_TYPE_HANDLERS,
+        default_interpolators_factory, pack_flags, CurveDef, FormatterIn, FormatterInFactory,
+        FormatterOut, FormatterOutFactory, InterpFnFactory, OptimizationFn,
+        ParametricCurveEvaluator, Plugin, TagDescriptor, TagTypeHandler, TransformFunc,
+        DEFAULT_FORMATTER_FACTORIES, DEFAULT_INTENTS, DEFAULT_MPE_TYPE_HANDLERS,
+        DEFAULT_OPTIMIZATIONS, DEFAULT_PARAMETRIC_CURVE, DEFAULT_TAGS, DEFAULT_TAG_TYPE_HANDLERS,
         DEFAULT_TRANSFORM_FACTORIES,
     },
-    sig, Result, MAX_CHANNELS, VERSION,
+    sig,
+    types::Signature,
+    Result, MAX_CHANNELS, VERSION,
 };
 
 use super::{ErrorCode, ErrorHandlerLogFunction, Intent, Parallelization, ParametricCurve, Tag};
@@ -26,7 +29,7 @@ pub const DEFAULT_CONTEXT: Lazy<Context> = Lazy::new(|| {
         alarm_codes: [
             0x7F00, 0x7F00, 0x7F00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         ],
-        adaptation_state: 1.0,
+        adaptation_state: UnsafeCell::new(1.0),
         user_data: None,
         error_logger: UnsafeCell::new(None),
         interp_factory: default_interpolators_factory,
@@ -48,7 +51,7 @@ pub struct Context(Arc<ContextInner>);
 
 struct ContextInner {
     alarm_codes: [u16; MAX_CHANNELS],
-    adaptation_state: f64,
+    adaptation_state: UnsafeCell<f64>,
     user_data: Option<Arc<Mutex<Box<dyn Any + Sync + Send>>>>,
     error_logger: UnsafeCell<Option<ErrorHandlerLogFunction>>,
     interp_factory: InterpFnFactory,
@@ -68,7 +71,9 @@ impl Clone for ContextInner {
     fn clone(&self) -> Self {
         Self {
             alarm_codes: self.alarm_codes.clone(),
-            adaptation_state: self.adaptation_state.clone(),
+            adaptation_state: UnsafeCell::new(
+                unsafe_block!("Access adaptation_state for cloning" => *self.adaptation_state.get()),
+            ),
             user_data: self.user_data.clone(),
             error_logger: UnsafeCell::new(
                 unsafe_block!("Access error_logger for cloning" => (&*self.error_logger.get()).clone()),
@@ -126,9 +131,124 @@ impl Context {
         num_intents
     }
 
+    /// Searches for the handler of a rendering intent. Plugins take precedence over the
+    /// defaults.
+    pub fn get_intent(&self, intent: u32) -> Option<&Intent> {
+        self.0.intents.iter().rev().find(|i| i.value == intent)
+    }
+
+    /// The adaptation state used when the observer isn't specified.
+    pub fn get_adaptation_state(&self) -> f64 {
+        unsafe_block!("Get the adaptation_state within the UnsafeCell" => *self.0.adaptation_state.get())
+    }
+
+    /// Sets the observer adaptation used when it isn't specified, from 0 (not adapted) to 1
+    /// (fully adapted).
+    pub fn set_adaptation_state(&self, adaptation_state: f64) {
+        unsafe_block!("" => *self.0.adaptation_state.get() = adaptation_state)
+    }
+
     pub fn get_interp_factory(&self) -> InterpFnFactory {
         self.0.interp_factory
     }
+
+    /// Searches for the evaluator of a parametric curve type, along with its definition. Negative
+    /// types are the inverses of their positive counterparts and share the same definition.
+    pub fn get_parametric_curve(
+        &self,
+        r#type: i32,
+    ) -> Option<(ParametricCurveEvaluator, CurveDef)> {
+        let fn_type = r#type.unsigned_abs();
+
+        self.0.curves.iter().rev().find_map(|collection| {
+            collection
+                .curves
+                .iter()
+                .find(|curve| curve.fn_type == fn_type)
+                .map(|curve| (collection.eval, *curve))
+        })
+    }
+
+    /// Searches for an input formatter able to read `r#type`, in 16 bits or floating point
+    /// depending on `flags`. Plugins take precedence over the defaults.
+    pub fn get_formatter_in(&self, r#type: u32, flags: u32) -> FormatterIn {
+        for factory in self.0.formatters_in.iter().rev() {
+            let formatter = factory(r#type, flags);
+            if matches!(
+                formatter,
+                FormatterIn::U16(Some(_)) | FormatterIn::F32(Some(_))
+            ) {
+                return formatter;
+            }
+        }
+
+        if flags & pack_flags::FLOAT != 0 {
+            FormatterIn::F32(None)
+        } else {
+            FormatterIn::U16(None)
+        }
+    }
+
+    /// Searches for an output formatter able to write `r#type`, in 16 bits or floating point
+    /// depending on `flags`. Plugins take precedence over the defaults.
+    pub fn get_formatter_out(&self, r#type: u32, flags: u32) -> FormatterOut {
+        for factory in self.0.formatters_out.iter().rev() {
+            let formatter = factory(r#type, flags);
+            if matches!(
+                formatter,
+                FormatterOut::U16(Some(_)) | FormatterOut::F32(Some(_))
+            ) {
+                return formatter;
+            }
+        }
+
+        if flags & pack_flags::FLOAT != 0 {
+            FormatterOut::F32(None)
+        } else {
+            FormatterOut::U16(None)
+        }
+    }
+
+    /// The pipeline optimizations, plugins first.
+    pub fn get_optimizations(&self) -> impl Iterator<Item = &OptimizationFn> {
+        self.0.optimizations.iter().rev()
+    }
+
+    /// The transform factories, plugins first.
+    pub fn get_transform_factories(&self) -> impl Iterator<Item = &TransformFunc> {
+        self.0.transforms.iter().rev()
+    }
+
+    /// Searches for the handler of a tag type. Plugins take precedence over the defaults.
+    pub fn get_tag_type_handler(&self, sig: Signature) -> Option<TagTypeHandler> {
+        self.0
+            .tag_types
+            .iter()
+            .rev()
+            .find(|handler| handler.sig == sig)
+            .cloned()
+    }
+
+    /// Searches for the handler of a multi process element type. Plugins take precedence over
+    /// the defaults.
+    pub fn get_mpe_type_handler(&self, sig: Signature) -> Option<TagTypeHandler> {
+        self.0
+            .mpe_types
+            .iter()
+            .rev()
+            .find(|handler| handler.sig == sig)
+            .cloned()
+    }
+
+    /// Searches for the descriptor of a tag. Plugins take precedence over the defaults.
+    pub fn get_tag_descriptor(&self, sig: Signature) -> Option<&'static TagDescriptor> {
+        self.0
+            .tags
+            .iter()
+            .rev()
+            .find(|tag| tag.sig == sig)
+            .map(|tag| tag.desc)
+    }
 }
 
 impl Default for Context {
@@ -45,6 +45,8 @@ fn register_tag_type_plugin_succeeds() -> Result<()> {
 static TEST_TAG_TYPE: &[TagTypeHandler] = &[TagTypeHandler {
     sig: Signature::from_str(b"BUTT"),
     read: |_, _, _, _| panic!("This function should never run!!!"),
+    write: |_, _, _, _| panic!("This function should never run!!!"),
+    dup: |_, _, _| panic!("This function should never run!!!"),
 }];
 static TEST_TAG_TYPE_PLUGIN: Plugin = Plugin::create_tag_type_plugin(&TEST_TAG_TYPE);
 
@@ -65,6 +67,8 @@ fn register_mpe_type_plugin_succeeds() -> Result<()> {
 static TEST_MPE_TYPE: &[TagTypeHandler] = &[TagTypeHandler {
     sig: Signature::from_str(b"BUTT"),
     read: |_, _, _, _| panic!("This function should never run!!!"),
+    write: |_, _, _, _| panic!("This function should never run!!!"),
+    dup: |_, _, _| panic!("This function should never run!!!"),
 }];
 static TEST_MPE_TYPE_PLUGIN: Plugin = Plugin::create_mpe_type_plugin(&TEST_MPE_TYPE);
 
@@ -0,0 +1,366 @@
+use crate::{state::Context, Result, D50};
+
+use super::{cone_matrix, IccViewingConditions, Mat3, Vec3, XYZ};
+
+/// The surround factors of a viewing condition: the degree of adaptation `f`, the impact of the
+/// surround `c`, and the chromatic induction `nc`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Surround {
+    pub f: f64,
+    pub c: f64,
+    pub nc: f64,
+}
+
+/// The surrounds CIECAM02 defines.
+pub mod surround {
+    use super::Surround;
+
+    /// Surface colors, as prints viewed in a light booth.
+    pub const AVERAGE: Surround = Surround {
+        f: 1.0,
+        c: 0.69,
+        nc: 1.0,
+    };
+
+    /// Television and monitors in a dim room.
+    pub const DIM: Surround = Surround {
+        f: 0.9,
+        c: 0.59,
+        nc: 0.95,
+    };
+
+    /// Projectors in a dark room.
+    pub const DARK: Surround = Surround {
+        f: 0.8,
+        c: 0.525,
+        nc: 0.8,
+    };
+
+    /// Transparencies on a light box.
+    pub const CUT_SHEET: Surround = Surround {
+        f: 0.8,
+        c: 0.41,
+        nc: 0.8,
+    };
+}
+
+/// The conditions a color is seen under.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewingConditions {
+    /// The adopted white, on the same scale as the colors it is used with.
+    pub white_point: XYZ,
+    /// The relative luminance of the background, with the white at 100.
+    pub yb: f64,
+    /// The luminance of the adapting field, in cd/m².
+    pub la: f64,
+    pub surround: Surround,
+    /// The degree of adaptation to the white, from 0 to 1. `None` computes it from the
+    /// surround and the adapting luminance.
+    pub d: Option<f64>,
+}
+
+/// The appearance correlates of a color: lightness J, chroma C, hue angle h in degrees,
+/// brightness Q, colorfulness M and saturation s.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Appearance {
+    pub j: f64,
+    pub c: f64,
+    pub h: f64,
+    pub q: f64,
+    pub m: f64,
+    pub s: f64,
+}
+
+/// A color appearance model, CIECAM02 or CAM16, set up for some viewing conditions.
+#[derive(Clone, Debug)]
+pub struct Cam {
+    /// XYZ to the cone responses chromatic adaptation works on.
+    cat: Mat3,
+    cat_inv: Mat3,
+    /// Adapted cone responses to the ones the post adaptation compression works on.
+    compression: Mat3,
+    compression_inv: Mat3,
+    /// The gain of each adapted cone.
+    gain: Vec3,
+    /// The factor taking colors to the 0..100 scale of the model.
+    scale: f64,
+    c: f64,
+    nc: f64,
+    fl: f64,
+    n: f64,
+    z: f64,
+    nbb: f64,
+    aw: f64,
+}
+
+/// The Hunt-Pointer-Estévez cone space CIECAM02 compresses in.
+const HPE: Mat3 = Mat3::new([
+    [0.38971, 0.68898, -0.07868],
+    [-0.22981, 1.18340, 0.04641],
+    [0.0, 0.0, 1.0],
+]);
+
+impl ViewingConditions {
+    /// The viewing conditions a profile tag describes. The background is taken as the usual 20%
+    /// grey, so the adapting field is a fifth of the illuminant, and the surround is picked
+    /// from the ratio of its luminance to the illuminant's, as CIE 159 suggests.
+    pub fn from_icc(view: &IccViewingConditions) -> Self {
+        let illuminant = view.illuminant;
+        let ratio = if illuminant.y > 0.0 {
+            view.surround.y / illuminant.y
+        } else {
+            0.0
+        };
+
+        let surround = if ratio <= 0.0 {
+            surround::DARK
+        } else if ratio < 0.2 {
+            surround::DIM
+        } else {
+            surround::AVERAGE
+        };
+
+        Self {
+            white_point: if illuminant.y > 0.0 {
+                XYZ::new(
+                    illuminant.x / illuminant.y,
+                    1.0,
+                    illuminant.z / illuminant.y,
+                )
+            } else {
+                D50
+            },
+            yb: 20.0,
+            la: illuminant.y / 5.0,
+            surround,
+            d: None,
+        }
+    }
+}
+
+impl Cam {
+    /// The CIECAM02 model, which adapts with CAT02 and compresses in the Hunt-Pointer-Estévez
+    /// space.
+    pub fn ciecam02(context_id: &Context, conditions: &ViewingConditions) -> Result<Self> {
+        let cat_inv = cat_inverse(context_id, &cone_matrix::CAT02)?;
+
+        Self::new(context_id, conditions, cone_matrix::CAT02, HPE * cat_inv)
+    }
+
+    /// The CAM16 model, which adapts and compresses in the same CAT16 space.
+    pub fn cam16(context_id: &Context, conditions: &ViewingConditions) -> Result<Self> {
+        Self::new(context_id, conditions, cone_matrix::CAT16, Mat3::IDENTITY)
+    }
+
+    fn new(
+        context_id: &Context,
+        conditions: &ViewingConditions,
+        cat: Mat3,
+        compression: Mat3,
+    ) -> Result<Self> {
+        let white = conditions.white_point;
+        if white.y <= 0.0 || conditions.yb <= 0.0 || conditions.la < 0.0 {
+            return err!(context_id, Error, Range, "Invalid viewing conditions: white Y = {}, Yb = {}, La = {}", white.y, conditions.yb, conditions.la; str => "Invalid viewing conditions");
+        }
+
+        let cat_inv = cat_inverse(context_id, &cat)?;
+        let compression_inv = match compression.inverse() {
+            Some(inv) => inv,
+            None => {
+                return err!(context_id, Error, Range, "Compression matrix is not invertible"; str => "Compression matrix is not invertible")
+            }
+        };
+
+        let Surround { f, c, nc } = conditions.surround;
+        let la = conditions.la;
+
+        let d = match conditions.d {
+            Some(d) => d,
+            None => f * (1.0 - (1.0 / 3.6) * ((-la - 42.0) / 92.0).exp()),
+        }
+        .clamp(0.0, 1.0);
+
+        // Everything works on a 0..100 scale
+        let white_scale = 100.0 / white.y;
+        let white = scale(&white, white_scale);
+        let rgb_w = cat * Vec3::from(white);
+        if rgb_w.0.iter().any(|c| c.abs() < 1e-9) {
+            return err!(context_id, Error, Range, "White has no cone response"; str => "White has no cone response");
+        }
+        let gain = Vec3(rgb_w.0.map(|c| 100.0 * d / c + 1.0 - d));
+
+        let k = 1.0 / (5.0 * la + 1.0);
+        let k4 = k * k * k * k;
+        let fl = 0.2 * k4 * (5.0 * la) + 0.1 * (1.0 - k4) * (1.0 - k4) * (5.0 * la).cbrt();
+
+        let n = conditions.yb / 100.0;
+        let z = 1.48 + n.sqrt();
+        let nbb = 0.725 * n.powf(-0.2);
+
+        let mut cam = Self {
+            cat,
+            cat_inv,
+            compression,
+            compression_inv,
+            gain,
+            scale: white_scale,
+            c,
+            nc,
+            fl,
+            n,
+            z,
+            nbb,
+            aw: 0.0,
+        };
+
+        let (_, _, aw) = cam.opponent(cam.post_adaptation(&white));
+        cam.aw = aw;
+
+        Ok(cam)
+    }
+
+    /// The appearance of `xyz`, which must be on the scale of the adopted white.
+    pub fn forward(&self, xyz: &XYZ) -> Appearance {
+        let rgb = self.post_adaptation(&scale(xyz, self.scale));
+        let (a, b, achromatic) = self.opponent(rgb);
+
+        let h = b.atan2(a).to_degrees();
+        let h = if h < 0.0 { h + 360.0 } else { h };
+
+        let j = 100.0 * (achromatic / self.aw).max(0.0).powf(self.c * self.z);
+        let q = (4.0 / self.c) * (j / 100.0).sqrt() * (self.aw + 4.0) * self.fl.powf(0.25);
+
+        let (ra, ga, ba) = rgb;
+        let t = (50000.0 / 13.0) * self.nc * self.nbb * eccentricity(h) * a.hypot(b)
+            / (ra + ga + (21.0 / 20.0) * ba);
+
+        let c = t.powf(0.9) * (j / 100.0).sqrt() * (1.64 - 0.29f64.powf(self.n)).powf(0.73);
+        let m = c * self.fl.powf(0.25);
+        let s = if q > 0.0 { 100.0 * (m / q).sqrt() } else { 0.0 };
+
+        Appearance { j, c, h, q, m, s }
+    }
+
+    /// The color of `appearance`, from its lightness J, chroma C and hue h. Brightness,
+    /// colorfulness and saturation are not used.
+    pub fn reverse(&self, appearance: &Appearance) -> XYZ {
+        let Appearance { j, c, h, .. } = *appearance;
+
+        let t = if j > 0.0 {
+            (c / ((j / 100.0).sqrt() * (1.64 - 0.29f64.powf(self.n)).powf(0.73))).powf(1.0 / 0.9)
+        } else {
+            0.0
+        };
+        let achromatic = self.aw * (j.max(0.0) / 100.0).powf(1.0 / (self.c * self.z));
+
+        let (a, b) = if t == 0.0 {
+            (0.0, 0.0)
+        } else {
+            let hr = h.to_radians();
+            let (sin, cos) = hr.sin_cos();
+            let p1 = (50000.0 / 13.0) * self.nc * self.nbb * eccentricity(h) / t;
+            let p2 = achromatic / self.nbb + 0.305;
+            let p3 = 21.0 / 20.0;
+
+            if sin.abs() >= cos.abs() {
+                let p4 = p1 / sin;
+                let b = p2 * (2.0 + p3) * (460.0 / 1403.0)
+                    / (p4 + (2.0 + p3) * (220.0 / 1403.0) * (cos / sin) - (27.0 / 1403.0)
+                        + p3 * (6300.0 / 1403.0));
+                (b * cos / sin, b)
+            } else {
+                let p5 = p1 / cos;
+                let a = p2 * (2.0 + p3) * (460.0 / 1403.0)
+                    / (p5 + (2.0 + p3) * (220.0 / 1403.0)
+                        - ((27.0 / 1403.0) - p3 * (6300.0 / 1403.0)) * (sin / cos));
+                (a, a * sin / cos)
+            }
+        };
+
+        let p2 = achromatic / self.nbb + 0.305;
+        let ra = (460.0 * p2 + 451.0 * a + 288.0 * b) / 1403.0;
+        let ga = (460.0 * p2 - 891.0 * a - 261.0 * b) / 1403.0;
+        let ba = (460.0 * p2 - 220.0 * a - 6300.0 * b) / 1403.0;
+
+        let rgb = Vec3([ra, ga, ba].map(|c| self.decompress(c)));
+        let rgb_c = self.compression_inv * rgb;
+        let rgb = Vec3([0, 1, 2].map(|i| rgb_c.0[i] / self.gain.0[i]));
+
+        scale(&(self.cat_inv * rgb).into(), 1.0 / self.scale)
+    }
+
+    /// The compressed cone responses of `xyz`, on the 0..100 scale.
+    fn post_adaptation(&self, xyz: &XYZ) -> (f64, f64, f64) {
+        let rgb = self.cat * Vec3::from(*xyz);
+        let rgb_c = Vec3([0, 1, 2].map(|i| rgb.0[i] * self.gain.0[i]));
+        let [r, g, b] = (self.compression * rgb_c).0.map(|c| self.compress(c));
+
+        (r, g, b)
+    }
+
+    /// The red-green and yellow-blue opponent signals, and the achromatic response of the
+    /// compressed cone responses.
+    fn opponent(&self, (r, g, b): (f64, f64, f64)) -> (f64, f64, f64) {
+        (
+            r - 12.0 * g / 11.0 + b / 11.0,
+            (r + g - 2.0 * b) / 9.0,
+            (2.0 * r + g + b / 20.0 - 0.305) * self.nbb,
+        )
+    }
+
+    /// The post adaptation non linear response compression.
+    fn compress(&self, c: f64) -> f64 {
+        let t = (self.fl * c.abs() / 100.0).powf(0.42);
+
+        c.signum() * 400.0 * t / (t + 27.13) + 0.1
+    }
+
+    /// The inverse of [`Cam::compress`].
+    fn decompress(&self, c: f64) -> f64 {
+        let c = c - 0.1;
+        let t = (27.13 * c.abs() / (400.0 - c.abs())).powf(1.0 / 0.42);
+
+        c.signum() * (100.0 / self.fl) * t
+    }
+}
+
+/// The difference between two appearances in the CAM16 uniform color space.
+pub fn delta_e_cam16_ucs(appearance1: &Appearance, appearance2: &Appearance) -> f64 {
+    let [j1, a1, b1] = ucs(appearance1);
+    let [j2, a2, b2] = ucs(appearance2);
+
+    ((j1 - j2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+}
+
+/// The J', a' and b' coordinates of the CAM16 uniform color space.
+fn ucs(appearance: &Appearance) -> [f64; 3] {
+    const C1: f64 = 0.007;
+    const C2: f64 = 0.0228;
+
+    let j = (1.0 + 100.0 * C1) * appearance.j / (1.0 + C1 * appearance.j);
+    let m = (1.0 + C2 * appearance.m).ln() / C2;
+    let (sin, cos) = appearance.h.to_radians().sin_cos();
+
+    [j, m * cos, m * sin]
+}
+
+/// The eccentricity factor of hue `h`, in degrees.
+fn eccentricity(h: f64) -> f64 {
+    0.25 * ((h.to_radians() + 2.0).cos() + 3.8)
+}
+
+fn cat_inverse(context_id: &Context, cat: &Mat3) -> Result<Mat3> {
+    match cat.inverse() {
+        Some(inv) => Ok(inv),
+        None => {
+            err!(context_id, Error, Range, "Cone matrix is not invertible"; str => "Cone matrix is not invertible")
+        }
+    }
+}
+
+fn scale(xyz: &XYZ, factor: f64) -> XYZ {
+    XYZ::new(xyz.x * factor, xyz.y * factor, xyz.z * factor)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,226 @@
+use crate::{
+    state::DEFAULT_CONTEXT,
+    types::{illuminant_type, IccViewingConditions, XYZ},
+    Result, D50,
+};
+
+use super::{delta_e_cam16_ucs, surround, Appearance, Cam, Surround, ViewingConditions};
+
+fn conditions(white_point: XYZ, la: f64, surround: Surround) -> ViewingConditions {
+    ViewingConditions {
+        white_point,
+        yb: 20.0,
+        la,
+        surround,
+        d: None,
+    }
+}
+
+fn assert_appearance(actual: &Appearance, expected: [f64; 6], tolerance: f64) {
+    let values = [actual.j, actual.c, actual.h, actual.q, actual.m, actual.s];
+    for (a, e) in values.iter().zip(expected) {
+        assert!((a - e).abs() < tolerance, "{:?} != {:?}", actual, expected);
+    }
+}
+
+/// The worked example of the colour-science package, as J, C, h, Q, M and s.
+const REFERENCE_XYZ: XYZ = XYZ::new(19.01, 20.0, 21.78);
+const REFERENCE_WHITE: XYZ = XYZ::new(95.05, 100.0, 108.88);
+
+#[test]
+fn ciecam02_matches_reference() -> Result<()> {
+    let cam = Cam::ciecam02(
+        &DEFAULT_CONTEXT,
+        &conditions(REFERENCE_WHITE, 318.31, surround::AVERAGE),
+    )?;
+
+    assert_appearance(
+        &cam.forward(&REFERENCE_XYZ),
+        [
+            41.73109113,
+            0.10470776,
+            219.04843266,
+            195.37132597,
+            0.10884218,
+            2.36030533,
+        ],
+        1e-6,
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cam16_matches_reference() -> Result<()> {
+    let cam = Cam::cam16(
+        &DEFAULT_CONTEXT,
+        &conditions(REFERENCE_WHITE, 318.31, surround::AVERAGE),
+    )?;
+
+    assert_appearance(
+        &cam.forward(&REFERENCE_XYZ),
+        [
+            41.73120791,
+            0.10335574,
+            217.06795977,
+            195.37170899,
+            0.10743677,
+            2.34501507,
+        ],
+        1e-6,
+    );
+
+    Ok(())
+}
+
+#[test]
+fn ciecam02_matches_lcms_with_cie_adaptation() -> Result<()> {
+    // Surround, La, XYZ over a D50 white of Y = 100, and J, C, h. These come from an unmodified
+    // lcms 2, running cmsCIECAM02Forward with Yb = 20. lcms computes its default D as
+    // F - exp((-La - 42) / 92) / 3.6, so its viewing conditions were given the CIE degree of
+    // adaptation, F * (1 - exp((-La - 42) / 92) / 3.6), as an explicit D.
+    #[rustfmt::skip]
+    let expected: [(Surround, f64, [f64; 3], [f64; 3]); 6] = [
+        (surround::AVERAGE, 64.0, [41.24, 21.26, 1.93], [46.22337734, 106.53657521, 30.34766048]),
+        (surround::AVERAGE, 64.0, [18.05, 7.22, 95.05], [23.91347392, 97.86988087, 256.49258880]),
+        (surround::AVERAGE, 64.0, [50.0, 50.0, 50.0], [68.80235814, 12.98541658, 290.22087983]),
+        (surround::DIM, 20.0, [5.0, 3.0, 1.0], [20.18723088, 45.26033947, 23.04770072]),
+        (surround::DIM, 20.0, [41.24, 21.26, 1.93], [51.59466129, 107.54946455, 30.56919948]),
+        (surround::DARK, 200.0, [18.05, 7.22, 95.05], [33.49543748, 94.62182249, 256.44926312]),
+    ];
+
+    for (surround, la, xyz, [j, c, h]) in expected {
+        let cam = Cam::ciecam02(&DEFAULT_CONTEXT, &conditions(D50, la, surround))?;
+        let actual = cam.forward(&XYZ::from(xyz.map(|v| v / 100.0)));
+
+        // lcms has its own rounding of the inverse of CAT02
+        assert!(
+            (actual.j - j).abs() < 1e-3,
+            "{:?} != {:?}",
+            actual,
+            [j, c, h]
+        );
+        assert!(
+            (actual.c - c).abs() < 1e-3,
+            "{:?} != {:?}",
+            actual,
+            [j, c, h]
+        );
+        assert!(
+            (actual.h - h).abs() < 1e-3,
+            "{:?} != {:?}",
+            actual,
+            [j, c, h]
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn appearance_models_round_trip() -> Result<()> {
+    for surround in [
+        surround::AVERAGE,
+        surround::DIM,
+        surround::DARK,
+        surround::CUT_SHEET,
+    ] {
+        let conditions = conditions(D50, 64.0, surround);
+
+        for cam in [
+            Cam::ciecam02(&DEFAULT_CONTEXT, &conditions)?,
+            Cam::cam16(&DEFAULT_CONTEXT, &conditions)?,
+        ] {
+            let black = cam.forward(&XYZ::default());
+            assert!(black.j < 1e-12);
+            let back = cam.reverse(&black);
+            assert!(back.x.abs() < 1e-12 && back.y.abs() < 1e-12 && back.z.abs() < 1e-12);
+
+            // Colors without luminance have no achromatic response to get back from
+            for x in 1..=10 {
+                for y in 1..=10 {
+                    for z in 1..=10 {
+                        let xyz = XYZ::new(x as f64 / 10.0, y as f64 / 10.0, z as f64 / 10.0);
+                        let back = cam.reverse(&cam.forward(&xyz));
+
+                        assert!(
+                            (back.x - xyz.x).abs() < 1e-9
+                                && (back.y - xyz.y).abs() < 1e-9
+                                && (back.z - xyz.z).abs() < 1e-9,
+                            "{:?} != {:?}",
+                            back,
+                            xyz
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn white_is_fully_light() -> Result<()> {
+    let conditions = conditions(D50, 64.0, surround::AVERAGE);
+
+    for cam in [
+        Cam::ciecam02(&DEFAULT_CONTEXT, &conditions)?,
+        Cam::cam16(&DEFAULT_CONTEXT, &conditions)?,
+    ] {
+        let white = cam.forward(&D50);
+        assert!((white.j - 100.0).abs() < 1e-9);
+        assert!(white.c < 2.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn delta_e_cam16_ucs_compares_appearances() -> Result<()> {
+    let cam = Cam::cam16(&DEFAULT_CONTEXT, &conditions(D50, 64.0, surround::AVERAGE))?;
+    let red = cam.forward(&XYZ::new(0.4124, 0.2126, 0.0193));
+    let blue = cam.forward(&XYZ::new(0.1805, 0.0722, 0.9505));
+
+    assert_eq!(delta_e_cam16_ucs(&red, &red), 0.0);
+    assert_eq!(
+        delta_e_cam16_ucs(&red, &blue),
+        delta_e_cam16_ucs(&blue, &red)
+    );
+
+    // Neutrals only differ in J' = 1.7 J / (1 + 0.007 J)
+    let gray1 = Appearance {
+        j: 40.0,
+        ..Default::default()
+    };
+    let gray2 = Appearance {
+        j: 60.0,
+        ..Default::default()
+    };
+    let expected = 1.7 * 60.0 / (1.0 + 0.007 * 60.0) - 1.7 * 40.0 / (1.0 + 0.007 * 40.0);
+    assert!((delta_e_cam16_ucs(&gray1, &gray2) - expected).abs() < 1e-12);
+
+    Ok(())
+}
+
+#[test]
+fn viewing_conditions_come_from_icc_tags() {
+    let view = IccViewingConditions {
+        illuminant: XYZ::new(0.9642 * 64.0, 64.0, 0.8249 * 64.0),
+        surround: XYZ::new(0.9642 * 12.8, 12.8, 0.8249 * 12.8),
+        illuminant_type: illuminant_type::D50,
+    };
+
+    let conditions = ViewingConditions::from_icc(&view);
+    assert!((conditions.white_point.x - 0.9642).abs() < 1e-12);
+    assert_eq!(conditions.white_point.y, 1.0);
+    assert!((conditions.white_point.z - 0.8249).abs() < 1e-12);
+    assert!((conditions.la - 12.8).abs() < 1e-12);
+    assert_eq!(conditions.surround, surround::AVERAGE);
+
+    let dim = ViewingConditions::from_icc(&IccViewingConditions {
+        surround: XYZ::new(0.9642, 1.0, 0.8249),
+        ..view
+    });
+    assert_eq!(dim.surround, surround::DIM);
+}
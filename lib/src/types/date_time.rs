@@ -1,4 +1,5 @@
 #[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct DateTimeNumber {
     pub year: u16,
     pub month: u16,
@@ -0,0 +1,220 @@
+use crate::Result;
+
+use super::{LCh, Lab};
+
+/// The CIE 1976 color difference: the euclidean distance between both colors.
+pub fn delta_e_76(lab1: &Lab, lab2: &Lab) -> f64 {
+    let dl = lab1.l - lab2.l;
+    let da = lab1.a - lab2.a;
+    let db = lab1.b - lab2.b;
+
+    (dl * dl + da * da + db * db).sqrt()
+}
+
+/// The CIE 1994 color difference of `sample` from `reference`, with the graphic arts constants.
+/// `kl`, `kc` and `kh` weight the lightness, chroma and hue differences, textiles use a `kl` of
+/// 2.
+pub fn delta_e_94(reference: &Lab, sample: &Lab, kl: f64, kc: f64, kh: f64) -> f64 {
+    const K1: f64 = 0.045;
+    const K2: f64 = 0.015;
+
+    let lch1 = LCh::from(*reference);
+    let lch2 = LCh::from(*sample);
+
+    let dl = reference.l - sample.l;
+    let dc = lch1.c - lch2.c;
+    let dh = hue_difference(reference, sample, dl, dc);
+
+    let sc = 1.0 + K1 * lch1.c;
+    let sh = 1.0 + K2 * lch1.c;
+
+    (sqr(dl / kl) + sqr(dc / (kc * sc)) + sqr(dh / (kh * sh))).sqrt()
+}
+
+/// The CMC l:c color difference of `sample` from `reference`. Acceptability is usually
+/// assessed with 2:1, and perceptibility with 1:1.
+pub fn delta_e_cmc(reference: &Lab, sample: &Lab, l: f64, c: f64) -> f64 {
+    if reference.l == 0.0 && sample.l == 0.0 {
+        return 0.0;
+    }
+
+    let lch1 = LCh::from(*reference);
+    let lch2 = LCh::from(*sample);
+
+    let dl = sample.l - reference.l;
+    let dc = lch2.c - lch1.c;
+    let dh = hue_difference(reference, sample, dl, dc);
+
+    let t = if lch1.h > 164.0 && lch1.h < 345.0 {
+        0.56 + (0.2 * (lch1.h + 168.0).to_radians().cos()).abs()
+    } else {
+        0.36 + (0.4 * (lch1.h + 35.0).to_radians().cos()).abs()
+    };
+
+    let sc = 0.0638 * lch1.c / (1.0 + 0.0131 * lch1.c) + 0.638;
+    let sl = if reference.l < 16.0 {
+        0.511
+    } else {
+        0.040975 * reference.l / (1.0 + 0.01765 * reference.l)
+    };
+
+    let c4 = sqr(sqr(lch1.c));
+    let f = (c4 / (c4 + 1900.0)).sqrt();
+    let sh = sc * (t * f + 1.0 - f);
+
+    (sqr(dl / (l * sl)) + sqr(dc / (c * sc)) + sqr(dh / sh)).sqrt()
+}
+
+/// The CIEDE2000 color difference, as given by Sharma, Wu and Dalal. `kl`, `kc` and `kh`
+/// weight the lightness, chroma and hue differences, and are usually 1.
+pub fn delta_e_2000(lab1: &Lab, lab2: &Lab, kl: f64, kc: f64, kh: f64) -> f64 {
+    let c1 = lab1.a.hypot(lab1.b);
+    let c2 = lab2.a.hypot(lab2.b);
+    let mean_c = (c1 + c2) / 2.0;
+
+    // Stretch a* so neutrals get a bigger hue difference
+    let g = 0.5 * (1.0 - chroma_weight(mean_c));
+    let lch1 = LCh::from(Lab::new(lab1.l, (1.0 + g) * lab1.a, lab1.b));
+    let lch2 = LCh::from(Lab::new(lab2.l, (1.0 + g) * lab2.a, lab2.b));
+
+    let dl = lch2.l - lch1.l;
+    let dc = lch2.c - lch1.c;
+
+    let neutral = lch1.c * lch2.c == 0.0;
+    let dh = if neutral {
+        0.0
+    } else {
+        let dh = lch2.h - lch1.h;
+        if dh > 180.0 {
+            dh - 360.0
+        } else if dh < -180.0 {
+            dh + 360.0
+        } else {
+            dh
+        }
+    };
+    let dh = 2.0 * (lch1.c * lch2.c).sqrt() * (dh / 2.0).to_radians().sin();
+
+    let mean_l = (lch1.l + lch2.l) / 2.0;
+    let mean_c = (lch1.c + lch2.c) / 2.0;
+    let mean_h = if neutral {
+        lch1.h + lch2.h
+    } else if (lch1.h - lch2.h).abs() <= 180.0 {
+        (lch1.h + lch2.h) / 2.0
+    } else if lch1.h + lch2.h < 360.0 {
+        (lch1.h + lch2.h + 360.0) / 2.0
+    } else {
+        (lch1.h + lch2.h - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (mean_h - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * mean_h).to_radians().cos()
+        + 0.32 * (3.0 * mean_h + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * mean_h - 63.0).to_radians().cos();
+
+    let sl = 1.0 + 0.015 * sqr(mean_l - 50.0) / (20.0 + sqr(mean_l - 50.0)).sqrt();
+    let sc = 1.0 + 0.045 * mean_c;
+    let sh = 1.0 + 0.015 * mean_c * t;
+
+    // Rotation of the blue region
+    let d_theta = 30.0 * (-sqr((mean_h - 275.0) / 25.0)).exp();
+    let rt = -(2.0 * d_theta).to_radians().sin() * 2.0 * chroma_weight(mean_c);
+
+    let l = dl / (kl * sl);
+    let c = dc / (kc * sc);
+    let h = dh / (kh * sh);
+
+    (l * l + c * c + h * h + rt * c * h).sqrt()
+}
+
+/// The BFD(l:c) color difference of `sample` from `reference`, from the Bradford University.
+pub fn delta_e_bfd(reference: &Lab, sample: &Lab) -> f64 {
+    let lch1 = LCh::from(*reference);
+    let lch2 = LCh::from(*sample);
+
+    let dl = bfd_lightness(sample) - bfd_lightness(reference);
+    let dc = lch2.c - lch1.c;
+    let dh = hue_difference(reference, sample, sample.l - reference.l, dc);
+
+    let mean_c = (lch1.c + lch2.c) / 2.0;
+    let mean_h = (lch1.h + lch2.h) / 2.0;
+    let cos = |n: f64, offset: f64| (n * mean_h + offset).to_radians().cos();
+
+    let sc = 0.035 * mean_c / (1.0 + 0.00365 * mean_c) + 0.521;
+    let c4 = sqr(sqr(mean_c));
+    let g = (c4 / (c4 + 14000.0)).sqrt();
+    let t = 0.627 + 0.055 * cos(1.0, -254.0) - 0.040 * cos(2.0, -136.0)
+        + 0.070 * cos(3.0, -31.0)
+        + 0.049 * cos(4.0, 114.0)
+        - 0.015 * cos(5.0, -103.0);
+    let sh = sc * (g * t + 1.0 - g);
+
+    let rh = -0.260 * cos(1.0, -308.0) - 0.379 * cos(2.0, -160.0) - 0.636 * cos(3.0, 254.0)
+        + 0.226 * cos(4.0, 140.0)
+        - 0.194 * cos(5.0, 280.0);
+    let c6 = c4 * sqr(mean_c);
+    let rc = (c6 / (c6 + 70000000.0)).sqrt();
+    let rt = rh * rc;
+
+    let c = dc / sc;
+    let h = dh / sh;
+
+    (dl * dl + c * c + h * h + rt * c * h).sqrt()
+}
+
+/// Runs `delta_e` over each pair of `references` and `samples`, which must be of the same
+/// length. Any of the formulas fit, through a closure giving their weights:
+/// `delta_e_batch(&measured, &predicted, |a, b| delta_e_cmc(a, b, 2.0, 1.0))`.
+pub fn delta_e_batch(
+    references: &[Lab],
+    samples: &[Lab],
+    delta_e: impl Fn(&Lab, &Lab) -> f64,
+) -> Result<Vec<f64>> {
+    if references.len() != samples.len() {
+        return err!(str => "Reference and sample counts differ");
+    }
+
+    Ok(references
+        .iter()
+        .zip(samples.iter())
+        .map(|(reference, sample)| delta_e(reference, sample))
+        .collect())
+}
+
+/// The magnitude of the hue difference, what is left of ΔE76 once lightness and chroma are
+/// accounted for.
+fn hue_difference(lab1: &Lab, lab2: &Lab, dl: f64, dc: f64) -> f64 {
+    let dh2 = sqr(delta_e_76(lab1, lab2)) - sqr(dl) - sqr(dc);
+
+    if dh2 > 0.0 {
+        dh2.sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// How close the chroma is to saturating CIEDE2000, sqrt(C^7 / (C^7 + 25^7)).
+fn chroma_weight(c: f64) -> f64 {
+    let c7 = c.powi(7);
+
+    (c7 / (c7 + 25f64.powi(7))).sqrt()
+}
+
+/// The lightness scale of BFD, logarithmic on Y.
+fn bfd_lightness(lab: &Lab) -> f64 {
+    let y = if lab.l > 7.996969 {
+        let fy = (lab.l + 16.0) / 116.0;
+        fy * fy * fy * 100.0
+    } else {
+        100.0 * (lab.l / 903.3)
+    };
+
+    54.6 * (y + 1.5).log10() - 9.6
+}
+
+fn sqr(x: f64) -> f64 {
+    x * x
+}
+
+#[cfg(test)]
+mod test;
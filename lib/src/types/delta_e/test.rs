@@ -0,0 +1,150 @@
+use crate::{types::Lab, Result};
+
+use super::{delta_e_2000, delta_e_76, delta_e_94, delta_e_batch, delta_e_bfd, delta_e_cmc};
+
+/// Sharma, Wu and Dalal, "The CIEDE2000 Color-Difference Formula: Implementation Notes,
+/// Supplementary Test Data, and Mathematical Observations", table 1.
+#[rustfmt::skip]
+const SHARMA_DATA: [([f64; 3], [f64; 3], f64); 34] = [
+    ([50.0000, 2.6772, -79.7751], [50.0000, 0.0000, -82.7485], 2.0425),
+    ([50.0000, 3.1571, -77.2803], [50.0000, 0.0000, -82.7485], 2.8615),
+    ([50.0000, 2.8361, -74.0200], [50.0000, 0.0000, -82.7485], 3.4412),
+    ([50.0000, -1.3802, -84.2814], [50.0000, 0.0000, -82.7485], 1.0000),
+    ([50.0000, -1.1848, -84.8006], [50.0000, 0.0000, -82.7485], 1.0000),
+    ([50.0000, -0.9009, -85.5211], [50.0000, 0.0000, -82.7485], 1.0000),
+    ([50.0000, 0.0000, 0.0000], [50.0000, -1.0000, 2.0000], 2.3669),
+    ([50.0000, -1.0000, 2.0000], [50.0000, 0.0000, 0.0000], 2.3669),
+    ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0009], 7.1792),
+    ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0010], 7.1792),
+    ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0011], 7.2195),
+    ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0012], 7.2195),
+    ([50.0000, -0.0010, 2.4900], [50.0000, 0.0009, -2.4900], 4.8045),
+    ([50.0000, -0.0010, 2.4900], [50.0000, 0.0010, -2.4900], 4.8045),
+    ([50.0000, -0.0010, 2.4900], [50.0000, 0.0011, -2.4900], 4.7461),
+    ([50.0000, 2.5000, 0.0000], [50.0000, 0.0000, -2.5000], 4.3065),
+    ([50.0000, 2.5000, 0.0000], [73.0000, 25.0000, -18.0000], 27.1492),
+    ([50.0000, 2.5000, 0.0000], [61.0000, -5.0000, 29.0000], 22.8977),
+    ([50.0000, 2.5000, 0.0000], [56.0000, -27.0000, -3.0000], 31.9030),
+    ([50.0000, 2.5000, 0.0000], [58.0000, 24.0000, 15.0000], 19.4535),
+    ([50.0000, 2.5000, 0.0000], [50.0000, 3.1736, 0.5854], 1.0000),
+    ([50.0000, 2.5000, 0.0000], [50.0000, 3.2972, 0.0000], 1.0000),
+    ([50.0000, 2.5000, 0.0000], [50.0000, 1.8634, 0.5757], 1.0000),
+    ([50.0000, 2.5000, 0.0000], [50.0000, 3.2592, 0.3350], 1.0000),
+    ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+    ([63.0109, -31.0961, -5.8663], [62.8187, -29.7946, -4.0864], 1.2630),
+    ([61.2901, 3.7196, -5.3901], [61.4292, 2.2480, -4.9620], 1.8731),
+    ([35.0831, -44.1164, 3.7933], [35.0232, -40.0716, 1.5901], 1.8645),
+    ([22.7233, 20.0904, -46.6940], [23.0331, 14.9730, -42.5619], 2.0373),
+    ([36.4612, 47.8580, 18.3852], [36.2715, 50.5065, 21.2231], 1.4146),
+    ([90.8027, -2.0831, 1.4410], [91.1528, -1.6435, 0.0447], 1.4441),
+    ([90.9257, -0.5406, -0.9208], [88.6381, -0.8985, -0.7239], 1.5381),
+    ([6.7747, -0.2908, -2.4247], [5.8714, -0.0985, -2.2286], 0.6377),
+    ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082),
+];
+
+/// Pairs of the Sharma data with their ΔE76, ΔE94, CMC(2:1), CMC(1:1) and BFD. All but ΔE94
+/// come from lcms 2. lcms only has a symmetric ΔE94, so that column comes from the graphic arts
+/// formula of CIE 116-1995 instead, weighted by the chroma of the first color and with the hue
+/// difference taken as what remains of ΔE76.
+#[rustfmt::skip]
+const OTHER_FORMULAS: [(usize, [f64; 5]); 4] = [
+    (16, [36.8680078117, 34.6891631980, 37.9232761694, 42.1087548456, 33.8576350998]),
+    (24, [3.1819238017, 1.3909947095, 1.4204860454, 1.4282295093, 2.0293039561]),
+    (28, [6.5846798867, 2.5561330876, 3.0604414320, 3.0869550793, 5.1090928111]),
+    (29, [3.8864141249, 1.4249130279, 1.7395722812, 1.7489353252, 1.7602584800]),
+];
+
+fn sharma_pair(i: usize) -> (Lab, Lab) {
+    let (lab1, lab2, _) = SHARMA_DATA[i];
+
+    (Lab::from(lab1), Lab::from(lab2))
+}
+
+#[test]
+fn delta_e_2000_matches_sharma_data() {
+    for (lab1, lab2, expected) in SHARMA_DATA {
+        let (lab1, lab2) = (Lab::from(lab1), Lab::from(lab2));
+
+        // The published values are rounded to 4 decimals
+        for actual in [
+            delta_e_2000(&lab1, &lab2, 1.0, 1.0, 1.0),
+            delta_e_2000(&lab2, &lab1, 1.0, 1.0, 1.0),
+        ] {
+            assert!(
+                (actual - expected).abs() < 5e-5,
+                "{:?} {:?}: {} != {}",
+                lab1,
+                lab2,
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn delta_e_2000_weights_lightness() {
+    // Lightness differences only, halved by kL = 2
+    let (lab1, lab2) = sharma_pair(16);
+    assert!((delta_e_2000(&lab1, &lab2, 2.0, 1.0, 1.0) - 21.0385965285).abs() < 1e-9);
+
+    let lab1 = Lab::new(50.0, 0.0, 0.0);
+    let lab2 = Lab::new(60.0, 0.0, 0.0);
+    let unit = delta_e_2000(&lab1, &lab2, 1.0, 1.0, 1.0);
+    assert!((delta_e_2000(&lab1, &lab2, 2.0, 1.0, 1.0) - unit / 2.0).abs() < 1e-12);
+    assert_eq!(delta_e_2000(&lab1, &lab2, 1.0, 2.0, 2.0), unit);
+}
+
+#[test]
+fn delta_e_formulas_match_references() {
+    for (i, [de76, de94, cmc21, cmc11, bfd]) in OTHER_FORMULAS {
+        let (lab1, lab2) = sharma_pair(i);
+
+        for (actual, expected) in [
+            (delta_e_76(&lab1, &lab2), de76),
+            (delta_e_94(&lab1, &lab2, 1.0, 1.0, 1.0), de94),
+            (delta_e_cmc(&lab1, &lab2, 2.0, 1.0), cmc21),
+            (delta_e_cmc(&lab1, &lab2, 1.0, 1.0), cmc11),
+            (delta_e_bfd(&lab1, &lab2), bfd),
+        ] {
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{}: {} != {}",
+                i,
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn delta_e_of_equal_colors_is_zero() {
+    for (lab, _, _) in SHARMA_DATA {
+        let lab = Lab::from(lab);
+
+        assert_eq!(delta_e_76(&lab, &lab), 0.0);
+        assert_eq!(delta_e_94(&lab, &lab, 1.0, 1.0, 1.0), 0.0);
+        assert_eq!(delta_e_cmc(&lab, &lab, 2.0, 1.0), 0.0);
+        assert_eq!(delta_e_2000(&lab, &lab, 1.0, 1.0, 1.0), 0.0);
+        assert_eq!(delta_e_bfd(&lab, &lab), 0.0);
+    }
+}
+
+#[test]
+fn delta_e_batch_runs_over_pairs() -> Result<()> {
+    let (references, samples): (Vec<Lab>, Vec<Lab>) =
+        (0..SHARMA_DATA.len()).map(sharma_pair).unzip();
+
+    let batch = delta_e_batch(&references, &samples, |a, b| {
+        delta_e_2000(a, b, 1.0, 1.0, 1.0)
+    })?;
+    assert_eq!(batch.len(), SHARMA_DATA.len());
+    for (actual, (_, _, expected)) in batch.iter().zip(SHARMA_DATA) {
+        assert!((actual - expected).abs() < 5e-5);
+    }
+
+    assert!(delta_e_batch(&references, &samples[1..], delta_e_76).is_err());
+
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use bitfield::bitfield;
 
+use crate::sig;
+
+use super::Signature;
+
 bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct Format(u32);
     pub u8, bytes, set_bytes: 2, 0;
     pub u8, channels, set_channels: 6, 3;
@@ -16,7 +21,24 @@ bitfield! {
     pub bool, premul, set_premul: 23;
 }
 
+impl From<u32> for Format {
+    fn from(value: u32) -> Self {
+        Format(value)
+    }
+}
+
+impl From<Format> for u32 {
+    fn from(value: Format) -> Self {
+        value.0
+    }
+}
+
 impl Format {
+    /// The raw value of the format.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
     pub const GRAY_8: Format = Format(colorspace_sh(pixel_type::GRAY) | channels_sh(1) | bytes_sh(1));
     pub const GRAY_8_REV: Format =
         Format(colorspace_sh(pixel_type::GRAY) | channels_sh(1) | bytes_sh(1) | flavor_sh(1));
@@ -494,6 +516,22 @@ impl Format {
     pub const CMYK_FLT: Format =
         Format(float_sh(1) | colorspace_sh(pixel_type::CMYK) | channels_sh(4) | bytes_sh(4));
 
+    // Perceptually uniform spaces, only in floating point
+    pub const OKLAB_FLT: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::OKLAB) | channels_sh(3) | bytes_sh(4));
+    /// Jzazbz with the media white at 203 cd/m², the reference white of ITU-R BT.2408. For
+    /// another luminance, build the pipeline with
+    /// [`Stage::alloc_xyz_to_jzazbz`](super::Stage::alloc_xyz_to_jzazbz) and
+    /// [`Stage::alloc_jzazbz_to_xyz`](super::Stage::alloc_jzazbz_to_xyz).
+    pub const JZAZBZ_FLT: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::JZAZBZ) | channels_sh(3) | bytes_sh(4));
+    /// ICtCp with the media white at 203 cd/m², the reference white of ITU-R BT.2408. For
+    /// another luminance, build the pipeline with
+    /// [`Stage::alloc_xyz_to_ictcp`](super::Stage::alloc_xyz_to_ictcp) and
+    /// [`Stage::alloc_ictcp_to_xyz`](super::Stage::alloc_ictcp_to_xyz).
+    pub const ICTCP_FLT: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::ICTCP) | channels_sh(3) | bytes_sh(4));
+
     // Floating point formatters.
     // NOTE THAT 'BYTES' FIELD IS SET TO ZERO ON DLB because 8 bytes overflows the bitfield
     pub const XYZ_DBL: Format =
@@ -509,6 +547,14 @@ impl Format {
         Format(float_sh(1) | colorspace_sh(pixel_type::RGB) | channels_sh(3) | bytes_sh(0) | doswap_sh(1));
     pub const CMYK_DBL: Format =
         Format(float_sh(1) | colorspace_sh(pixel_type::CMYK) | channels_sh(4) | bytes_sh(0));
+    pub const OKLAB_DBL: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::OKLAB) | channels_sh(3) | bytes_sh(0));
+    /// As [`Format::JZAZBZ_FLT`], with the media white at 203 cd/m².
+    pub const JZAZBZ_DBL: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::JZAZBZ) | channels_sh(3) | bytes_sh(0));
+    /// As [`Format::ICTCP_FLT`], with the media white at 203 cd/m².
+    pub const ICTCP_DBL: Format =
+        Format(float_sh(1) | colorspace_sh(pixel_type::ICTCP) | channels_sh(3) | bytes_sh(0));
 
     // IEEE 754-2008 "half"
     pub const GRAY_HALF_FLT: Format =
@@ -592,8 +638,84 @@ pub const fn bytes_sh(m: u32) -> u32 {
     m << 0
 }
 
+/// The [`pixel_type`] holding an ICC color space, or [`pixel_type::ANY`] if there is none.
+pub fn pixel_type_of_color_space(color_space: Signature) -> u32 {
+    use sig::colorspace::*;
+
+    match color_space {
+        GRAY => pixel_type::GRAY,
+        RGB => pixel_type::RGB,
+        CMY => pixel_type::CMY,
+        CMYK => pixel_type::CMYK,
+        YCBCR => pixel_type::YCB_CR,
+        LUV => pixel_type::YUV,
+        XYZ => pixel_type::XYZ,
+        LAB => pixel_type::LAB,
+        LUVK => pixel_type::YUVK,
+        HSV => pixel_type::HSV,
+        HLS => pixel_type::HLS,
+        YXY => pixel_type::YXY,
+        COLOR1 | MCH1 => pixel_type::MCH1,
+        COLOR2 | MCH2 => pixel_type::MCH2,
+        COLOR3 | MCH3 => pixel_type::MCH3,
+        COLOR4 | MCH4 => pixel_type::MCH4,
+        COLOR5 | MCH5 => pixel_type::MCH5,
+        COLOR6 | MCH6 => pixel_type::MCH6,
+        COLOR7 | MCH7 => pixel_type::MCH7,
+        COLOR8 | MCH8 => pixel_type::MCH8,
+        COLOR9 | MCH9 => pixel_type::MCH9,
+        COLOR10 | MCHA => pixel_type::MCH10,
+        COLOR11 | MCHB => pixel_type::MCH11,
+        COLOR12 | MCHC => pixel_type::MCH12,
+        COLOR13 | MCHD => pixel_type::MCH13,
+        COLOR14 | MCHE => pixel_type::MCH14,
+        COLOR15 | MCHF => pixel_type::MCH15,
+        _ => pixel_type::ANY,
+    }
+}
+
+/// Number of channels of an ICC color space, or `None` if the color space is unknown.
+pub fn channels_of_color_space(color_space: Signature) -> Option<usize> {
+    use sig::colorspace::*;
+
+    Some(match color_space {
+        MCH1 | COLOR1 | GRAY => 1,
+        MCH2 | COLOR2 => 2,
+        XYZ | LAB | LUV | YCBCR | YXY | RGB | HSV | HLS | CMY | MCH3 | COLOR3 => 3,
+        LUVK | CMYK | MCH4 | COLOR4 => 4,
+        MCH5 | COLOR5 => 5,
+        MCH6 | COLOR6 => 6,
+        MCH7 | COLOR7 => 7,
+        MCH8 | COLOR8 => 8,
+        MCH9 | COLOR9 => 9,
+        MCHA | COLOR10 => 10,
+        MCHB | COLOR11 => 11,
+        MCHC | COLOR12 => 12,
+        MCHD | COLOR13 => 13,
+        MCHE | COLOR14 => 14,
+        MCHF | COLOR15 => 15,
+        _ => return None,
+    })
+}
+
+/// The 16 bits white and black of a color space, in that order, or `None` if the color space
+/// has no obvious ones. CMYK black is 400% of ink, and Lab is in the v4 encoding.
+pub fn end_points_by_space(color_space: Signature) -> Option<(&'static [u16], &'static [u16])> {
+    match color_space {
+        sig::colorspace::GRAY => Some((&[0xFFFF], &[0])),
+        sig::colorspace::RGB => Some((&[0xFFFF; 3], &[0; 3])),
+        sig::colorspace::LAB => Some((&[0xFFFF, 0x8080, 0x8080], &[0, 0x8080, 0x8080])),
+        sig::colorspace::CMYK => Some((&[0; 4], &[0xFFFF; 4])),
+        sig::colorspace::CMY => Some((&[0; 3], &[0xFFFF; 3])),
+        _ => None,
+    }
+}
+
 pub mod pixel_type {
     pub const ANY: u32 = 0;
+    // Perceptually uniform spaces take the values the 5 bits field still has free
+    pub const OKLAB: u32 = 1;
+    pub const JZAZBZ: u32 = 2;
     pub const GRAY: u32 = 3;
     pub const RGB: u32 = 4;
     pub const CMY: u32 = 5;
@@ -622,4 +744,5 @@ pub mod pixel_type {
     pub const MCH14: u32 = 28;
     pub const MCH15: u32 = 29;
     pub const LAB_V2: u32 = 30;
+    pub const ICTCP: u32 = 31;
 }
@@ -0,0 +1,122 @@
+use super::{Mat3, Vec3, XYZ};
+
+/// A color in the ICtCp space of ITU-R BT.2100, with the PQ transfer function. Intensity goes
+/// from 0 to 1, the peak luminance of PQ being 10000 cd/m², and both chroma components are
+/// mostly found within -0.5..0.5.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ICtCp {
+    pub i: f64,
+    pub ct: f64,
+    pub cp: f64,
+}
+
+/// XYZ, relative to D65, to linear BT.2020 RGB.
+const XYZ_TO_BT2020: Mat3 = Mat3::new([
+    [1.7166511880, -0.3556707838, -0.2533662814],
+    [-0.6666843518, 1.6164812366, 0.0157685458],
+    [0.0176398574, -0.0427706133, 0.9421031212],
+]);
+
+const BT2020_TO_XYZ: Mat3 = Mat3::new([
+    [0.6369580483, 0.1446169036, 0.1688809752],
+    [0.2627002120, 0.6779980715, 0.0593017165],
+    [0.0000000000, 0.0280726930, 1.0609850577],
+]);
+
+/// Linear BT.2020 RGB to the cone responses of ICtCp.
+const RGB_TO_LMS: Mat3 = Mat3::new([
+    [1688.0 / 4096.0, 2146.0 / 4096.0, 262.0 / 4096.0],
+    [683.0 / 4096.0, 2951.0 / 4096.0, 462.0 / 4096.0],
+    [99.0 / 4096.0, 309.0 / 4096.0, 3688.0 / 4096.0],
+]);
+
+#[rustfmt::skip]
+const LMS_TO_RGB: Mat3 = Mat3::new([
+    [3.4366066943330784, -2.50645211865627, 0.06984542432319148],
+    [-0.7913295555989287, 1.9836004517922907, -0.192270896193362],
+    [-0.025949899690592672, -0.09891371471172644, 1.1248636144023192],
+]);
+
+/// PQ encoded cone responses to ICtCp.
+const LMS_TO_ICTCP: Mat3 = Mat3::new([
+    [0.5, 0.5, 0.0],
+    [6610.0 / 4096.0, -13613.0 / 4096.0, 7003.0 / 4096.0],
+    [17933.0 / 4096.0, -17390.0 / 4096.0, -543.0 / 4096.0],
+]);
+
+const ICTCP_TO_LMS: Mat3 = Mat3::new([
+    [1.0, 0.008609037037932756, 0.11102962500302596],
+    [1.0, -0.008609037037932756, -0.11102962500302596],
+    [1.0, 0.5600313357106791, -0.32062717498731885],
+]);
+
+/// The exponent of the PQ curve of ICtCp.
+const PQ_M2: f64 = 2523.0 / 4096.0 * 128.0;
+
+impl ICtCp {
+    pub const fn new(i: f64, ct: f64, cp: f64) -> Self {
+        Self { i, ct, cp }
+    }
+
+    /// Converts `xyz`, relative to D65 with Y in cd/m², to ICtCp.
+    pub fn from_xyz(xyz: &XYZ) -> Self {
+        Self::from_bt2020(&(XYZ_TO_BT2020 * Vec3::from(*xyz)).0)
+    }
+
+    /// Converts the color to XYZ relative to D65, with Y in cd/m².
+    pub fn to_xyz(&self) -> XYZ {
+        (BT2020_TO_XYZ * Vec3(self.to_bt2020())).into()
+    }
+
+    /// Converts linear BT.2020 RGB, in cd/m², to ICtCp.
+    pub fn from_bt2020(rgb: &[f64; 3]) -> Self {
+        let lms = RGB_TO_LMS * Vec3(*rgb);
+        let [i, ct, cp] = (LMS_TO_ICTCP * Vec3(lms.0.map(|c| pq_encode(c, PQ_M2)))).0;
+
+        Self { i, ct, cp }
+    }
+
+    /// Converts the color to linear BT.2020 RGB, in cd/m².
+    pub fn to_bt2020(&self) -> [f64; 3] {
+        let lms = ICTCP_TO_LMS * Vec3::new(self.i, self.ct, self.cp);
+
+        (LMS_TO_RGB * Vec3(lms.0.map(|c| pq_decode(c, PQ_M2)))).0
+    }
+}
+
+impl From<[f64; 3]> for ICtCp {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<ICtCp> for [f64; 3] {
+    fn from(value: ICtCp) -> Self {
+        [value.i, value.ct, value.cp]
+    }
+}
+
+const PQ_M1: f64 = 2610.0 / 16384.0;
+const PQ_C1: f64 = 3424.0 / 4096.0;
+const PQ_C2: f64 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f64 = 2392.0 / 4096.0 * 32.0;
+
+/// The SMPTE ST 2084 perceptual quantizer of a luminance in cd/m², raised to `m2`. Negative
+/// luminances are taken as black.
+pub(super) fn pq_encode(luminance: f64, m2: f64) -> f64 {
+    let y = (luminance.max(0.0) / 10000.0).powf(PQ_M1);
+
+    ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(m2)
+}
+
+/// The largest value [`pq_decode`] decodes, once raised to `1 / m2`. It is just below `c2 / c3`,
+/// where the curve goes to infinity.
+const PQ_MAX: f64 = PQ_C2 / PQ_C3 * (1.0 - f64::EPSILON);
+
+/// The inverse of [`pq_encode`]. Negative values are taken as black, and values past the
+/// asymptote of the curve as [`PQ_MAX`], so this never gives NaN.
+pub(super) fn pq_decode(value: f64, m2: f64) -> f64 {
+    let v = value.max(0.0).powf(1.0 / m2).min(PQ_MAX);
+
+    10000.0 * ((v - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * v)).powf(1.0 / PQ_M1)
+}
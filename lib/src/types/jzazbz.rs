@@ -0,0 +1,95 @@
+use super::{
+    ictcp::{pq_decode, pq_encode},
+    Mat3, Vec3, XYZ,
+};
+
+/// A color in the Jzazbz space of Safdar et al., built for high dynamic range. Jz goes from 0
+/// to about 1 at 10000 cd/m², az and bz are mostly found within -0.5..0.5.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Jzazbz {
+    pub jz: f64,
+    pub az: f64,
+    pub bz: f64,
+}
+
+/// Skewed XYZ to the cone responses of Jzazbz.
+const XYZ_TO_LMS: Mat3 = Mat3::new([
+    [0.41478972, 0.579999, 0.0146480],
+    [-0.2015100, 1.120649, 0.0531008],
+    [-0.0166008, 0.264800, 0.6684799],
+]);
+
+#[rustfmt::skip]
+const LMS_TO_XYZ: Mat3 = Mat3::new([
+    [1.9242264357876067, -1.0047923125953655, 0.03765140403061801],
+    [0.35031676209499907, 0.7264811939316552, -0.06538442294808502],
+    [-0.09098281098284758, -0.312728290523074, 1.5227665613052606],
+]);
+
+/// PQ encoded cone responses to Iz, az and bz.
+const LMS_TO_IAB: Mat3 = Mat3::new([
+    [0.5, 0.5, 0.0],
+    [3.524000, -4.066708, 0.542708],
+    [0.199076, 1.096799, -1.295875],
+]);
+
+const IAB_TO_LMS: Mat3 = Mat3::new([
+    [1.0, 0.1386050432715393, 0.058047316156118876],
+    [1.0, -0.1386050432715393, -0.058047316156118876],
+    [1.0, -0.09601924202631895, -0.811891896056039],
+]);
+
+const B: f64 = 1.15;
+const G: f64 = 0.66;
+const D: f64 = -0.56;
+const D0: f64 = 1.6295499532821566e-11;
+/// The exponent of the PQ curve of Jzazbz.
+const P: f64 = 1.7 * 2523.0 / 32.0;
+
+impl Jzazbz {
+    pub const fn new(jz: f64, az: f64, bz: f64) -> Self {
+        Self { jz, az, bz }
+    }
+
+    /// Converts `xyz`, relative to D65 with Y in cd/m², to Jzazbz.
+    pub fn from_xyz(xyz: &XYZ) -> Self {
+        // Skew X and Y, to fix the blue hue shift
+        let x = B * xyz.x - (B - 1.0) * xyz.z;
+        let y = G * xyz.y - (G - 1.0) * xyz.x;
+
+        let lms = XYZ_TO_LMS * Vec3::new(x, y, xyz.z);
+        let [iz, az, bz] = (LMS_TO_IAB * Vec3(lms.0.map(|c| pq_encode(c, P)))).0;
+
+        Self {
+            jz: (1.0 + D) * iz / (1.0 + D * iz) - D0,
+            az,
+            bz,
+        }
+    }
+
+    /// Converts the color to XYZ relative to D65, with Y in cd/m².
+    pub fn to_xyz(&self) -> XYZ {
+        let jz = self.jz + D0;
+        let iz = jz / (1.0 + D - D * jz);
+
+        let lms = IAB_TO_LMS * Vec3::new(iz, self.az, self.bz);
+        let [x, y, z] = (LMS_TO_XYZ * Vec3(lms.0.map(|c| pq_decode(c, P)))).0;
+
+        let x = (x + (B - 1.0) * z) / B;
+        let y = (y + (G - 1.0) * x) / G;
+
+        XYZ { x, y, z }
+    }
+}
+
+impl From<[f64; 3]> for Jzazbz {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<Jzazbz> for [f64; 3] {
+    fn from(value: Jzazbz) -> Self {
+        [value.jz, value.az, value.bz]
+    }
+}
@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+
+use crate::quick_saturate_word;
+
+use super::XYZ;
+
+/// A CIE L*a*b* color. L* goes from 0 to 100, a* and b* are mostly found within -128..127.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// The polar form of [`Lab`]: lightness, chroma and hue angle in degrees, from 0 to 360.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LCh {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+/// The largest L* v2 encoding can hold, as 0xFF00 is L* = 100.
+const MAX_ENCODEABLE_L_V2: f64 = (0xFFFF as f64 * 100.0) / 0xFF00 as f64;
+const MIN_ENCODEABLE_AB_V2: f64 = -128.0;
+const MAX_ENCODEABLE_AB_V2: f64 = (65535.0 / 256.0) - 128.0;
+const MIN_ENCODEABLE_AB_V4: f64 = -128.0;
+const MAX_ENCODEABLE_AB_V4: f64 = 127.0;
+
+impl Lab {
+    pub const fn new(l: f64, a: f64, b: f64) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Converts `xyz`, relative to `white_point`, to Lab.
+    pub fn from_xyz(xyz: &XYZ, white_point: &XYZ) -> Self {
+        let fx = f(xyz.x / white_point.x);
+        let fy = f(xyz.y / white_point.y);
+        let fz = f(xyz.z / white_point.z);
+
+        Self {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+
+    /// Converts the color to XYZ relative to `white_point`.
+    pub fn to_xyz(&self, white_point: &XYZ) -> XYZ {
+        let y = (self.l + 16.0) / 116.0;
+        let x = y + 0.002 * self.a;
+        let z = y - 0.005 * self.b;
+
+        XYZ {
+            x: f_1(x) * white_point.x,
+            y: f_1(y) * white_point.y,
+            z: f_1(z) * white_point.z,
+        }
+    }
+
+    /// Decodes 16 bits ICC v4 Lab, where L* 0..100 and a*, b* -128..127 take the whole range.
+    pub fn decode(lab: &[u16; 3]) -> Self {
+        Self {
+            l: lab[0] as f64 / 655.35,
+            a: lab[1] as f64 / 257.0 - 128.0,
+            b: lab[2] as f64 / 257.0 - 128.0,
+        }
+    }
+
+    /// Decodes 16 bits ICC v2 Lab, where L* = 100 is 0xFF00 and a*, b* are 8.8 fixed point
+    /// offset by 128.
+    pub fn decode_v2(lab: &[u16; 3]) -> Self {
+        Self {
+            l: lab[0] as f64 / 652.8,
+            a: lab[1] as f64 / 256.0 - 128.0,
+            b: lab[2] as f64 / 256.0 - 128.0,
+        }
+    }
+
+    /// Encodes the color as 16 bits ICC v4 Lab, clamping it to the encodeable range.
+    pub fn encode(&self) -> [u16; 3] {
+        let l = self.l.clamp(0.0, 100.0);
+        let a = self.a.clamp(MIN_ENCODEABLE_AB_V4, MAX_ENCODEABLE_AB_V4);
+        let b = self.b.clamp(MIN_ENCODEABLE_AB_V4, MAX_ENCODEABLE_AB_V4);
+
+        [
+            quick_saturate_word(l * 655.35),
+            quick_saturate_word((a + 128.0) * 257.0),
+            quick_saturate_word((b + 128.0) * 257.0),
+        ]
+    }
+
+    /// Encodes the color as 16 bits ICC v2 Lab, clamping it to the encodeable range.
+    pub fn encode_v2(&self) -> [u16; 3] {
+        let l = self.l.clamp(0.0, MAX_ENCODEABLE_L_V2);
+        let a = self.a.clamp(MIN_ENCODEABLE_AB_V2, MAX_ENCODEABLE_AB_V2);
+        let b = self.b.clamp(MIN_ENCODEABLE_AB_V2, MAX_ENCODEABLE_AB_V2);
+
+        [
+            quick_saturate_word(l * 652.8),
+            quick_saturate_word((a + 128.0) * 256.0),
+            quick_saturate_word((b + 128.0) * 256.0),
+        ]
+    }
+}
+
+impl From<[f64; 3]> for Lab {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<Lab> for [f64; 3] {
+    fn from(value: Lab) -> Self {
+        [value.l, value.a, value.b]
+    }
+}
+
+impl From<Lab> for LCh {
+    fn from(value: Lab) -> Self {
+        let h = if value.a == 0.0 && value.b == 0.0 {
+            0.0
+        } else {
+            value.b.atan2(value.a).to_degrees()
+        };
+
+        Self {
+            l: value.l,
+            c: value.a.hypot(value.b),
+            h: if h < 0.0 { h + 360.0 } else { h },
+        }
+    }
+}
+
+impl From<LCh> for Lab {
+    fn from(value: LCh) -> Self {
+        let h = (value.h * PI) / 180.0;
+
+        Self {
+            l: value.l,
+            a: value.c * h.cos(),
+            b: value.c * h.sin(),
+        }
+    }
+}
+
+/// The CIE Lab companding function.
+pub(super) fn f(t: f64) -> f64 {
+    const LIMIT: f64 = (24.0 / 116.0) * (24.0 / 116.0) * (24.0 / 116.0);
+
+    if t <= LIMIT {
+        (841.0 / 108.0) * t + (16.0 / 116.0)
+    } else {
+        t.cbrt()
+    }
+}
+
+/// The inverse of [`f`].
+pub(super) fn f_1(t: f64) -> f64 {
+    const LIMIT: f64 = 24.0 / 116.0;
+
+    if t <= LIMIT {
+        (108.0 / 841.0) * (t - (16.0 / 116.0))
+    } else {
+        t * t * t
+    }
+}
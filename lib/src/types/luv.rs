@@ -0,0 +1,73 @@
+use super::{
+    lab::{f, f_1},
+    XYZ,
+};
+
+/// A CIE 1976 L*u*v* color. L* goes from 0 to 100, as it does in [`Lab`](super::Lab).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Luv {
+    pub l: f64,
+    pub u: f64,
+    pub v: f64,
+}
+
+impl Luv {
+    pub const fn new(l: f64, u: f64, v: f64) -> Self {
+        Self { l, u, v }
+    }
+
+    /// Converts `xyz`, relative to `white_point`, to Luv.
+    pub fn from_xyz(xyz: &XYZ, white_point: &XYZ) -> Self {
+        let (u, v) = uv_prime(xyz);
+        let (un, vn) = uv_prime(white_point);
+
+        let l = 116.0 * f(xyz.y / white_point.y) - 16.0;
+
+        Self {
+            l,
+            u: 13.0 * l * (u - un),
+            v: 13.0 * l * (v - vn),
+        }
+    }
+
+    /// Converts the color to XYZ relative to `white_point`.
+    pub fn to_xyz(&self, white_point: &XYZ) -> XYZ {
+        if self.l <= 0.0 {
+            return XYZ::default();
+        }
+
+        let (un, vn) = uv_prime(white_point);
+        let u = self.u / (13.0 * self.l) + un;
+        let v = self.v / (13.0 * self.l) + vn;
+
+        let y = f_1((self.l + 16.0) / 116.0) * white_point.y;
+
+        XYZ {
+            x: y * (9.0 * u) / (4.0 * v),
+            y,
+            z: y * (12.0 - 3.0 * u - 20.0 * v) / (4.0 * v),
+        }
+    }
+}
+
+impl From<[f64; 3]> for Luv {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<Luv> for [f64; 3] {
+    fn from(value: Luv) -> Self {
+        [value.l, value.u, value.v]
+    }
+}
+
+/// The CIE 1976 u' and v' chromaticity coordinates.
+fn uv_prime(xyz: &XYZ) -> (f64, f64) {
+    let d = xyz.x + 15.0 * xyz.y + 3.0 * xyz.z;
+    if d == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    (4.0 * xyz.x / d, 9.0 * xyz.y / d)
+}
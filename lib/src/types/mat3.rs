@@ -0,0 +1,176 @@
+use std::ops::{Mul, Sub};
+
+use crate::MATRIX_DET_TOLERANCE;
+
+use super::XYZ;
+
+/// A 3 component vector, mostly holding XYZ or cone responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3(pub [f64; 3]);
+
+/// A 3x3 matrix, stored as rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mat3(pub [Vec3; 3]);
+
+impl Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self([x, y, z])
+    }
+
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.0[0] * other.0[0] + self.0[1] * other.0[1] + self.0[2] * other.0[2]
+    }
+
+    pub fn cross(&self, other: &Self) -> Self {
+        let [ux, uy, uz] = self.0;
+        let [vx, vy, vz] = other.0;
+
+        Self::new(uy * vz - vy * uz, uz * vx - vz * ux, ux * vy - vx * uy)
+    }
+
+    /// The euclidean length of the vector.
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    /// The euclidean distance between two points.
+    pub fn distance(&self, other: &Self) -> f64 {
+        (*self - *other).length()
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(
+            self.0[0] - rhs.0[0],
+            self.0[1] - rhs.0[1],
+            self.0[2] - rhs.0[2],
+        )
+    }
+}
+
+impl From<XYZ> for Vec3 {
+    fn from(value: XYZ) -> Self {
+        Self::new(value.x, value.y, value.z)
+    }
+}
+
+impl From<Vec3> for XYZ {
+    fn from(value: Vec3) -> Self {
+        XYZ {
+            x: value.0[0],
+            y: value.0[1],
+            z: value.0[2],
+        }
+    }
+}
+
+impl Mat3 {
+    pub const IDENTITY: Self = Self::diagonal(1.0, 1.0, 1.0);
+
+    pub const fn new(rows: [[f64; 3]; 3]) -> Self {
+        Self([Vec3(rows[0]), Vec3(rows[1]), Vec3(rows[2])])
+    }
+
+    pub const fn diagonal(x: f64, y: f64, z: f64) -> Self {
+        Self::new([[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z]])
+    }
+
+    /// Whether every element is within 1/65535 of the identity matrix, which is all a 16 bits
+    /// pipeline can tell apart.
+    pub fn is_identity(&self) -> bool {
+        const EPSILON: f64 = 1.0 / 65535.0;
+
+        self.0
+            .iter()
+            .zip(Self::IDENTITY.0.iter())
+            .all(|(row, identity)| {
+                row.0
+                    .iter()
+                    .zip(identity.0.iter())
+                    .all(|(a, b)| (a - b).abs() < EPSILON)
+            })
+    }
+
+    /// The matrix as a row-major array, as matrix stages take them.
+    pub fn to_array(&self) -> [f64; 9] {
+        let [a, b, c] = self.0;
+
+        [
+            a.0[0], a.0[1], a.0[2], b.0[0], b.0[1], b.0[2], c.0[0], c.0[1], c.0[2],
+        ]
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let [a, b, c] = self.0.map(|row| row.0);
+
+        a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0])
+    }
+
+    /// The inverse matrix, or `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let [a, b, c] = self.0.map(|row| row.0);
+
+        let c0 = b[1] * c[2] - b[2] * c[1];
+        let c1 = -b[0] * c[2] + b[2] * c[0];
+        let c2 = b[0] * c[1] - b[1] * c[0];
+
+        let det = a[0] * c0 + a[1] * c1 + a[2] * c2;
+        if det.abs() < MATRIX_DET_TOLERANCE {
+            return None;
+        }
+
+        Some(Self::new([
+            [
+                c0 / det,
+                (a[2] * c[1] - a[1] * c[2]) / det,
+                (a[1] * b[2] - a[2] * b[1]) / det,
+            ],
+            [
+                c1 / det,
+                (a[0] * c[2] - a[2] * c[0]) / det,
+                (a[2] * b[0] - a[0] * b[2]) / det,
+            ],
+            [
+                c2 / det,
+                (a[1] * c[0] - a[0] * c[1]) / det,
+                (a[0] * b[1] - a[1] * b[0]) / det,
+            ],
+        ]))
+    }
+
+    /// Solves `self * x = b`, or `None` if the matrix is singular.
+    pub fn solve(&self, b: &Vec3) -> Option<Vec3> {
+        Some(self.inverse()? * *b)
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let row = |i: usize| {
+            let a = self.0[i].0;
+            let col = |j: usize| a[0] * rhs.0[0].0[j] + a[1] * rhs.0[1].0[j] + a[2] * rhs.0[2].0[j];
+
+            Vec3::new(col(0), col(1), col(2))
+        };
+
+        Self([row(0), row(1), row(2)])
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(
+            self.0[0].dot(&rhs),
+            self.0[1].dot(&rhs),
+            self.0[2].dot(&rhs),
+        )
+    }
+}
@@ -1,23 +1,52 @@
+mod cam;
 mod date_time;
+mod delta_e;
 mod format;
+mod ictcp;
 mod interp_params;
+mod jzazbz;
+mod lab;
+mod luv;
+mod mat3;
+mod oklab;
 mod pipeline;
 mod position;
 mod profile;
 mod response;
 mod signature;
 mod stage;
+mod tone_curve;
 mod transform;
+mod viewing_conditions;
+mod white_point;
 mod xyz;
 
+pub use cam::{delta_e_cam16_ucs, surround, Appearance, Cam, Surround, ViewingConditions};
 pub use date_time::DateTimeNumber;
-pub use format::Format;
+pub use delta_e::{delta_e_2000, delta_e_76, delta_e_94, delta_e_batch, delta_e_bfd, delta_e_cmc};
+pub use format::*;
+pub use ictcp::ICtCp;
 pub use interp_params::{InterpFn, InterpFunction, InterpParams};
-pub use pipeline::Pipeline;
+pub use jzazbz::Jzazbz;
+pub use lab::{LCh, Lab};
+pub use luv::Luv;
+pub use mat3::{Mat3, Vec3};
+pub use oklab::OkLab;
+pub use pipeline::{Pipeline, PipelineDupFn, PipelineEval16Fn, PipelineEvalFloatFn, StageLoc};
 pub use position::PositionNumber;
-pub use profile::Profile;
+pub use profile::{used_as, Profile};
 pub use response::ResponseNumber;
 pub use signature::Signature;
-pub use stage::{Stage, StageDupFn, StageEvalFn};
+pub(crate) use stage::cube_size;
+pub use stage::{
+    sampler_flags, Stage, StageCLutData, StageDupFn, StageEvalFn, StageMatrixData,
+    StageToneCurvesData,
+};
+pub use tone_curve::{CurveSegment, ToneCurve};
 pub use transform::*;
-pub use xyz::{XYZNumber, XYZ};
+pub use viewing_conditions::{illuminant_type, IccViewingConditions};
+pub use white_point::{
+    adapt_to_illuminant, adaptation_matrix, cone_matrix, partial_adaptation_matrix,
+    temp_from_white_point, white_point_from_temp,
+};
+pub use xyz::{xyY, XYZNumber, XYZ};
@@ -0,0 +1,71 @@
+use super::{Mat3, Vec3, XYZ};
+
+/// A color in Björn Ottosson's OKLab space. L goes from 0 to 1, a and b are mostly found
+/// within -0.4..0.4.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OkLab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// XYZ, relative to D65, to the cone responses of OKLab.
+const XYZ_TO_LMS: Mat3 = Mat3::new([
+    [0.8189330101, 0.3618667424, -0.1288597137],
+    [0.0329845436, 0.9293118715, 0.0361456387],
+    [0.0482003018, 0.2643662691, 0.6338517070],
+]);
+
+#[rustfmt::skip]
+const LMS_TO_XYZ: Mat3 = Mat3::new([
+    [1.2270138511035211, -0.5577999806518222, 0.28125614896646783],
+    [-0.04058017842328059, 1.11225686961683, -0.0716766786656012],
+    [-0.07638128450570689, -0.4214819784180127, 1.5861632204407947],
+]);
+
+/// Compressed cone responses to OKLab.
+const LMS_TO_LAB: Mat3 = Mat3::new([
+    [0.2104542553, 0.7936177850, -0.0040720468],
+    [1.9779984951, -2.4285922050, 0.4505937099],
+    [0.0259040371, 0.7827717662, -0.8086757660],
+]);
+
+#[rustfmt::skip]
+const LAB_TO_LMS: Mat3 = Mat3::new([
+    [0.9999999984505198, 0.39633779217376786, 0.2158037580607588],
+    [1.0000000088817609, -0.10556134232365635, -0.06385417477170591],
+    [1.0000000546724108, -0.08948418209496575, -1.2914855378640917],
+]);
+
+impl OkLab {
+    pub const fn new(l: f64, a: f64, b: f64) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Converts `xyz`, relative to D65 with a white of Y = 1, to OKLab.
+    pub fn from_xyz(xyz: &XYZ) -> Self {
+        let lms = XYZ_TO_LMS * Vec3::from(*xyz);
+        let [l, a, b] = (LMS_TO_LAB * Vec3(lms.0.map(f64::cbrt))).0;
+
+        Self { l, a, b }
+    }
+
+    /// Converts the color to XYZ relative to D65, with a white of Y = 1.
+    pub fn to_xyz(&self) -> XYZ {
+        let lms = LAB_TO_LMS * Vec3::new(self.l, self.a, self.b);
+
+        (LMS_TO_XYZ * Vec3(lms.0.map(|c| c * c * c))).into()
+    }
+}
+
+impl From<[f64; 3]> for OkLab {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<OkLab> for [f64; 3] {
+    fn from(value: OkLab) -> Self {
+        [value.l, value.a, value.b]
+    }
+}
@@ -1 +1,247 @@
-pub struct Pipeline {}
+use std::{any::Any, slice};
+
+use crate::{quick_saturate_word, state::Context, Result, MAX_STAGE_CHANNELS};
+
+use super::Stage;
+
+pub type PipelineEval16Fn = fn(r#in: &[u16], out: &mut [u16], lut: &Pipeline);
+pub type PipelineEvalFloatFn = fn(r#in: &[f32], out: &mut [f32], lut: &Pipeline);
+pub type PipelineDupFn = fn(data: &dyn Any) -> Box<dyn Any>;
+
+/// Where to insert or remove a [`Stage`] in a [`Pipeline`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StageLoc {
+    AtBegin,
+    AtEnd,
+}
+
+/// An ordered list of [`Stage`]s evaluated one after another. The output of each stage feeds
+/// the input of the next one.
+pub struct Pipeline {
+    context_id: Context,
+    stages: Vec<Stage>,
+    in_chans: usize,
+    out_chans: usize,
+    eval_16: PipelineEval16Fn,
+    eval_f32: PipelineEvalFloatFn,
+    data: Option<Box<dyn Any>>,
+    dup: Option<PipelineDupFn>,
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline. An empty pipeline passes its input through unchanged.
+    pub fn new(context_id: &Context, in_chans: usize, out_chans: usize) -> Result<Self> {
+        // A value of zero in channels is allowed as placeholder
+        if in_chans > MAX_STAGE_CHANNELS || out_chans > MAX_STAGE_CHANNELS {
+            return err!(context_id, Error, Range, "Too many channels in pipeline ({} in, {} out, max={})", in_chans, out_chans, MAX_STAGE_CHANNELS; str => "Too many channels in pipeline");
+        }
+
+        Ok(Self {
+            context_id: context_id.clone(),
+            stages: Vec::new(),
+            in_chans,
+            out_chans,
+            eval_16: eval_16_default,
+            eval_f32: eval_f32_default,
+            data: None,
+            dup: None,
+        })
+    }
+
+    pub fn context_id(&self) -> &Context {
+        &self.context_id
+    }
+
+    pub fn get_input_channels(&self) -> usize {
+        self.in_chans
+    }
+
+    pub fn get_output_channels(&self) -> usize {
+        self.out_chans
+    }
+
+    pub fn stage_count(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn first_stage(&self) -> Option<&Stage> {
+        self.stages.first()
+    }
+
+    pub fn last_stage(&self) -> Option<&Stage> {
+        self.stages.last()
+    }
+
+    /// Iterates the stages in evaluation order.
+    pub fn stages(&self) -> slice::Iter<'_, Stage> {
+        self.stages.iter()
+    }
+
+    /// Iterates the stages in evaluation order, allowing them to be modified in place.
+    pub fn stages_mut(&mut self) -> slice::IterMut<'_, Stage> {
+        self.stages.iter_mut()
+    }
+
+    /// Inserts `stage` at `loc`. Fails, leaving the pipeline untouched, if the channels of the
+    /// stage don't match those of its neighbors.
+    pub fn insert_stage(&mut self, loc: StageLoc, stage: Stage) -> Result<()> {
+        match loc {
+            StageLoc::AtBegin => self.stages.insert(0, stage),
+            StageLoc::AtEnd => self.stages.push(stage),
+        }
+
+        if let Err(err) = self.bless() {
+            self.unlink_stage(loc);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the stage at `loc`, returning it. Returns `None` if the pipeline is empty.
+    pub fn unlink_stage(&mut self, loc: StageLoc) -> Option<Stage> {
+        let stage = match loc {
+            StageLoc::AtBegin if !self.stages.is_empty() => Some(self.stages.remove(0)),
+            StageLoc::AtBegin => None,
+            StageLoc::AtEnd => self.stages.pop(),
+        };
+
+        // Removing a stage can't break the chain
+        let _ = self.bless();
+
+        stage
+    }
+
+    /// Appends a copy of every stage in `other` to this pipeline.
+    pub fn cat(&mut self, other: &Pipeline) -> Result<()> {
+        // If both pipelines have no stages, we need to inherit the number of channels
+        if self.stages.is_empty() && other.stages.is_empty() {
+            self.in_chans = other.in_chans;
+            self.out_chans = other.out_chans;
+        }
+
+        let len = self.stages.len();
+        self.stages
+            .extend(other.stages.iter().map(|stage| stage.duplicate()));
+
+        if let Err(err) = self.bless() {
+            self.stages.truncate(len);
+            let _ = self.bless();
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the pipeline in 16 bits.
+    pub fn eval_16(&self, r#in: &[u16], out: &mut [u16]) {
+        (self.eval_16)(r#in, out, self)
+    }
+
+    /// Evaluates the pipeline in floating point.
+    pub fn eval_f32(&self, r#in: &[f32], out: &mut [f32]) {
+        (self.eval_f32)(r#in, out, self)
+    }
+
+    /// Deep copies the pipeline, including any optimization data.
+    pub fn duplicate(&self) -> Self {
+        let data = match (&self.data, self.dup) {
+            (Some(data), Some(dup)) => Some(dup(data.as_ref())),
+            _ => None,
+        };
+
+        Self {
+            context_id: self.context_id.clone(),
+            stages: self.stages.iter().map(|stage| stage.duplicate()).collect(),
+            in_chans: self.in_chans,
+            out_chans: self.out_chans,
+            eval_16: self.eval_16,
+            eval_f32: self.eval_f32,
+            data,
+            dup: self.dup,
+        }
+    }
+
+    /// Replaces the 16 bits evaluator. Used by optimization plugins, `dup` must deep copy
+    /// `data`. Without `dup` the data is dropped when the pipeline is duplicated.
+    pub fn set_optimization_parameters(
+        &mut self,
+        eval_16: PipelineEval16Fn,
+        data: Option<Box<dyn Any>>,
+        dup: Option<PipelineDupFn>,
+    ) {
+        self.eval_16 = eval_16;
+        self.data = data;
+        self.dup = dup;
+    }
+
+    /// The data set by [`Pipeline::set_optimization_parameters`].
+    pub fn get_data(&self) -> Option<&dyn Any> {
+        self.data.as_deref()
+    }
+
+    /// Updates the channel counts from the stages, checking that each stage takes as many
+    /// channels as the previous one outputs.
+    fn bless(&mut self) -> Result<()> {
+        let (first, last) = match (self.stages.first(), self.stages.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(()),
+        };
+
+        for pair in self.stages.windows(2) {
+            if pair[1].get_input_channels() != pair[0].get_output_channels() {
+                return err!(self.context_id, Error, Range, "Stage with {} input channels follows a stage with {} output channels", pair[1].get_input_channels(), pair[0].get_output_channels(); str => "Channel count mismatch between stages");
+            }
+        }
+
+        self.in_chans = first.get_input_channels();
+        self.out_chans = last.get_output_channels();
+
+        Ok(())
+    }
+}
+
+/// Runs the stages, alternating between two buffers.
+fn eval_stages(lut: &Pipeline, storage: &mut [[f32; MAX_STAGE_CHANNELS]; 2]) -> usize {
+    let mut phase = 0;
+
+    for stage in lut.stages.iter() {
+        let (first, second) = storage.split_at_mut(1);
+        let (src, dst) = if phase == 0 {
+            (&first[0], &mut second[0])
+        } else {
+            (&second[0], &mut first[0])
+        };
+
+        stage.eval(src, dst);
+        phase ^= 1;
+    }
+
+    phase
+}
+
+fn eval_16_default(r#in: &[u16], out: &mut [u16], lut: &Pipeline) {
+    let mut storage = [[0f32; MAX_STAGE_CHANNELS]; 2];
+
+    for (dst, src) in storage[0].iter_mut().zip(r#in).take(lut.in_chans) {
+        *dst = *src as f32 / 65535.0;
+    }
+
+    let phase = eval_stages(lut, &mut storage);
+
+    for (dst, src) in out.iter_mut().zip(storage[phase]).take(lut.out_chans) {
+        *dst = quick_saturate_word(src as f64 * 65535.0);
+    }
+}
+
+fn eval_f32_default(r#in: &[f32], out: &mut [f32], lut: &Pipeline) {
+    let mut storage = [[0f32; MAX_STAGE_CHANNELS]; 2];
+
+    storage[0][..lut.in_chans].copy_from_slice(&r#in[..lut.in_chans]);
+
+    let phase = eval_stages(lut, &mut storage);
+
+    out[..lut.out_chans].copy_from_slice(&storage[phase][..lut.out_chans]);
+}
+
+mod reverse;
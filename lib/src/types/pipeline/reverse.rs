@@ -0,0 +1,141 @@
+use crate::{
+    types::{Mat3, Vec3},
+    Result, INVERSION_MAX_ITERATIONS,
+};
+
+use super::Pipeline;
+
+const JACOBIAN_EPSILON: f32 = 0.001;
+
+impl Pipeline {
+    /// Finds the input producing `target`, using the Newton-Raphson method on the float
+    /// evaluator. Only 3 -> 3 and 4 -> 3 pipelines are supported, in the later case the fourth
+    /// input channel is fixed to `target[3]`. The search starts at `hint`, or at 0.3 on every
+    /// channel.
+    ///
+    /// The best input found is written to `result`, and the distance between its output and
+    /// `target` is returned. A non-zero distance means `target` could not be reached exactly.
+    pub fn eval_reverse_f32(
+        &self,
+        target: &[f32],
+        result: &mut [f32],
+        hint: Option<&[f32]>,
+    ) -> Result<f64> {
+        self.eval_reverse_f32_ex(target, result, hint, 0.0, INVERSION_MAX_ITERATIONS)
+    }
+
+    /// Same as [`Pipeline::eval_reverse_f32`], stopping as soon as the distance to `target` is
+    /// at most `tolerance`, or after `max_iterations` iterations.
+    pub fn eval_reverse_f32_ex(
+        &self,
+        target: &[f32],
+        result: &mut [f32],
+        hint: Option<&[f32]>,
+        tolerance: f64,
+        max_iterations: usize,
+    ) -> Result<f64> {
+        let in_chans = self.get_input_channels();
+
+        if (in_chans != 3 && in_chans != 4) || self.get_output_channels() != 3 {
+            return err!(self.context_id(), Error, Range, "Unable to reverse a pipeline with {} input and {} output channels", in_chans, self.get_output_channels(); str => "Only 3 -> 3 and 4 -> 3 pipelines can be reversed");
+        }
+        if target.len() < in_chans || result.len() < in_chans {
+            return err!(self.context_id(), Error, Range, "Reverse evaluation needs {} channels", in_chans; str => "Not enough channels for reverse evaluation");
+        }
+
+        // Take the hint as starting point if specified, otherwise begin at 1/3 of the CMY axis
+        let mut x = [0.3f32; 4];
+        if let Some(hint) = hint {
+            if hint.len() < 3 {
+                return err!(self.context_id(), Error, Range, "Reverse evaluation hint needs 3 channels, but has {}", hint.len(); str => "Not enough channels in the reverse evaluation hint");
+            }
+            x[..3].copy_from_slice(&hint[..3]);
+        }
+
+        // If the pipeline is 4 dimensional, the last channel is fixed
+        x[3] = if in_chans == 4 { target[3] } else { 0.0 };
+
+        let mut last_error = f64::MAX;
+        let mut fx = [0f32; 3];
+        let mut fxd = [0f32; 3];
+
+        result[..in_chans].copy_from_slice(&x[..in_chans]);
+
+        for _ in 0..max_iterations {
+            // Get beginning fx
+            self.eval_f32(&x, &mut fx);
+
+            let error = euclidean_distance(&fx, &target[..3]);
+
+            // If not convergent, return last safe value
+            if error >= last_error {
+                break;
+            }
+
+            // Keep latest values
+            last_error = error;
+            result[..in_chans].copy_from_slice(&x[..in_chans]);
+
+            if error <= tolerance {
+                break;
+            }
+
+            // Obtain slope (the Jacobian)
+            let mut jacobian = [[0f64; 3]; 3];
+            for j in 0..3 {
+                let mut xd = x;
+                inc_delta(&mut xd[j]);
+
+                self.eval_f32(&xd, &mut fxd);
+
+                // Near 1.0 the step goes backwards, so divide by the actual step
+                let step = xd[j] - x[j];
+                for (row, (fxd, fx)) in jacobian.iter_mut().zip(fxd.iter().zip(fx)) {
+                    row[j] = ((fxd - fx) / step) as f64;
+                }
+            }
+
+            // Solve system
+            let b = [
+                (fx[0] - target[0]) as f64,
+                (fx[1] - target[1]) as f64,
+                (fx[2] - target[2]) as f64,
+            ];
+            let delta = match Mat3::new(jacobian).solve(&Vec3(b)) {
+                Some(delta) => delta,
+                None => {
+                    return err!(self.context_id(), Error, Range, "Singular jacobian while reversing pipeline"; str => "Singular jacobian")
+                }
+            };
+
+            // Move our guess, with some clipping
+            for (x, delta) in x.iter_mut().zip(delta.0) {
+                *x = (*x - delta as f32).clamp(0.0, 1.0);
+            }
+        }
+
+        if last_error == f64::MAX {
+            // No iteration took place, report the distance from the starting point
+            self.eval_f32(&x, &mut fx);
+            last_error = euclidean_distance(&fx, &target[..3]);
+        }
+
+        Ok(last_error)
+    }
+}
+
+fn inc_delta(val: &mut f32) {
+    if *val < 1.0 - JACOBIAN_EPSILON {
+        *val += JACOBIAN_EPSILON;
+    } else {
+        *val -= JACOBIAN_EPSILON;
+    }
+}
+
+fn euclidean_distance(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
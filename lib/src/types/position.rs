@@ -1,10 +1,18 @@
 #[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct PositionNumber {
     offset: u32,
     size: u32,
 }
 
 impl PositionNumber {
+    pub fn new(offset: usize, size: usize) -> Self {
+        PositionNumber {
+            offset: offset as u32,
+            size: size as u32,
+        }
+    }
+
     pub fn get_offset(&self) -> usize {
         self.offset as usize
     }
@@ -0,0 +1,330 @@
+use crate::{
+    plugin::intent,
+    sig,
+    types::{end_points_by_space, Lab, Mat3, Pipeline, Vec3, XYZ},
+    Result, D50, PERCEPTUAL_BLACK,
+};
+
+use super::{used_as, Profile};
+
+const ZERO: XYZ = XYZ {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+impl Profile {
+    /// Detects the black point of the profile used as input with `intent`. Black point tags are
+    /// ignored, they are bogus on too many profiles. The black is always forced to be neutral.
+    ///
+    /// Returns `None` on device links, abstract and named color profiles, on intents other than
+    /// perceptual, relative colorimetric and saturation, or if the black can't be computed.
+    pub fn detect_black_point(&self, intent: u32, flags: u32) -> Option<XYZ> {
+        if !self.black_point_applies(intent) {
+            return None;
+        }
+
+        // v4 + perceptual & saturation intents does have its own black point, and it is
+        // well specified enough to use it. Black point tag is deprecated in V4.
+        if let Some(black_point) = self.v4_perceptual_black_point(intent) {
+            return black_point;
+        }
+
+        // That is about v2 profiles.
+
+        // If output profile, discount ink-limiting and that's all
+        if intent == intent::RELATIVE_COLORIMETRIC
+            && self.get_device_class() == sig::class::OUTPUT
+            && self.get_color_space() == sig::colorspace::CMYK
+        {
+            return self.black_point_using_perceptual_black();
+        }
+
+        // Nope, compute BP using current intent.
+        self.black_point_as_darker_colorant(intent, flags)
+    }
+
+    /// Detects the black point of the profile used as output with `intent`. This is the
+    /// algorithm in Adobe's black point compensation paper: the black is found by looking at
+    /// the shadows of a Lab round trip through the profile, so ink limits in LUT based gray, RGB
+    /// and CMYK profiles are taken into account. Any other profile is handled as
+    /// [`Profile::detect_black_point`] does.
+    pub fn detect_destination_black_point(&self, intent: u32, flags: u32) -> Option<XYZ> {
+        if !self.black_point_applies(intent) {
+            return None;
+        }
+
+        if let Some(black_point) = self.v4_perceptual_black_point(intent) {
+            return black_point;
+        }
+
+        // Check if the profile is lut based and gray, rgb or cmyk (7.2 in Adobe's document)
+        let space = self.get_color_space();
+        if !self.is_clut(intent, used_as::OUTPUT)
+            || (space != sig::colorspace::GRAY
+                && space != sig::colorspace::RGB
+                && space != sig::colorspace::CMYK)
+        {
+            // In this case, handle as input case
+            return self.detect_black_point(intent, flags);
+        }
+
+        // It is one of the valid cases!, use Adobe algorithm
+
+        // Set a first guess, that should work on good profiles.
+        let initial_lab = if intent == intent::RELATIVE_COLORIMETRIC {
+            // Calculate initial Lab as source black point
+            Lab::from_xyz(&self.detect_black_point(intent, flags)?, &D50)
+        } else {
+            // Set the initial Lab to zero, that should be the black point for perceptual and
+            // saturation
+            Lab::default()
+        };
+
+        // Step 2
+        // ======
+
+        // Create a roundtrip. Define a Transform BT for all x in L*a*b*
+        let round_trip = self.create_round_trip(intent).ok()?;
+
+        // Compute ramps
+        let mut in_ramp = [0f64; 256];
+        let mut out_ramp = [0f64; 256];
+        let a = initial_lab.a.clamp(-50.0, 50.0);
+        let b = initial_lab.b.clamp(-50.0, 50.0);
+
+        for l in 0..256 {
+            let lab = [l as f64 * 100.0 / 255.0, a, b];
+            let dest_lab = eval_lab(&round_trip, &lab);
+
+            in_ramp[l] = lab[0];
+            out_ramp[l] = dest_lab[0];
+        }
+
+        // Make monotonic
+        for l in (1..255).rev() {
+            out_ramp[l] = out_ramp[l].min(out_ramp[l + 1]);
+        }
+
+        // Check
+        if out_ramp[0] >= out_ramp[255] {
+            return None;
+        }
+
+        // Test for mid range straight (only on relative colorimetric)
+        let min_l = out_ramp[0];
+        let max_l = out_ramp[255];
+        if intent == intent::RELATIVE_COLORIMETRIC {
+            let nearly_straight_midrange =
+                in_ramp.iter().zip(out_ramp.iter()).all(|(r#in, out)| {
+                    *r#in <= min_l + 0.2 * (max_l - min_l) || (r#in - out).abs() < 4.0
+                });
+
+            // If the mid range is straight (as determined above) then the
+            // DestinationBlackPoint shall be the same as initialLab.
+            // Otherwise, the DestinationBlackPoint shall be determined
+            // using curve fitting.
+            if nearly_straight_midrange {
+                return Some(initial_lab.to_xyz(&D50));
+            }
+        }
+
+        // Curve fitting: The round-trip curve normally looks like a nearly constant section at
+        // the black point, with a corner and a nearly straight line to the white point.
+        let (lo, hi) = if intent == intent::RELATIVE_COLORIMETRIC {
+            (0.1, 0.5)
+        } else {
+            // Perceptual and saturation
+            (0.03, 0.25)
+        };
+
+        // Capture shadow points for the fitting.
+        let (x, y): (Vec<f64>, Vec<f64>) = in_ramp
+            .iter()
+            .zip(out_ramp.iter())
+            .map(|(r#in, out)| (*r#in, (out - min_l) / (max_l - min_l)))
+            .filter(|(_, y)| *y >= lo && *y < hi)
+            .unzip();
+
+        // No suitable points
+        if x.len() < 3 {
+            return None;
+        }
+
+        // Fit and get the vertex of quadratic curve, clipped to zero L* if negative
+        let l = root_of_least_squares_fit_quadratic_curve(&x, &y).max(0.0);
+
+        Some(Lab::new(l, initial_lab.a, initial_lab.b).to_xyz(&D50))
+    }
+
+    /// Black point detection only applies to device profiles, and the non absolute ICC intents.
+    fn black_point_applies(&self, intent: u32) -> bool {
+        // Make sure the device class is adequate
+        let class = self.get_device_class();
+        if class == sig::class::LINK
+            || class == sig::class::ABSTRACT
+            || class == sig::class::NAMED_COLOR
+        {
+            return false;
+        }
+
+        // Make sure intent is adequate
+        intent == intent::PERCEPTUAL
+            || intent == intent::RELATIVE_COLORIMETRIC
+            || intent == intent::SATURATION
+    }
+
+    /// The black point of perceptual and saturation intents on v4 profiles, which is fixed
+    /// unless the profile is a matrix shaper. `None` if that doesn't apply.
+    fn v4_perceptual_black_point(&self, intent: u32) -> Option<Option<XYZ>> {
+        if self.get_encoded_icc_version() < 0x4000000
+            || (intent != intent::PERCEPTUAL && intent != intent::SATURATION)
+        {
+            return None;
+        }
+
+        // Matrix shaper share MRC & perceptual intents
+        if self.is_matrix_shaper() {
+            return Some(self.black_point_as_darker_colorant(intent::RELATIVE_COLORIMETRIC, 0));
+        }
+
+        // Get Perceptual black out of v4 profiles. That is fixed for perceptual & saturation
+        // intents
+        Some(Some(PERCEPTUAL_BLACK))
+    }
+
+    /// Uses the darkest colorants to obtain the black point. This works in the relative
+    /// colorimetric intent and assumes more ink results in darker colors. No ink limit is
+    /// assumed.
+    fn black_point_as_darker_colorant(&self, intent: u32, _flags: u32) -> Option<XYZ> {
+        // If the profile does not support input direction, assume Black point 0
+        if !self.is_intent_supported(intent, used_as::INPUT) {
+            return None;
+        }
+
+        // This function returns darker colorant in 16 bits for several spaces
+        let (_, black) = end_points_by_space(self.get_color_space())?;
+
+        let lut = self.device_to_lab_lut(intent).ok()?;
+        if lut.get_input_channels() != black.len() {
+            return None;
+        }
+
+        // Convert black to Lab
+        let black = black
+            .iter()
+            .map(|black| *black as f32 / 65535.0)
+            .collect::<Vec<_>>();
+        let mut lab = [0f32; 3];
+        lut.eval_f32(&black, &mut lab);
+
+        // Force it to be neutral, check for inconsistencies
+        let mut l = lab[0] as f64 * 100.0;
+        if !(0.0..=50.0).contains(&l) {
+            l = 0.0;
+        }
+
+        // Convert from Lab (which is now clipped) to XYZ.
+        Some(Lab::new(l, 0.0, 0.0).to_xyz(&D50))
+    }
+
+    /// Gets the black point of an output CMYK profile, discounting any ink-limiting embedded
+    /// in the profile. For doing that, we use perceptual intent in input direction:
+    /// Lab (0, 0, 0) -> [Perceptual] Profile -> CMYK -> [Rel. colorimetric] Profile -> Lab
+    fn black_point_using_perceptual_black(&self) -> Option<XYZ> {
+        // Is the intent supported by the profile?
+        if !self.is_intent_supported(intent::PERCEPTUAL, used_as::INPUT) {
+            return Some(ZERO);
+        }
+
+        let round_trip = self.create_round_trip(intent::PERCEPTUAL).ok()?;
+
+        // Clip Lab to reasonable limits
+        let lab = eval_lab(&round_trip, &[0.0; 3]);
+        let l = lab[0].min(50.0);
+
+        // Convert it to XYZ
+        Some(Lab::new(l, 0.0, 0.0).to_xyz(&D50))
+    }
+
+    /// A Lab to Lab round trip through the profile, going to the device with `intent` and back
+    /// to Lab with relative colorimetric.
+    fn create_round_trip(&self, intent: u32) -> Result<Pipeline> {
+        let mut lut = self.lab_to_device_lut(intent)?;
+        lut.cat(&self.device_to_lab_lut(intent::RELATIVE_COLORIMETRIC)?)?;
+
+        Ok(lut)
+    }
+}
+
+/// Evaluates a pipeline on Lab, L* in 0..100 and a*, b* in -128..127.
+fn eval_lab(lut: &Pipeline, lab: &[f64; 3]) -> [f64; 3] {
+    let r#in = [
+        (lab[0] / 100.0) as f32,
+        ((lab[1] + 128.0) / 255.0) as f32,
+        ((lab[2] + 128.0) / 255.0) as f32,
+    ];
+    let mut out = [0f32; 3];
+
+    lut.eval_f32(&r#in, &mut out);
+
+    [
+        out[0] as f64 * 100.0,
+        out[1] as f64 * 255.0 - 128.0,
+        out[2] as f64 * 255.0 - 128.0,
+    ]
+}
+
+/// Fits a quadratic curve to the data by least squares, and returns its root clipped to
+/// 0..50, or 0 if there is none.
+/// <http://www.personal.psu.edu/jhm/f90/lectures/lsq2.html>
+fn root_of_least_squares_fit_quadratic_curve(x: &[f64], y: &[f64]) -> f64 {
+    if x.len() < 4 {
+        return 0.0;
+    }
+
+    let mut sum_x = 0.0;
+    let mut sum_x2 = 0.0;
+    let mut sum_x3 = 0.0;
+    let mut sum_x4 = 0.0;
+    let mut sum_y = 0.0;
+    let mut sum_yx = 0.0;
+    let mut sum_yx2 = 0.0;
+
+    for (xn, yn) in x.iter().zip(y.iter()) {
+        sum_x += xn;
+        sum_x2 += xn * xn;
+        sum_x3 += xn * xn * xn;
+        sum_x4 += xn * xn * xn * xn;
+
+        sum_y += yn;
+        sum_yx += yn * xn;
+        sum_yx2 += yn * xn * xn;
+    }
+
+    let n = x.len() as f64;
+    let m = Mat3::new([
+        [n, sum_x, sum_x2],
+        [sum_x, sum_x2, sum_x3],
+        [sum_x2, sum_x3, sum_x4],
+    ]);
+    let v = Vec3::new(sum_y, sum_yx, sum_yx2);
+
+    let [c, b, a] = match m.solve(&v) {
+        Some(res) => res.0,
+        None => return 0.0,
+    };
+
+    if a.abs() < 1.0e-10 {
+        // lcms2 clips -c / b as min(0, max(50, -c / b)), which is 0 for any straight line
+        return 0.0;
+    }
+
+    let d = b * b - 4.0 * a * c;
+    if d <= 0.0 {
+        return 0.0;
+    }
+
+    let rt = (-b + d.sqrt()) / (2.0 * a);
+    rt.clamp(0.0, 50.0)
+}
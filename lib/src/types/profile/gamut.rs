@@ -0,0 +1,58 @@
+use crate::{plugin::intent, quantize_val, sig, types::channels_of_color_space, MAX_CHANNELS};
+
+use super::Profile;
+
+/// Grid the Lab space is sampled on to estimate the total area coverage.
+const TAC_GRID_POINTS: [usize; 3] = [6, 74, 74];
+
+impl Profile {
+    /// Estimates the total area coverage of an output profile, the maximum sum of all inks in
+    /// percent. The profile is evaluated on a grid over the Lab space, so the result is only as
+    /// good as its perceptual table. Returns 0 for anything but output profiles.
+    pub fn detect_tac(&self) -> f64 {
+        // TAC only works on output profiles
+        if self.get_device_class() != sig::class::OUTPUT {
+            return 0.0;
+        }
+
+        // Create a fake formatter for result
+        let n_chans = match channels_of_color_space(self.get_color_space()) {
+            Some(n_chans) if n_chans < MAX_CHANNELS => n_chans,
+            _ => return 0.0,
+        };
+
+        // Setup a roundtrip on perceptual intent in output profile for TAC estimation
+        let lab_to_device = match self.lab_to_device_lut(intent::PERCEPTUAL) {
+            Ok(lut) => lut,
+            Err(_) => return 0.0,
+        };
+
+        let mut max_tac = 0f64;
+        let mut lab = [0f32; 3];
+        let mut out = [0f32; MAX_CHANNELS];
+
+        for l in 0..TAC_GRID_POINTS[0] {
+            lab[0] = quantize_val(l as f64, TAC_GRID_POINTS[0]) as f32 / 65535.0;
+
+            for a in 0..TAC_GRID_POINTS[1] {
+                lab[1] = quantize_val(a as f64, TAC_GRID_POINTS[1]) as f32 / 65535.0;
+
+                for b in 0..TAC_GRID_POINTS[2] {
+                    lab[2] = quantize_val(b as f64, TAC_GRID_POINTS[2]) as f32 / 65535.0;
+
+                    lab_to_device.eval_f32(&lab, &mut out);
+
+                    // All ink percentages
+                    let sum = out[..n_chans]
+                        .iter()
+                        .map(|ink| *ink as f64 * 100.0)
+                        .sum::<f64>();
+
+                    max_tac = max_tac.max(sum);
+                }
+            }
+        }
+
+        max_tac
+    }
+}
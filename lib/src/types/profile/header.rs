@@ -0,0 +1,165 @@
+use crate::{
+    io::IoHandler,
+    sig,
+    types::{DateTimeNumber, Signature, XYZ},
+    Result,
+};
+
+use super::Profile;
+
+impl Profile {
+    /// Reads and validates the 128 byte ICC header from the profile's [`IoHandler`].
+    pub(super) fn read_header(&mut self) -> Result<()> {
+        let io = match self.io_handler {
+            Some(ref io) => io.clone(),
+            None => return err!(str => "Profile has no IoHandler to read from"),
+        };
+        let mut io = io.lock().unwrap();
+        let io: &mut dyn IoHandler = &mut *io;
+
+        let header = match read_raw_header(io) {
+            Ok(header) => header,
+            Err(_) => {
+                return err!(self.context_id, Error, Read, "Unable to read ICC profile header"; str => "Unable to read ICC profile header")
+            }
+        };
+
+        if header.magic != sig::MAGIC_NUMBER {
+            return err!(self.context_id, Error, BadSignature, "Not an ICC profile, invalid signature"; str => "Invalid ICC signature");
+        }
+
+        // Clip the reported size to the real size of the stream
+        let reported_size = io.reported_size();
+        self.size = if header.size >= reported_size {
+            reported_size
+        } else {
+            header.size
+        };
+
+        self.cmm = header.cmm;
+        self.version = validated_version(header.version);
+        self.device_class = header.device_class;
+        self.color_space = header.color_space;
+        self.pcs = header.pcs;
+        self.created = header.created;
+        self.platform = header.platform;
+        self.flags = header.flags;
+        self.manufacturer = header.manufacturer;
+        self.model = header.model;
+        self.attributes = header.attributes;
+        self.rendering_intent = header.rendering_intent;
+        self.illuminant = header.illuminant;
+        self.creator = header.creator;
+        self.profile_id = header.profile_id;
+
+        Ok(())
+    }
+
+    /// Writes the 128 byte ICC header, `size` being the total size of the profile in bytes.
+    pub(super) fn write_header(&self, io: &mut dyn IoHandler, size: usize) -> std::io::Result<()> {
+        io.write_u32(size as u32)?;
+        io.write_signature(self.cmm)?;
+        io.write_u32(self.version)?;
+        io.write_signature(self.device_class)?;
+        io.write_signature(self.color_space)?;
+        io.write_signature(self.pcs)?;
+        io.write_date_time_number(self.created)?;
+        io.write_signature(sig::MAGIC_NUMBER)?;
+        io.write_signature(self.platform)?;
+        io.write_u32(self.flags)?;
+        io.write_u32(self.manufacturer)?;
+        io.write_u32(self.model)?;
+        io.write_u64(self.attributes)?;
+        io.write_u32(self.rendering_intent)?;
+        io.write_xyz(self.illuminant)?;
+        io.write_signature(self.creator)?;
+        io.write(16, &self.profile_id)?;
+        io.write(28, &[0u8; 28])?;
+
+        Ok(())
+    }
+}
+
+struct RawHeader {
+    size: usize,
+    cmm: Signature,
+    version: u32,
+    device_class: Signature,
+    color_space: Signature,
+    pcs: Signature,
+    created: DateTimeNumber,
+    magic: Signature,
+    platform: Signature,
+    flags: u32,
+    manufacturer: u32,
+    model: u32,
+    attributes: u64,
+    rendering_intent: u32,
+    illuminant: XYZ,
+    creator: Signature,
+    profile_id: [u8; 16],
+}
+
+fn read_raw_header(io: &mut dyn IoHandler) -> std::io::Result<RawHeader> {
+    let size = io.read_u32()? as usize;
+    let cmm = io.read_signature()?;
+    let version = io.read_u32()?;
+    let device_class = io.read_signature()?;
+    let color_space = io.read_signature()?;
+    let pcs = io.read_signature()?;
+    let created = io.read_date_time_number()?;
+    let magic = io.read_signature()?;
+    let platform = io.read_signature()?;
+    let flags = io.read_u32()?;
+    let manufacturer = io.read_u32()?;
+    let model = io.read_u32()?;
+    let attributes = io.read_u64()?;
+    let rendering_intent = io.read_u32()?;
+    let illuminant = io.read_xyz()?;
+    let creator = io.read_signature()?;
+
+    let mut profile_id = [0u8; 16];
+    io.read(&mut profile_id, 16, 1)?;
+
+    let mut reserved = [0u8; 28];
+    io.read(&mut reserved, 28, 1)?;
+
+    Ok(RawHeader {
+        size,
+        cmm,
+        version,
+        device_class,
+        color_space,
+        pcs,
+        created,
+        magic,
+        platform,
+        flags,
+        manufacturer,
+        model,
+        attributes,
+        rendering_intent,
+        illuminant,
+        creator,
+        profile_id,
+    })
+}
+
+/// Enforces that the profile version is per spec. Operates on the big-endian bytes from the
+/// profile. Free bytes are set to zero.
+fn validated_version(version: u32) -> u32 {
+    let mut bytes = version.to_be_bytes();
+
+    if bytes[0] > 0x09 {
+        bytes[0] = 0x09;
+    }
+
+    let temp1 = (bytes[1] & 0xf0).min(0x90);
+    let temp2 = (bytes[1] & 0x0f).min(0x09);
+
+    bytes[1] = temp1 | temp2;
+    bytes[2] = 0;
+    bytes[3] = 0;
+
+    u32::from_be_bytes(bytes)
+}
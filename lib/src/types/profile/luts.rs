@@ -0,0 +1,629 @@
+use crate::{
+    plugin::{intent, lerp_flags},
+    sig,
+    types::{
+        adaptation_matrix, cone_matrix, IccViewingConditions, Mat3, Pipeline, Signature, Stage,
+        StageCLutData, StageLoc, ToneCurve, ViewingConditions, XYZ,
+    },
+    Result, D50, MAX_ENCODEABLE_XYZ,
+};
+
+use super::Profile;
+
+/// Tags holding the device to PCS LUT of each ICC intent. Absolute colorimetric reuses the
+/// relative colorimetric table.
+const DEVICE_TO_PCS_16: [Signature; 4] = [
+    sig::tags::A_TO_B0,
+    sig::tags::A_TO_B1,
+    sig::tags::A_TO_B2,
+    sig::tags::A_TO_B1,
+];
+const DEVICE_TO_PCS_FLOAT: [Signature; 4] = [
+    sig::tags::D_TO_B0,
+    sig::tags::D_TO_B1,
+    sig::tags::D_TO_B2,
+    sig::tags::D_TO_B3,
+];
+const PCS_TO_DEVICE_16: [Signature; 4] = [
+    sig::tags::B_TO_A0,
+    sig::tags::B_TO_A1,
+    sig::tags::B_TO_A2,
+    sig::tags::B_TO_A1,
+];
+const PCS_TO_DEVICE_FLOAT: [Signature; 4] = [
+    sig::tags::B_TO_D0,
+    sig::tags::B_TO_D1,
+    sig::tags::B_TO_D2,
+    sig::tags::B_TO_D3,
+];
+
+/// Factor converting from the 1.15 fixed point XYZ encoding to 0..1.
+const INP_ADJ: f64 = 1.0 / MAX_ENCODEABLE_XYZ;
+/// Factor converting from 0..1 to the 1.15 fixed point XYZ encoding.
+const OUTP_ADJ: f64 = MAX_ENCODEABLE_XYZ;
+
+const GRAY_INPUT_MATRIX: [f64; 3] = [INP_ADJ * D50.x, INP_ADJ * D50.y, INP_ADJ * D50.z];
+const ONE_TO_THREE_INPUT_MATRIX: [f64; 3] = [1.0, 1.0, 1.0];
+const PICK_Y_MATRIX: [f64; 3] = [0.0, OUTP_ADJ * D50.y, 0.0];
+const PICK_LSTAR_MATRIX: [f64; 3] = [1.0, 0.0, 0.0];
+
+impl Profile {
+    /// The media white point, D50 if the profile has none. v2 display profiles always get D50,
+    /// as their white point tag holds the display white instead.
+    pub fn read_media_white_point(&self) -> XYZ {
+        let white_point = match self.read_xyz_tag(sig::tags::MEDIA_WHITE_POINT) {
+            Ok(white_point) => white_point,
+            Err(_) => return D50,
+        };
+
+        if self.get_encoded_icc_version() < 0x4000000
+            && self.get_device_class() == sig::class::DISPLAY
+        {
+            return D50;
+        }
+
+        white_point
+    }
+
+    /// The chromatic adaptation from the actual illuminant to D50 the profile went through. v2
+    /// display profiles without a `chad` tag get the Bradford adaptation of their media white,
+    /// any other profile without one gets the identity.
+    pub fn read_chad(&self) -> Result<Mat3> {
+        if self.is_tag(sig::tags::CHROMATIC_ADAPTATION) {
+            return match self
+                .read_tag(sig::tags::CHROMATIC_ADAPTATION)?
+                .downcast_ref::<Vec<f64>>()
+            {
+                Some(chad) if chad.len() >= 9 => Ok(Mat3::new([
+                    [chad[0], chad[1], chad[2]],
+                    [chad[3], chad[4], chad[5]],
+                    [chad[6], chad[7], chad[8]],
+                ])),
+                _ => {
+                    err!(self.context_id, Error, BadSignature, "Tag 'chad' doesn't hold a matrix"; str => "Wrong tag data type")
+                }
+            };
+        }
+
+        // V2 display profiles should give D50
+        if self.get_encoded_icc_version() < 0x4000000
+            && self.get_device_class() == sig::class::DISPLAY
+        {
+            if let Ok(white_point) = self.read_xyz_tag(sig::tags::MEDIA_WHITE_POINT) {
+                return adaptation_matrix(&cone_matrix::BRADFORD, &white_point, &D50);
+            }
+        }
+
+        // No CHAD available, default it to identity
+        Ok(Mat3::IDENTITY)
+    }
+
+    /// Stores `chad` as the chromatic adaptation of the profile.
+    pub fn write_chad(&mut self, chad: &Mat3) -> Result<()> {
+        self.write_tag(
+            sig::tags::CHROMATIC_ADAPTATION,
+            Box::new(chad.to_array().to_vec()),
+        )
+    }
+
+    /// The conditions the profile's viewing conditions tag describes, ready for a color
+    /// appearance model.
+    pub fn read_viewing_conditions(&self) -> Result<ViewingConditions> {
+        match self
+            .read_tag(sig::tags::VIEWING_CONDITIONS)?
+            .downcast_ref::<IccViewingConditions>()
+        {
+            Some(view) => Ok(ViewingConditions::from_icc(view)),
+            None => {
+                err!(self.context_id, Error, BadSignature, "Tag 'view' doesn't hold viewing conditions"; str => "Wrong tag data type")
+            }
+        }
+    }
+
+    /// Builds the pipeline taking the profile's color space to its PCS for `intent`. Float
+    /// tags take precedence over 16 bits ones, and profiles without either are built as matrix
+    /// shapers. Any intent past [`intent::ABSOLUTE_COLORIMETRIC`] always reads the matrix
+    /// shaper.
+    pub fn read_input_lut(&self, intent: u32) -> Result<Pipeline> {
+        if intent <= intent::ABSOLUTE_COLORIMETRIC {
+            let tag_float = DEVICE_TO_PCS_FLOAT[intent as usize];
+            let mut tag_16 = DEVICE_TO_PCS_16[intent as usize];
+
+            // Float tag takes precedence. Floating point LUTs are always v4
+            if self.is_tag(tag_float) {
+                return self.read_float_input_tag(tag_float);
+            }
+
+            // Revert to perceptual if no tag is found
+            if !self.is_tag(tag_16) {
+                tag_16 = DEVICE_TO_PCS_16[0];
+            }
+
+            if self.is_tag(tag_16) {
+                let mut lut = self.read_pipeline(tag_16)?;
+
+                // We need to adjust data only for Lab16 on output
+                if self.get_tag_true_type(tag_16) != Some(sig::types::LUT16)
+                    || self.get_pcs() != sig::colorspace::LAB
+                {
+                    return Ok(lut);
+                }
+
+                // If the input is Lab, add also a conversion at the begin
+                if self.get_color_space() == sig::colorspace::LAB {
+                    lut.insert_stage(
+                        StageLoc::AtBegin,
+                        Stage::alloc_lab_v4_to_v2(&self.context_id)?,
+                    )?;
+                }
+
+                // Add a matrix for conversion V2 to V4 Lab PCS
+                lut.insert_stage(
+                    StageLoc::AtEnd,
+                    Stage::alloc_lab_v2_to_v4(&self.context_id)?,
+                )?;
+
+                return Ok(lut);
+            }
+        }
+
+        // Lut was not found, try to create a matrix-shaper
+        if self.get_color_space() == sig::colorspace::GRAY {
+            return self.build_gray_input_matrix_pipeline();
+        }
+
+        self.build_rgb_input_matrix_shaper()
+    }
+
+    /// Builds the pipeline taking the profile's PCS to its color space for `intent`. Like
+    /// [`Profile::read_input_lut`], falling back to an inverted matrix shaper.
+    pub fn read_output_lut(&self, intent: u32) -> Result<Pipeline> {
+        if intent <= intent::ABSOLUTE_COLORIMETRIC {
+            let tag_float = PCS_TO_DEVICE_FLOAT[intent as usize];
+            let mut tag_16 = PCS_TO_DEVICE_16[intent as usize];
+
+            // Float tag takes precedence. Floating point LUTs are always v4
+            if self.is_tag(tag_float) {
+                return self.read_float_output_tag(tag_float);
+            }
+
+            // Revert to perceptual if no tag is found
+            if !self.is_tag(tag_16) {
+                tag_16 = PCS_TO_DEVICE_16[0];
+            }
+
+            if self.is_tag(tag_16) {
+                let mut lut = self.read_pipeline(tag_16)?;
+
+                // 3D LUTs indexed by Lab interpolate better trilinearly
+                if self.get_pcs() == sig::colorspace::LAB {
+                    change_interpolation_to_trilinear(&mut lut)?;
+                }
+
+                // We need to adjust data only for Lab and Lut16 type
+                if self.get_tag_true_type(tag_16) != Some(sig::types::LUT16)
+                    || self.get_pcs() != sig::colorspace::LAB
+                {
+                    return Ok(lut);
+                }
+
+                // Add a matrix for conversion V4 to V2 Lab PCS
+                lut.insert_stage(
+                    StageLoc::AtBegin,
+                    Stage::alloc_lab_v4_to_v2(&self.context_id)?,
+                )?;
+
+                // If the output is Lab, add also a conversion at the end
+                if self.get_color_space() == sig::colorspace::LAB {
+                    lut.insert_stage(
+                        StageLoc::AtEnd,
+                        Stage::alloc_lab_v2_to_v4(&self.context_id)?,
+                    )?;
+                }
+
+                return Ok(lut);
+            }
+        }
+
+        // Lut not found, try to create a matrix-shaper
+        if self.get_color_space() == sig::colorspace::GRAY {
+            return self.build_gray_output_pipeline();
+        }
+
+        // Not gray, create a normal matrix-shaper, which only operates in XYZ space
+        self.build_rgb_output_matrix_shaper()
+    }
+
+    /// Builds the pipeline of a device link or abstract profile for `intent`. Those profiles
+    /// can't be matrix shapers.
+    pub fn read_devicelink_lut(&self, intent: u32) -> Result<Pipeline> {
+        if intent > intent::ABSOLUTE_COLORIMETRIC {
+            return err!(self.context_id, Error, Range, "Unsupported intent '{}' for device link", intent; str => "Unsupported intent");
+        }
+
+        let tag_float = DEVICE_TO_PCS_FLOAT[intent as usize];
+        let mut tag_16 = DEVICE_TO_PCS_16[intent as usize];
+
+        // Float tag takes precedence. Floating point LUTs are always v4, and normalized on
+        // both sides just like on input
+        if self.is_tag(tag_float) {
+            return self.read_float_input_tag(tag_float);
+        }
+
+        let tag_float = DEVICE_TO_PCS_FLOAT[0];
+        if self.is_tag(tag_float) {
+            return self.read_pipeline(tag_float);
+        }
+
+        if !self.is_tag(tag_16) {
+            tag_16 = DEVICE_TO_PCS_16[0];
+        }
+
+        let mut lut = self.read_pipeline(tag_16)?;
+
+        // 3D LUTs indexed by Lab interpolate better trilinearly
+        if self.get_pcs() == sig::colorspace::LAB {
+            change_interpolation_to_trilinear(&mut lut)?;
+        }
+
+        // We need to adjust data for Lab16 on output
+        if self.get_tag_true_type(tag_16) != Some(sig::types::LUT16) {
+            return Ok(lut);
+        }
+
+        // Here it is possible to get Lab on both sides
+        if self.get_color_space() == sig::colorspace::LAB {
+            lut.insert_stage(
+                StageLoc::AtBegin,
+                Stage::alloc_lab_v4_to_v2(&self.context_id)?,
+            )?;
+        }
+        if self.get_pcs() == sig::colorspace::LAB {
+            lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_lab_v2_to_v4(&self.context_id)?,
+            )?;
+        }
+
+        Ok(lut)
+    }
+
+    /// Whether the profile holds what a gray or RGB matrix shaper needs.
+    pub fn is_matrix_shaper(&self) -> bool {
+        match self.get_color_space() {
+            sig::colorspace::GRAY => self.is_tag(sig::tags::GRAY_TRC),
+            sig::colorspace::RGB => [
+                sig::tags::RED_COLORANT,
+                sig::tags::GREEN_COLORANT,
+                sig::tags::BLUE_COLORANT,
+                sig::tags::RED_TRC,
+                sig::tags::GREEN_TRC,
+                sig::tags::BLUE_TRC,
+            ]
+            .iter()
+            .all(|sig| self.is_tag(*sig)),
+            _ => false,
+        }
+    }
+
+    /// Whether the profile has a 16 bits LUT for `intent` when used in `direction`, one of
+    /// [`used_as`]. Device links only support the intent in their header.
+    pub fn is_clut(&self, intent: u32, direction: u32) -> bool {
+        // For devicelinks, the supported intent is that one stated in the header
+        if self.get_device_class() == sig::class::LINK {
+            return self.get_header_rendering_intent() == intent;
+        }
+
+        let tag_table = match direction {
+            used_as::INPUT => &DEVICE_TO_PCS_16,
+            used_as::OUTPUT => &PCS_TO_DEVICE_16,
+            // For proofing, we need rel. colorimetric in output. Let's do some recursion
+            used_as::PROOF => {
+                return self.is_intent_supported(intent, used_as::INPUT)
+                    && self.is_intent_supported(intent::RELATIVE_COLORIMETRIC, used_as::OUTPUT)
+            }
+            _ => {
+                self.context_id.signal_error(
+                    log::Level::Error,
+                    crate::state::ErrorCode::Range,
+                    &format!("Unexpected direction ({})", direction),
+                );
+                return false;
+            }
+        };
+
+        // Extended intents are not strictly CLUT-based
+        if intent > intent::ABSOLUTE_COLORIMETRIC {
+            return false;
+        }
+
+        self.is_tag(tag_table[intent as usize])
+    }
+
+    /// Whether the profile can be used in `direction`, one of [`used_as`], for `intent`. Matrix
+    /// shapers support every intent, even if v2 ones can't deal with non-zero black points.
+    pub fn is_intent_supported(&self, intent: u32, direction: u32) -> bool {
+        self.is_clut(intent, direction) || self.is_matrix_shaper()
+    }
+
+    /// The output table for `intent`, taking Lab in the 0..1 pipeline encoding whatever the
+    /// PCS.
+    pub(super) fn lab_to_device_lut(&self, intent: u32) -> Result<Pipeline> {
+        let mut lut = Pipeline::new(&self.context_id, 3, 3)?;
+
+        if self.get_pcs() == sig::colorspace::XYZ {
+            lut.insert_stage(StageLoc::AtEnd, Stage::alloc_lab_to_xyz(&self.context_id)?)?;
+        }
+
+        lut.cat(&self.read_output_lut(intent)?)?;
+
+        Ok(lut)
+    }
+
+    /// The input table for `intent`, giving Lab in the 0..1 pipeline encoding whatever the PCS.
+    pub(super) fn device_to_lab_lut(&self, intent: u32) -> Result<Pipeline> {
+        let mut lut = self.read_input_lut(intent)?;
+
+        if self.get_pcs() == sig::colorspace::XYZ {
+            lut.insert_stage(StageLoc::AtEnd, Stage::alloc_xyz_to_lab(&self.context_id)?)?;
+        }
+
+        Ok(lut)
+    }
+
+    /// Reads a float device to PCS tag. Lab and XYZ in float tags use their natural ranges,
+    /// so they are normalized to and from the 0..1 pipeline encoding.
+    fn read_float_input_tag(&self, tag_float: Signature) -> Result<Pipeline> {
+        let mut lut = self.read_pipeline(tag_float)?;
+
+        self.normalize_float_input(&mut lut, self.get_color_space())?;
+        self.normalize_float_output(&mut lut, self.get_pcs())?;
+
+        Ok(lut)
+    }
+
+    /// Reads a float PCS to device tag, see [`Profile::read_float_input_tag`].
+    fn read_float_output_tag(&self, tag_float: Signature) -> Result<Pipeline> {
+        let mut lut = self.read_pipeline(tag_float)?;
+
+        self.normalize_float_input(&mut lut, self.get_pcs())?;
+        self.normalize_float_output(&mut lut, self.get_color_space())?;
+
+        Ok(lut)
+    }
+
+    /// Converts the 0..1 pipeline encoding of `space` to the range a float tag expects.
+    fn normalize_float_input(&self, lut: &mut Pipeline, space: Signature) -> Result<()> {
+        match space {
+            sig::colorspace::LAB => lut.insert_stage(
+                StageLoc::AtBegin,
+                Stage::alloc_normalize_to_lab_float(&self.context_id)?,
+            ),
+            sig::colorspace::XYZ => lut.insert_stage(
+                StageLoc::AtBegin,
+                Stage::alloc_normalize_to_xyz_float(&self.context_id)?,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Converts what a float tag outputs in `space` to the 0..1 pipeline encoding.
+    fn normalize_float_output(&self, lut: &mut Pipeline, space: Signature) -> Result<()> {
+        match space {
+            sig::colorspace::LAB => lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_normalize_from_lab_float(&self.context_id)?,
+            ),
+            sig::colorspace::XYZ => lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_normalize_from_xyz_float(&self.context_id)?,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The gray TRC scales the PCS illuminant. On Lab, it becomes L* with a* and b* kept at 0.
+    fn build_gray_input_matrix_pipeline(&self) -> Result<Pipeline> {
+        let ctx = &self.context_id;
+        let gray_trc = self.read_tone_curve(sig::tags::GRAY_TRC)?;
+
+        let mut lut = Pipeline::new(ctx, 1, 3)?;
+
+        if self.get_pcs() == sig::colorspace::LAB {
+            // An identity matrix plus 3 tone curves
+            let empty_tab = ToneCurve::build_tabulated_16(ctx, &[0x8080, 0x8080])?;
+            let lab_curves = [gray_trc.clone(), empty_tab.clone(), empty_tab];
+
+            lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_matrix(ctx, 3, 1, &ONE_TO_THREE_INPUT_MATRIX, None)?,
+            )?;
+            lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_tone_curves(ctx, 3, Some(&lab_curves))?,
+            )?;
+        } else {
+            lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_tone_curves(ctx, 1, Some(std::slice::from_ref(gray_trc)))?,
+            )?;
+            lut.insert_stage(
+                StageLoc::AtEnd,
+                Stage::alloc_matrix(ctx, 3, 1, &GRAY_INPUT_MATRIX, None)?,
+            )?;
+        }
+
+        Ok(lut)
+    }
+
+    fn build_rgb_input_matrix_shaper(&self) -> Result<Pipeline> {
+        let ctx = &self.context_id;
+
+        // XYZ PCS is encoded in 1.15 format, and the matrix output comes in 0..0xffff range, so
+        // we need to adjust the output by a factor of (0x10000/0xffff) to put data in a 1.16
+        // range, and then a >> 1 to obtain 1.15. The total factor is (65536.0)/(65535.0*2)
+        let mat = self
+            .read_icc_matrix_rgb_to_xyz()?
+            .map(|row| row.map(|v| v * INP_ADJ));
+        let shapes = self.read_rgb_trcs()?;
+
+        let mut lut = Pipeline::new(ctx, 3, 3)?;
+        lut.insert_stage(
+            StageLoc::AtEnd,
+            Stage::alloc_tone_curves(ctx, 3, Some(&shapes))?,
+        )?;
+        lut.insert_stage(
+            StageLoc::AtEnd,
+            Stage::alloc_matrix(ctx, 3, 3, &mat.concat(), None)?,
+        )?;
+
+        // A matrix shaper on Lab is not allowed by the spec, but can be found on profiles that
+        // only use it as fallback for a LUT based output tag
+        if self.get_pcs() == sig::colorspace::LAB {
+            lut.insert_stage(StageLoc::AtEnd, Stage::alloc_xyz_to_lab(ctx)?)?;
+        }
+
+        Ok(lut)
+    }
+
+    fn build_gray_output_pipeline(&self) -> Result<Pipeline> {
+        let ctx = &self.context_id;
+        let rev_gray_trc = self.read_tone_curve(sig::tags::GRAY_TRC)?.reverse()?;
+
+        let pick = if self.get_pcs() == sig::colorspace::LAB {
+            &PICK_LSTAR_MATRIX
+        } else {
+            &PICK_Y_MATRIX
+        };
+
+        let mut lut = Pipeline::new(ctx, 3, 1)?;
+        lut.insert_stage(StageLoc::AtEnd, Stage::alloc_matrix(ctx, 1, 3, pick, None)?)?;
+        lut.insert_stage(
+            StageLoc::AtEnd,
+            Stage::alloc_tone_curves(ctx, 1, Some(&[rev_gray_trc]))?,
+        )?;
+
+        Ok(lut)
+    }
+
+    fn build_rgb_output_matrix_shaper(&self) -> Result<Pipeline> {
+        let ctx = &self.context_id;
+
+        let inv = match Mat3::new(self.read_icc_matrix_rgb_to_xyz()?).inverse() {
+            Some(inv) => inv,
+            None => {
+                return err!(ctx, Error, NotSuitable, "Colorant matrix is not invertible"; str => "Colorant matrix is not invertible")
+            }
+        };
+
+        // XYZ PCS is encoded in 1.15 format, and the matrix input should come in 0..0xffff
+        // range, so we need to adjust the input by a << 1 to obtain a 1.16 fixed and then by a
+        // factor of (0xffff/0x10000) to put data in 0..0xffff range. Total factor is
+        // (2.0*65535.0)/65536.0
+        let inv = inv.to_array().map(|v| v * OUTP_ADJ);
+
+        let inv_shapes = self
+            .read_rgb_trcs()?
+            .iter()
+            .map(|shape| shape.reverse())
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut lut = Pipeline::new(ctx, 3, 3)?;
+
+        // A matrix shaper on Lab is not allowed by the spec, but can be found on profiles that
+        // only use it as fallback for a LUT based input tag
+        if self.get_pcs() == sig::colorspace::LAB {
+            lut.insert_stage(StageLoc::AtEnd, Stage::alloc_lab_to_xyz(ctx)?)?;
+        }
+
+        lut.insert_stage(StageLoc::AtEnd, Stage::alloc_matrix(ctx, 3, 3, &inv, None)?)?;
+        lut.insert_stage(
+            StageLoc::AtEnd,
+            Stage::alloc_tone_curves(ctx, 3, Some(&inv_shapes))?,
+        )?;
+
+        Ok(lut)
+    }
+
+    /// The colorants as the columns of the matrix converting RGB to XYZ.
+    fn read_icc_matrix_rgb_to_xyz(&self) -> Result<[[f64; 3]; 3]> {
+        let red = self.read_xyz_tag(sig::tags::RED_COLORANT)?;
+        let green = self.read_xyz_tag(sig::tags::GREEN_COLORANT)?;
+        let blue = self.read_xyz_tag(sig::tags::BLUE_COLORANT)?;
+
+        Ok([
+            [red.x, green.x, blue.x],
+            [red.y, green.y, blue.y],
+            [red.z, green.z, blue.z],
+        ])
+    }
+
+    fn read_rgb_trcs(&self) -> Result<[ToneCurve; 3]> {
+        Ok([
+            self.read_tone_curve(sig::tags::RED_TRC)?.clone(),
+            self.read_tone_curve(sig::tags::GREEN_TRC)?.clone(),
+            self.read_tone_curve(sig::tags::BLUE_TRC)?.clone(),
+        ])
+    }
+
+    /// A copy of the pipeline in tag `sig`.
+    fn read_pipeline(&self, sig: Signature) -> Result<Pipeline> {
+        match self.read_tag(sig)?.downcast_ref::<Pipeline>() {
+            Some(lut) => Ok(lut.duplicate()),
+            None => {
+                err!(self.context_id, Error, BadSignature, "Tag '{}' doesn't hold a pipeline", sig; str => "Wrong tag data type")
+            }
+        }
+    }
+
+    fn read_tone_curve(&self, sig: Signature) -> Result<&ToneCurve> {
+        match self.read_tag(sig)?.downcast_ref::<ToneCurve>() {
+            Some(curve) => Ok(curve),
+            None => {
+                err!(self.context_id, Error, BadSignature, "Tag '{}' doesn't hold a tone curve", sig; str => "Wrong tag data type")
+            }
+        }
+    }
+
+    fn read_xyz_tag(&self, sig: Signature) -> Result<XYZ> {
+        match self.read_tag(sig)?.downcast_ref::<XYZ>() {
+            Some(xyz) => Ok(*xyz),
+            None => {
+                err!(self.context_id, Error, BadSignature, "Tag '{}' doesn't hold an XYZ value", sig; str => "Wrong tag data type")
+            }
+        }
+    }
+}
+
+/// The directions a profile may be used in.
+pub mod used_as {
+    pub const INPUT: u32 = 0;
+    pub const OUTPUT: u32 = 1;
+    pub const PROOF: u32 = 2;
+}
+
+/// Switches every CLUT stage of `lut` to trilinear interpolation.
+fn change_interpolation_to_trilinear(lut: &mut Pipeline) -> Result<()> {
+    for stage in lut.stages_mut() {
+        if stage.get_type() != sig::mpe_stage::CLUT {
+            continue;
+        }
+
+        let ctx = stage.context_id().clone();
+        let data = stage.get_data_mut();
+
+        if let Some(clut) = data.downcast_mut::<StageCLutData<u16>>() {
+            let params = &mut clut.params;
+            params.flags |= lerp_flags::TRILINEAR;
+            params.interpolation =
+                (ctx.get_interp_factory())(params.n_inputs, params.n_outputs, params.flags)?;
+        } else if let Some(clut) = data.downcast_mut::<StageCLutData<f32>>() {
+            let params = &mut clut.params;
+            params.flags |= lerp_flags::TRILINEAR;
+            params.interpolation =
+                (ctx.get_interp_factory())(params.n_inputs, params.n_outputs, params.flags)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use std::{
+    fs::OpenOptions,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use once_cell::unsync::OnceCell;
+
+use crate::{
+    decode_date_time, encode_date_time,
+    io::{File, FileMem, IoHandler},
+    sig,
+    state::Context,
+    types::{DateTimeNumber, Signature, XYZ},
+    Result, D50,
+};
+
+/// Size of the bare ICC header. The tag count comes right after it, then the tag directory.
+const HEADER_SIZE: usize = 128;
+/// Size of a tag directory entry: signature, offset and size.
+const DIRECTORY_ENTRY_SIZE: usize = 12;
+
+pub struct Profile {
+    context_id: Context,
+    io_handler: Option<Arc<Mutex<dyn IoHandler>>>,
+
+    // Header
+    size: usize,
+    cmm: Signature,
+    version: u32,
+    device_class: Signature,
+    color_space: Signature,
+    pcs: Signature,
+    created: DateTimeNumber,
+    platform: Signature,
+    flags: u32,
+    manufacturer: u32,
+    model: u32,
+    attributes: u64,
+    rendering_intent: u32,
+    illuminant: XYZ,
+    creator: Signature,
+    profile_id: [u8; 16],
+
+    tags: Vec<TagEntry>,
+}
+
+impl Profile {
+    /// Creates an empty ICC v4.3 profile with no tags.
+    pub fn new(context_id: &Context) -> Self {
+        let mut result = Profile {
+            context_id: context_id.clone(),
+            io_handler: None,
+            size: 0,
+            cmm: sig::LCMS_SIGNATURE,
+            version: 0x02100000,
+            device_class: Signature::default(),
+            color_space: Signature::default(),
+            pcs: Signature::default(),
+            created: encode_date_time(Utc::now()),
+            platform: if cfg!(windows) {
+                sig::platform::MICROSOFT
+            } else {
+                sig::platform::MACINTOSH
+            },
+            flags: 0,
+            manufacturer: 0,
+            model: 0,
+            attributes: 0,
+            rendering_intent: 0,
+            illuminant: D50,
+            creator: sig::LCMS_SIGNATURE,
+            profile_id: [0u8; 16],
+            tags: Vec::new(),
+        };
+        result.set_version(4.3);
+
+        result
+    }
+
+    /// Opens a profile from any [`IoHandler`], reading and validating its header and tag
+    /// directory. Tags are loaded on demand.
+    pub fn open_from_io(context_id: &Context, io: Arc<Mutex<dyn IoHandler>>) -> Result<Self> {
+        let mut result = Profile::new(context_id);
+        result.io_handler = Some(io);
+
+        result.read_header()?;
+        result.read_tag_directory()?;
+
+        Ok(result)
+    }
+
+    pub fn open_from_mem(context_id: &Context, buffer: &[u8]) -> Result<Self> {
+        Self::open_from_io(context_id, FileMem::open_for_reading(context_id, buffer))
+    }
+
+    pub fn open_from_file(context_id: &Context, path: &Path) -> Result<Self> {
+        let io = File::open_for_reading(context_id, path, OpenOptions::new())
+            .map_err(|_| "Unable to open profile file")?;
+
+        Self::open_from_io(context_id, io)
+    }
+
+    pub fn context_id(&self) -> &Context {
+        &self.context_id
+    }
+
+    /// The size of the profile as reported by its header, clipped to the size of the underlying
+    /// stream.
+    pub fn get_header_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get_header_cmm(&self) -> Signature {
+        self.cmm
+    }
+
+    pub fn set_header_cmm(&mut self, cmm: Signature) {
+        self.cmm = cmm
+    }
+
+    pub fn get_device_class(&self) -> Signature {
+        self.device_class
+    }
+
+    pub fn set_device_class(&mut self, class: Signature) {
+        self.device_class = class
+    }
+
+    pub fn get_color_space(&self) -> Signature {
+        self.color_space
+    }
+
+    pub fn set_color_space(&mut self, color_space: Signature) {
+        self.color_space = color_space
+    }
+
+    pub fn get_pcs(&self) -> Signature {
+        self.pcs
+    }
+
+    pub fn set_pcs(&mut self, pcs: Signature) {
+        self.pcs = pcs
+    }
+
+    pub fn get_header_creation_date_time_number(&self) -> DateTimeNumber {
+        self.created
+    }
+
+    /// The creation date stored in the header, or `None` if the stored date is not a valid date.
+    pub fn get_header_creation_date_time(&self) -> Option<DateTime<Utc>> {
+        decode_date_time(self.created).single()
+    }
+
+    pub fn get_header_platform(&self) -> Signature {
+        self.platform
+    }
+
+    pub fn set_header_platform(&mut self, platform: Signature) {
+        self.platform = platform
+    }
+
+    pub fn get_header_flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn set_header_flags(&mut self, flags: u32) {
+        self.flags = flags
+    }
+
+    pub fn get_header_manufacturer(&self) -> u32 {
+        self.manufacturer
+    }
+
+    pub fn set_header_manufacturer(&mut self, manufacturer: u32) {
+        self.manufacturer = manufacturer
+    }
+
+    pub fn get_header_model(&self) -> u32 {
+        self.model
+    }
+
+    pub fn set_header_model(&mut self, model: u32) {
+        self.model = model
+    }
+
+    pub fn get_header_attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    pub fn set_header_attributes(&mut self, attributes: u64) {
+        self.attributes = attributes
+    }
+
+    pub fn get_header_rendering_intent(&self) -> u32 {
+        self.rendering_intent
+    }
+
+    pub fn set_header_rendering_intent(&mut self, intent: u32) {
+        self.rendering_intent = intent
+    }
+
+    pub fn get_header_illuminant(&self) -> &XYZ {
+        &self.illuminant
+    }
+
+    pub fn get_header_creator(&self) -> Signature {
+        self.creator
+    }
+
+    pub fn get_header_profile_id(&self) -> [u8; 16] {
+        self.profile_id
+    }
+
+    pub fn set_header_profile_id(&mut self, profile_id: [u8; 16]) {
+        self.profile_id = profile_id
+    }
+
+    pub fn get_encoded_icc_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn set_encoded_icc_version(&mut self, version: u32) {
+        self.version = version
+    }
+
+    /// The profile version as a decimal number, e.g. `4.3`.
+    pub fn get_version(&self) -> f64 {
+        base_to_base(self.version >> 16, 16, 10) as f64 / 100.0
+    }
+
+    pub fn set_version(&mut self, version: f64) {
+        // 4.2 -> 0x04200000
+        self.version = base_to_base((version * 100.0 + 0.5).floor() as u32, 10, 16) << 16;
+    }
+
+    /// Deep copies the profile. Tags written by the user are duplicated through their
+    /// [`TagTypeHandler`](crate::plugin::TagTypeHandler), tags that come from the source profile
+    /// are reloaded on demand.
+    pub fn duplicate(&self) -> Result<Self> {
+        let mut tags = Vec::with_capacity(self.tags.len());
+
+        for tag in self.tags.iter() {
+            let loaded = match tag.loaded.get() {
+                Some(loaded) if tag.modified => {
+                    let data = match (loaded.handler.dup)(
+                        &loaded.handler,
+                        loaded.data.as_ref(),
+                        loaded.n_items,
+                    ) {
+                        Ok(data) => data,
+                        Err(_) => {
+                            return err!(self.context_id, Error, Internal, "Unable to duplicate tag '{}'", tag.sig; str => "Unable to duplicate tag")
+                        }
+                    };
+
+                    OnceCell::with_value(LoadedTag {
+                        handler: loaded.handler.clone(),
+                        n_items: loaded.n_items,
+                        data,
+                    })
+                }
+                _ => OnceCell::new(),
+            };
+
+            tags.push(TagEntry {
+                pos: tag.pos,
+                linked: tag.linked,
+                raw: tag.raw.clone(),
+                modified: tag.modified,
+                loaded,
+                ..TagEntry::new(tag.sig)
+            });
+        }
+
+        Ok(Profile {
+            context_id: self.context_id.clone(),
+            io_handler: self.io_handler.clone(),
+            size: self.size,
+            cmm: self.cmm,
+            version: self.version,
+            device_class: self.device_class,
+            color_space: self.color_space,
+            pcs: self.pcs,
+            created: self.created,
+            platform: self.platform,
+            flags: self.flags,
+            manufacturer: self.manufacturer,
+            model: self.model,
+            attributes: self.attributes,
+            rendering_intent: self.rendering_intent,
+            illuminant: self.illuminant,
+            creator: self.creator,
+            profile_id: self.profile_id,
+            tags,
+        })
+    }
+}
+
+/// Reinterprets the digits of `n` written in base `base_in` as a number in base `base_out`.
+fn base_to_base(mut n: u32, base_in: u32, base_out: u32) -> u32 {
+    let mut buf = [0u32; 30];
+    let mut len = 0usize;
+
+    while n > 0 && len < buf.len() {
+        buf[len] = n % base_in;
+        n /= base_in;
+        len += 1;
+    }
+
+    buf[..len]
+        .iter()
+        .rev()
+        .fold(0u32, |out, digit| out * base_out + digit)
+}
+
+mod black_point;
+mod gamut;
+mod header;
+mod luts;
+mod tags;
+mod write;
+
+pub use luts::used_as;
+
+use tags::{LoadedTag, TagEntry};
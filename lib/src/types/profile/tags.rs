@@ -0,0 +1,358 @@
+use std::any::Any;
+
+use once_cell::unsync::OnceCell;
+
+use crate::{
+    io::IoHandler,
+    plugin::{TagDescriptor, TagTypeHandler},
+    types::{PositionNumber, Signature},
+    Result, MAX_TABLE_TAG,
+};
+
+use super::{Profile, DIRECTORY_ENTRY_SIZE, HEADER_SIZE};
+
+pub(super) struct TagEntry {
+    pub(super) sig: Signature,
+    pub(super) pos: PositionNumber,
+    pub(super) linked: Option<Signature>,
+    pub(super) raw: Option<Vec<u8>>,
+    pub(super) modified: bool,
+    pub(super) loaded: OnceCell<LoadedTag>,
+}
+
+impl TagEntry {
+    pub(super) fn new(sig: Signature) -> Self {
+        TagEntry {
+            sig,
+            pos: PositionNumber::default(),
+            linked: None,
+            raw: None,
+            modified: false,
+            loaded: OnceCell::new(),
+        }
+    }
+}
+
+pub(super) struct LoadedTag {
+    pub(super) handler: TagTypeHandler,
+    pub(super) n_items: usize,
+    pub(super) data: Box<dyn Any>,
+}
+
+impl Profile {
+    /// Reads the tag directory that follows the header. Tag contents are not read until they are
+    /// requested through [`Profile::read_tag`].
+    pub(super) fn read_tag_directory(&mut self) -> Result<()> {
+        let io = match self.io_handler {
+            Some(ref io) => io.clone(),
+            None => return err!(str => "Profile has no IoHandler to read from"),
+        };
+        let mut io = io.lock().unwrap();
+        let io: &mut dyn IoHandler = &mut *io;
+
+        if io.seek(HEADER_SIZE).is_err() {
+            return err!(self.context_id, Error, Read, "Unable to read ICC tag directory"; str => "Unable to read ICC tag directory");
+        }
+
+        let tag_count = match io.read_u32() {
+            Ok(count) => count as usize,
+            Err(_) => {
+                return err!(self.context_id, Error, Read, "Unable to read ICC tag directory"; str => "Unable to read ICC tag directory")
+            }
+        };
+
+        if tag_count > MAX_TABLE_TAG {
+            return err!(self.context_id, Error, Range, "Too many tags ({})", tag_count; str => "Too many tags");
+        }
+
+        let directory_end = HEADER_SIZE + 4 + tag_count * DIRECTORY_ENTRY_SIZE;
+        let mut tags: Vec<TagEntry> = Vec::with_capacity(tag_count);
+
+        for _ in 0..tag_count {
+            let (sig, offset, size) = match read_directory_entry(io) {
+                Ok(entry) => entry,
+                Err(_) => {
+                    return err!(self.context_id, Error, Read, "Unable to read ICC tag directory"; str => "Unable to read ICC tag directory")
+                }
+            };
+
+            // Offset + size should fall inside the profile, past the directory
+            let end = match offset.checked_add(size) {
+                Some(end) if offset >= directory_end && end <= self.size => end,
+                _ => {
+                    self.context_id.signal_error(
+                        log::Level::Warn,
+                        crate::state::ErrorCode::CorruptionDetected,
+                        &format!("Tag '{}' points outside of the profile, ignored", sig),
+                    );
+                    continue;
+                }
+            };
+
+            if tags.iter().any(|tag| tag.sig == sig) {
+                self.context_id.signal_error(
+                    log::Level::Warn,
+                    crate::state::ErrorCode::CorruptionDetected,
+                    &format!("Duplicated tag '{}', ignored", sig),
+                );
+                continue;
+            }
+
+            // Tags sharing the same block are links. Any other kind of overlap is corruption.
+            let mut linked = None;
+            let mut overlaps = false;
+            for tag in tags.iter() {
+                let tag_offset = tag.pos.get_offset();
+                let tag_end = tag_offset + tag.pos.get_size();
+
+                if tag_offset == offset && tag_end == end {
+                    if linked.is_none()
+                        && tag.linked.is_none()
+                        && compatible_types(
+                            self.context_id.get_tag_descriptor(tag.sig),
+                            self.context_id.get_tag_descriptor(sig),
+                        )
+                    {
+                        linked = Some(tag.sig);
+                    }
+                } else if offset < tag_end && tag_offset < end {
+                    overlaps = true;
+                    break;
+                }
+            }
+
+            if overlaps {
+                self.context_id.signal_error(
+                    log::Level::Warn,
+                    crate::state::ErrorCode::CorruptionDetected,
+                    &format!("Tag '{}' overlaps another tag, ignored", sig),
+                );
+                continue;
+            }
+
+            tags.push(TagEntry {
+                pos: PositionNumber::new(offset, size),
+                linked,
+                ..TagEntry::new(sig)
+            });
+        }
+
+        self.tags = tags;
+
+        Ok(())
+    }
+
+    pub fn get_tag_count(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn get_tag_signature(&self, n: usize) -> Option<Signature> {
+        self.tags.get(n).map(|tag| tag.sig)
+    }
+
+    pub fn is_tag(&self, sig: Signature) -> bool {
+        self.search_tag(sig, false).is_some()
+    }
+
+    /// Returns the signature of the tag `sig` is linked to, if any.
+    pub fn get_tag_linked_to(&self, sig: Signature) -> Option<Signature> {
+        self.tags[self.search_tag(sig, false)?].linked
+    }
+
+    /// Reads and deserializes a tag through the [`TagTypeHandler`] registered for its type. The
+    /// result is cached so subsequent reads, including those through linked tags, are free.
+    pub fn read_tag(&self, sig: Signature) -> Result<&dyn Any> {
+        let n = match self.search_tag(sig, true) {
+            Some(n) => n,
+            None => return err!(str => "Tag not found"),
+        };
+
+        let tag = &self.tags[n];
+
+        // If the tag was written as raw data, refuse to cook it
+        if tag.raw.is_some() {
+            return err!(self.context_id, Error, NotSuitable, "Tag '{}' was written as raw data", sig; str => "Tag was written as raw data");
+        }
+
+        let loaded = tag.loaded.get_or_try_init(|| self.load_tag(tag))?;
+
+        Ok(loaded.data.as_ref())
+    }
+
+    /// The type a tag was read as or will be written as, or `None` if the tag hasn't been
+    /// loaded yet.
+    pub(crate) fn get_tag_true_type(&self, sig: Signature) -> Option<Signature> {
+        let n = self.search_tag(sig, true)?;
+
+        self.tags[n].loaded.get().map(|loaded| loaded.handler.sig)
+    }
+
+    /// Reads the undecoded contents of a tag, including its type base.
+    pub fn read_raw_tag(&self, sig: Signature) -> Result<Vec<u8>> {
+        let n = match self.search_tag(sig, true) {
+            Some(n) => n,
+            None => return err!(str => "Tag not found"),
+        };
+
+        let tag = &self.tags[n];
+        match tag.raw {
+            Some(ref raw) => Ok(raw.clone()),
+            None if tag.modified => self.serialize_tag(tag),
+            None => self.read_tag_block(tag),
+        }
+    }
+
+    /// Adds a new tag to the directory, or resets the existing one with the same signature.
+    pub(super) fn new_tag(&mut self, sig: Signature) -> Result<&mut TagEntry> {
+        let n = match self.search_tag(sig, false) {
+            Some(n) => {
+                self.tags[n] = TagEntry::new(sig);
+                n
+            }
+            None => {
+                if self.tags.len() >= MAX_TABLE_TAG {
+                    return err!(self.context_id, Error, Range, "Too many tags ({})", MAX_TABLE_TAG; str => "Too many tags");
+                }
+                self.tags.push(TagEntry::new(sig));
+                self.tags.len() - 1
+            }
+        };
+
+        Ok(&mut self.tags[n])
+    }
+
+    /// Reads the block a tag occupies in the profile's [`IoHandler`].
+    pub(super) fn read_tag_block(&self, tag: &TagEntry) -> Result<Vec<u8>> {
+        let io = match self.io_handler {
+            Some(ref io) => io.clone(),
+            None => return err!(str => "Tag not found"),
+        };
+        let mut io = io.lock().unwrap();
+        let io: &mut dyn IoHandler = &mut *io;
+
+        let size = tag.pos.get_size();
+        let mut block = vec![0u8; size];
+
+        if io.seek(tag.pos.get_offset()).is_err() || io.read(&mut block, size, 1).is_err() {
+            return err!(self.context_id, Error, Read, "Unable to read tag '{}'", tag.sig; str => "Unable to read tag");
+        }
+
+        Ok(block)
+    }
+
+    pub(super) fn search_tag(&self, sig: Signature, follow_links: bool) -> Option<usize> {
+        let mut sig = sig;
+
+        // Links may only be followed as many times as there are tags, anything else is a loop
+        for _ in 0..=self.tags.len() {
+            let n = self.tags.iter().position(|tag| tag.sig == sig)?;
+
+            match self.tags[n].linked {
+                Some(linked) if follow_links => sig = linked,
+                _ => return Some(n),
+            }
+        }
+
+        None
+    }
+
+    fn load_tag(&self, tag: &TagEntry) -> Result<LoadedTag> {
+        let io = match self.io_handler {
+            Some(ref io) => io.clone(),
+            None => return err!(str => "Tag not found"),
+        };
+        let mut io = io.lock().unwrap();
+        let io: &mut dyn IoHandler = &mut *io;
+
+        let offset = tag.pos.get_offset();
+        let size = tag.pos.get_size();
+
+        if io.seek(offset).is_err() {
+            return err!(self.context_id, Error, Read, "Unable to seek to tag '{}'", tag.sig; str => "Unable to read tag");
+        }
+
+        let descriptor = match self.context_id.get_tag_descriptor(tag.sig) {
+            Some(descriptor) => descriptor,
+            None => {
+                return err!(self.context_id, Error, UnknownExtension, "Unknown tag type '{}' found.", tag.sig; str => "Unknown tag")
+            }
+        };
+
+        let base_type = match io.read_type_base() {
+            Ok(base_type) => base_type,
+            Err(_) => {
+                return err!(self.context_id, Error, Read, "Unable to read type of tag '{}'", tag.sig; str => "Unable to read tag")
+            }
+        };
+
+        if !descriptor.supported_types.contains(&base_type) {
+            return err!(self.context_id, Error, BadSignature, "Unsupported type '{}' for tag '{}'", base_type, tag.sig; str => "Unsupported tag type");
+        }
+
+        let handler = match self.context_id.get_tag_type_handler(base_type) {
+            Some(handler) => handler,
+            None => {
+                return err!(self.context_id, Error, UnknownExtension, "Unknown tag type handler '{}'", base_type; str => "Unknown tag type")
+            }
+        };
+
+        // Already read by the type base logic
+        let tag_size = match size.checked_sub(8) {
+            Some(size) => size,
+            None => {
+                return err!(self.context_id, Error, CorruptionDetected, "Tag '{}' is too small", tag.sig; str => "Corrupted tag")
+            }
+        };
+
+        let mut n_items = 0usize;
+        let data = match (handler.read)(&handler, io, &mut n_items, tag_size) {
+            Ok(data) => data,
+            Err(_) => {
+                return err!(self.context_id, Error, CorruptionDetected, "Corrupted tag '{}'", tag.sig; str => "Corrupted tag")
+            }
+        };
+
+        // This is a weird error that may be a symptom of something more serious, the number of
+        // stored items is actually less than the number of required elements.
+        if n_items < descriptor.elem_count {
+            return err!(
+                self.context_id,
+                Error,
+                CorruptionDetected,
+                "'{}' Inconsistent number of items: expected {}, got {}",
+                tag.sig,
+                descriptor.elem_count,
+                n_items;
+                str =>
+                "Corrupted tag"
+            );
+        }
+
+        Ok(LoadedTag {
+            handler,
+            n_items,
+            data,
+        })
+    }
+}
+
+fn read_directory_entry(io: &mut dyn IoHandler) -> std::io::Result<(Signature, usize, usize)> {
+    let sig = io.read_signature()?;
+    let offset = io.read_u32()? as usize;
+    let size = io.read_u32()? as usize;
+
+    Ok((sig, offset, size))
+}
+
+/// Two tags may only be linked if they can hold the same types.
+fn compatible_types(
+    desc1: Option<&'static TagDescriptor>,
+    desc2: Option<&'static TagDescriptor>,
+) -> bool {
+    match (desc1, desc2) {
+        (Some(desc1), Some(desc2)) => {
+            desc1.elem_count == desc2.elem_count && desc1.supported_types == desc2.supported_types
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    any::Any,
+    fs::OpenOptions,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use once_cell::unsync::OnceCell;
+
+use crate::{
+    io::{File, FileMem, FileNull, IoHandler},
+    types::{PositionNumber, Signature},
+    Result,
+};
+
+use super::{
+    tags::{LoadedTag, TagEntry},
+    Profile, DIRECTORY_ENTRY_SIZE, HEADER_SIZE,
+};
+
+impl Profile {
+    /// Stores `data` as the contents of tag `sig`. The tag type is chosen by the tag's
+    /// [`TagDescriptor`](crate::plugin::TagDescriptor) and serialized by its
+    /// [`TagTypeHandler`](crate::plugin::TagTypeHandler) when the profile is saved.
+    pub fn write_tag(&mut self, sig: Signature, data: Box<dyn Any>) -> Result<()> {
+        let descriptor = match self.context_id.get_tag_descriptor(sig) {
+            Some(descriptor) => descriptor,
+            None => {
+                return err!(self.context_id, Error, UnknownExtension, "Unknown tag type '{}'", sig; str => "Unknown tag")
+            }
+        };
+
+        let r#type = match descriptor.decide_type {
+            Some(decide_type) => decide_type(self.get_version(), &data),
+            None => match descriptor.supported_types.first() {
+                Some(r#type) => *r#type,
+                None => return err!(str => "Tag descriptor has no supported types"),
+            },
+        };
+
+        if !descriptor.supported_types.contains(&r#type) {
+            return err!(self.context_id, Error, BadSignature, "Wrong type '{}' for tag '{}'", r#type, sig; str => "Unsupported tag type");
+        }
+
+        let handler = match self.context_id.get_tag_type_handler(r#type) {
+            Some(handler) => handler,
+            None => {
+                return err!(self.context_id, Error, UnknownExtension, "Unknown tag type handler '{}'", r#type; str => "Unknown tag type")
+            }
+        };
+
+        let tag = self.new_tag(sig)?;
+        tag.modified = true;
+        tag.loaded = OnceCell::with_value(LoadedTag {
+            handler,
+            n_items: descriptor.elem_count,
+            data,
+        });
+
+        Ok(())
+    }
+
+    /// Stores `data` as the complete contents of tag `sig`, type base included. The data is saved
+    /// verbatim and is not available through [`Profile::read_tag`].
+    pub fn write_raw_tag(&mut self, sig: Signature, data: &[u8]) -> Result<()> {
+        let tag = self.new_tag(sig)?;
+        tag.raw = Some(data.to_vec());
+
+        Ok(())
+    }
+
+    /// Makes `sig` share the contents of `dest`. Linked tags are only stored once.
+    pub fn link_tag(&mut self, sig: Signature, dest: Signature) -> Result<()> {
+        let tag = self.new_tag(sig)?;
+        tag.linked = Some(dest);
+
+        Ok(())
+    }
+
+    /// Serializes the profile to `io`, returning the number of bytes the profile takes.
+    ///
+    /// Saving to a [`FileNull`] performs a dry run that only computes the size.
+    pub fn save_to_io(&self, io: &Arc<Mutex<dyn IoHandler>>) -> Result<usize> {
+        if let Some(ref source) = self.io_handler {
+            if Arc::ptr_eq(source, io) {
+                return err!(self.context_id, Error, Write, "Cannot save a profile over its own source"; str => "Cannot save a profile over its own source");
+            }
+        }
+
+        let mut io = io.lock().unwrap();
+        let io: &mut dyn IoHandler = &mut *io;
+
+        match self.write_profile(io) {
+            Ok(size) => Ok(size),
+            Err(error) => {
+                err!(self.context_id, Error, Write, "Unable to save profile: {}", error; str => "Unable to save profile")
+            }
+        }
+    }
+
+    pub fn save_to_mem(&self) -> Result<Vec<u8>> {
+        let size = self.save_to_io(&FileNull::open(&self.context_id))?;
+
+        let block: Arc<Mutex<Box<[u8]>>> = Arc::new(Mutex::new(vec![0u8; size].into_boxed_slice()));
+        self.save_to_io(&FileMem::open_for_writing(&self.context_id, &block))?;
+
+        let result = block.lock().unwrap().to_vec();
+        Ok(result)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<usize> {
+        let mut access = OpenOptions::new();
+        access.create(true).truncate(true);
+
+        let io = match File::open_for_writing(&self.context_id, path, access) {
+            Ok(io) => io,
+            Err(_) => return err!(str => "Unable to open file for writing"),
+        };
+
+        self.save_to_io(&io)
+    }
+
+    fn write_profile(&self, io: &mut dyn IoHandler) -> Result<usize> {
+        let map_err = |_| "IO error";
+
+        // Offsets in the profile are relative to its start
+        let base = io.tell().map_err(map_err)?;
+        let directory_size = 4 + self.tags.len() * DIRECTORY_ENTRY_SIZE;
+
+        // Reserve the header and directory, they are filled once every offset is known
+        io.write(
+            HEADER_SIZE + directory_size,
+            &vec![0u8; HEADER_SIZE + directory_size],
+        )
+        .map_err(map_err)?;
+
+        let mut positions: Vec<Option<PositionNumber>> = vec![None; self.tags.len()];
+
+        for (i, tag) in self.tags.iter().enumerate() {
+            if tag.linked.is_some() {
+                continue;
+            }
+
+            let begin = io.tell().map_err(map_err)?;
+
+            match tag.raw {
+                Some(ref raw) => io.write(raw.len(), raw).map_err(map_err)?,
+                None if tag.modified => self.write_tag_data(tag, io)?,
+                None => {
+                    // Tags that were not modified are copied verbatim from the source profile
+                    let block = self.read_tag_block(tag)?;
+                    io.write(block.len(), &block).map_err(map_err)?;
+                }
+            }
+
+            let end = io.tell().map_err(map_err)?;
+            positions[i] = Some(PositionNumber::new(begin - base, end - begin));
+
+            // Align to a 32 bit boundary
+            io.write_alignment().map_err(map_err)?;
+        }
+
+        // Linked tags point to the block of the tag they are linked to
+        for (i, tag) in self.tags.iter().enumerate() {
+            if tag.linked.is_none() {
+                continue;
+            }
+
+            positions[i] = match self.search_tag(tag.sig, true) {
+                Some(n) => positions[n],
+                None => None,
+            };
+
+            if positions[i].is_none() {
+                return err!(self.context_id, Error, Internal, "Tag '{}' is linked to a missing tag", tag.sig; str => "Broken tag link");
+            }
+        }
+
+        let size = io.tell().map_err(map_err)? - base;
+
+        io.seek(base).map_err(map_err)?;
+        self.write_header(io, size).map_err(map_err)?;
+
+        io.write_u32(self.tags.len() as u32).map_err(map_err)?;
+        for (tag, pos) in self.tags.iter().zip(positions) {
+            let pos = pos.unwrap_or_default();
+
+            io.write_signature(tag.sig).map_err(map_err)?;
+            io.write_u32(pos.get_offset() as u32).map_err(map_err)?;
+            io.write_u32(pos.get_size() as u32).map_err(map_err)?;
+        }
+
+        io.seek(base + size).map_err(map_err)?;
+
+        Ok(size)
+    }
+
+    /// Writes the type base and contents of a cooked tag through its handler.
+    fn write_tag_data(&self, tag: &TagEntry, io: &mut dyn IoHandler) -> Result<()> {
+        let loaded = match tag.loaded.get() {
+            Some(loaded) => loaded,
+            None => return err!(str => "Tag has no data"),
+        };
+
+        if io.write_type_base(loaded.handler.sig).is_err() {
+            return err!(self.context_id, Error, Write, "Unable to write type of tag '{}'", tag.sig; str => "Unable to write tag");
+        }
+
+        match (loaded.handler.write)(&loaded.handler, io, loaded.data.as_ref(), loaded.n_items) {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                err!(self.context_id, Error, Write, "Couldn't write type '{}'", loaded.handler.sig; str => "Unable to write tag")
+            }
+        }
+    }
+
+    /// Serializes a cooked tag into memory, type base included.
+    pub(super) fn serialize_tag(&self, tag: &TagEntry) -> Result<Vec<u8>> {
+        let null = FileNull::open(&self.context_id);
+        self.write_tag_data(tag, &mut *null.lock().unwrap())?;
+        let size = null.lock().unwrap().used_space();
+
+        let block: Arc<Mutex<Box<[u8]>>> = Arc::new(Mutex::new(vec![0u8; size].into_boxed_slice()));
+        let mem = FileMem::open_for_writing(&self.context_id, &block);
+        self.write_tag_data(tag, &mut *mem.lock().unwrap())?;
+
+        let result = block.lock().unwrap().to_vec();
+        Ok(result)
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{
+    plugin::lerp_flags,
+    quantize_val, quick_saturate_word, sig,
+    state::Context,
+    types::{InterpFunction, InterpParams, Stage},
+    Result, MAX_INPUT_DIMENSIONS, MAX_STAGE_CHANNELS,
+};
+
+use super::dup_data;
+
+/// The data of a [`sig::mpe_stage::CLUT`] stage. `tab` holds the output channels of every grid
+/// node, the last input channel varying fastest.
+#[derive(Clone)]
+pub struct StageCLutData<T: Copy + 'static> {
+    pub tab: Vec<T>,
+    pub params: InterpParams<'static, T>,
+}
+
+impl<T: Copy + 'static> StageCLutData<T> {
+    /// The interpolation parameters, bound to the table.
+    pub fn interp_params(&self) -> InterpParams<'_, T> {
+        InterpParams {
+            table: &self.tab,
+            ..self.params.clone()
+        }
+    }
+}
+
+impl Stage {
+    /// Creates a 16 bit CLUT stage with `clut_points[i]` grid points for input channel `i`.
+    /// Without a table, every node is zero.
+    pub fn alloc_clut_16bit_granular(
+        context_id: &Context,
+        clut_points: &[usize],
+        in_chans: usize,
+        out_chans: usize,
+        table: Option<&[u16]>,
+    ) -> Result<Self> {
+        let tab = alloc_table(context_id, clut_points, in_chans, out_chans, table)?;
+        let params = InterpParams::compute_ex(
+            context_id,
+            clut_points,
+            in_chans,
+            out_chans,
+            &[],
+            lerp_flags::BITS_16,
+        )?;
+
+        Self::new(
+            context_id,
+            sig::mpe_stage::CLUT,
+            in_chans,
+            out_chans,
+            evaluate_clut_f32_in_16,
+            dup_data::<StageCLutData<u16>>,
+            Box::new(StageCLutData { tab, params }),
+        )
+    }
+
+    /// Creates a 16 bit CLUT stage with the same number of grid points for every input channel.
+    pub fn alloc_clut_16bit(
+        context_id: &Context,
+        n_grid_points: usize,
+        in_chans: usize,
+        out_chans: usize,
+        table: Option<&[u16]>,
+    ) -> Result<Self> {
+        Self::alloc_clut_16bit_granular(
+            context_id,
+            &[n_grid_points; MAX_INPUT_DIMENSIONS],
+            in_chans,
+            out_chans,
+            table,
+        )
+    }
+
+    /// Creates a float CLUT stage with `clut_points[i]` grid points for input channel `i`.
+    /// Without a table, every node is zero.
+    pub fn alloc_clut_f32_granular(
+        context_id: &Context,
+        clut_points: &[usize],
+        in_chans: usize,
+        out_chans: usize,
+        table: Option<&[f32]>,
+    ) -> Result<Self> {
+        let tab = alloc_table(context_id, clut_points, in_chans, out_chans, table)?;
+        let params = InterpParams::compute_ex(
+            context_id,
+            clut_points,
+            in_chans,
+            out_chans,
+            &[],
+            lerp_flags::FLOAT,
+        )?;
+
+        Self::new(
+            context_id,
+            sig::mpe_stage::CLUT,
+            in_chans,
+            out_chans,
+            evaluate_clut_f32,
+            dup_data::<StageCLutData<f32>>,
+            Box::new(StageCLutData { tab, params }),
+        )
+    }
+
+    /// Creates a float CLUT stage with the same number of grid points for every input channel.
+    pub fn alloc_clut_f32(
+        context_id: &Context,
+        n_grid_points: usize,
+        in_chans: usize,
+        out_chans: usize,
+        table: Option<&[f32]>,
+    ) -> Result<Self> {
+        Self::alloc_clut_f32_granular(
+            context_id,
+            &[n_grid_points; MAX_INPUT_DIMENSIONS],
+            in_chans,
+            out_chans,
+            table,
+        )
+    }
+
+    /// Calls `sampler` on every node of a 16 bit CLUT stage, with the node's input and its
+    /// current output. The output `sampler` leaves is stored back in the table, unless
+    /// [`sampler_flags::INSPECT`] is set. Sampling stops as soon as `sampler` returns `false`.
+    pub fn sample_clut_16bit(
+        &mut self,
+        mut sampler: impl FnMut(&[u16], &mut [u16]) -> bool,
+        flags: u32,
+    ) -> Result<()> {
+        let context_id = self.context_id().clone();
+        let clut = match self.get_data_mut().downcast_mut::<StageCLutData<u16>>() {
+            Some(clut) => clut,
+            None => {
+                return err!(context_id, Error, NotSuitable, "Stage is not a 16 bits CLUT"; str => "Stage is not a 16 bits CLUT")
+            }
+        };
+
+        let n_inputs = clut.params.n_inputs;
+        let n_outputs = clut.params.n_outputs;
+        let n_samples = clut.params.n_samples;
+
+        if n_inputs == 0 || n_inputs > MAX_INPUT_DIMENSIONS {
+            return err!(context_id, Error, Range, "Too many input channels ({} channels, max={})", n_inputs, MAX_INPUT_DIMENSIONS; str => "Too many input channels");
+        }
+        if n_outputs == 0 || n_outputs >= MAX_STAGE_CHANNELS {
+            return err!(context_id, Error, Range, "Too many output channels ({} channels, max={})", n_outputs, MAX_STAGE_CHANNELS; str => "Too many output channels");
+        }
+
+        let n_total_points = match cube_size(&n_samples, n_inputs) {
+            Some(size) => size,
+            None => {
+                return err!(context_id, Error, Range, "Invalid CLUT size"; str => "Invalid CLUT size")
+            }
+        };
+
+        let mut r#in = [0u16; MAX_INPUT_DIMENSIONS];
+        let mut out = [0u16; MAX_STAGE_CHANNELS];
+
+        for i in 0..n_total_points {
+            let mut rest = i;
+            for t in (0..n_inputs).rev() {
+                let colorant = rest % n_samples[t];
+                rest /= n_samples[t];
+
+                r#in[t] = quantize_val(colorant as f64, n_samples[t]);
+            }
+
+            let index = i * n_outputs;
+            out[..n_outputs].copy_from_slice(&clut.tab[index..index + n_outputs]);
+
+            if !sampler(&r#in[..n_inputs], &mut out[..n_outputs]) {
+                return err!(context_id, Error, Internal, "CLUT sampler failed at node {}", i; str => "CLUT sampler failed");
+            }
+
+            if flags & sampler_flags::INSPECT == 0 {
+                clut.tab[index..index + n_outputs].copy_from_slice(&out[..n_outputs]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub mod sampler_flags {
+    /// Only inspect the CLUT, leaving the table untouched.
+    pub const INSPECT: u32 = 0x01000000;
+}
+
+/// Number of nodes in a grid of `dims[..b]`, or `None` on overflow or degenerated grids.
+pub(crate) fn cube_size(dims: &[usize], b: usize) -> Option<usize> {
+    let mut rv = 1usize;
+
+    for dim in dims[..b].iter().rev() {
+        if *dim <= 1 {
+            return None;
+        }
+
+        rv = rv.checked_mul(*dim)?;
+    }
+
+    // Again, prevent overflow
+    if rv > u32::MAX as usize / 15 {
+        return None;
+    }
+
+    Some(rv)
+}
+
+fn alloc_table<T: Copy + Default>(
+    context_id: &Context,
+    clut_points: &[usize],
+    in_chans: usize,
+    out_chans: usize,
+    table: Option<&[T]>,
+) -> Result<Vec<T>> {
+    if in_chans > MAX_INPUT_DIMENSIONS {
+        return err!(context_id, Error, Range, "Too many input channels ({} channels, max={})", in_chans, MAX_INPUT_DIMENSIONS; str => "Too many input channels");
+    }
+    if clut_points.len() < in_chans {
+        return err!(context_id, Error, Range, "Missing grid points for {} input channels", in_chans; str => "Missing grid points");
+    }
+
+    let n = match cube_size(clut_points, in_chans) {
+        Some(size) => size * out_chans,
+        None => {
+            return err!(context_id, Error, Range, "Invalid CLUT size"; str => "Invalid CLUT size")
+        }
+    };
+    if n == 0 {
+        return err!(context_id, Error, Range, "Invalid CLUT size"; str => "Invalid CLUT size");
+    }
+
+    match table {
+        Some(table) if table.len() < n => {
+            err!(context_id, Error, Range, "CLUT needs {} values, but got {}", n, table.len(); str => "Not enough values for CLUT")
+        }
+        Some(table) => Ok(table[..n].to_vec()),
+        None => Ok(vec![T::default(); n]),
+    }
+}
+
+fn evaluate_clut_f32_in_16(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    let data = match stage.get_data().downcast_ref::<StageCLutData<u16>>() {
+        Some(data) => data,
+        None => return,
+    };
+
+    let mut in16 = [0u16; MAX_INPUT_DIMENSIONS];
+    let mut out16 = [0u16; MAX_STAGE_CHANNELS];
+
+    for (i, value) in in16.iter_mut().enumerate().take(stage.get_input_channels()) {
+        *value = quick_saturate_word(r#in[i] as f64 * 65535.0);
+    }
+
+    if let InterpFunction::U16(lerp) = data.params.interpolation {
+        lerp(&in16, &mut out16, &data.interp_params());
+    }
+
+    for (i, value) in out.iter_mut().enumerate().take(stage.get_output_channels()) {
+        *value = out16[i] as f32 / 65535.0;
+    }
+}
+
+fn evaluate_clut_f32(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    let data = match stage.get_data().downcast_ref::<StageCLutData<f32>>() {
+        Some(data) => data,
+        None => return,
+    };
+
+    if let InterpFunction::F32(lerp) = data.params.interpolation {
+        lerp(r#in, out, &data.interp_params());
+    }
+}
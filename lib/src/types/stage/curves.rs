@@ -0,0 +1,62 @@
+use crate::{
+    sig,
+    state::Context,
+    types::{Stage, ToneCurve},
+    Result,
+};
+
+use super::dup_data;
+
+/// The data of a [`sig::mpe_stage::CURVE_SET`] stage, one curve per channel.
+#[derive(Clone)]
+pub struct StageToneCurvesData {
+    pub curves: Vec<ToneCurve>,
+}
+
+impl Stage {
+    /// Creates a stage applying one curve per channel. Without curves, every channel gets an
+    /// identity curve.
+    pub fn alloc_tone_curves(
+        context_id: &Context,
+        n_chans: usize,
+        curves: Option<&[ToneCurve]>,
+    ) -> Result<Self> {
+        let curves = match curves {
+            Some(curves) => {
+                if curves.len() < n_chans {
+                    return err!(context_id, Error, Range, "Curve set needs {} curves, but got {}", n_chans, curves.len(); str => "Not enough curves for curve set");
+                }
+
+                curves[..n_chans].to_vec()
+            }
+            None => (0..n_chans)
+                .map(|_| ToneCurve::build_gamma(context_id, 1.0))
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        Self::new(
+            context_id,
+            sig::mpe_stage::CURVE_SET,
+            n_chans,
+            n_chans,
+            evaluate_curves,
+            dup_data::<StageToneCurvesData>,
+            Box::new(StageToneCurvesData { curves }),
+        )
+    }
+
+    /// The curves of a curve set stage.
+    pub fn get_curves(&self) -> Option<&[ToneCurve]> {
+        self.get_data()
+            .downcast_ref::<StageToneCurvesData>()
+            .map(|data| data.curves.as_slice())
+    }
+}
+
+fn evaluate_curves(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    if let Some(curves) = stage.get_curves() {
+        for (i, curve) in curves.iter().enumerate() {
+            out[i] = curve.eval_f32(r#in[i]);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{sig, state::Context, types::Stage, Result};
+
+use super::dup_data;
+
+/// The data of a [`sig::mpe_stage::MATRIX`] stage. `double` holds one row per output channel,
+/// `offset` one value per output channel.
+#[derive(Clone)]
+pub struct StageMatrixData {
+    pub double: Vec<f64>,
+    pub offset: Option<Vec<f64>>,
+}
+
+impl Stage {
+    /// Creates a stage multiplying its input by a `rows` x `cols` matrix, then adding `offset`.
+    /// The stage takes `cols` channels and outputs `rows` channels.
+    pub fn alloc_matrix(
+        context_id: &Context,
+        rows: usize,
+        cols: usize,
+        matrix: &[f64],
+        offset: Option<&[f64]>,
+    ) -> Result<Self> {
+        let n = rows * cols;
+
+        // Check for overflow
+        if n == 0 || n < rows || n < cols {
+            return err!(context_id, Error, Range, "Invalid matrix size {}x{}", rows, cols; str => "Invalid matrix size");
+        }
+        if matrix.len() < n {
+            return err!(context_id, Error, Range, "Matrix needs {} values, but got {}", n, matrix.len(); str => "Not enough values for matrix");
+        }
+        if offset.is_some_and(|offset| offset.len() < rows) {
+            return err!(context_id, Error, Range, "Matrix offset needs {} values", rows; str => "Not enough values for matrix offset");
+        }
+
+        Self::new(
+            context_id,
+            sig::mpe_stage::MATRIX,
+            cols,
+            rows,
+            evaluate_matrix,
+            dup_data::<StageMatrixData>,
+            Box::new(StageMatrixData {
+                double: matrix[..n].to_vec(),
+                offset: offset.map(|offset| offset[..rows].to_vec()),
+            }),
+        )
+    }
+}
+
+fn evaluate_matrix(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    let data = match stage.get_data().downcast_ref::<StageMatrixData>() {
+        Some(data) => data,
+        None => return,
+    };
+    let in_chans = stage.get_input_channels();
+
+    // Input is already in 0..1.0 notation
+    for (i, out) in out.iter_mut().enumerate().take(stage.get_output_channels()) {
+        let row = &data.double[i * in_chans..(i + 1) * in_chans];
+        let mut tmp = r#in
+            .iter()
+            .zip(row)
+            .map(|(x, m)| *x as f64 * m)
+            .sum::<f64>();
+
+        if let Some(offset) = &data.offset {
+            tmp += offset[i];
+        }
+
+        *out = tmp as f32;
+    }
+}
@@ -1,12 +1,14 @@
 use std::any::Any;
 
-use crate::state::Context;
+use crate::{sig, state::Context, Result, MAX_STAGE_CHANNELS};
 
 use super::Signature;
 
 pub type StageEvalFn = fn(r#in: &[f32], out: &mut [f32], stage: &Stage);
 pub type StageDupFn = fn(stage: &Stage) -> Stage;
 
+/// One processing element of a [`Pipeline`](super::Pipeline). Stages always evaluate in
+/// floating point, with values normalized to `[0, 1]`.
 pub struct Stage {
     context_id: Context,
     r#type: Signature,
@@ -15,5 +17,130 @@ pub struct Stage {
     out_chans: usize,
     eval: StageEvalFn,
     dup: StageDupFn,
-    data: Box<dyn Any>
+    data: Box<dyn Any>,
 }
+
+impl Stage {
+    /// Creates a stage of any kind. Used by plugins to build their own stage types, `dup` must
+    /// deep copy `data`.
+    pub fn new(
+        context_id: &Context,
+        r#type: Signature,
+        in_chans: usize,
+        out_chans: usize,
+        eval: StageEvalFn,
+        dup: StageDupFn,
+        data: Box<dyn Any>,
+    ) -> Result<Self> {
+        if in_chans > MAX_STAGE_CHANNELS || out_chans > MAX_STAGE_CHANNELS {
+            return err!(context_id, Error, Range, "Too many channels in stage ({} in, {} out, max={})", in_chans, out_chans, MAX_STAGE_CHANNELS; str => "Too many channels in stage");
+        }
+
+        Ok(Self {
+            context_id: context_id.clone(),
+            r#type,
+            implements: r#type,
+            in_chans,
+            out_chans,
+            eval,
+            dup,
+            data,
+        })
+    }
+
+    /// Creates a stage copying its input to its output.
+    pub fn alloc_identity(context_id: &Context, n_chans: usize) -> Result<Self> {
+        Self::new(
+            context_id,
+            sig::mpe_stage::IDENTITY,
+            n_chans,
+            n_chans,
+            evaluate_identity,
+            dup_data::<()>,
+            Box::new(()),
+        )
+    }
+
+    /// A copy of this stage holding `data` instead. Meant for [`StageDupFn`]s.
+    pub fn clone_with_data(&self, data: Box<dyn Any>) -> Self {
+        Self {
+            context_id: self.context_id.clone(),
+            r#type: self.r#type,
+            implements: self.implements,
+            in_chans: self.in_chans,
+            out_chans: self.out_chans,
+            eval: self.eval,
+            dup: self.dup,
+            data,
+        }
+    }
+
+    /// Deep copies the stage.
+    pub fn duplicate(&self) -> Self {
+        (self.dup)(self)
+    }
+
+    pub fn eval(&self, r#in: &[f32], out: &mut [f32]) {
+        (self.eval)(r#in, out, self)
+    }
+
+    pub fn context_id(&self) -> &Context {
+        &self.context_id
+    }
+
+    pub fn get_type(&self) -> Signature {
+        self.r#type
+    }
+
+    /// The kind of stage this stage behaves as, which may differ from its type when an
+    /// optimization replaces a stage with a faster version of it.
+    pub fn get_implements(&self) -> Signature {
+        self.implements
+    }
+
+    pub fn set_implements(&mut self, implements: Signature) {
+        self.implements = implements
+    }
+
+    pub fn get_input_channels(&self) -> usize {
+        self.in_chans
+    }
+
+    pub fn get_output_channels(&self) -> usize {
+        self.out_chans
+    }
+
+    pub fn get_data(&self) -> &dyn Any {
+        self.data.as_ref()
+    }
+
+    pub fn get_data_mut(&mut self) -> &mut dyn Any {
+        self.data.as_mut()
+    }
+}
+
+/// Duplicates a stage whose data is a `T`.
+pub(crate) fn dup_data<T: Clone + 'static>(stage: &Stage) -> Stage {
+    let data = stage
+        .get_data()
+        .downcast_ref::<T>()
+        .expect("Stage data of an unexpected type")
+        .clone();
+
+    stage.clone_with_data(Box::new(data))
+}
+
+fn evaluate_identity(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    out[..stage.in_chans].copy_from_slice(&r#in[..stage.in_chans]);
+}
+
+mod clut;
+mod curves;
+mod matrix;
+mod pcs;
+mod uniform;
+
+pub(crate) use clut::cube_size;
+pub use clut::{sampler_flags, StageCLutData};
+pub use curves::StageToneCurvesData;
+pub use matrix::StageMatrixData;
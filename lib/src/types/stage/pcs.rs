@@ -0,0 +1,150 @@
+use crate::{
+    sig,
+    state::Context,
+    types::{Lab, Signature, Stage, XYZ},
+    Result, D50, MAX_ENCODEABLE_XYZ,
+};
+
+use super::dup_data;
+
+impl Stage {
+    /// Creates a stage converting Lab to XYZ, both in their 0..1 pipeline encoding. XYZ is
+    /// relative to D50.
+    pub fn alloc_lab_to_xyz(context_id: &Context) -> Result<Self> {
+        Self::new(
+            context_id,
+            sig::mpe_stage::LAB_2_XYZ,
+            3,
+            3,
+            evaluate_lab_to_xyz,
+            dup_data::<()>,
+            Box::new(()),
+        )
+    }
+
+    /// Creates a stage converting XYZ to Lab, both in their 0..1 pipeline encoding. XYZ is
+    /// relative to D50.
+    pub fn alloc_xyz_to_lab(context_id: &Context) -> Result<Self> {
+        Self::new(
+            context_id,
+            sig::mpe_stage::XYZ_2_LAB,
+            3,
+            3,
+            evaluate_xyz_to_lab,
+            dup_data::<()>,
+            Box::new(()),
+        )
+    }
+
+    /// Creates a stage converting 16 bits ICC v2 Lab to v4 Lab. Both encode L* = 100 as 0xFF00
+    /// and 0xFFFF respectively, so this is only a matter of scaling.
+    pub fn alloc_lab_v2_to_v4(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::LAB_V2_TO_V4,
+            &[65535.0 / 65280.0; 3],
+            None,
+        )
+    }
+
+    /// Creates a stage converting 16 bits ICC v4 Lab to v2 Lab.
+    pub fn alloc_lab_v4_to_v2(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::LAB_V4_TO_V2,
+            &[65280.0 / 65535.0; 3],
+            None,
+        )
+    }
+
+    /// Creates a stage converting floating point Lab, L* in 0..100 and a*, b* in -128..127, to
+    /// the 0..1 pipeline encoding.
+    pub fn alloc_normalize_from_lab_float(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::LAB_2_FLOAT_PCS,
+            &[1.0 / 100.0, 1.0 / 255.0, 1.0 / 255.0],
+            Some(&[0.0, 128.0 / 255.0, 128.0 / 255.0]),
+        )
+    }
+
+    /// Creates a stage converting floating point XYZ to the 0..1 pipeline encoding.
+    pub fn alloc_normalize_from_xyz_float(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::XYZ_2_FLOAT_PCS,
+            &[32768.0 / 65535.0; 3],
+            None,
+        )
+    }
+
+    /// Creates a stage converting Lab in the 0..1 pipeline encoding to floating point Lab.
+    pub fn alloc_normalize_to_lab_float(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::FLOAT_PCS_2_LAB,
+            &[100.0, 255.0, 255.0],
+            Some(&[0.0, -128.0, -128.0]),
+        )
+    }
+
+    /// Creates a stage converting XYZ in the 0..1 pipeline encoding to floating point XYZ.
+    pub fn alloc_normalize_to_xyz_float(context_id: &Context) -> Result<Self> {
+        alloc_scaling(
+            context_id,
+            sig::mpe_stage::FLOAT_PCS_2_XYZ,
+            &[65535.0 / 32768.0; 3],
+            None,
+        )
+    }
+}
+
+/// A diagonal matrix stage scaling each of three channels, implementing `implements`.
+fn alloc_scaling(
+    context_id: &Context,
+    implements: Signature,
+    scale: &[f64; 3],
+    offset: Option<&[f64]>,
+) -> Result<Stage> {
+    #[rustfmt::skip]
+    let matrix = [
+        scale[0], 0.0, 0.0,
+        0.0, scale[1], 0.0,
+        0.0, 0.0, scale[2],
+    ];
+
+    let mut stage = Stage::alloc_matrix(context_id, 3, 3, &matrix, offset)?;
+    stage.set_implements(implements);
+
+    Ok(stage)
+}
+
+fn evaluate_lab_to_xyz(r#in: &[f32], out: &mut [f32], _stage: &Stage) {
+    // V4 rules
+    let lab = Lab {
+        l: r#in[0] as f64 * 100.0,
+        a: r#in[1] as f64 * 255.0 - 128.0,
+        b: r#in[2] as f64 * 255.0 - 128.0,
+    };
+
+    let xyz = lab.to_xyz(&D50);
+
+    out[0] = (xyz.x / MAX_ENCODEABLE_XYZ) as f32;
+    out[1] = (xyz.y / MAX_ENCODEABLE_XYZ) as f32;
+    out[2] = (xyz.z / MAX_ENCODEABLE_XYZ) as f32;
+}
+
+fn evaluate_xyz_to_lab(r#in: &[f32], out: &mut [f32], _stage: &Stage) {
+    let xyz = XYZ {
+        x: r#in[0] as f64 * MAX_ENCODEABLE_XYZ,
+        y: r#in[1] as f64 * MAX_ENCODEABLE_XYZ,
+        z: r#in[2] as f64 * MAX_ENCODEABLE_XYZ,
+    };
+
+    let lab = Lab::from_xyz(&xyz, &D50);
+
+    // From V4 Lab to 0..1.0
+    out[0] = (lab.l / 100.0) as f32;
+    out[1] = ((lab.a + 128.0) / 255.0) as f32;
+    out[2] = ((lab.b + 128.0) / 255.0) as f32;
+}
@@ -0,0 +1,157 @@
+use crate::{
+    sig,
+    state::Context,
+    types::{
+        adaptation_matrix, cone_matrix, ICtCp, Jzazbz, Mat3, OkLab, Signature, Stage, StageEvalFn,
+        Vec3, XYZ,
+    },
+    Result, D50, D65, MAX_ENCODEABLE_XYZ,
+};
+
+use super::dup_data;
+
+/// How a perceptually uniform space is reached from XYZ relative to D65.
+#[derive(Clone)]
+struct UniformSpaceData {
+    /// Pipeline XYZ, relative to D50, to the XYZ the space takes.
+    to_d65: Mat3,
+    from_d65: Mat3,
+    /// From XYZ relative to D65 to the space, or the other way around.
+    convert: fn(&[f64; 3]) -> [f64; 3],
+}
+
+impl Stage {
+    /// Creates a stage converting XYZ, in its 0..1 pipeline encoding relative to D50, to
+    /// OKLab.
+    pub fn alloc_xyz_to_oklab(context_id: &Context) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::XYZ_2_OKLAB,
+            1.0,
+            evaluate_from_xyz,
+            |xyz| OkLab::from_xyz(&XYZ::from(*xyz)).into(),
+        )
+    }
+
+    /// Creates a stage converting OKLab to XYZ, in its 0..1 pipeline encoding relative to D50.
+    pub fn alloc_oklab_to_xyz(context_id: &Context) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::OKLAB_2_XYZ,
+            1.0,
+            evaluate_to_xyz,
+            |lab| OkLab::from(*lab).to_xyz().into(),
+        )
+    }
+
+    /// Creates a stage converting XYZ, in its 0..1 pipeline encoding relative to D50, to
+    /// Jzazbz. `white_luminance` is the luminance of the media white, in cd/m².
+    pub fn alloc_xyz_to_jzazbz(context_id: &Context, white_luminance: f64) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::XYZ_2_JZAZBZ,
+            white_luminance,
+            evaluate_from_xyz,
+            |xyz| Jzazbz::from_xyz(&XYZ::from(*xyz)).into(),
+        )
+    }
+
+    /// Creates a stage converting Jzazbz to XYZ, in its 0..1 pipeline encoding relative to D50.
+    /// `white_luminance` is the luminance of the media white, in cd/m².
+    pub fn alloc_jzazbz_to_xyz(context_id: &Context, white_luminance: f64) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::JZAZBZ_2_XYZ,
+            white_luminance,
+            evaluate_to_xyz,
+            |jab| Jzazbz::from(*jab).to_xyz().into(),
+        )
+    }
+
+    /// Creates a stage converting XYZ, in its 0..1 pipeline encoding relative to D50, to
+    /// ICtCp. `white_luminance` is the luminance of the media white, in cd/m².
+    pub fn alloc_xyz_to_ictcp(context_id: &Context, white_luminance: f64) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::XYZ_2_ICTCP,
+            white_luminance,
+            evaluate_from_xyz,
+            |xyz| ICtCp::from_xyz(&XYZ::from(*xyz)).into(),
+        )
+    }
+
+    /// Creates a stage converting ICtCp to XYZ, in its 0..1 pipeline encoding relative to D50.
+    /// `white_luminance` is the luminance of the media white, in cd/m².
+    pub fn alloc_ictcp_to_xyz(context_id: &Context, white_luminance: f64) -> Result<Self> {
+        alloc_uniform(
+            context_id,
+            sig::mpe_stage::ICTCP_2_XYZ,
+            white_luminance,
+            evaluate_to_xyz,
+            |itp| ICtCp::from(*itp).to_xyz().into(),
+        )
+    }
+}
+
+/// A stage going to or from a perceptually uniform space through `convert`, as `eval` tells.
+/// The pipeline white is adapted to D65 with Bradford, and scaled to `white_luminance`.
+fn alloc_uniform(
+    context_id: &Context,
+    r#type: Signature,
+    white_luminance: f64,
+    eval: StageEvalFn,
+    convert: fn(&[f64; 3]) -> [f64; 3],
+) -> Result<Stage> {
+    if white_luminance <= 0.0 {
+        return err!(context_id, Error, Range, "Invalid white luminance {}", white_luminance; str => "Invalid white luminance");
+    }
+
+    let scale = Mat3::diagonal(white_luminance, white_luminance, white_luminance);
+    let to_d65 = scale * adaptation_matrix(&cone_matrix::BRADFORD, &D50, &D65)?;
+    let from_d65 = match to_d65.inverse() {
+        Some(inv) => inv,
+        None => return err!(str => "Adaptation matrix is not invertible"),
+    };
+
+    Stage::new(
+        context_id,
+        r#type,
+        3,
+        3,
+        eval,
+        dup_data::<UniformSpaceData>,
+        Box::new(UniformSpaceData {
+            to_d65,
+            from_d65,
+            convert,
+        }),
+    )
+}
+
+fn evaluate_from_xyz(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    let data = match stage.get_data().downcast_ref::<UniformSpaceData>() {
+        Some(data) => data,
+        None => return,
+    };
+
+    let xyz = Vec3([0, 1, 2].map(|i| r#in[i] as f64 * MAX_ENCODEABLE_XYZ));
+    let value = (data.convert)(&(data.to_d65 * xyz).0);
+
+    for (out, value) in out.iter_mut().zip(value) {
+        *out = value as f32;
+    }
+}
+
+fn evaluate_to_xyz(r#in: &[f32], out: &mut [f32], stage: &Stage) {
+    let data = match stage.get_data().downcast_ref::<UniformSpaceData>() {
+        Some(data) => data,
+        None => return,
+    };
+
+    let value = [0, 1, 2].map(|i| r#in[i] as f64);
+    let xyz = data.from_d65 * Vec3((data.convert)(&value));
+
+    for (out, value) in out.iter_mut().zip(xyz.0) {
+        *out = (value / MAX_ENCODEABLE_XYZ) as f32;
+    }
+}
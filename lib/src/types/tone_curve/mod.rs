@@ -0,0 +1,542 @@
+use crate::{
+    plugin::{lerp_flags, ParametricCurveEvaluator},
+    quick_saturate_word,
+    state::Context,
+    types::{InterpFunction, InterpParams},
+    Result, MINUS_INF, PLUS_INF,
+};
+
+/// Number of entries of the 16 bit table approximating curves defined by segments.
+const DEFAULT_GRID_POINTS: usize = 4096;
+const MAX_TABLE_ENTRIES: usize = 65530;
+const MAX_NODES_IN_CURVE: usize = 4097;
+
+/// One piece of a [`ToneCurve`], covering `(x0, x1]`. A non zero `type` selects a parametric
+/// function taking `params`, a zero `type` means the segment is sampled at `sampled_points`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurveSegment {
+    pub x0: f32,
+    pub x1: f32,
+    pub r#type: i32,
+    pub params: [f64; 10],
+    pub sampled_points: Vec<f32>,
+}
+
+/// A one dimensional curve. Curves are either a table of 16 bit values or a list of float
+/// segments, in which case a 16 bit table approximating them is kept for 8 and 16 bit
+/// transforms.
+#[derive(Clone)]
+pub struct ToneCurve {
+    context_id: Context,
+    interp_params: InterpParams<'static, u16>,
+    segments: Vec<CurveSegment>,
+    seg_interp: Vec<Option<InterpParams<'static, f32>>>,
+    evals: Vec<Option<ParametricCurveEvaluator>>,
+    table16: Vec<u16>,
+}
+
+impl ToneCurve {
+    fn new(
+        context_id: &Context,
+        n_entries: usize,
+        segments: &[CurveSegment],
+        values: Option<&[u16]>,
+    ) -> Result<Self> {
+        if n_entries == 0 && segments.is_empty() {
+            return err!(context_id, Error, Range, "Couldn't create tone curve of no segments and no table"; str => "Couldn't create tone curve of no segments and no table");
+        }
+        if n_entries > MAX_TABLE_ENTRIES {
+            return err!(context_id, Error, Range, "Couldn't create tone curve of more than {} entries", MAX_TABLE_ENTRIES; str => "Couldn't create tone curve of more than 65530 entries");
+        }
+
+        // The evaluator for each segment is located in advance to maximize performance.
+        let mut seg_interp = Vec::with_capacity(segments.len());
+        let mut evals = Vec::with_capacity(segments.len());
+        for seg in segments {
+            // Type 0 is a special marker for table-based segments
+            if seg.r#type == 0 {
+                if seg.sampled_points.len() < 2 {
+                    return err!(context_id, Error, Range, "Sampled curve segments need at least 2 points"; str => "Sampled curve segments need at least 2 points");
+                }
+
+                seg_interp.push(Some(InterpParams::compute(
+                    context_id,
+                    seg.sampled_points.len(),
+                    1,
+                    1,
+                    &[],
+                    lerp_flags::FLOAT,
+                )?));
+                evals.push(None);
+                continue;
+            }
+
+            match context_id.get_parametric_curve(seg.r#type) {
+                Some((eval, _)) => {
+                    seg_interp.push(None);
+                    evals.push(Some(eval));
+                }
+                None => {
+                    return err!(context_id, Error, UnknownExtension, "Invalid parametric curve type {}", seg.r#type; str => "Invalid parametric curve type")
+                }
+            }
+        }
+
+        let table16 = match values {
+            Some(values) => values.to_vec(),
+            None => vec![0u16; n_entries],
+        };
+
+        Ok(Self {
+            context_id: context_id.clone(),
+            interp_params: InterpParams::compute(
+                context_id,
+                n_entries,
+                1,
+                1,
+                &[],
+                lerp_flags::BITS_16,
+            )?,
+            segments: segments.to_vec(),
+            seg_interp,
+            evals,
+            table16,
+        })
+    }
+
+    /// Builds a curve from a table of 16 bit values, evenly spaced across the domain.
+    pub fn build_tabulated_16(context_id: &Context, values: &[u16]) -> Result<Self> {
+        Self::new(context_id, values.len(), &[], Some(values))
+    }
+
+    /// Builds a curve out of float segments. Segments are searched from last to first, so later
+    /// segments take priority where they overlap. The 16 bit approximation is computed from
+    /// them.
+    pub fn build_segmented(context_id: &Context, segments: &[CurveSegment]) -> Result<Self> {
+        if segments.is_empty() {
+            return err!(context_id, Error, Range, "Couldn't create tone curve of no segments"; str => "Couldn't create tone curve of no segments");
+        }
+
+        // Optimization for identity curves
+        let n_grid_points = if segments.len() == 1 && segments[0].r#type == 1 {
+            entries_by_gamma(segments[0].params[0])
+        } else {
+            DEFAULT_GRID_POINTS
+        };
+
+        let mut result = Self::new(context_id, n_grid_points, segments, None)?;
+
+        // A 16 bit approximation of the curve, normally only used on 8 and 16 bit transforms.
+        for i in 0..n_grid_points {
+            let r = i as f64 / (n_grid_points - 1) as f64;
+            let val = result.eval_segmented(r);
+
+            result.table16[i] = quick_saturate_word(val * 65535.0);
+        }
+
+        Ok(result)
+    }
+
+    /// Builds a curve from float samples evenly spaced across `[0, 1]`. Values outside of the
+    /// domain are clamped to the first and last samples.
+    pub fn build_tabulated_f32(context_id: &Context, values: &[f32]) -> Result<Self> {
+        if values.is_empty() {
+            return err!(context_id, Error, Range, "Couldn't create tone curve of no samples"; str => "Couldn't create tone curve of no samples");
+        }
+
+        // Constant segments before and after the samples
+        let constant = |x0: f32, x1: f32, value: f32| {
+            let mut seg = CurveSegment {
+                x0,
+                x1,
+                r#type: 6,
+                params: [0.0; 10],
+                sampled_points: Vec::new(),
+            };
+            seg.params[0] = 1.0;
+            seg.params[3] = value as f64;
+
+            seg
+        };
+
+        let segments = [
+            constant(MINUS_INF, 0.0, values[0]),
+            CurveSegment {
+                x0: 0.0,
+                x1: 1.0,
+                r#type: 0,
+                params: [0.0; 10],
+                sampled_points: values.to_vec(),
+            },
+            constant(1.0, PLUS_INF, values[values.len() - 1]),
+        ];
+
+        Self::build_segmented(context_id, &segments)
+    }
+
+    /// Builds a curve from one of the parametric functions registered in `context_id`.
+    /// `params` must hold at least as many values as the function takes.
+    pub fn build_parametric(context_id: &Context, r#type: i32, params: &[f64]) -> Result<Self> {
+        let param_count = match context_id.get_parametric_curve(r#type) {
+            Some((_, def)) => def.param_count,
+            None => {
+                return err!(context_id, Error, UnknownExtension, "Invalid parametric curve type {}", r#type; str => "Invalid parametric curve type")
+            }
+        };
+        if params.len() < param_count {
+            return err!(context_id, Error, Range, "Parametric curve type {} needs {} parameters", r#type, param_count; str => "Not enough parameters for parametric curve");
+        }
+
+        let mut seg = CurveSegment {
+            x0: MINUS_INF,
+            x1: PLUS_INF,
+            r#type,
+            params: [0.0; 10],
+            sampled_points: Vec::new(),
+        };
+        seg.params[..param_count].copy_from_slice(&params[..param_count]);
+
+        Self::build_segmented(context_id, &[seg])
+    }
+
+    /// Builds the curve `y = x ^ gamma`.
+    pub fn build_gamma(context_id: &Context, gamma: f64) -> Result<Self> {
+        Self::build_parametric(context_id, 1, &[gamma])
+    }
+
+    pub fn context_id(&self) -> &Context {
+        &self.context_id
+    }
+
+    pub fn segments(&self) -> &[CurveSegment] {
+        &self.segments
+    }
+
+    /// The type of the parametric function defining the curve, or `0` if the curve is not made
+    /// of a single parametric segment.
+    pub fn get_parametric_type(&self) -> i32 {
+        if self.segments.len() != 1 {
+            return 0;
+        }
+
+        self.segments[0].r#type
+    }
+
+    /// The parameters of the function defining the curve, if it is made of a single parametric
+    /// segment.
+    pub fn get_params(&self) -> Option<&[f64; 10]> {
+        if self.segments.len() != 1 {
+            return None;
+        }
+
+        Some(&self.segments[0].params)
+    }
+
+    pub fn get_estimated_table_entries(&self) -> usize {
+        self.table16.len()
+    }
+
+    pub fn get_estimated_table(&self) -> &[u16] {
+        &self.table16
+    }
+
+    /// Evaluates the curve in floating point. Curves made of segments are evaluated on them,
+    /// tabulated curves are interpolated in 16 bits.
+    pub fn eval_f32(&self, v: f32) -> f32 {
+        // Check for 16 bits table. If so, this is a limited-precision tone curve
+        if self.segments.is_empty() {
+            let r#in = quick_saturate_word(v as f64 * 65535.0);
+            let out = self.eval_u16(r#in);
+
+            return (out as f64 / 65535.0) as f32;
+        }
+
+        self.eval_segmented(v as f64) as f32
+    }
+
+    /// Evaluates the 16 bit approximation of the curve.
+    pub fn eval_u16(&self, v: u16) -> u16 {
+        let mut out = [0u16];
+
+        if let InterpFunction::U16(lerp) = self.interp_params.interpolation {
+            lerp(&[v], &mut out, &self.interp_params_16());
+        }
+
+        out[0]
+    }
+
+    /// Reverses the curve. Parametric curves are reversed analytically, anything else is
+    /// reversed on a 4096 entry table.
+    pub fn reverse(&self) -> Result<Self> {
+        self.reverse_ex(DEFAULT_GRID_POINTS)
+    }
+
+    /// Reverses the curve, reversing tables on `n_result_samples` entries, 2 at least.
+    pub fn reverse_ex(&self, n_result_samples: usize) -> Result<Self> {
+        if n_result_samples < 2 {
+            return err!(self.context_id, Error, Range, "Couldn't reverse tone curve on {} samples", n_result_samples; str => "Reversed tone curves need at least 2 samples");
+        }
+
+        // Try to reverse it analytically whatever possible
+        if self.segments.len() == 1
+            && self.segments[0].r#type > 0
+            && self
+                .context_id
+                .get_parametric_curve(self.segments[0].r#type)
+                .is_some()
+        {
+            return Self::build_parametric(
+                &self.context_id,
+                -self.segments[0].r#type,
+                &self.segments[0].params,
+            );
+        }
+
+        // Nope, reverse the table
+        let mut values = vec![0u16; n_result_samples];
+        let ascending = !self.is_descending();
+        let n_entries = self.table16.len();
+
+        let (mut a, mut b) = (0.0, 0.0);
+
+        // Iterate across Y axis
+        for (i, value) in values.iter_mut().enumerate() {
+            let y = i as f64 * 65535.0 / (n_result_samples - 1) as f64;
+
+            // Find the interval y is within
+            if let Some(j) = self.get_interval(y) {
+                // Get limits of interval
+                let x1 = self.table16[j] as f64;
+                let x2 = self.table16[j + 1] as f64;
+
+                let y1 = (j as f64 * 65535.0) / (n_entries - 1) as f64;
+                let y2 = ((j + 1) as f64 * 65535.0) / (n_entries - 1) as f64;
+
+                // If collapsed, then use any
+                if x1 == x2 {
+                    *value = quick_saturate_word(if ascending { y2 } else { y1 });
+                    continue;
+                }
+
+                // Interpolate
+                a = (y2 - y1) / (x2 - x1);
+                b = y2 - a * x2;
+            }
+
+            *value = quick_saturate_word(a * y + b);
+        }
+
+        Self::build_tabulated_16(&self.context_id, &values)
+    }
+
+    /// Joins two curves, so that `x` is evaluated first and the result is then looked up in the
+    /// reverse of `y`. The result is a float table of `n_points` samples, 2 at least.
+    pub fn join(context_id: &Context, x: &Self, y: &Self, n_points: usize) -> Result<Self> {
+        if n_points < 2 {
+            return err!(context_id, Error, Range, "Couldn't join tone curves on {} points", n_points; str => "Joined tone curves need at least 2 points");
+        }
+
+        let y_reversed = y.reverse_ex(n_points)?;
+
+        let values = (0..n_points)
+            .map(|i| {
+                let t = i as f32 / (n_points - 1) as f32;
+                y_reversed.eval_f32(x.eval_f32(t))
+            })
+            .collect::<Vec<_>>();
+
+        Self::build_tabulated_f32(context_id, &values)
+    }
+
+    /// Whether the 16 bit table is close enough to the identity.
+    pub fn is_linear(&self) -> bool {
+        let n_entries = self.table16.len();
+
+        self.table16.iter().enumerate().all(|(i, value)| {
+            let expected = quantize_val(i as f64, n_entries);
+
+            (*value as i32).abs_diff(expected as i32) <= 0x0f
+        })
+    }
+
+    /// Whether the 16 bit table only goes in one direction, allowing for some ripple.
+    pub fn is_monotonic(&self) -> bool {
+        let n = self.table16.len();
+
+        // Degenerated curves are monotonic? Ok, let's pass them
+        if n < 2 {
+            return true;
+        }
+
+        // Walk the table against its direction
+        if self.is_descending() {
+            !has_ripples(self.table16.iter())
+        } else {
+            !has_ripples(self.table16.iter().rev())
+        }
+    }
+
+    pub fn is_descending(&self) -> bool {
+        self.table16[0] > self.table16[self.table16.len() - 1]
+    }
+
+    pub fn is_multisegment(&self) -> bool {
+        self.segments.len() > 1
+    }
+
+    /// Estimates the gamma of the curve, provided the standard deviation of the estimation is
+    /// within `precision`.
+    pub fn estimate_gamma(&self, precision: f64) -> Option<f64> {
+        match self.estimate_gamma_with_deviation() {
+            Some((gamma, std)) if std <= precision => Some(gamma),
+            _ => None,
+        }
+    }
+
+    /// Estimates the gamma of the curve, along with the standard deviation of the estimation.
+    /// Returns `None` if the curve doesn't have enough points within `(0, 1)`.
+    pub fn estimate_gamma_with_deviation(&self) -> Option<(f64, f64)> {
+        let mut sum = 0.0;
+        let mut sum2 = 0.0;
+        let mut n = 0.0;
+
+        // Excluding endpoints
+        for i in 1..(MAX_NODES_IN_CURVE - 1) {
+            let x = i as f64 / (MAX_NODES_IN_CURVE - 1) as f64;
+            let y = self.eval_f32(x as f32) as f64;
+
+            // Avoid 7% on lower part to prevent artifacts due to linear ramps
+            if y > 0.0 && y < 1.0 && x > 0.07 {
+                let gamma = y.ln() / x.ln();
+                sum += gamma;
+                sum2 += gamma * gamma;
+                n += 1.0;
+            }
+        }
+
+        // We need enough valid samples
+        if n <= 1.0 {
+            return None;
+        }
+
+        // Take a look on SD to see if gamma isn't exponential at all
+        let std = ((n * sum2 - sum * sum) / (n * (n - 1.0))).sqrt();
+
+        Some((sum / n, std))
+    }
+
+    /// Finds the interval of the 16 bit table containing `r#in`.
+    fn get_interval(&self, r#in: f64) -> Option<usize> {
+        let lut_table = &self.table16;
+        let domain = self.interp_params.domain[0];
+
+        // A 1 point table is not allowed
+        if domain < 1 {
+            return None;
+        }
+
+        let contains = |i: &usize| {
+            let y0 = lut_table[*i] as f64;
+            let y1 = lut_table[*i + 1] as f64;
+
+            if y0 <= y1 {
+                r#in >= y0 && r#in <= y1
+            } else {
+                r#in >= y1 && r#in <= y0
+            }
+        };
+
+        // Let's see if ascending or descending
+        if lut_table[0] < lut_table[domain] {
+            (0..domain).rev().find(contains)
+        } else {
+            (0..domain).find(contains)
+        }
+    }
+
+    /// The 16 bit interpolation parameters, bound to the 16 bit table.
+    pub(crate) fn interp_params_16(&self) -> InterpParams<'_, u16> {
+        InterpParams {
+            table: &self.table16,
+            ..self.interp_params.clone()
+        }
+    }
+
+    fn eval_segmented(&self, r: f64) -> f64 {
+        for i in (0..self.segments.len()).rev() {
+            let seg = &self.segments[i];
+
+            // Check for domain
+            if r <= seg.x0 as f64 || r > seg.x1 as f64 {
+                continue;
+            }
+
+            let out = match (&self.seg_interp[i], self.evals[i]) {
+                (Some(params), _) => {
+                    let r1 = ((r - seg.x0 as f64) / (seg.x1 - seg.x0) as f64) as f32;
+                    let mut out = [0f32];
+
+                    if let InterpFunction::F32(lerp) = params.interpolation {
+                        let params = InterpParams {
+                            table: &seg.sampled_points,
+                            ..params.clone()
+                        };
+                        lerp(&[r1], &mut out, &params);
+                    }
+
+                    out[0] as f64
+                }
+                (None, Some(eval)) => eval(seg.r#type, &seg.params, r),
+                (None, None) => continue,
+            };
+
+            if out.is_infinite() {
+                return if out > 0.0 {
+                    PLUS_INF as f64
+                } else {
+                    MINUS_INF as f64
+                };
+            }
+
+            return out;
+        }
+
+        MINUS_INF as f64
+    }
+}
+
+/// Whether `values` ever go up by more than the allowed ripple.
+fn has_ripples<'a>(mut values: impl Iterator<Item = &'a u16>) -> bool {
+    let mut last = match values.next() {
+        Some(last) => *last as i32,
+        None => return false,
+    };
+
+    for value in values {
+        let value = *value as i32;
+        if value - last > 2 {
+            return true;
+        }
+        last = value;
+    }
+
+    false
+}
+
+/// The value of entry `i` of an identity table of `max_samples` entries.
+fn quantize_val(i: f64, max_samples: usize) -> u16 {
+    let x = (i * 65535.0) / (max_samples - 1) as f64;
+
+    quick_saturate_word(x)
+}
+
+/// Curves close to the identity only need the two end points.
+fn entries_by_gamma(gamma: f64) -> usize {
+    if (gamma - 1.0).abs() < 0.001 {
+        return 2;
+    }
+
+    DEFAULT_GRID_POINTS
+}
+
+mod smooth;
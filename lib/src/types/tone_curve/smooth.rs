@@ -0,0 +1,122 @@
+use crate::{quick_saturate_word, Result};
+
+use super::{ToneCurve, MAX_NODES_IN_CURVE};
+
+impl ToneCurve {
+    /// Smooths the 16 bit table of the curve with a Whittaker smoother of strength `lambda`.
+    /// Linear curves are left untouched. Results that are not monotonic, or are mostly zeros or
+    /// poles, are rejected unless `lambda` is negative.
+    pub fn smooth(&mut self, lambda: f64) -> Result<()> {
+        let ctx = self.context_id.clone();
+
+        // Only non-linear curves need smoothing
+        if self.is_linear() {
+            return Ok(());
+        }
+
+        let n_items = self.table16.len();
+        if n_items >= MAX_NODES_IN_CURVE {
+            return err!(ctx, Error, Range, "smooth: Too many points."; str => "Too many points");
+        }
+        // A straight line between the end points is as smooth as it gets
+        if n_items < 3 {
+            return Ok(());
+        }
+
+        // Vectors go from 1 to n_items
+        let mut w = vec![0f32; n_items + 1];
+        let mut y = vec![0f32; n_items + 1];
+        let mut z = vec![0f32; n_items + 1];
+
+        for i in 0..n_items {
+            y[i + 1] = self.table16[i] as f32;
+            w[i + 1] = 1.0;
+        }
+
+        let (lambda, not_check) = if lambda < 0.0 {
+            (-lambda, true)
+        } else {
+            (lambda, false)
+        };
+
+        smooth2(&w, &y, &mut z, lambda as f32, n_items);
+
+        if !not_check {
+            // Do some reality checking...
+            let mut zeros = 0;
+            let mut poles = 0;
+            for i in (2..=n_items).rev() {
+                if z[i] == 0.0 {
+                    zeros += 1;
+                }
+                if z[i] >= 65535.0 {
+                    poles += 1;
+                }
+                if z[i] < z[i - 1] {
+                    return err!(ctx, Error, Range, "smooth: Non-Monotonic."; str => "Non-Monotonic");
+                }
+            }
+
+            if zeros > n_items / 3 {
+                return err!(ctx, Error, Range, "smooth: Degenerated, mostly zeros."; str => "Degenerated, mostly zeros");
+            }
+            if poles > n_items / 3 {
+                return err!(ctx, Error, Range, "smooth: Degenerated, mostly poles."; str => "Degenerated, mostly poles");
+            }
+        }
+
+        // Seems ok
+        for i in 0..n_items {
+            self.table16[i] = quick_saturate_word(z[i + 1] as f64);
+        }
+
+        Ok(())
+    }
+}
+
+/// Smoothing and interpolation with second differences.
+///
+/// Takes weights `w` and data `y`, both indexed from 1 to `m`, and the smoothing parameter
+/// `lambda`. The smoothed data is stored in `z`, also indexed from 1 to `m`.
+fn smooth2(w: &[f32], y: &[f32], z: &mut [f32], lambda: f32, m: usize) {
+    let mut c = vec![0f32; m + 1];
+    let mut d = vec![0f32; m + 1];
+    let mut e = vec![0f32; m + 1];
+
+    d[1] = w[1] + lambda;
+    c[1] = -2.0 * lambda / d[1];
+    e[1] = lambda / d[1];
+    z[1] = w[1] * y[1];
+    d[2] = w[2] + 5.0 * lambda - d[1] * c[1] * c[1];
+    c[2] = (-4.0 * lambda - d[1] * c[1] * e[1]) / d[2];
+    e[2] = lambda / d[2];
+    z[2] = w[2] * y[2] - c[1] * z[1];
+
+    for i in 3..(m - 1) {
+        let i1 = i - 1;
+        let i2 = i - 2;
+
+        d[i] = w[i] + 6.0 * lambda - c[i1] * c[i1] * d[i1] - e[i2] * e[i2] * d[i2];
+        c[i] = (-4.0 * lambda - d[i1] * c[i1] * e[i1]) / d[i];
+        e[i] = lambda / d[i];
+        z[i] = w[i] * y[i] - c[i1] * z[i1] - e[i2] * z[i2];
+    }
+
+    let i1 = m - 2;
+    let i2 = m - 3;
+
+    d[m - 1] = w[m - 1] + 5.0 * lambda - c[i1] * c[i1] * d[i1] - e[i2] * e[i2] * d[i2];
+    c[m - 1] = (-2.0 * lambda - d[i1] * c[i1] * e[i1]) / d[m - 1];
+    z[m - 1] = w[m - 1] * y[m - 1] - c[i1] * z[i1] - e[i2] * z[i2];
+
+    let i1 = m - 1;
+    let i2 = m - 2;
+
+    d[m] = w[m] + lambda - c[i1] * c[i1] * d[i1] - e[i2] * e[i2] * d[i2];
+    z[m] = (w[m] * y[m] - c[i1] * z[i1] - e[i2] * z[i2]) / d[m];
+    z[m - 1] = z[m - 1] / d[m - 1] - c[m - 1] * z[m];
+
+    for i in (1..=(m - 2)).rev() {
+        z[i] = z[i] / d[i] - c[i] * z[i + 1] - e[i] * z[i + 2];
+    }
+}
@@ -0,0 +1,97 @@
+use crate::plugin::{read_sample, sample_size, write_sample};
+
+use super::{transform_flags, Format, Stride, Transform};
+
+impl Transform {
+    /// Copies the extra channels, like alpha or spot colors, from `in_buf` to `out_buf` when
+    /// the transform was created with [`transform_flags::COPY_ALPHA`]. Samples are converted
+    /// when the bit depths of the input and output formats differ.
+    pub(crate) fn handle_extra_channels(
+        &self,
+        in_buf: &[u8],
+        out_buf: &mut [u8],
+        pixels_per_line: usize,
+        line_count: usize,
+        stride: &Stride,
+    ) {
+        // Make sure we need some copy
+        if self.flags & transform_flags::COPY_ALPHA == 0 {
+            return;
+        }
+
+        // Both formats must have the same number of extra channels, which is checked when
+        // creating the transform
+        let n_extra = self.input_format.extra();
+        if n_extra == 0 || n_extra != self.output_format.extra() {
+            return;
+        }
+
+        let (src_starts, src_inc) = extra_channel_layout(self.input_format, stride.per_plane_in);
+        let (dst_starts, dst_inc) = extra_channel_layout(self.output_format, stride.per_plane_out);
+
+        let in_format = self.input_format;
+        let out_format = self.output_format;
+        let size = sample_size(in_format);
+        let same_kind = in_format.float() == out_format.float()
+            && in_format.bytes() == out_format.bytes()
+            && in_format.endian16() == out_format.endian16();
+
+        for line in 0..line_count {
+            let in_line = line * stride.per_line_in;
+            let out_line = line * stride.per_line_out;
+
+            for (src_start, dst_start) in src_starts.iter().zip(dst_starts.iter()) {
+                for i in 0..pixels_per_line {
+                    let src = in_line + src_start + i * src_inc;
+                    let dst = out_line + dst_start + i * dst_inc;
+
+                    if same_kind {
+                        out_buf[dst..dst + size].copy_from_slice(&in_buf[src..src + size]);
+                    } else {
+                        write_sample(
+                            out_format,
+                            out_buf,
+                            dst,
+                            read_sample(in_format, in_buf, src),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Byte offsets of the extra channels from the start of a pixel, and the distance in bytes
+/// between two consecutive pixels. `bytes_per_plane` is only used by planar formats.
+fn extra_channel_layout(format: Format, bytes_per_plane: usize) -> (Vec<usize>, usize) {
+    let n_chans = format.channels() as usize;
+    let total_chans = n_chans + format.extra() as usize;
+    let size = sample_size(format);
+
+    // Handle do swap
+    let mut channels = (0..total_chans)
+        .map(|i| {
+            if format.doswap() {
+                total_chans - i - 1
+            } else {
+                i
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Handle swap first (ROL of positions), example CMYK -> KCMY | 0123 -> 3012
+    if format.swapfirst() && total_chans > 1 {
+        channels.rotate_left(1);
+    }
+
+    let (step, increment) = if format.planar() {
+        (bytes_per_plane, size)
+    } else {
+        (size, size * total_chans)
+    };
+
+    (
+        channels[n_chans..].iter().map(|pos| pos * step).collect(),
+        increment,
+    )
+}
@@ -0,0 +1,224 @@
+use crate::{
+    plugin::{intent, link_profiles},
+    sig,
+    state::Context,
+    types::{
+        channels_of_color_space, pixel_type, pixel_type_of_color_space, Format, Pipeline, Profile,
+        Signature, Stage, StageLoc,
+    },
+    Result,
+};
+
+use super::{transform_flags, Transform};
+
+impl Transform {
+    /// Creates a transform from `input` to `output` using `intent`.
+    pub fn new_with_profiles(
+        context_id: &Context,
+        input: Profile,
+        input_format: Format,
+        output: Profile,
+        output_format: Format,
+        intent: u32,
+        flags: u32,
+    ) -> Result<Self> {
+        Self::new_multiprofile(
+            context_id,
+            vec![input, output],
+            input_format,
+            output_format,
+            intent,
+            flags,
+        )
+    }
+
+    /// Creates a transform going through every profile in `profiles`, using `intent` on every
+    /// step. Black point compensation is enabled with
+    /// [`transform_flags::BLACKPOINTCOMPENSATION`], and the adaptation state is the one of
+    /// `context_id`.
+    pub fn new_multiprofile(
+        context_id: &Context,
+        profiles: Vec<Profile>,
+        input_format: Format,
+        output_format: Format,
+        intent: u32,
+        flags: u32,
+    ) -> Result<Self> {
+        let n_profiles = profiles.len();
+        let bpc = vec![flags & transform_flags::BLACKPOINTCOMPENSATION != 0; n_profiles];
+        let intents = vec![intent; n_profiles];
+        let adaptation_states = vec![context_id.get_adaptation_state(); n_profiles];
+
+        Self::new_extended(
+            context_id,
+            profiles,
+            &bpc,
+            &intents,
+            &adaptation_states,
+            input_format,
+            output_format,
+            flags,
+        )
+    }
+
+    /// Creates a transform going through every profile in `profiles`, each with its own intent,
+    /// black point compensation and adaptation state.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_extended(
+        context_id: &Context,
+        profiles: Vec<Profile>,
+        bpc: &[bool],
+        intents: &[u32],
+        adaptation_states: &[f64],
+        input_format: Format,
+        output_format: Format,
+        flags: u32,
+    ) -> Result<Self> {
+        // If it is a fake transform, there is nothing to link
+        if flags & transform_flags::NULLTRANSFORM != 0 {
+            let lut = Pipeline::new(
+                context_id,
+                input_format.channels() as usize,
+                output_format.channels() as usize,
+            )?;
+            return Self::new(
+                context_id,
+                lut,
+                intent::PERCEPTUAL,
+                input_format,
+                output_format,
+                flags,
+            );
+        }
+
+        let n_profiles = profiles.len();
+
+        let (entry_color_space, exit_color_space) = match get_xform_color_spaces(&profiles) {
+            Some(spaces) => spaces,
+            None => {
+                return err!(context_id, Error, NotSuitable, "NULL input profiles on transform"; str => "NULL input profiles on transform")
+            }
+        };
+
+        // Check if proper color spaces
+        if !is_proper_color_space(entry_color_space, input_format) {
+            return err!(context_id, Error, ColorspaceCheck, "Wrong input color space on transform"; str => "Wrong input color space on transform");
+        }
+        if !is_proper_color_space(exit_color_space, output_format) {
+            return err!(context_id, Error, ColorspaceCheck, "Wrong output color space on transform"; str => "Wrong output color space on transform");
+        }
+        for format in [input_format, output_format] {
+            if is_uniform_space(format.colorspace() as u32) && !format.float() {
+                return err!(context_id, Error, NotSuitable, "Perceptually uniform spaces need floating point formats"; str => "Perceptually uniform spaces need floating point formats");
+            }
+        }
+
+        let mut lut = link_profiles(
+            context_id,
+            intents.into(),
+            profiles.into_boxed_slice(),
+            bpc.into(),
+            adaptation_states.into(),
+            flags,
+        )?;
+
+        // Check channel count
+        if channels_of_color_space(entry_color_space) != Some(lut.get_input_channels())
+            || channels_of_color_space(exit_color_space) != Some(lut.get_output_channels())
+        {
+            return err!(context_id, Error, NotSuitable, "Channel count doesn't match. Profile is corrupted"; str => "Channel count doesn't match. Profile is corrupted");
+        }
+
+        // Perceptually uniform spaces are reached from the PCS the profiles take or give
+        if let Some(stage) = uniform_space_stage(context_id, input_format, true) {
+            if entry_color_space == sig::colorspace::LAB {
+                lut.insert_stage(StageLoc::AtBegin, Stage::alloc_xyz_to_lab(context_id)?)?;
+            }
+            lut.insert_stage(StageLoc::AtBegin, stage?)?;
+        }
+        if let Some(stage) = uniform_space_stage(context_id, output_format, false) {
+            if exit_color_space == sig::colorspace::LAB {
+                lut.insert_stage(StageLoc::AtEnd, Stage::alloc_lab_to_xyz(context_id)?)?;
+            }
+            lut.insert_stage(StageLoc::AtEnd, stage?)?;
+        }
+
+        // Linking succeeded, so there is an intent for every profile
+        Self::new(
+            context_id,
+            lut,
+            intents[n_profiles - 1],
+            input_format,
+            output_format,
+            flags,
+        )
+    }
+}
+
+/// Gets the color spaces the first profile takes and the last profile gives when linked.
+fn get_xform_color_spaces(profiles: &[Profile]) -> Option<(Signature, Signature)> {
+    let mut entry = None;
+    let mut post = sig::colorspace::COLOR1;
+
+    for (i, profile) in profiles.iter().enumerate() {
+        let l_is_input = post != sig::colorspace::XYZ && post != sig::colorspace::LAB;
+
+        let class = profile.get_device_class();
+        let (color_space_in, color_space_out) = if class == sig::class::NAMED_COLOR {
+            let color_space_out = if profiles.len() > 1 {
+                profile.get_pcs()
+            } else {
+                profile.get_color_space()
+            };
+            (sig::colorspace::COLOR1, color_space_out)
+        } else if l_is_input || class == sig::class::LINK {
+            (profile.get_color_space(), profile.get_pcs())
+        } else {
+            (profile.get_pcs(), profile.get_color_space())
+        };
+
+        if i == 0 {
+            entry = Some(color_space_in);
+        }
+        post = color_space_out;
+    }
+
+    Some((entry?, post))
+}
+
+/// Whether buffers of `format` can hold colors of `check`.
+fn is_proper_color_space(check: Signature, format: Format) -> bool {
+    let space1 = format.colorspace() as u32;
+    let space2 = pixel_type_of_color_space(check);
+
+    space1 == pixel_type::ANY
+        || space1 == space2
+        || (space1 == pixel_type::LAB_V2 && space2 == pixel_type::LAB)
+        || (space1 == pixel_type::LAB && space2 == pixel_type::LAB_V2)
+        || (is_uniform_space(space1) && (space2 == pixel_type::XYZ || space2 == pixel_type::LAB))
+}
+
+/// The luminance the white of perceptually uniform spaces is given, in cd/m². This is the
+/// reference white of ITU-R BT.2408, as documented on the Jzazbz and ICtCp formats.
+const REFERENCE_WHITE_LUMINANCE: f64 = 203.0;
+
+/// Whether `space` is a perceptually uniform [`pixel_type`], only reachable from XYZ.
+fn is_uniform_space(space: u32) -> bool {
+    space == pixel_type::OKLAB || space == pixel_type::JZAZBZ || space == pixel_type::ICTCP
+}
+
+/// The stage taking the colors of `format` to pipeline XYZ if `input`, or the other way around.
+/// `None` if `format` isn't a perceptually uniform space.
+fn uniform_space_stage(context_id: &Context, format: Format, input: bool) -> Option<Result<Stage>> {
+    let luminance = REFERENCE_WHITE_LUMINANCE;
+
+    Some(match (format.colorspace() as u32, input) {
+        (pixel_type::OKLAB, true) => Stage::alloc_oklab_to_xyz(context_id),
+        (pixel_type::OKLAB, false) => Stage::alloc_xyz_to_oklab(context_id),
+        (pixel_type::JZAZBZ, true) => Stage::alloc_jzazbz_to_xyz(context_id, luminance),
+        (pixel_type::JZAZBZ, false) => Stage::alloc_xyz_to_jzazbz(context_id, luminance),
+        (pixel_type::ICTCP, true) => Stage::alloc_ictcp_to_xyz(context_id, luminance),
+        (pixel_type::ICTCP, false) => Stage::alloc_xyz_to_ictcp(context_id, luminance),
+        _ => return None,
+    })
+}
@@ -0,0 +1,495 @@
+use std::any::Any;
+
+use crate::{
+    plugin::{
+        pack_flags, sample_size, FormatterIn, FormatterIn16, FormatterInFloat, FormatterOut,
+        FormatterOut16, FormatterOutFloat, Transform2Fn, TransformFn, TransformFunc,
+    },
+    state::Context,
+    Result, MAX_CHANNELS,
+};
+
+use super::{channels_of_color_space, pixel_type, Format, Pipeline, Signature};
+
+/// A color transform, converting pixels between the buffer formats it was created with by
+/// running them through a [`Pipeline`].
+pub struct Transform {
+    context_id: Context,
+    pub(crate) input_format: Format,
+    pub(crate) output_format: Format,
+    pub(crate) flags: u32,
+    lut: Pipeline,
+    from_input: Option<FormatterIn16>,
+    to_output: Option<FormatterOut16>,
+    from_input_float: Option<FormatterInFloat>,
+    to_output_float: Option<FormatterOutFloat>,
+    cache: Cache,
+    xform: Transform2Fn,
+    old_xform: Option<TransformFn>,
+    user_data: Option<Box<dyn Any>>,
+}
+
+/// The last pixel transformed in 16 bits, and its result.
+#[derive(Clone, Copy)]
+struct Cache {
+    cache_in: [u16; MAX_CHANNELS],
+    cache_out: [u16; MAX_CHANNELS],
+}
+
+impl Transform {
+    /// Creates a transform running `lut` between buffers of `input_format` and
+    /// `output_format`. Unless [`transform_flags::NOOPTIMIZE`] is set, transform plugins get
+    /// the chance to take over, and optimization plugins to rewrite `lut`.
+    pub fn new(
+        context_id: &Context,
+        lut: Pipeline,
+        intent: u32,
+        input_format: Format,
+        output_format: Format,
+        flags: u32,
+    ) -> Result<Self> {
+        let mut lut = lut;
+        let mut input_format = input_format;
+        let mut output_format = output_format;
+        let mut flags = flags;
+
+        // A format of zero means the formatters are set later on
+        if (input_format.bits() != 0
+            && input_format.channels() as usize != lut.get_input_channels())
+            || (output_format.bits() != 0
+                && output_format.channels() as usize != lut.get_output_channels())
+        {
+            return err!(context_id, Error, NotSuitable, "Channel count doesn't match. Formats have {} and {} channels, pipeline has {} and {}", input_format.channels(), output_format.channels(), lut.get_input_channels(), lut.get_output_channels(); str => "Channel count doesn't match");
+        }
+
+        if flags & transform_flags::COPY_ALPHA != 0 && input_format.extra() != output_format.extra()
+        {
+            return err!(context_id, Error, NotSuitable, "Mismatched alpha channels"; str => "Mismatched alpha channels");
+        }
+
+        if flags & transform_flags::COPY_ALPHA == 0 && is_premultiplied(output_format) {
+            return err!(context_id, Error, NotSuitable, "Premultiplied output formats need COPY_ALPHA"; str => "Premultiplied output needs COPY_ALPHA");
+        }
+
+        // On floating point transforms, inhibit cache
+        if input_format.float() || output_format.float() {
+            flags |= transform_flags::NOCACHE;
+        }
+
+        let mut xform = None;
+        if flags & transform_flags::NOOPTIMIZE == 0 {
+            // Let's see if any plugin wants to do the transform by itself
+            for factory in context_id.get_transform_factories() {
+                let result = match factory {
+                    TransformFunc::Factory(factory) => {
+                        factory(&mut lut, &mut input_format, &mut output_format, &mut flags)
+                            .map(|result| (result.xform, None, result.data))
+                    }
+                    TransformFunc::OldFactory(factory) => {
+                        factory(&mut lut, &mut input_format, &mut output_format, &mut flags).map(
+                            |result| {
+                                (
+                                    transform_2_to_transform_adaptor as Transform2Fn,
+                                    Some(result.xform),
+                                    result.data,
+                                )
+                            },
+                        )
+                    }
+                };
+
+                if let Ok(result) = result {
+                    xform = Some(result);
+                    break;
+                }
+            }
+
+            // Not suitable for the transform plugins, let's check the pipeline optimizations
+            if xform.is_none() {
+                for optimization in context_id.get_optimizations() {
+                    if optimization(
+                        &mut lut,
+                        intent,
+                        &mut input_format,
+                        &mut output_format,
+                        &mut flags,
+                    )
+                    .is_ok()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let (from_input, to_output) = get_formatters_16(context_id, input_format, output_format);
+        let (from_input_float, to_output_float) =
+            get_formatters_float(context_id, input_format, output_format);
+
+        let (xform, old_xform, user_data) = match xform {
+            // Plugins get every formatter available, it is up to them to decide what to do
+            // when some are missing
+            Some(xform) => xform,
+            None if input_format.float() || output_format.float() => {
+                if from_input_float.is_none() || to_output_float.is_none() {
+                    return err!(context_id, Error, UnknownExtension, "Unsupported raster format"; str => "Unsupported raster format");
+                }
+
+                let xform: Transform2Fn = if flags & transform_flags::NULLTRANSFORM != 0 {
+                    null_float_xform
+                } else {
+                    float_xform
+                };
+                (xform, None, None)
+            }
+            None => {
+                if input_format.bits() == 0 && output_format.bits() == 0 {
+                    flags |= transform_flags::CAN_CHANGE_FORMATTER;
+                } else {
+                    if from_input.is_none() || to_output.is_none() {
+                        return err!(context_id, Error, UnknownExtension, "Unsupported raster format"; str => "Unsupported raster format");
+                    }
+
+                    // Transforms fed with 8 bits may have been optimized for 8 bits only
+                    let bytes = input_format.bytes();
+                    if bytes == 0 || bytes >= 2 {
+                        flags |= transform_flags::CAN_CHANGE_FORMATTER;
+                    }
+                }
+
+                let xform: Transform2Fn = if flags & transform_flags::NULLTRANSFORM != 0 {
+                    null_xform
+                } else if flags & transform_flags::NOCACHE != 0 {
+                    precalculated_xform
+                } else {
+                    cached_xform
+                };
+                (xform, None, None)
+            }
+        };
+
+        let mut cache = Cache {
+            cache_in: [0; MAX_CHANNELS],
+            cache_out: [0; MAX_CHANNELS],
+        };
+        if flags & transform_flags::NOCACHE == 0 {
+            lut.eval_16(&cache.cache_in, &mut cache.cache_out);
+        }
+
+        Ok(Self {
+            context_id: context_id.clone(),
+            input_format,
+            output_format,
+            flags,
+            lut,
+            from_input,
+            to_output,
+            from_input_float,
+            to_output_float,
+            cache,
+            xform,
+            old_xform,
+            user_data,
+        })
+    }
+
+    pub fn context_id(&self) -> &Context {
+        &self.context_id
+    }
+
+    pub fn get_input_format(&self) -> Format {
+        self.input_format
+    }
+
+    pub fn get_output_format(&self) -> Format {
+        self.output_format
+    }
+
+    /// The [`transform_flags`] the transform was created with.
+    pub fn get_flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// The data set by the transform plugin which took over this transform, if any.
+    pub fn get_user_data(&self) -> Option<&dyn Any> {
+        self.user_data.as_deref()
+    }
+
+    /// The 16 bits formatters of the buffers, for the use of transform plugins.
+    pub fn get_formatters_16(&self) -> (Option<FormatterIn16>, Option<FormatterOut16>) {
+        (self.from_input, self.to_output)
+    }
+
+    /// The floating point formatters of the buffers, for the use of transform plugins.
+    pub fn get_formatters_float(&self) -> (Option<FormatterInFloat>, Option<FormatterOutFloat>) {
+        (self.from_input_float, self.to_output_float)
+    }
+
+    /// Transforms `pixel_count` pixels from `in_buf` into `out_buf`. Planar buffers hold
+    /// `pixel_count` samples in each plane. Fails if either buffer is too small for them.
+    pub fn do_transform(
+        &self,
+        in_buf: &[u8],
+        out_buf: &mut [u8],
+        pixel_count: usize,
+    ) -> Result<()> {
+        let stride = Stride {
+            per_line_in: 0,
+            per_line_out: 0,
+            per_plane_in: pixel_count.saturating_mul(sample_size(self.input_format)),
+            per_plane_out: pixel_count.saturating_mul(sample_size(self.output_format)),
+        };
+
+        self.do_transform_line_stride(in_buf, out_buf, pixel_count, 1, stride)
+    }
+
+    /// Transforms `line_count` lines of `pixels_per_line` pixels, for images whose lines are
+    /// padded or whose planes aren't contiguous. `stride` holds the distances in bytes between
+    /// the start of two lines, and between the start of two planes. Fails if either buffer is
+    /// too small for them.
+    pub fn do_transform_line_stride(
+        &self,
+        in_buf: &[u8],
+        out_buf: &mut [u8],
+        pixels_per_line: usize,
+        line_count: usize,
+        stride: Stride,
+    ) -> Result<()> {
+        let in_size = buffer_size(
+            self.input_format,
+            pixels_per_line,
+            line_count,
+            stride.per_line_in,
+            stride.per_plane_in,
+        );
+        let out_size = buffer_size(
+            self.output_format,
+            pixels_per_line,
+            line_count,
+            stride.per_line_out,
+            stride.per_plane_out,
+        );
+
+        if in_size.map_or(true, |size| in_buf.len() < size) {
+            return err!(self.context_id, Error, Range, "Input buffer of {} bytes is too small for {} lines of {} pixels", in_buf.len(), line_count, pixels_per_line; str => "Input buffer too small");
+        }
+        if out_size.map_or(true, |size| out_buf.len() < size) {
+            return err!(self.context_id, Error, Range, "Output buffer of {} bytes is too small for {} lines of {} pixels", out_buf.len(), line_count, pixels_per_line; str => "Output buffer too small");
+        }
+
+        (self.xform)(self, in_buf, out_buf, pixels_per_line, line_count, stride);
+        Ok(())
+    }
+
+    /// Changes the formats of the buffers. Only the layout may change, the new formats must
+    /// hold the same color spaces with the same number of channels. This is not available for
+    /// floating point transforms, nor for those fed with 8 bits, which may have been optimized
+    /// for 8 bits only.
+    pub fn change_buffers_format(
+        &mut self,
+        input_format: Format,
+        output_format: Format,
+    ) -> Result<()> {
+        if self.flags & transform_flags::CAN_CHANGE_FORMATTER == 0 {
+            return err!(self.context_id, Error, NotSuitable, "change_buffers_format works only on transforms created originally with at least 16 bits of precision"; str => "Transform formats can't be changed");
+        }
+
+        if input_format.float() || output_format.float() {
+            return err!(self.context_id, Error, NotSuitable, "change_buffers_format can't switch a transform to floating point"; str => "Transform formats can't be changed");
+        }
+
+        if !is_compatible(
+            self.input_format,
+            input_format,
+            self.lut.get_input_channels(),
+        ) || !is_compatible(
+            self.output_format,
+            output_format,
+            self.lut.get_output_channels(),
+        ) {
+            return err!(self.context_id, Error, ColorspaceCheck, "New formats don't match the color spaces of the transform"; str => "Wrong color space on new formats");
+        }
+
+        if self.flags & transform_flags::COPY_ALPHA == 0 && is_premultiplied(output_format) {
+            return err!(self.context_id, Error, NotSuitable, "Premultiplied output formats need COPY_ALPHA"; str => "Premultiplied output needs COPY_ALPHA");
+        }
+
+        let (from_input, to_output) = match get_formatters_16(
+            &self.context_id,
+            input_format,
+            output_format,
+        ) {
+            (Some(from_input), Some(to_output)) => (from_input, to_output),
+            _ => {
+                return err!(self.context_id, Error, UnknownExtension, "Unsupported raster format"; str => "Unsupported raster format")
+            }
+        };
+
+        self.input_format = input_format;
+        self.output_format = output_format;
+        self.from_input = Some(from_input);
+        self.to_output = Some(to_output);
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Stride {
+    pub per_line_in: usize,
+    pub per_line_out: usize,
+    pub per_plane_in: usize,
+    pub per_plane_out: usize,
+}
+
+/// Number of bytes a buffer of `format` spans for `line_count` lines of `pixels_per_line`
+/// pixels, `None` on overflow.
+fn buffer_size(
+    format: Format,
+    pixels_per_line: usize,
+    line_count: usize,
+    per_line: usize,
+    per_plane: usize,
+) -> Option<usize> {
+    let n_samples = format.channels() as usize + format.extra() as usize;
+    if pixels_per_line == 0 || line_count == 0 || n_samples == 0 {
+        return Some(0);
+    }
+
+    let line_size = if format.planar() {
+        (n_samples - 1)
+            .checked_mul(per_plane)?
+            .checked_add(pixels_per_line.checked_mul(sample_size(format))?)?
+    } else {
+        pixels_per_line
+            .checked_mul(n_samples)?
+            .checked_mul(sample_size(format))?
+    };
+
+    (line_count - 1)
+        .checked_mul(per_line)?
+        .checked_add(line_size)
+}
+
+fn get_formatters_16(
+    context_id: &Context,
+    input_format: Format,
+    output_format: Format,
+) -> (Option<FormatterIn16>, Option<FormatterOut16>) {
+    let from_input = match context_id.get_formatter_in(input_format.bits(), pack_flags::BITS_16) {
+        FormatterIn::U16(from_input) => from_input,
+        FormatterIn::F32(_) => None,
+    };
+    let to_output = match context_id.get_formatter_out(output_format.bits(), pack_flags::BITS_16) {
+        FormatterOut::U16(to_output) => to_output,
+        FormatterOut::F32(_) => None,
+    };
+
+    (from_input, to_output)
+}
+
+fn get_formatters_float(
+    context_id: &Context,
+    input_format: Format,
+    output_format: Format,
+) -> (Option<FormatterInFloat>, Option<FormatterOutFloat>) {
+    let from_input = match context_id.get_formatter_in(input_format.bits(), pack_flags::FLOAT) {
+        FormatterIn::F32(from_input) => from_input,
+        FormatterIn::U16(_) => None,
+    };
+    let to_output = match context_id.get_formatter_out(output_format.bits(), pack_flags::FLOAT) {
+        FormatterOut::F32(to_output) => to_output,
+        FormatterOut::U16(_) => None,
+    };
+
+    (from_input, to_output)
+}
+
+/// Whether a buffer of format `new` holds the same color space as `old`, with `channels`
+/// channels.
+fn is_compatible(old: Format, new: Format, channels: usize) -> bool {
+    let is_lab = |space| space == pixel_type::LAB || space == pixel_type::LAB_V2;
+    let old = old.colorspace() as u32;
+    let new_space = new.colorspace() as u32;
+
+    new.channels() as usize == channels
+        && (old == pixel_type::ANY
+            || new_space == pixel_type::ANY
+            || old == new_space
+            || (is_lab(old) && is_lab(new_space)))
+}
+
+/// Whether the colorants of `format` are premultiplied by alpha. Those are packed with the
+/// alpha found in the output buffer, which only [`transform_flags::COPY_ALPHA`] puts there.
+fn is_premultiplied(format: Format) -> bool {
+    format.premul() && format.extra() != 0
+}
+
+pub mod transform_flags {
+    /// Don't keep the result of the last pixel to skip transforming it again.
+    pub const NOCACHE: u32 = 0x0040;
+    /// Don't let plugins optimize the pipeline, or take over the transform.
+    pub const NOOPTIMIZE: u32 = 0x0100;
+    /// Don't transform, only convert between the buffer formats.
+    pub const NULLTRANSFORM: u32 = 0x0200;
+    /// Use more grid points when precalculating device links.
+    pub const HIGHRESPRECALC: u32 = 0x0400;
+    /// Use less grid points when precalculating device links.
+    pub const LOWRESPRECALC: u32 = 0x0800;
+    /// Compensate the black points of the profiles being linked.
+    pub const BLACKPOINTCOMPENSATION: u32 = 0x2000;
+    /// Set on transforms whose buffer formats can be changed after creation.
+    pub const CAN_CHANGE_FORMATTER: u32 = 0x02000000;
+    /// Copy the extra channels, like alpha, from the input to the output buffer.
+    pub const COPY_ALPHA: u32 = 0x04000000;
+
+    /// Fixes the number of grid points of precalculated device links to `n`.
+    pub const fn grid_points(n: u32) -> u32 {
+        (n & 0xFF) << 16
+    }
+}
+
+/// The number of grid points a precalculated device link from `color_space` should have,
+/// unless set through [`transform_flags::grid_points`] in `flags`.
+pub fn reasonable_gridpoints_by_color_space(color_space: Signature, flags: u32) -> usize {
+    // Already specified?
+    if flags & 0x00FF0000 != 0 {
+        return ((flags >> 16) & 0xFF) as usize;
+    }
+
+    let n_chans = channels_of_color_space(color_space).unwrap_or(3);
+
+    // HighResPrecalc is maximum resolution
+    if flags & transform_flags::HIGHRESPRECALC != 0 {
+        return match n_chans {
+            n if n > 4 => 7,
+            4 => 23,
+            _ => 49,
+        };
+    }
+
+    // LowResPrecal is lower resolution
+    if flags & transform_flags::LOWRESPRECALC != 0 {
+        return match n_chans {
+            n if n > 4 => 6,
+            1 => 33,
+            _ => 17,
+        };
+    }
+
+    // Default values
+    match n_chans {
+        n if n > 4 => 7,
+        4 => 17,
+        _ => 33,
+    }
+}
+
+mod alpha;
+mod link;
+mod xform;
+
+use xform::*;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,1314 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::Level;
+
+use crate::{
+    plugin::{intent, Plugin, TagTypeHandler},
+    sig,
+    state::{Context, ErrorCode, Intent, DEFAULT_CONTEXT},
+    types::{
+        adapt_to_illuminant, adaptation_matrix, bytes_sh, channels_sh, colorspace_sh, cone_matrix,
+        illuminant_type, partial_adaptation_matrix, pixel_type, surround, temp_from_white_point,
+        white_point_from_temp, Format, ICtCp, IccViewingConditions, Jzazbz, Mat3, OkLab, Pipeline,
+        Profile, Signature, Stage, StageLoc, ToneCurve, Vec3, XYZ,
+    },
+    Result, D50, MAX_ENCODEABLE_XYZ,
+};
+
+use super::{transform_flags, Stride, Transform};
+
+fn transform(input_format: Format, output_format: Format, flags: u32) -> Transform {
+    let lut = Pipeline::new(
+        &DEFAULT_CONTEXT,
+        input_format.channels() as usize,
+        output_format.channels() as usize,
+    )
+    .unwrap();
+
+    Transform::new(
+        &DEFAULT_CONTEXT,
+        lut,
+        0,
+        input_format,
+        output_format,
+        flags | transform_flags::NULLTRANSFORM,
+    )
+    .unwrap()
+}
+
+fn chunky_stride(bytes_in: usize, bytes_out: usize) -> Stride {
+    Stride {
+        per_line_in: bytes_in,
+        per_line_out: bytes_out,
+        per_plane_in: 0,
+        per_plane_out: 0,
+    }
+}
+
+#[test]
+fn extra_channels_are_opt_in() {
+    let xform = transform(Format::RGBA_8, Format::RGBA_8, 0);
+    let input = [1u8, 2, 3, 4];
+    let mut output = [0u8; 4];
+
+    xform.handle_extra_channels(&input, &mut output, 1, 1, &chunky_stride(4, 4));
+
+    assert_eq!(output, [0; 4]);
+}
+
+#[test]
+fn extra_channels_follow_channel_order() {
+    let stride = chunky_stride(8, 8);
+    let input = [1u8, 2, 3, 0xA1, 4, 5, 6, 0xA2];
+
+    let xform = transform(Format::RGBA_8, Format::ARGB_8, transform_flags::COPY_ALPHA);
+    let mut output = [0u8; 8];
+    xform.handle_extra_channels(&input, &mut output, 2, 1, &stride);
+    assert_eq!(output, [0xA1, 0, 0, 0, 0xA2, 0, 0, 0]);
+
+    let xform = transform(Format::ABGR_8, Format::BGRA_8, transform_flags::COPY_ALPHA);
+    let mut output = [0u8; 8];
+    xform.handle_extra_channels(&input, &mut output, 2, 1, &stride);
+    assert_eq!(output, [0, 0, 0, 1, 0, 0, 0, 4]);
+}
+
+#[test]
+fn extra_channels_convert_bit_depth() {
+    let xform = transform(Format::RGBA_8, Format::RGBA_16, transform_flags::COPY_ALPHA);
+    let input = [1u8, 2, 3, 0x80];
+    let mut output = [0u8; 8];
+    xform.handle_extra_channels(&input, &mut output, 1, 1, &chunky_stride(4, 8));
+    assert_eq!(output[6..8], 0x8080u16.to_ne_bytes());
+
+    let xform = transform(
+        Format::RGBA_16,
+        Format::RGBA_FLT,
+        transform_flags::COPY_ALPHA,
+    );
+    let mut input = [0u8; 8];
+    input[6..8].copy_from_slice(&0xffffu16.to_ne_bytes());
+    let mut output = [0u8; 16];
+    xform.handle_extra_channels(&input, &mut output, 1, 1, &chunky_stride(8, 16));
+    assert_eq!(output[12..16], 1f32.to_ne_bytes());
+
+    let xform = transform(
+        Format::RGBA_FLT,
+        Format::RGBA_8,
+        transform_flags::COPY_ALPHA,
+    );
+    let mut input = [0u8; 16];
+    input[12..16].copy_from_slice(&0.5f32.to_ne_bytes());
+    let mut output = [0u8; 4];
+    xform.handle_extra_channels(&input, &mut output, 1, 1, &chunky_stride(16, 4));
+    assert_eq!(output[3], 0x80);
+}
+
+#[test]
+fn extra_channels_handle_planes_and_lines() {
+    // Two lines of two pixels, planes of 2 bytes with lines padded to 10 bytes
+    let xform = transform(
+        Format::RGBA_8_PLANAR,
+        Format::RGBA_8,
+        transform_flags::COPY_ALPHA,
+    );
+    let stride = Stride {
+        per_line_in: 10,
+        per_line_out: 8,
+        per_plane_in: 2,
+        per_plane_out: 0,
+    };
+
+    #[rustfmt::skip]
+    let input = [
+        0, 0, 0, 0, 0, 0, 0xA1, 0xA2, 0xFF, 0xFF,
+        0, 0, 0, 0, 0, 0, 0xA3, 0xA4, 0xFF, 0xFF,
+    ];
+    let mut output = [0u8; 16];
+    xform.handle_extra_channels(&input, &mut output, 2, 2, &stride);
+
+    assert_eq!(
+        output,
+        [0, 0, 0, 0xA1, 0, 0, 0, 0xA2, 0, 0, 0, 0xA3, 0, 0, 0, 0xA4]
+    );
+}
+
+/// Pipeline swapping the first and last of three channels.
+fn swap_pipeline() -> Pipeline {
+    #[rustfmt::skip]
+    let matrix = [
+        0.0, 0.0, 1.0,
+        0.0, 1.0, 0.0,
+        1.0, 0.0, 0.0,
+    ];
+
+    let mut lut = Pipeline::new(&DEFAULT_CONTEXT, 3, 3).unwrap();
+    lut.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(&DEFAULT_CONTEXT, 3, 3, &matrix, None).unwrap(),
+    )
+    .unwrap();
+
+    lut
+}
+
+fn swap_transform(input_format: Format, output_format: Format, flags: u32) -> Result<Transform> {
+    Transform::new(
+        &DEFAULT_CONTEXT,
+        swap_pipeline(),
+        0,
+        input_format,
+        output_format,
+        flags,
+    )
+}
+
+#[test]
+fn transforms_run_the_pipeline() -> Result<()> {
+    #[rustfmt::skip]
+    let input = [
+        0u8, 0, 0,
+        1, 2, 3,
+        1, 2, 3,
+        4, 5, 6,
+        0, 0, 0,
+    ];
+    #[rustfmt::skip]
+    let expected = [
+        0u8, 0, 0,
+        3, 2, 1,
+        3, 2, 1,
+        6, 5, 4,
+        0, 0, 0,
+    ];
+
+    // With and without cache
+    for flags in [0, transform_flags::NOCACHE] {
+        let xform = swap_transform(Format::RGB_8, Format::RGB_8, flags)?;
+        let mut output = [0xFFu8; 15];
+
+        xform.do_transform(&input, &mut output, 5)?;
+        assert_eq!(output, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn transforms_run_in_floating_point() -> Result<()> {
+    let xform = swap_transform(Format::RGB_FLT, Format::RGB_16, 0)?;
+    let input = [0.25f32, 0.5, 1.0]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect::<Vec<_>>();
+    let mut output = [0u8; 6];
+
+    xform.do_transform(&input, &mut output, 1)?;
+
+    let output = output
+        .chunks(2)
+        .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+        .collect::<Vec<_>>();
+    assert_eq!(output, [0xffff, 0x8000, 0x4000]);
+
+    Ok(())
+}
+
+#[test]
+fn transforms_follow_line_and_plane_strides() -> Result<()> {
+    let xform = swap_transform(Format::RGB_8, Format::RGB_8_PLANAR, 0)?;
+
+    // Two lines of two pixels, input lines padded to 8 bytes
+    #[rustfmt::skip]
+    let input = [
+        1u8, 2, 3, 4, 5, 6, 0xEE, 0xEE,
+        7, 8, 9, 10, 11, 12, 0xEE, 0xEE,
+    ];
+    let stride = Stride {
+        per_line_in: 8,
+        per_line_out: 2,
+        per_plane_in: 0,
+        per_plane_out: 4,
+    };
+    let mut output = [0u8; 12];
+
+    xform.do_transform_line_stride(&input, &mut output, 2, 2, stride)?;
+
+    #[rustfmt::skip]
+    assert_eq!(output, [
+        3, 6, 9, 12,
+        2, 5, 8, 11,
+        1, 4, 7, 10,
+    ]);
+
+    // A whole planar image
+    let xform = swap_transform(Format::RGB_8_PLANAR, Format::RGB_8, 0)?;
+    let mut back = [0u8; 12];
+    xform.do_transform(&output, &mut back, 4)?;
+    assert_eq!(back, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+    Ok(())
+}
+
+#[test]
+fn transforms_reject_buffers_too_small() -> Result<()> {
+    let xform = swap_transform(Format::RGB_8, Format::RGB_8_PLANAR, 0)?;
+    let mut output = [0u8; 12];
+
+    assert!(xform.do_transform(&[0; 11], &mut output, 4).is_err());
+    assert!(xform.do_transform(&[0; 12], &mut output[..11], 4).is_err());
+    assert!(xform
+        .do_transform(&[0; 3], &mut output, usize::MAX)
+        .is_err());
+
+    // The last plane starts after the stride of the other two
+    let stride = Stride {
+        per_line_in: 0,
+        per_line_out: 0,
+        per_plane_in: 0,
+        per_plane_out: 5,
+    };
+    assert!(xform
+        .do_transform_line_stride(&[0; 12], &mut output, 4, 1, stride)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn transforms_copy_alpha_on_demand() -> Result<()> {
+    let mut lut = Pipeline::new(&DEFAULT_CONTEXT, 3, 3)?;
+    lut.cat(&swap_pipeline())?;
+
+    let xform = Transform::new(
+        &DEFAULT_CONTEXT,
+        lut,
+        0,
+        Format::RGBA_8,
+        Format::ARGB_8,
+        transform_flags::COPY_ALPHA,
+    )?;
+    let input = [1u8, 2, 3, 0x80];
+    let mut output = [0u8; 4];
+    xform.do_transform(&input, &mut output, 1)?;
+    assert_eq!(output, [0x80, 3, 2, 1]);
+
+    // Both buffers need the same extra channels
+    assert!(swap_transform(Format::RGBA_8, Format::RGB_8, transform_flags::COPY_ALPHA).is_err());
+    assert!(swap_transform(Format::RGBA_8, Format::RGB_8, 0).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn transforms_need_alpha_for_premultiplied_output() -> Result<()> {
+    // Without the alpha copied, packing would use whatever alpha the output buffer held
+    assert!(swap_transform(Format::RGBA_8, Format::RGBA_8_PREMUL, 0).is_err());
+    assert!(swap_transform(Format::RGBA_8_PREMUL, Format::RGBA_8, 0).is_ok());
+
+    let xform = swap_transform(
+        Format::RGBA_8,
+        Format::RGBA_8_PREMUL,
+        transform_flags::COPY_ALPHA,
+    )?;
+    let mut output = [0u8; 4];
+    xform.do_transform(&[255, 0, 0x80, 0x80], &mut output, 1)?;
+    assert_eq!(output, [0x40, 0, 0x80, 0x80]);
+
+    let mut xform = swap_transform(Format::RGBA_16, Format::RGBA_16, 0)?;
+    assert!(xform
+        .change_buffers_format(Format::RGBA_16, Format::RGBA_16_PREMUL)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn transforms_without_formats_signal_errors() -> Result<()> {
+    static ERRORS: AtomicUsize = AtomicUsize::new(0);
+    fn count_errors(_: &Context, _: Level, _: ErrorCode, _: &str) {
+        ERRORS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let ctx = DEFAULT_CONTEXT.clone();
+    ctx.set_error_logger(Some(count_errors));
+    let mut xform = Transform::new(
+        &ctx,
+        swap_pipeline(),
+        0,
+        Format::from(0),
+        Format::from(0),
+        0,
+    )?;
+
+    // Nothing to read the buffers with yet
+    let mut output = [0u8; 3];
+    xform.do_transform(&[1, 2, 3], &mut output, 1)?;
+    assert_eq!(ERRORS.load(Ordering::Relaxed), 1);
+    assert_eq!(output, [0, 0, 0]);
+
+    xform.change_buffers_format(Format::RGB_8, Format::RGB_8)?;
+    xform.do_transform(&[1, 2, 3], &mut output, 1)?;
+    assert_eq!(ERRORS.load(Ordering::Relaxed), 1);
+    assert_eq!(output, [3, 2, 1]);
+
+    Ok(())
+}
+
+#[test]
+fn transforms_check_channels() {
+    assert!(swap_transform(Format::CMYK_8, Format::RGB_8, 0).is_err());
+    assert!(swap_transform(Format::RGB_8, Format::GRAY_8, 0).is_err());
+}
+
+#[test]
+fn transforms_change_buffers_format() -> Result<()> {
+    let mut xform = swap_transform(Format::RGB_16, Format::RGB_16, 0)?;
+
+    xform.change_buffers_format(Format::BGR_8, Format::RGB_8)?;
+    let mut output = [0u8; 3];
+    xform.do_transform(&[1, 2, 3], &mut output, 1)?;
+    assert_eq!(output, [1, 2, 3]);
+
+    // The color spaces are fixed
+    assert!(xform
+        .change_buffers_format(Format::CMYK_8, Format::RGB_8)
+        .is_err());
+    assert!(xform
+        .change_buffers_format(Format::RGB_8, Format::LAB_8)
+        .is_err());
+    assert!(xform
+        .change_buffers_format(Format::RGB_FLT, Format::RGB_8)
+        .is_err());
+    assert!(xform.get_input_format() == Format::BGR_8);
+
+    // Transforms fed with 8 bits may be optimized for them
+    let mut xform = swap_transform(Format::RGB_8, Format::RGB_16, 0)?;
+    assert!(xform
+        .change_buffers_format(Format::RGB_16, Format::RGB_16)
+        .is_err());
+
+    Ok(())
+}
+
+/// Links into [`swap_pipeline`], failing unless black point compensation is enabled on every
+/// profile.
+fn swap_intent(
+    _: &Context,
+    _: usize,
+    _: Box<[u32]>,
+    _: Box<[Profile]>,
+    bpc: Box<[bool]>,
+    _: Box<[f64]>,
+    _: u32,
+) -> Result<Pipeline> {
+    if bpc.iter().all(|bpc| *bpc) {
+        Ok(swap_pipeline())
+    } else {
+        Err("Black point compensation is disabled")
+    }
+}
+
+static TEST_INTENTS: &[Intent] = &[
+    Intent {
+        value: intent::PERCEPTUAL,
+        desc: "Swap",
+        r#fn: swap_intent,
+    },
+    Intent {
+        value: intent::RELATIVE_COLORIMETRIC,
+        desc: "Swap",
+        r#fn: swap_intent,
+    },
+    Intent {
+        value: intent::ABSOLUTE_COLORIMETRIC,
+        desc: "Swap",
+        r#fn: swap_intent,
+    },
+];
+static TEST_INTENTS_PLUGIN: Plugin = Plugin::create_intents_plugin(&TEST_INTENTS);
+
+fn rgb_display_profile(context_id: &Context, version: f64) -> Profile {
+    let mut profile = Profile::new(context_id);
+    profile.set_version(version);
+    profile.set_device_class(sig::class::DISPLAY);
+    profile.set_color_space(sig::colorspace::RGB);
+    profile.set_pcs(sig::colorspace::XYZ);
+
+    profile
+}
+
+#[test]
+fn transforms_link_profiles() -> Result<()> {
+    let ctx = DEFAULT_CONTEXT.register_plugins(&[&TEST_INTENTS_PLUGIN])?;
+
+    let xform = Transform::new_with_profiles(
+        &ctx,
+        rgb_display_profile(&ctx, 4.3),
+        Format::RGB_8,
+        rgb_display_profile(&ctx, 4.3),
+        Format::RGB_8,
+        intent::PERCEPTUAL,
+        0,
+    )?;
+
+    let mut output = [0u8; 3];
+    xform.do_transform(&[1, 2, 3], &mut output, 1)?;
+    assert_eq!(output, [3, 2, 1]);
+
+    Ok(())
+}
+
+#[test]
+fn transforms_force_black_point_compensation_on_v4() -> Result<()> {
+    let ctx = DEFAULT_CONTEXT.register_plugins(&[&TEST_INTENTS_PLUGIN])?;
+    let link = |version, intent, flags| {
+        Transform::new_multiprofile(
+            &ctx,
+            vec![
+                rgb_display_profile(&ctx, version),
+                rgb_display_profile(&ctx, version),
+            ],
+            Format::RGB_8,
+            Format::RGB_8,
+            intent,
+            flags,
+        )
+    };
+
+    assert!(link(4.3, intent::PERCEPTUAL, 0).is_ok());
+    assert!(link(2.1, intent::PERCEPTUAL, 0).is_err());
+    assert!(link(4.3, intent::RELATIVE_COLORIMETRIC, 0).is_err());
+    assert!(link(
+        4.3,
+        intent::RELATIVE_COLORIMETRIC,
+        transform_flags::BLACKPOINTCOMPENSATION
+    )
+    .is_ok());
+
+    // Never on absolute colorimetric, and the reason linking failed is kept
+    assert_eq!(
+        link(
+            4.3,
+            intent::ABSOLUTE_COLORIMETRIC,
+            transform_flags::BLACKPOINTCOMPENSATION
+        )
+        .err(),
+        Some("Black point compensation is disabled")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn transforms_check_profiles() -> Result<()> {
+    let ctx = DEFAULT_CONTEXT.register_plugins(&[&TEST_INTENTS_PLUGIN])?;
+    let link = |input_format, output_format, intent| {
+        Transform::new_with_profiles(
+            &ctx,
+            rgb_display_profile(&ctx, 4.3),
+            input_format,
+            rgb_display_profile(&ctx, 4.3),
+            output_format,
+            intent,
+            0,
+        )
+    };
+
+    assert!(link(Format::CMYK_8, Format::RGB_8, intent::PERCEPTUAL).is_err());
+    assert!(link(Format::RGB_8, Format::CMYK_8, intent::PERCEPTUAL).is_err());
+    assert!(link(Format::RGB_8, Format::RGB_8, 0x1234).is_err());
+
+    Ok(())
+}
+
+/// A v4 RGB display profile whose primaries only touch one XYZ channel each, so RGB is XYZ
+/// relative to D50.
+fn diagonal_matrix_shaper(
+    ctx: &Context,
+    pcs: Signature,
+    white_point: Option<XYZ>,
+) -> Result<Profile> {
+    let mut profile = rgb_display_profile(ctx, 4.3);
+    profile.set_pcs(pcs);
+
+    let colorant = |x, y, z| Box::new(XYZ { x, y, z });
+    profile.write_tag(sig::tags::RED_COLORANT, colorant(D50.x, 0.0, 0.0))?;
+    profile.write_tag(sig::tags::GREEN_COLORANT, colorant(0.0, D50.y, 0.0))?;
+    profile.write_tag(sig::tags::BLUE_COLORANT, colorant(0.0, 0.0, D50.z))?;
+
+    for trc in [
+        sig::tags::RED_TRC,
+        sig::tags::GREEN_TRC,
+        sig::tags::BLUE_TRC,
+    ] {
+        profile.write_tag(trc, Box::new(ToneCurve::build_gamma(ctx, 1.0)?))?;
+    }
+
+    if let Some(white_point) = white_point {
+        profile.write_tag(sig::tags::MEDIA_WHITE_POINT, Box::new(white_point))?;
+    }
+
+    Ok(profile)
+}
+
+fn rgb_8_transform(input: Profile, output: Profile, intent: u32) -> Result<[u8; 3]> {
+    let xform = Transform::new_with_profiles(
+        &DEFAULT_CONTEXT,
+        input,
+        Format::RGB_8,
+        output,
+        Format::RGB_8,
+        intent,
+        0,
+    )?;
+
+    let mut output = [0u8; 3];
+    xform.do_transform(&[255, 255, 255], &mut output, 1)?;
+
+    Ok(output)
+}
+
+#[test]
+fn default_context_supports_icc_intents() {
+    let mut intents = Vec::new();
+    DEFAULT_CONTEXT.get_supported_intents(10, &mut intents);
+
+    for value in [
+        intent::PERCEPTUAL,
+        intent::RELATIVE_COLORIMETRIC,
+        intent::SATURATION,
+        intent::ABSOLUTE_COLORIMETRIC,
+    ] {
+        assert!(intents.iter().any(|(i, _)| *i == value));
+    }
+}
+
+#[test]
+fn matrix_shapers_map_white_to_the_pcs_white() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let profile = diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?;
+
+    let mut xyz = [0f32; 3];
+    profile
+        .read_input_lut(intent::PERCEPTUAL)?
+        .eval_f32(&[1.0, 1.0, 1.0], &mut xyz);
+
+    for (xyz, d50) in xyz.iter().zip([D50.x, D50.y, D50.z]) {
+        assert!((*xyz as f64 * MAX_ENCODEABLE_XYZ - d50).abs() < 1e-4);
+    }
+
+    let mut rgb = [0f32; 3];
+    profile
+        .read_output_lut(intent::PERCEPTUAL)?
+        .eval_f32(&xyz, &mut rgb);
+
+    for rgb in rgb {
+        assert!((rgb - 1.0).abs() < 1e-4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn icc_intents_convert_between_lab_and_xyz() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let output = rgb_8_transform(
+        diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?,
+        diagonal_matrix_shaper(ctx, sig::colorspace::LAB, None)?,
+        intent::RELATIVE_COLORIMETRIC,
+    )?;
+    assert_eq!(output, [255, 255, 255]);
+
+    Ok(())
+}
+
+#[test]
+fn absolute_colorimetric_scales_media_white() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let dim_white = XYZ {
+        x: D50.x / 2.0,
+        y: D50.y / 2.0,
+        z: D50.z / 2.0,
+    };
+
+    let relative = rgb_8_transform(
+        diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, Some(dim_white))?,
+        diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?,
+        intent::RELATIVE_COLORIMETRIC,
+    )?;
+    assert_eq!(relative, [255, 255, 255]);
+
+    let absolute = rgb_8_transform(
+        diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, Some(dim_white))?,
+        diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?,
+        intent::ABSOLUTE_COLORIMETRIC,
+    )?;
+    assert_eq!(absolute, [128, 128, 128]);
+
+    Ok(())
+}
+
+/// A v2 RGB printer with 16 bits LUTs and a Lab PCS, written by lcms 2. Its colorimetric input
+/// table is a lut8, see `testdata/generate.py`.
+static LUT16_PROFILE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/lut16.icc"));
+
+#[test]
+fn v2_lab_luts_are_promoted_to_v4() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let profile = Profile::open_from_mem(ctx, LUT16_PROFILE)?;
+
+    let input = profile.read_input_lut(intent::PERCEPTUAL)?;
+    assert!(input.last_stage().unwrap().get_implements() == sig::mpe_stage::LAB_V2_TO_V4);
+
+    // 8 bits encode Lab the same in both versions
+    let input = profile.read_input_lut(intent::RELATIVE_COLORIMETRIC)?;
+    assert!(input.last_stage().unwrap().get_implements() != sig::mpe_stage::LAB_V2_TO_V4);
+
+    // Relative colorimetric falls back to the perceptual table
+    let output = profile.read_output_lut(intent::RELATIVE_COLORIMETRIC)?;
+    assert!(output.first_stage().unwrap().get_implements() == sig::mpe_stage::LAB_V4_TO_V2);
+
+    Ok(())
+}
+
+#[test]
+fn default_context_supports_black_preserving_intents() {
+    let mut intents = Vec::new();
+    DEFAULT_CONTEXT.get_supported_intents(20, &mut intents);
+
+    for (value, desc) in [
+        (
+            intent::PRESERVE_K_ONLY_PERCEPTUAL,
+            "Perceptual preserving black ink",
+        ),
+        (
+            intent::PRESERVE_K_PLANE_RELATIVE_COLORIMETRIC,
+            "Relative colorimetric preserving black plane",
+        ),
+    ] {
+        assert!(intents.contains(&(value, desc)));
+    }
+}
+
+/// A v2 CMYK printer where every ink lowers L* by a quarter, and CMY pull a* and b* apart. The
+/// output table rebuilds the color from CMY alone, never using K.
+fn cmyk_printer_profile(ctx: &Context) -> Result<Profile> {
+    let mut profile = Profile::new(ctx);
+    profile.set_version(2.1);
+    profile.set_device_class(sig::class::OUTPUT);
+    profile.set_color_space(sig::colorspace::CMYK);
+    profile.set_pcs(sig::colorspace::LAB);
+
+    #[rustfmt::skip]
+    let cmyk_to_lab = [
+        -0.25, -0.25, -0.25, -0.25,
+        -0.25, 0.25, 0.0, 0.0,
+        0.0, -0.25, 0.25, 0.0,
+    ];
+    let mut a_to_b = Pipeline::new(ctx, 4, 3)?;
+    a_to_b.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(ctx, 3, 4, &cmyk_to_lab, Some(&[1.0, 0.5, 0.5]))?,
+    )?;
+
+    #[rustfmt::skip]
+    let lab_to_cmyk = [
+        -4.0 / 3.0, -8.0 / 3.0, -4.0 / 3.0,
+        -4.0 / 3.0, 4.0 / 3.0, -4.0 / 3.0,
+        -4.0 / 3.0, 4.0 / 3.0, 8.0 / 3.0,
+        0.0, 0.0, 0.0,
+    ];
+    let mut b_to_a = Pipeline::new(ctx, 3, 4)?;
+    b_to_a.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(
+            ctx,
+            4,
+            3,
+            &lab_to_cmyk,
+            Some(&[10.0 / 3.0, 4.0 / 3.0, -2.0 / 3.0, 0.0]),
+        )?,
+    )?;
+
+    profile.write_tag(sig::tags::A_TO_B0, Box::new(a_to_b))?;
+    profile.write_tag(sig::tags::B_TO_A0, Box::new(b_to_a))?;
+
+    Ok(profile)
+}
+
+fn cmyk_8_transform(ctx: &Context, intent: u32, input: &[u8]) -> Result<Vec<u8>> {
+    let xform = Transform::new_with_profiles(
+        ctx,
+        cmyk_printer_profile(ctx)?,
+        Format::CMYK_8,
+        cmyk_printer_profile(ctx)?,
+        Format::CMYK_8,
+        intent,
+        0,
+    )?;
+
+    let mut output = vec![0u8; input.len()];
+    xform.do_transform(input, &mut output, input.len() / 4)?;
+
+    Ok(output)
+}
+
+fn assert_close(actual: &[u8], expected: &[u8]) {
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!(
+            actual.abs_diff(*expected) <= 1,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn icc_intents_rebuild_black_from_cmy() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let output = cmyk_8_transform(ctx, intent::PERCEPTUAL, &[0, 0, 0, 255])?;
+    assert_close(&output, &[85, 85, 85, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn k_only_intents_keep_pure_black() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let output = cmyk_8_transform(
+        ctx,
+        intent::PRESERVE_K_ONLY_PERCEPTUAL,
+        &[0, 0, 0, 255, 0, 0, 0, 128, 51, 51, 51, 128],
+    )?;
+
+    // Only pure K is preserved, the rest is colorimetric
+    assert_close(&output, &[0, 0, 0, 255, 0, 0, 0, 128, 94, 94, 94, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn k_plane_intents_keep_black_and_match_cmy() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let output = cmyk_8_transform(
+        ctx,
+        intent::PRESERVE_K_PLANE_RELATIVE_COLORIMETRIC,
+        &[0, 0, 0, 255, 51, 51, 51, 128],
+    )?;
+    assert_close(&output, &[0, 0, 0, 255, 51, 51, 51, 128]);
+
+    Ok(())
+}
+
+/// Display RGB profiles written by lcms 2, with D50 diagonal colorants so RGB is XYZ. The TRCs
+/// of the diagonal one are linear, those of the raised black one never go below 0.05, so its
+/// black is 5% of D50.
+static DIAGONAL_PROFILE: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/testdata/diagonal.icc"
+));
+static RAISED_BLACK_PROFILE: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/testdata/raised_black.icc"
+));
+
+/// A v2 gray balanced CMYK printer whose darkest black is L* = 20, written by lcms 2. The output
+/// table puts the same amount of every ink, clipped and raised to 1.5, so the darkest colors are
+/// lost.
+static CMYK_PROFILE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/cmyk.icc"));
+
+/// Black points and transform results below are the ones lcms 2 gives on the same profiles, as
+/// printed by `testdata/generate.py`. Black points have to be within 1e-5 of them, in XYZ
+/// relative to D50.
+fn assert_black_point(black_point: Option<XYZ>, expected: [f64; 3]) {
+    let black_point = black_point.expect("No black point detected");
+
+    for (actual, expected) in [black_point.x, black_point.y, black_point.z]
+        .iter()
+        .zip(expected)
+    {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn matrix_shaper_black_points_match_lcms() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let profile = Profile::open_from_mem(ctx, RAISED_BLACK_PROFILE)?;
+
+    for intent in [
+        intent::PERCEPTUAL,
+        intent::RELATIVE_COLORIMETRIC,
+        intent::SATURATION,
+    ] {
+        let expected = [0.0482132, 0.0500033, 0.0412477];
+        assert_black_point(profile.detect_black_point(intent, 0), expected);
+        assert_black_point(profile.detect_destination_black_point(intent, 0), expected);
+    }
+
+    // Absolute colorimetric never compensates black
+    assert!(profile
+        .detect_black_point(intent::ABSOLUTE_COLORIMETRIC, 0)
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn cmyk_black_points_match_lcms() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let profile = Profile::open_from_mem(ctx, CMYK_PROFILE)?;
+
+    let darkest = [0.0290085, 0.0300855, 0.0248176];
+    assert_black_point(profile.detect_black_point(intent::PERCEPTUAL, 0), darkest);
+    assert_black_point(
+        profile.detect_black_point(intent::RELATIVE_COLORIMETRIC, 0),
+        darkest,
+    );
+    assert_black_point(
+        profile.detect_destination_black_point(intent::RELATIVE_COLORIMETRIC, 0),
+        darkest,
+    );
+
+    // The shadows of the round trip are a straight line, which lcms 2 clips to 0
+    assert_black_point(
+        profile.detect_destination_black_point(intent::PERCEPTUAL, 0),
+        [0.0, 0.0, 0.0],
+    );
+
+    // There is no saturation table to detect the black with
+    assert!(profile.detect_black_point(intent::SATURATION, 0).is_none());
+
+    Ok(())
+}
+
+fn rgb_16_transform(
+    ctx: &Context,
+    input: Profile,
+    input_format: Format,
+    output: Profile,
+    flags: u32,
+    pixels: &[u16],
+) -> Result<Vec<u16>> {
+    let xform = Transform::new_with_profiles(
+        ctx,
+        input,
+        input_format,
+        output,
+        Format::RGB_16,
+        intent::RELATIVE_COLORIMETRIC,
+        flags,
+    )?;
+
+    let n_pixels = pixels.len() / input_format.channels() as usize;
+    let input = pixels
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect::<Vec<_>>();
+    let mut output = vec![0u8; n_pixels * 6];
+
+    xform.do_transform(&input, &mut output, n_pixels)?;
+
+    Ok(output
+        .chunks(2)
+        .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+        .collect())
+}
+
+/// Transform results may be off by 1 from lcms2, as rounding may differ on some platforms.
+fn assert_close_16(actual: &[u16], expected: &[u16]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!(a.abs_diff(*e) <= 1, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn black_point_compensation_maps_black_to_black() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let pixels = [0, 0, 0, 32768, 32768, 32768];
+
+    let output = rgb_16_transform(
+        ctx,
+        Profile::open_from_mem(ctx, RAISED_BLACK_PROFILE)?,
+        Format::RGB_16,
+        Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?,
+        0,
+        &pixels,
+    )?;
+    assert_close_16(&output, &[3277, 3277, 3277, 16077, 16077, 16077]);
+
+    let output = rgb_16_transform(
+        ctx,
+        Profile::open_from_mem(ctx, RAISED_BLACK_PROFILE)?,
+        Format::RGB_16,
+        Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?,
+        transform_flags::BLACKPOINTCOMPENSATION,
+        &pixels,
+    )?;
+    assert_close_16(&output, &[0, 0, 0, 13475, 13474, 13475]);
+
+    let output = rgb_16_transform(
+        ctx,
+        Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?,
+        Format::RGB_16,
+        Profile::open_from_mem(ctx, RAISED_BLACK_PROFILE)?,
+        transform_flags::BLACKPOINTCOMPENSATION,
+        &pixels,
+    )?;
+    assert_close_16(&output, &[0, 0, 0, 49111, 49111, 49111]);
+
+    Ok(())
+}
+
+#[test]
+fn black_point_compensation_goes_through_lab() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let pixels = [65535, 65535, 65535, 65535, 32768, 32768, 32768, 32768];
+
+    let output = rgb_16_transform(
+        ctx,
+        Profile::open_from_mem(ctx, CMYK_PROFILE)?,
+        Format::CMYK_16,
+        Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?,
+        0,
+        &pixels,
+    )?;
+    assert_close_16(&output, &[1972, 1972, 1972, 18602, 18602, 18602]);
+
+    let output = rgb_16_transform(
+        ctx,
+        Profile::open_from_mem(ctx, CMYK_PROFILE)?,
+        Format::CMYK_16,
+        Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?,
+        transform_flags::BLACKPOINTCOMPENSATION,
+        &pixels,
+    )?;
+    assert_close_16(&output, &[0, 0, 0, 17146, 17146, 17146]);
+
+    Ok(())
+}
+
+const D65: XYZ = XYZ {
+    x: 0.95047,
+    y: 1.0,
+    z: 1.08883,
+};
+
+/// The `chad` tag of sRGB profiles, exact in s15Fixed16.
+const SRGB_CHAD: Mat3 = Mat3::new([
+    [1.047882080078125, 0.022918701171875, -0.0502166748046875],
+    [0.0295867919921875, 0.990478515625, -0.0170745849609375],
+    [-0.009246826171875, 0.01507568359375, 0.751678466796875],
+]);
+
+fn assert_xyz(actual: XYZ, expected: [f64; 3], tolerance: f64) {
+    let actual = [actual.x, actual.y, actual.z];
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < tolerance, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn adaptation_matrices_map_the_illuminants() -> Result<()> {
+    for cone in [
+        cone_matrix::BRADFORD,
+        cone_matrix::VON_KRIES,
+        cone_matrix::CAT02,
+        cone_matrix::CAT16,
+    ] {
+        let m = adaptation_matrix(&cone, &D65, &D50)?;
+        assert_xyz((m * Vec3::from(D65)).into(), [D50.x, D50.y, D50.z], 1e-9);
+
+        let back = adaptation_matrix(&cone, &D50, &D65)?;
+        assert!((back * m).is_identity());
+
+        assert_eq!(partial_adaptation_matrix(&cone, &D65, &D50, 1.0)?, m);
+        assert!(partial_adaptation_matrix(&cone, &D65, &D50, 0.0)?.is_identity());
+
+        // Half adapted whites land in between
+        let half = partial_adaptation_matrix(&cone, &D65, &D50, 0.5)? * Vec3::from(D65);
+        assert!(half.0[0] > D50.x.min(D65.x) && half.0[0] < D50.x.max(D65.x));
+        assert!(half.0[2] > D50.z && half.0[2] < D65.z);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn white_points_match_lcms() -> Result<()> {
+    let adapted = adapt_to_illuminant(&D65, &D50, &XYZ::new(0.5, 0.4, 0.3))?;
+    assert_xyz(adapted, [0.5180249, 0.4058539, 0.2269540], 1e-7);
+
+    for (temp, expected) in [
+        (5000.0, [0.9639633, 1.0, 0.8241448]),
+        (6504.0, [0.9501547, 1.0, 1.0882591]),
+        (9300.0, [0.9529880, 1.0, 1.4127361]),
+    ] {
+        assert_xyz(white_point_from_temp(temp)?.into(), expected, 1e-7);
+    }
+    assert!(white_point_from_temp(3000.0).is_err());
+
+    assert!((temp_from_white_point(&D65.into())? - 6502.0822).abs() < 1e-4);
+    assert!((temp_from_white_point(&D50.into())? - 5000.7261).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn chad_tags_round_trip() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut profile = diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?;
+    assert_eq!(profile.read_chad()?, Mat3::IDENTITY);
+
+    profile.write_chad(&SRGB_CHAD)?;
+    let reloaded = Profile::open_from_mem(ctx, &profile.save_to_mem()?)?;
+    assert_eq!(reloaded.read_chad()?, SRGB_CHAD);
+
+    // v2 display profiles are adapted from their media white
+    let mut v2 = rgb_display_profile(ctx, 2.1);
+    v2.write_tag(sig::tags::MEDIA_WHITE_POINT, Box::new(D65))?;
+    assert_eq!(
+        v2.read_chad()?,
+        adaptation_matrix(&cone_matrix::BRADFORD, &D65, &D50)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn viewing_conditions_tags_round_trip() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut profile = diagonal_matrix_shaper(ctx, sig::colorspace::XYZ, None)?;
+    assert!(profile.read_viewing_conditions().is_err());
+
+    profile.write_tag(
+        sig::tags::VIEWING_CONDITIONS,
+        Box::new(IccViewingConditions {
+            illuminant: XYZ::new(D65.x * 80.0, 80.0, D65.z * 80.0),
+            surround: XYZ::new(D65.x * 4.0, 4.0, D65.z * 4.0),
+            illuminant_type: illuminant_type::D65,
+        }),
+    )?;
+    let reloaded = Profile::open_from_mem(ctx, &profile.save_to_mem()?)?;
+    let conditions = reloaded.read_viewing_conditions()?;
+
+    assert_xyz(conditions.white_point, [D65.x, D65.y, D65.z], 1e-4);
+    assert_eq!(conditions.la, 16.0);
+    assert_eq!(conditions.surround, surround::DIM);
+
+    Ok(())
+}
+
+#[test]
+fn absolute_colorimetric_follows_the_adaptation_state() -> Result<()> {
+    let white_point = XYZ {
+        x: 0.9,
+        y: 0.95,
+        z: 0.7,
+    };
+    let pixels = [65535, 65535, 65535, 32768, 32768, 32768];
+
+    for (adaptation_state, expected) in [
+        (1.0, [61171, 62258, 55612, 30586, 31130, 27807]),
+        (0.0, [60300, 62258, 65535, 30150, 31129, 36711]),
+        (0.5, [60417, 62108, 62609, 30209, 31054, 31305]),
+    ] {
+        let ctx = DEFAULT_CONTEXT.clone();
+        ctx.set_adaptation_state(adaptation_state);
+
+        let mut input = diagonal_matrix_shaper(&ctx, sig::colorspace::XYZ, Some(white_point))?;
+        input.write_chad(&SRGB_CHAD)?;
+
+        let xform = Transform::new_with_profiles(
+            &ctx,
+            input,
+            Format::RGB_16,
+            diagonal_matrix_shaper(&ctx, sig::colorspace::XYZ, None)?,
+            Format::RGB_16,
+            intent::ABSOLUTE_COLORIMETRIC,
+            0,
+        )?;
+
+        let input = pixels
+            .iter()
+            .flat_map(|v: &u16| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let mut output = [0u8; 12];
+        xform.do_transform(&input, &mut output, 2)?;
+
+        let output = output
+            .chunks(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>();
+        assert_close_16(&output, &expected);
+    }
+
+    Ok(())
+}
+
+/// The XYZ identity abstract profile of lcms 2, to reach an output profile from the PCS.
+static XYZ_IDENTITY_PROFILE: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/testdata/xyz_identity.icc"
+));
+
+/// A float transform through the diagonal matrix shaper, whose RGB is XYZ relative to D50, from
+/// or to a perceptually uniform `format`.
+fn uniform_space_transform(format: Format, to_uniform: bool) -> Result<Transform> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let profile = Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?;
+    let (profiles, input_format, output_format) = if to_uniform {
+        (vec![profile], Format::RGB_DBL, format)
+    } else {
+        (
+            vec![Profile::open_from_mem(ctx, XYZ_IDENTITY_PROFILE)?, profile],
+            format,
+            Format::RGB_DBL,
+        )
+    };
+
+    Transform::new_multiprofile(
+        ctx,
+        profiles,
+        input_format,
+        output_format,
+        intent::RELATIVE_COLORIMETRIC,
+        transform_flags::NOCACHE,
+    )
+}
+
+/// Transforms a pixel of three doubles.
+fn transform_dbl(xform: &Transform, input: &[f64; 3]) -> Result<[f64; 3]> {
+    let input = input
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect::<Vec<_>>();
+    let mut output = [0u8; 24];
+
+    xform.do_transform(&input, &mut output, 1)?;
+
+    Ok([0, 1, 2].map(|i| f64::from_ne_bytes(output[i * 8..(i + 1) * 8].try_into().unwrap())))
+}
+
+#[test]
+fn transforms_target_uniform_spaces() -> Result<()> {
+    let bradford = adaptation_matrix(&cone_matrix::BRADFORD, &D50, &crate::D65)?;
+    // The profile takes RGB to XYZ by scaling it by the D50 white
+    let to_d65 = |rgb: &[f64; 3], luminance: f64| {
+        let xyz = Vec3::new(rgb[0] * D50.x, rgb[1] * D50.y, rgb[2] * D50.z);
+        let [x, y, z] = (bradford * xyz).0.map(|v| v * luminance);
+        XYZ::new(x, y, z)
+    };
+
+    for format in [Format::OKLAB_DBL, Format::JZAZBZ_DBL, Format::ICTCP_DBL] {
+        let forward = uniform_space_transform(format, true)?;
+        let reverse = uniform_space_transform(format, false)?;
+
+        for rgb in [
+            [1.0, 1.0, 1.0],
+            [0.6, 0.4, 0.3],
+            [0.3, 0.4, 0.7],
+            [0.2, 0.3, 0.1],
+        ] {
+            let expected: [f64; 3] = match format.colorspace() as u32 {
+                pixel_type::OKLAB => OkLab::from_xyz(&to_d65(&rgb, 1.0)).into(),
+                pixel_type::JZAZBZ => Jzazbz::from_xyz(&to_d65(&rgb, 203.0)).into(),
+                _ => ICtCp::from_xyz(&to_d65(&rgb, 203.0)).into(),
+            };
+
+            let value = transform_dbl(&forward, &rgb)?;
+            for (v, e) in value.iter().zip(expected) {
+                assert!((v - e).abs() < 1e-5, "{:?} != {:?}", value, expected);
+            }
+
+            let back = transform_dbl(&reverse, &value)?;
+            for (b, e) in back.iter().zip(rgb) {
+                assert!((b - e).abs() < 1e-4, "{:?} != {:?}", back, rgb);
+            }
+        }
+    }
+
+    // The D50 white is the D65 white of every space
+    let white = transform_dbl(
+        &uniform_space_transform(Format::OKLAB_DBL, true)?,
+        &[1.0, 1.0, 1.0],
+    )?;
+    assert!((white[0] - 1.0).abs() < 1e-4 && white[1].abs() < 1e-3 && white[2].abs() < 1e-3);
+
+    Ok(())
+}
+
+#[test]
+fn uniform_spaces_need_float_and_pcs() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let oklab_16 = Format::from(colorspace_sh(pixel_type::OKLAB) | channels_sh(3) | bytes_sh(2));
+    assert!(uniform_space_transform(oklab_16, true).is_err());
+
+    // Device colors can't be uniform
+    let profile = Profile::open_from_mem(ctx, DIAGONAL_PROFILE)?;
+    assert!(Transform::new_multiprofile(
+        ctx,
+        vec![profile],
+        Format::OKLAB_DBL,
+        Format::XYZ_DBL,
+        intent::RELATIVE_COLORIMETRIC,
+        0,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn uniform_space_stages_round_trip() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    for (forward, reverse) in [
+        (
+            Stage::alloc_xyz_to_oklab(ctx)?,
+            Stage::alloc_oklab_to_xyz(ctx)?,
+        ),
+        (
+            Stage::alloc_xyz_to_jzazbz(ctx, 1000.0)?,
+            Stage::alloc_jzazbz_to_xyz(ctx, 1000.0)?,
+        ),
+        (
+            Stage::alloc_xyz_to_ictcp(ctx, 1000.0)?,
+            Stage::alloc_ictcp_to_xyz(ctx, 1000.0)?,
+        ),
+    ] {
+        assert_eq!(forward.get_input_channels(), 3);
+        assert_eq!(reverse.get_output_channels(), 3);
+
+        let mut lut = Pipeline::new(ctx, 3, 3)?;
+        lut.insert_stage(StageLoc::AtEnd, forward)?;
+        lut.insert_stage(StageLoc::AtEnd, reverse)?;
+
+        for xyz in [[0.1f32, 0.2, 0.3], [0.45, 0.5, 0.4], [0.02, 0.01, 0.03]] {
+            let mut out = [0f32; 3];
+            lut.eval_f32(&xyz, &mut out);
+
+            for (o, x) in out.iter().zip(xyz) {
+                assert!((o - x).abs() < 1e-5, "{:?} != {:?}", out, xyz);
+            }
+        }
+    }
+
+    assert!(Stage::alloc_xyz_to_jzazbz(ctx, 0.0).is_err());
+
+    Ok(())
+}
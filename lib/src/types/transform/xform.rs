@@ -0,0 +1,203 @@
+use log::Level;
+
+use crate::{state::ErrorCode, MAX_CHANNELS};
+
+use super::{Stride, Transform};
+
+/// Signals a transform run without formatters, as those created with formats of zero are
+/// until [`Transform::change_buffers_format`] gives them some.
+fn missing_formatters(p: &Transform) {
+    p.context_id.signal_error(
+        Level::Error,
+        ErrorCode::NotSuitable,
+        "The transform has no formatters, set the buffer formats first",
+    );
+}
+
+// 16 bits
+
+/// Only converts between the buffer formats.
+pub(super) fn null_xform(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let (unroll, pack) = match (p.from_input, p.to_output) {
+        (Some(unroll), Some(pack)) => (unroll, pack),
+        _ => return missing_formatters(p),
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    let mut w_in = [0u16; MAX_CHANNELS];
+
+    for i in 0..line_count {
+        let mut accum = &in_buf[i * stride.per_line_in..];
+        let mut output = &mut out_buf[i * stride.per_line_out..];
+
+        for _ in 0..pixels_per_line {
+            accum = unroll(p, &mut w_in, accum, stride.per_plane_in as u32);
+            output = pack(p, &w_in, output, stride.per_plane_out as u32);
+        }
+    }
+}
+
+/// Evaluates the pipeline on every pixel.
+pub(super) fn precalculated_xform(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let (unroll, pack) = match (p.from_input, p.to_output) {
+        (Some(unroll), Some(pack)) => (unroll, pack),
+        _ => return missing_formatters(p),
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    let mut w_in = [0u16; MAX_CHANNELS];
+    let mut w_out = [0u16; MAX_CHANNELS];
+
+    for i in 0..line_count {
+        let mut accum = &in_buf[i * stride.per_line_in..];
+        let mut output = &mut out_buf[i * stride.per_line_out..];
+
+        for _ in 0..pixels_per_line {
+            accum = unroll(p, &mut w_in, accum, stride.per_plane_in as u32);
+            p.lut.eval_16(&w_in, &mut w_out);
+            output = pack(p, &w_out, output, stride.per_plane_out as u32);
+        }
+    }
+}
+
+/// Evaluates the pipeline only when a pixel differs from the previous one.
+pub(super) fn cached_xform(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let (unroll, pack) = match (p.from_input, p.to_output) {
+        (Some(unroll), Some(pack)) => (unroll, pack),
+        _ => return missing_formatters(p),
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    let mut w_in = [0u16; MAX_CHANNELS];
+    let mut cache = p.cache;
+
+    for i in 0..line_count {
+        let mut accum = &in_buf[i * stride.per_line_in..];
+        let mut output = &mut out_buf[i * stride.per_line_out..];
+
+        for _ in 0..pixels_per_line {
+            accum = unroll(p, &mut w_in, accum, stride.per_plane_in as u32);
+
+            if w_in != cache.cache_in {
+                p.lut.eval_16(&w_in, &mut cache.cache_out);
+                cache.cache_in = w_in;
+            }
+
+            output = pack(p, &cache.cache_out, output, stride.per_plane_out as u32);
+        }
+    }
+}
+
+// Floating point
+
+/// Only converts between the buffer formats.
+pub(super) fn null_float_xform(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let (unroll, pack) = match (p.from_input_float, p.to_output_float) {
+        (Some(unroll), Some(pack)) => (unroll, pack),
+        _ => return missing_formatters(p),
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    let mut f_in = [0f32; MAX_CHANNELS];
+
+    for i in 0..line_count {
+        let mut accum = &in_buf[i * stride.per_line_in..];
+        let mut output = &mut out_buf[i * stride.per_line_out..];
+
+        for _ in 0..pixels_per_line {
+            accum = unroll(p, &mut f_in, accum, stride.per_plane_in as u32);
+            output = pack(p, &f_in, output, stride.per_plane_out as u32);
+        }
+    }
+}
+
+pub(super) fn float_xform(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let (unroll, pack) = match (p.from_input_float, p.to_output_float) {
+        (Some(unroll), Some(pack)) => (unroll, pack),
+        _ => return missing_formatters(p),
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    let mut f_in = [0f32; MAX_CHANNELS];
+    let mut f_out = [0f32; MAX_CHANNELS];
+
+    for i in 0..line_count {
+        let mut accum = &in_buf[i * stride.per_line_in..];
+        let mut output = &mut out_buf[i * stride.per_line_out..];
+
+        for _ in 0..pixels_per_line {
+            accum = unroll(p, &mut f_in, accum, stride.per_plane_in as u32);
+            p.lut.eval_f32(&f_in, &mut f_out);
+            output = pack(p, &f_out, output, stride.per_plane_out as u32);
+        }
+    }
+}
+
+/// Runs the worker of a plugin using the old, single line, [`TransformFn`] signature.
+///
+/// [`TransformFn`]: crate::plugin::TransformFn
+pub(super) fn transform_2_to_transform_adaptor(
+    p: &Transform,
+    in_buf: &[u8],
+    out_buf: &mut [u8],
+    pixels_per_line: usize,
+    line_count: usize,
+    stride: Stride,
+) {
+    let old_xform = match p.old_xform {
+        Some(old_xform) => old_xform,
+        None => return,
+    };
+
+    p.handle_extra_channels(in_buf, out_buf, pixels_per_line, line_count, &stride);
+
+    for i in 0..line_count {
+        old_xform(
+            p,
+            &in_buf[i * stride.per_line_in..],
+            &mut out_buf[i * stride.per_line_out..],
+            pixels_per_line,
+            stride.per_plane_in,
+        );
+    }
+}
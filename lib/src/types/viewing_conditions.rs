@@ -0,0 +1,25 @@
+use super::XYZ;
+
+/// The contents of a viewing conditions tag.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IccViewingConditions {
+    /// The illuminant, not normalized, with Y in cd/m².
+    pub illuminant: XYZ,
+    /// The surround, not normalized, with Y in cd/m².
+    pub surround: XYZ,
+    /// One of [`illuminant_type`].
+    pub illuminant_type: u32,
+}
+
+/// Standard illuminants, as measurement and viewing conditions tags encode them.
+pub mod illuminant_type {
+    pub const UNKNOWN: u32 = 0x0000;
+    pub const D50: u32 = 0x0001;
+    pub const D65: u32 = 0x0002;
+    pub const D93: u32 = 0x0003;
+    pub const F2: u32 = 0x0004;
+    pub const D55: u32 = 0x0005;
+    pub const A: u32 = 0x0006;
+    pub const E: u32 = 0x0007;
+    pub const F8: u32 = 0x0008;
+}
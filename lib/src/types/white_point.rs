@@ -0,0 +1,170 @@
+use crate::{Result, MATRIX_DET_TOLERANCE};
+
+use super::{xyY, Mat3, Vec3, XYZ};
+
+/// Matrices taking XYZ to the cone response domain of the chromatic adaptation transforms.
+pub mod cone_matrix {
+    use crate::types::Mat3;
+
+    /// Lam & Rigg, the transform ICC profiles use.
+    pub const BRADFORD: Mat3 = Mat3::new([
+        [0.8951, 0.2664, -0.1614],
+        [-0.7502, 1.7135, 0.0367],
+        [0.0389, -0.0685, 1.0296],
+    ]);
+
+    /// Hunt-Pointer-Estévez, normalized to D65.
+    pub const VON_KRIES: Mat3 = Mat3::new([
+        [0.40024, 0.70760, -0.08081],
+        [-0.22630, 1.16532, 0.04570],
+        [0.0, 0.0, 0.91822],
+    ]);
+
+    /// The transform of CIECAM02.
+    pub const CAT02: Mat3 = Mat3::new([
+        [0.7328, 0.4296, -0.1624],
+        [-0.7036, 1.6975, 0.0061],
+        [0.0030, 0.0136, 0.9834],
+    ]);
+
+    /// The transform of CAM16.
+    pub const CAT16: Mat3 = Mat3::new([
+        [0.401288, 0.650173, -0.051461],
+        [-0.250268, 1.204414, 0.045854],
+        [-0.002079, 0.048952, 0.953127],
+    ]);
+}
+
+/// Builds the matrix taking colors seen under `from_illuminant` to the colors matching them
+/// under `to_illuminant`, by scaling the cone responses `cone_matrix` computes.
+pub fn adaptation_matrix(
+    cone_matrix: &Mat3,
+    from_illuminant: &XYZ,
+    to_illuminant: &XYZ,
+) -> Result<Mat3> {
+    partial_adaptation_matrix(cone_matrix, from_illuminant, to_illuminant, 1.0)
+}
+
+/// As [`adaptation_matrix`], for an observer adapted to `to_illuminant` only by
+/// `adaptation_state`. 1 is a fully adapted observer, and 0 a non adapted one, which keeps the
+/// colors unchanged.
+pub fn partial_adaptation_matrix(
+    cone_matrix: &Mat3,
+    from_illuminant: &XYZ,
+    to_illuminant: &XYZ,
+    adaptation_state: f64,
+) -> Result<Mat3> {
+    let cone_matrix_inv = match cone_matrix.inverse() {
+        Some(inv) => inv,
+        None => return err!(str => "Cone matrix is not invertible"),
+    };
+
+    let cone_source = *cone_matrix * Vec3::from(*from_illuminant);
+    let cone_dest = *cone_matrix * Vec3::from(*to_illuminant);
+
+    if cone_source.0.iter().any(|c| c.abs() < MATRIX_DET_TOLERANCE) {
+        return err!(str => "Illuminant has no cone response");
+    }
+
+    // The degree of adaptation blends each cone gain with no gain at all
+    let [l, m, s] = [0, 1, 2]
+        .map(|i| adaptation_state * cone_dest.0[i] / cone_source.0[i] + (1.0 - adaptation_state));
+    let cone = Mat3::diagonal(l, m, s);
+
+    Ok(cone_matrix_inv * (cone * *cone_matrix))
+}
+
+/// Adapts `value`, seen under `source_white_point`, to `illuminant` with the Bradford
+/// transform.
+pub fn adapt_to_illuminant(source_white_point: &XYZ, illuminant: &XYZ, value: &XYZ) -> Result<XYZ> {
+    let bradford = adaptation_matrix(&cone_matrix::BRADFORD, source_white_point, illuminant)?;
+
+    Ok((bradford * Vec3::from(*value)).into())
+}
+
+/// The white point of the CIE daylight illuminant of correlated color temperature `temp_k`,
+/// with Y = 1. Only temperatures from 4000K to 25000K are defined.
+pub fn white_point_from_temp(temp_k: f64) -> Result<xyY> {
+    let t = temp_k;
+    let t2 = t * t; // Square
+    let t3 = t2 * t; // Cube
+
+    let x = if (4000.0..=7000.0).contains(&t) {
+        // For correlated color temperature (T) between 4000K and 7000K:
+        -4.6070 * (1e9 / t3) + 2.9678 * (1e6 / t2) + 0.09911 * (1e3 / t) + 0.244063
+    } else if t > 7000.0 && t <= 25000.0 {
+        // or for correlated color temperature (T) between 7000K and 25000K:
+        -2.0064 * (1e9 / t3) + 1.9018 * (1e6 / t2) + 0.24748 * (1e3 / t) + 0.237040
+    } else {
+        return err!(str => "Invalid white point temperature");
+    };
+
+    // Obtain y(x)
+    let y = -3.000 * (x * x) + 2.870 * x - 0.275;
+
+    Ok(xyY { x, y, Y: 1.0 })
+}
+
+/// The correlated color temperature of `white_point`, by Robertson's method. Fails if the white
+/// is too far away from the blackbody locus.
+pub fn temp_from_white_point(white_point: &xyY) -> Result<f64> {
+    let xs = white_point.x;
+    let ys = white_point.y;
+
+    // convert (x,y) to CIE 1960 (u,v)
+    let us = (2.0 * xs) / (-xs + 6.0 * ys + 1.5);
+    let vs = (3.0 * ys) / (-xs + 6.0 * ys + 1.5);
+
+    let mut di = 0.0;
+    let mut mi = 0.0;
+    for (j, &(mj, uj, vj, tj)) in ISOTEMPERATURE_DATA.iter().enumerate() {
+        let dj = ((vs - vj) - tj * (us - uj)) / (1.0 + tj * tj).sqrt();
+
+        if j != 0 && di / dj < 0.0 {
+            // Found a match
+            return Ok(1_000_000.0 / (mi + (di / (di - dj)) * (mj - mi)));
+        }
+
+        di = dj;
+        mi = mj;
+    }
+
+    err!(str => "White point temperature not found")
+}
+
+/// The isotemperature lines of Robertson's method: temperature in microreciprocal kelvin, u and v
+/// coords of the intersection with the blackbody locus, and slope of the line.
+#[rustfmt::skip]
+const ISOTEMPERATURE_DATA: [(f64, f64, f64, f64); 31] = [
+    (0.0,   0.18006, 0.26352, -0.24341),
+    (10.0,  0.18066, 0.26589, -0.25479),
+    (20.0,  0.18133, 0.26846, -0.26876),
+    (30.0,  0.18208, 0.27119, -0.28539),
+    (40.0,  0.18293, 0.27407, -0.30470),
+    (50.0,  0.18388, 0.27709, -0.32675),
+    (60.0,  0.18494, 0.28021, -0.35156),
+    (70.0,  0.18611, 0.28342, -0.37915),
+    (80.0,  0.18740, 0.28668, -0.40955),
+    (90.0,  0.18880, 0.28997, -0.44278),
+    (100.0, 0.19032, 0.29326, -0.47888),
+    (125.0, 0.19462, 0.30141, -0.58204),
+    (150.0, 0.19962, 0.30921, -0.70471),
+    (175.0, 0.20525, 0.31647, -0.84901),
+    (200.0, 0.21142, 0.32312, -1.0182),
+    (225.0, 0.21807, 0.32909, -1.2168),
+    (250.0, 0.22511, 0.33439, -1.4512),
+    (275.0, 0.23247, 0.33904, -1.7298),
+    (300.0, 0.24010, 0.34308, -2.0637),
+    (325.0, 0.24702, 0.34655, -2.4681),
+    (350.0, 0.25591, 0.34951, -2.9641),
+    (375.0, 0.26400, 0.35200, -3.5814),
+    (400.0, 0.27218, 0.35407, -4.3633),
+    (425.0, 0.28039, 0.35577, -5.3762),
+    (450.0, 0.28863, 0.35714, -6.7262),
+    (475.0, 0.29685, 0.35823, -8.5955),
+    (500.0, 0.30505, 0.35907, -11.324),
+    (525.0, 0.31320, 0.35968, -15.628),
+    (550.0, 0.32129, 0.36011, -23.325),
+    (575.0, 0.32931, 0.36038, -40.770),
+    (600.0, 0.33724, 0.36051, -116.45),
+];
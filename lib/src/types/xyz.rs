@@ -1,4 +1,4 @@
-use crate::S15Fixed16Number;
+use crate::{quick_saturate_word, S15Fixed16Number, D50, MAX_ENCODEABLE_XYZ};
 
 #[repr(C)]
 pub struct XYZNumber {
@@ -8,8 +8,85 @@ pub struct XYZNumber {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct XYZ {
     pub x: f64,
     pub y: f64,
     pub z: f64,
 }
+
+/// A chromaticity, along with its luminance `Y`.
+#[allow(non_camel_case_types, non_snake_case)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct xyY {
+    pub x: f64,
+    pub y: f64,
+    pub Y: f64,
+}
+
+impl XYZ {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Decodes 16 bits ICC XYZ, 1.15 fixed point.
+    pub fn decode(xyz: &[u16; 3]) -> Self {
+        Self {
+            x: xyz[0] as f64 / 32768.0,
+            y: xyz[1] as f64 / 32768.0,
+            z: xyz[2] as f64 / 32768.0,
+        }
+    }
+
+    /// Encodes the color as 16 bits ICC XYZ, 1.15 fixed point. Colors without luminance are
+    /// encoded as black, and every component is clamped to the encodeable range.
+    pub fn encode(&self) -> [u16; 3] {
+        if self.y <= 0.0 {
+            return [0, 0, 0];
+        }
+
+        [self.x, self.y, self.z]
+            .map(|v| quick_saturate_word(v.clamp(0.0, MAX_ENCODEABLE_XYZ) * 32768.0))
+    }
+}
+
+impl From<[f64; 3]> for XYZ {
+    fn from(value: [f64; 3]) -> Self {
+        Self::new(value[0], value[1], value[2])
+    }
+}
+
+impl From<XYZ> for [f64; 3] {
+    fn from(value: XYZ) -> Self {
+        [value.x, value.y, value.z]
+    }
+}
+
+impl From<XYZ> for xyY {
+    /// Black has no chromaticity, it gets the one of D50.
+    fn from(value: XYZ) -> Self {
+        let sum = value.x + value.y + value.z;
+        if sum == 0.0 {
+            let d50 = Self::from(D50);
+            return Self { Y: 0.0, ..d50 };
+        }
+
+        let i_sum = 1.0 / sum;
+
+        Self {
+            x: value.x * i_sum,
+            y: value.y * i_sum,
+            Y: value.y,
+        }
+    }
+}
+
+impl From<xyY> for XYZ {
+    fn from(value: xyY) -> Self {
+        Self {
+            x: (value.x / value.y) * value.Y,
+            y: value.Y,
+            z: ((1.0 - value.x - value.y) / value.y) * value.Y,
+        }
+    }
+}
@@ -0,0 +1,300 @@
+"""Builds the LUT based test profiles with Little CMS 2 and prints the values lcms evaluates
+them to. Needs liblcms2 2.14 or later; run from this directory."""
+
+import ctypes as C
+import math
+
+lcms = C.CDLL("liblcms2.so.2")
+P = C.c_void_p
+
+
+def fn(name, res, *args):
+    f = getattr(lcms, name)
+    f.restype = res
+    f.argtypes = list(args)
+    return f
+
+
+CreatePlaceholder = fn("cmsCreateProfilePlaceholder", P, P)
+SetVersion = fn("cmsSetProfileVersion", None, P, C.c_double)
+SetClass = fn("cmsSetDeviceClass", None, P, C.c_uint32)
+SetColorSpace = fn("cmsSetColorSpace", None, P, C.c_uint32)
+SetPCS = fn("cmsSetPCS", None, P, C.c_uint32)
+WriteTag = fn("cmsWriteTag", C.c_int, P, C.c_uint32, P)
+ReadTag = fn("cmsReadTag", P, P, C.c_uint32)
+SaveToMem = fn("cmsSaveProfileToMem", C.c_int, P, P, P)
+OpenFromMem = fn("cmsOpenProfileFromMem", P, C.c_char_p, C.c_uint32)
+CloseProfile = fn("cmsCloseProfile", C.c_int, P)
+PipelineAlloc = fn("cmsPipelineAlloc", P, P, C.c_uint32, C.c_uint32)
+InsertStage = fn("cmsPipelineInsertStage", C.c_int, P, C.c_int, P)
+SaveAs8Bits = fn("cmsPipelineSetSaveAs8bitsFlag", C.c_int, P, C.c_int)
+EvalFloat = fn("cmsPipelineEvalFloat", None, P, P, P)
+AllocToneCurves = fn("cmsStageAllocToneCurves", P, P, C.c_uint32, P)
+AllocMatrix = fn("cmsStageAllocMatrix", P, P, C.c_uint32, C.c_uint32, P, P)
+AllocCLut16 = fn("cmsStageAllocCLut16bitGranular", P, P, P, C.c_uint32, C.c_uint32, P)
+AllocCLutFloat = fn("cmsStageAllocCLutFloatGranular", P, P, P, C.c_uint32, C.c_uint32, P)
+Tabulated16 = fn("cmsBuildTabulatedToneCurve16", P, P, C.c_uint32, P)
+Parametric = fn("cmsBuildParametricToneCurve", P, P, C.c_int32, P)
+Segmented = fn("cmsBuildSegmentedToneCurve", P, P, C.c_uint32, P)
+
+
+class Segment(C.Structure):
+    _fields_ = [("x0", C.c_float), ("x1", C.c_float), ("Type", C.c_int32),
+                ("Params", C.c_double * 10), ("nGridPoints", C.c_uint32),
+                ("SampledPoints", P)]
+
+
+def sig(s):
+    return int.from_bytes(s.encode("latin1"), "big")
+
+
+def u16s(values):
+    return (C.c_uint16 * len(values))(*values)
+
+
+def f32s(values):
+    return (C.c_float * len(values))(*values)
+
+
+def f64s(values):
+    return (C.c_double * len(values))(*values)
+
+
+def tabulated(n, f):
+    return Tabulated16(None, n, u16s([round(65535 * f(i / (n - 1))) for i in range(n)]))
+
+
+def parametric(kind, params):
+    return Parametric(None, kind, f64s(params))
+
+
+def curves(*curves):
+    return AllocToneCurves(None, len(curves), (P * len(curves))(*curves))
+
+
+def matrix(rows, cols, values, offset=None):
+    return AllocMatrix(None, rows, cols, f64s(values), f64s(offset) if offset else None)
+
+
+def nodes(grid, n_in):
+    """Every node of a CLUT, the last input varying fastest, as values in 0..1."""
+    if n_in == 0:
+        yield ()
+        return
+    for i in range(grid[0]):
+        for rest in nodes(grid[1:], n_in - 1):
+            yield (i / (grid[0] - 1),) + rest
+
+
+def clut16(grid, n_in, n_out, f):
+    table = [round(65535 * min(max(v, 0.0), 1.0)) for x in nodes(grid, n_in) for v in f(x)]
+    return AllocCLut16(None, (C.c_uint32 * len(grid))(*grid), n_in, n_out, u16s(table))
+
+
+def clut_float(grid, n_in, n_out, f):
+    table = [v for x in nodes(grid, n_in) for v in f(x)]
+    return AllocCLutFloat(None, (C.c_uint32 * len(grid))(*grid), n_in, n_out, f32s(table))
+
+
+def pipeline(n_in, n_out, *stages, eight_bits=False):
+    lut = PipelineAlloc(None, n_in, n_out)
+    for stage in stages:
+        assert InsertStage(lut, 1, stage)
+    if eight_bits:
+        SaveAs8Bits(lut, 1)
+    return lut
+
+
+def profile(version, space, pcs, tags):
+    h = CreatePlaceholder(None)
+    SetVersion(h, version)
+    SetClass(h, sig("prtr"))
+    SetColorSpace(h, sig(space))
+    SetPCS(h, sig(pcs))
+    for tag, lut in tags:
+        assert WriteTag(h, sig(tag), lut)
+
+    n = C.c_uint32(0)
+    SaveToMem(h, None, C.byref(n))
+    buf = C.create_string_buffer(n.value)
+    assert SaveToMem(h, buf, C.byref(n))
+    CloseProfile(h)
+    return buf.raw[: n.value]
+
+
+def to_lab(x):
+    r, g, b = x
+    return (0.2 + 0.6 * (r * g + b) / 2, 0.5 + 0.3 * (r - g) * b, 0.5 - 0.25 * math.sin(r + b))
+
+
+def from_lab(x):
+    l, a, b = x
+    return (l * l, (l + a) / 2, math.sqrt(b) * (1 - a / 3))
+
+
+SAMPLES = [(0.0, 0.0, 0.0), (0.1, 0.5, 0.9), (0.33, 0.66, 0.25), (0.8, 0.2, 0.6), (1.0, 1.0, 1.0)]
+
+# Version 2: 16 and 8 bits LUTs
+lut16 = profile(2.1, "RGB ", "Lab ", [
+    ("A2B0", pipeline(3, 3,
+        matrix(3, 3, [0.9, 0.1, 0.0, 0.05, 0.9, 0.05, 0.0, 0.2, 0.8]),
+        curves(tabulated(256, lambda x: x ** 2.2), tabulated(256, lambda x: x ** 1.8),
+               tabulated(256, lambda x: x)),
+        clut16([5, 5, 5], 3, 3, to_lab),
+        curves(tabulated(64, lambda x: x ** 0.9), tabulated(64, lambda x: x),
+               tabulated(64, lambda x: math.sqrt(x))))),
+    ("A2B1", pipeline(3, 3,
+        curves(tabulated(256, lambda x: x ** 1.5), tabulated(256, lambda x: x),
+               tabulated(256, lambda x: 1 - (1 - x) ** 2)),
+        clut16([4, 4, 4], 3, 3, to_lab),
+        curves(tabulated(256, lambda x: x), tabulated(256, lambda x: x ** 1.2),
+               tabulated(256, lambda x: x)),
+        eight_bits=True)),
+    ("B2A0", pipeline(3, 3,
+        curves(tabulated(128, lambda x: x ** 0.8), tabulated(128, lambda x: x),
+               tabulated(128, lambda x: x)),
+        clut16([5, 5, 5], 3, 3, from_lab),
+        curves(tabulated(2, lambda x: x), tabulated(2, lambda x: x),
+               tabulated(2, lambda x: x)))),
+])
+
+# Version 4: lutAtoB, lutBtoA and multi process elements
+segments = (Segment * 3)(
+    Segment(-1e22, 0.0, 6, (C.c_double * 10)(1.0, 1.0, 0.0, 0.0)),
+    Segment(0.0, 1.0, 6, (C.c_double * 10)(2.4, 1.0, 0.0, 0.0)),
+    Segment(1.0, 1e22, 6, (C.c_double * 10)(1.0, 1.0, 0.0, 0.0)),
+)
+lut_ab = profile(4.3, "RGB ", "Lab ", [
+    ("A2B0", pipeline(3, 3,
+        curves(parametric(1, [2.2]), parametric(4, [2.4, 1 / 1.055, 0.055 / 1.055, 1 / 12.92, 0.04045]),
+               tabulated(32, lambda x: x ** 1.3)),
+        clut16([3, 4, 5], 3, 3, to_lab),
+        curves(parametric(2, [1.5, 1.1, -0.1]), parametric(1, [1.0]), parametric(3, [2.0, 1.0, 0.0, 0.5])),
+        matrix(3, 3, [0.8, 0.1, 0.1, 0.0, 0.9, 0.1, 0.05, 0.05, 0.9], [0.01, -0.02, 0.03]),
+        curves(parametric(1, [1.1]), parametric(1, [0.9]), parametric(5, [1.8, 1.0, 0.0, 0.2, 0.1, 0.01, 0.02])))),
+    ("B2A0", pipeline(3, 3,
+        curves(parametric(1, [0.9]), parametric(1, [1.0]), parametric(1, [1.1])),
+        matrix(3, 3, [1.0, 0.1, 0.0, 0.0, 1.0, -0.1, 0.1, 0.0, 0.9], [0.0, 0.05, -0.05]),
+        curves(parametric(1, [1.3]), tabulated(16, lambda x: x ** 0.7), parametric(1, [1.0])),
+        clut16([5, 5, 5], 3, 3, from_lab),
+        curves(parametric(1, [1 / 2.2]), parametric(1, [1.0]), parametric(1, [1.2])))),
+    ("D2B0", pipeline(3, 3,
+        curves(Segmented(None, 3, segments), Segmented(None, 3, segments), Segmented(None, 3, segments)),
+        matrix(3, 3, [0.4361, 0.3851, 0.1431, 0.2225, 0.7169, 0.0606, 0.0139, 0.0971, 0.7141],
+               [0.0, 0.01, -0.01]),
+        clut_float([2, 3, 2], 3, 3, lambda x: (x[0] * 0.9, x[1] * 1.1, x[2] * 0.5 + 0.1)))),
+])
+
+for name, data in [("lut16.icc", lut16), ("lut_ab.icc", lut_ab)]:
+    with open(name, "wb") as f:
+        f.write(data)
+
+    h = OpenFromMem(data, len(data))
+    for tag in ["A2B0", "A2B1", "B2A0", "D2B0"]:
+        lut = ReadTag(h, sig(tag))
+        if not lut:
+            continue
+        print(name, tag)
+        for x in SAMPLES:
+            out = f32s([0.0] * 3)
+            EvalFloat(f32s(x), out, lut)
+            print("    [%s]," % ", ".join("%.6f" % v for v in out))
+    CloseProfile(h)
+
+# Black points: a CMYK printer whose darkest black is L* = 20, and a matrix shaper whose TRCs
+# never go below 0.05. The output table of the printer puts the same amount of every ink,
+# clipped and raised to 1.5, so the darkest colors are lost.
+class XYZ(C.Structure):
+    _fields_ = [("X", C.c_double), ("Y", C.c_double), ("Z", C.c_double)]
+
+
+DetectBlackPoint = fn("cmsDetectBlackPoint", C.c_int, P, P, C.c_uint32, C.c_uint32)
+DetectDestinationBlackPoint = fn("cmsDetectDestinationBlackPoint", C.c_int, P, P, C.c_uint32, C.c_uint32)
+CreateTransform = fn("cmsCreateTransform", P, P, C.c_uint32, P, C.c_uint32, C.c_uint32, C.c_uint32)
+DoTransform = fn("cmsDoTransform", None, P, P, P, C.c_uint32)
+DeleteTransform = fn("cmsDeleteTransform", None, P)
+
+D50 = (0.9642, 1.0, 0.8249)
+TYPE_RGB_16 = (4 << 16) | (3 << 3) | 2
+TYPE_CMYK_16 = (6 << 16) | (4 << 3) | 2
+BLACKPOINTCOMPENSATION = 0x2000
+
+cmyk = profile(2.1, "CMYK", "Lab ", [
+    ("A2B0", pipeline(4, 3,
+        clut16([5] * 4, 4, 3, lambda x: (1 - 0.2 * sum(x), 0.5 - 0.1 * x[0] + 0.1 * x[1],
+                                         0.5 - 0.1 * x[1] + 0.1 * x[2])))),
+    ("B2A0", pipeline(3, 4,
+        clut16([9] * 3, 3, 4, lambda x: [1.25 * (1 - x[0])] * 4),
+        curves(*[tabulated(256, lambda x: x ** 1.5)] * 4))),
+])
+
+
+def matrix_shaper(trc):
+    h = CreatePlaceholder(None)
+    SetVersion(h, 4.3)
+    SetClass(h, sig("mntr"))
+    SetColorSpace(h, sig("RGB "))
+    SetPCS(h, sig("XYZ "))
+    for tag, xyz in [("rXYZ", (D50[0], 0, 0)), ("gXYZ", (0, D50[1], 0)), ("bXYZ", (0, 0, D50[2]))]:
+        WriteTag(h, sig(tag), C.byref(XYZ(*xyz)))
+    for tag in ["rTRC", "gTRC", "bTRC"]:
+        WriteTag(h, sig(tag), trc)
+    return h
+
+
+def save(h):
+    n = C.c_uint32(0)
+    SaveToMem(h, None, C.byref(n))
+    buf = C.create_string_buffer(n.value)
+    assert SaveToMem(h, buf, C.byref(n))
+    CloseProfile(h)
+    return buf.raw[: n.value]
+
+
+# Its RGB is XYZ relative to D50
+diagonal = save(matrix_shaper(parametric(1, [1.0])))
+with open("diagonal.icc", "wb") as f:
+    f.write(diagonal)
+
+raised_black = save(matrix_shaper(parametric(3, [2.2, 0.95, 0.001, 0.05])))
+
+for name, data in [("cmyk.icc", cmyk), ("raised_black.icc", raised_black)]:
+    with open(name, "wb") as f:
+        f.write(data)
+
+    h = OpenFromMem(data, len(data))
+    for intent in [0, 1, 2]:
+        for detect in [DetectBlackPoint, DetectDestinationBlackPoint]:
+            bp = XYZ()
+            if detect(C.byref(bp), h, intent, 0):
+                print(name, intent, detect.__name__, "[%.7f, %.7f, %.7f]" % (bp.X, bp.Y, bp.Z))
+            else:
+                print(name, intent, detect.__name__, "None")
+    CloseProfile(h)
+
+# Relative colorimetric transforms, with and without black point compensation
+def open_file(name):
+    data = open(name, "rb").read()
+    return OpenFromMem(data, len(data))
+
+
+for name, dest, fmt, pixels in [
+    ("raised_black.icc", "diagonal.icc", TYPE_RGB_16, [0, 0, 0, 32768, 32768, 32768]),
+    ("cmyk.icc", "diagonal.icc", TYPE_CMYK_16, [65535] * 4 + [32768] * 4),
+    ("diagonal.icc", "raised_black.icc", TYPE_RGB_16, [0, 0, 0, 32768, 32768, 32768]),
+]:
+    for flags in [0, BLACKPOINTCOMPENSATION]:
+        h_in, h_out = open_file(name), open_file(dest)
+        xform = CreateTransform(h_in, fmt, h_out, TYPE_RGB_16, 1, flags)
+        n_pixels = len(pixels) // (4 if fmt == TYPE_CMYK_16 else 3)
+        out = u16s([0] * 3 * n_pixels)
+        DoTransform(xform, u16s(pixels), out, n_pixels)
+        print(name, "->", dest, flags, list(out))
+        DeleteTransform(xform)
+        CloseProfile(h_in)
+        CloseProfile(h_out)
+
+# Uniform spaces are reached from the PCS, so transforms from them start with this abstract
+# profile leaving XYZ untouched
+with open("xyz_identity.icc", "wb") as f:
+    f.write(save(fn("cmsCreateXYZProfile", P)()))
@@ -0,0 +1,277 @@
+use rs_cms::{
+    types::{delta_e_76, xyY, ICtCp, Jzazbz, LCh, Lab, Luv, OkLab, XYZ},
+    Result, D50,
+};
+
+use crate::helpers::fail;
+
+/// Runs `round_trip` on a grid of Lab values of lightness `min_l` and above, returning the
+/// largest distance found. Any NaN is returned as is.
+fn max_lab_round_trip_error(min_l: i32, round_trip: impl Fn(&Lab) -> Lab) -> f64 {
+    let mut max = 0f64;
+
+    for l in (min_l..=100).step_by(10) {
+        for a in (-128..=128).step_by(8) {
+            for b in (-128..=128).step_by(8) {
+                let lab = Lab::new(l as f64, a as f64, b as f64);
+                let dist = delta_e_76(&lab, &round_trip(&lab));
+
+                if dist.is_nan() {
+                    return dist;
+                }
+                if dist > max {
+                    max = dist;
+                }
+            }
+        }
+    }
+
+    max
+}
+
+fn check_lab_round_trip(title: &str, min_l: i32, round_trip: impl Fn(&Lab) -> Lab) -> Result<()> {
+    let max = max_lab_round_trip_error(min_l, round_trip);
+
+    if max.is_nan() || max >= 1e-12 {
+        fail(&format!("{} |{}|", title, max));
+        return Err("Lab roundtrip error outside allowed range");
+    }
+
+    Ok(())
+}
+
+pub fn check_lab_to_lch() -> Result<()> {
+    check_lab_round_trip("Lab to LCh", 0, |lab| Lab::from(LCh::from(*lab)))
+}
+
+pub fn check_lab_to_xyz() -> Result<()> {
+    check_lab_round_trip("Lab to XYZ", 0, |lab| {
+        Lab::from_xyz(&lab.to_xyz(&D50), &D50)
+    })
+}
+
+fn lab_to_xyy_round_trip(lab: &Lab) -> Lab {
+    let xyy = xyY::from(lab.to_xyz(&D50));
+    Lab::from_xyz(&XYZ::from(xyy), &D50)
+}
+
+pub fn check_lab_to_xyy() -> Result<()> {
+    // Besides black, the colors of lightness 0 have no luminance, so xyY can't hold them
+    check_lab_round_trip("Lab to xyY", 10, lab_to_xyy_round_trip)?;
+
+    // Black takes the chromaticity of D50
+    let black = xyY::from(XYZ::new(0.0, 0.0, 0.0));
+    let d50 = xyY::from(D50);
+    if black.x != d50.x || black.y != d50.y || black.Y != 0.0 {
+        fail(&format!(
+            "Black to xyY ({}, {}, {})",
+            black.x, black.y, black.Y
+        ));
+        return Err("Black has no D50 chromaticity");
+    }
+
+    let lab = Lab::new(0.0, 0.0, 0.0);
+    let dist = delta_e_76(&lab, &lab_to_xyy_round_trip(&lab));
+    if dist.is_nan() || dist >= 1e-12 {
+        fail(&format!("Black to xyY |{}|", dist));
+        return Err("Lab roundtrip error outside allowed range");
+    }
+
+    Ok(())
+}
+
+pub fn check_xyz_to_luv() -> Result<()> {
+    // Luv only holds physical colors, so go over XYZ instead
+    let mut max = 0f64;
+    for x in 1..=20 {
+        for y in 1..=20 {
+            for z in 1..=20 {
+                let xyz = XYZ::new(x as f64 / 20.0, y as f64 / 20.0, z as f64 / 20.0);
+                let xyz2 = Luv::from_xyz(&xyz, &D50).to_xyz(&D50);
+
+                let dist = ((xyz.x - xyz2.x).powi(2)
+                    + (xyz.y - xyz2.y).powi(2)
+                    + (xyz.z - xyz2.z).powi(2))
+                .sqrt();
+                max = max.max(dist);
+            }
+        }
+    }
+
+    if max >= 1e-12 {
+        fail(&format!("XYZ to Luv |{}|", max));
+        return Err("Luv roundtrip error outside allowed range");
+    }
+
+    // sRGB red under D65
+    let d65 = XYZ::new(0.95047, 1.0, 1.08883);
+    let luv = Luv::from_xyz(&XYZ::new(0.4124, 0.2126, 0.0193), &d65);
+    let expected = Luv::new(53.2329, 175.0530, 37.7505);
+
+    for (value, expected) in [
+        (luv.l, expected.l),
+        (luv.u, expected.u),
+        (luv.v, expected.v),
+    ] {
+        if (value - expected).abs() > 1e-3 {
+            fail(&format!(
+                "Luv of sRGB red: Must be {:?}, but is {:?}",
+                expected, luv
+            ));
+            return Err("Luv value outside allowed range");
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `round_trip` on a grid of positive values up to `max`, returning the largest distance
+/// found relative to `max`.
+fn max_round_trip_error(max: f64, round_trip: impl Fn(&[f64; 3]) -> [f64; 3]) -> f64 {
+    let mut max_error = 0f64;
+
+    for x in 1..=20 {
+        for y in 1..=20 {
+            for z in 1..=20 {
+                let value = [x, y, z].map(|v| v as f64 * max / 20.0);
+                let value2 = round_trip(&value);
+
+                let dist = ((value[0] - value2[0]).powi(2)
+                    + (value[1] - value2[1]).powi(2)
+                    + (value[2] - value2[2]).powi(2))
+                .sqrt();
+                max_error = max_error.max(dist / max);
+            }
+        }
+    }
+
+    max_error
+}
+
+/// Checks a round trip, and a value against its published reference.
+fn check_uniform_space(
+    title: &str,
+    max: f64,
+    round_trip: impl Fn(&[f64; 3]) -> [f64; 3],
+    value: [f64; 3],
+    expected: [f64; 3],
+) -> Result<()> {
+    let error = max_round_trip_error(max, round_trip);
+    if error >= 1e-9 {
+        fail(&format!("{} |{}|", title, error));
+        return Err("Roundtrip error outside allowed range");
+    }
+
+    if value
+        .iter()
+        .zip(expected)
+        .any(|(v, e)| (v - e).abs() > 1e-6)
+    {
+        fail(&format!(
+            "{}: Must be {:?}, but is {:?}",
+            title, expected, value
+        ));
+        return Err("Value outside allowed range");
+    }
+
+    Ok(())
+}
+
+pub fn check_xyz_to_oklab() -> Result<()> {
+    // The XYZ of sRGB red
+    let oklab = OkLab::from_xyz(&XYZ::new(0.4124, 0.2126, 0.0193));
+
+    check_uniform_space(
+        "XYZ to OKLab",
+        1.0,
+        |[x, y, z]| OkLab::from_xyz(&XYZ::new(*x, *y, *z)).to_xyz().into(),
+        oklab.into(),
+        [0.627926, 0.224888, 0.125805],
+    )
+}
+
+pub fn check_xyz_to_jzazbz() -> Result<()> {
+    let jzazbz = Jzazbz::from_xyz(&XYZ::new(0.20654008, 0.12197225, 0.05136952));
+
+    check_uniform_space(
+        "XYZ to Jzazbz",
+        10000.0,
+        |[x, y, z]| Jzazbz::from_xyz(&XYZ::new(*x, *y, *z)).to_xyz().into(),
+        jzazbz.into(),
+        [0.005350476, 0.009243017, 0.005260072],
+    )
+}
+
+pub fn check_bt2020_to_ictcp() -> Result<()> {
+    let ictcp = ICtCp::from_bt2020(&[0.45620519, 0.03081071, 0.04091952]);
+
+    // Negative cone responses are clipped, so only go over the BT.2020 gamut
+    check_uniform_space(
+        "BT.2020 to ICtCp",
+        10000.0,
+        |rgb| ICtCp::from_bt2020(rgb).to_bt2020(),
+        ictcp.into(),
+        [0.07351364, 0.00475253, 0.09351596],
+    )
+}
+
+/// Intensities past the asymptote of the PQ curve decode to a finite color, brighter than the
+/// peak one.
+pub fn check_pq_out_of_range() -> Result<()> {
+    let peak = ICtCp::new(1.0, 0.0, 0.0).to_bt2020();
+    let past = ICtCp::new(3.0, 0.0, 0.0).to_bt2020();
+
+    if past.iter().zip(peak).any(|(v, p)| !v.is_finite() || *v < p) {
+        fail(&format!(
+            "ICtCp past the PQ curve: Must be at least {:?}, but is {:?}",
+            peak, past
+        ));
+        return Err("Value outside allowed range");
+    }
+
+    Ok(())
+}
+
+fn check_lab_encoding(decode: fn(&[u16; 3]) -> Lab, encode: fn(&Lab) -> [u16; 3]) -> Result<()> {
+    let mut n_errors = 0;
+
+    for j in 0..u16::MAX {
+        let lab = decode(&[j; 3]);
+        let encoded = encode(&lab);
+
+        n_errors += encoded.iter().filter(|v| **v != j).count();
+    }
+
+    if n_errors != 0 {
+        fail(&format!("{} values changed on a Lab roundtrip", n_errors));
+        return Err("Lab encoding is not reversible");
+    }
+
+    Ok(())
+}
+
+pub fn check_lab_v2_encoding() -> Result<()> {
+    check_lab_encoding(Lab::decode_v2, Lab::encode_v2)
+}
+
+pub fn check_lab_v4_encoding() -> Result<()> {
+    check_lab_encoding(Lab::decode, Lab::encode)
+}
+
+pub fn check_xyz_encoding() -> Result<()> {
+    let mut n_errors = 0;
+
+    // Zero luminance always encodes as black
+    for j in 1..u16::MAX {
+        let xyz = XYZ::decode(&[j; 3]);
+
+        n_errors += xyz.encode().iter().filter(|v| **v != j).count();
+    }
+
+    if n_errors != 0 {
+        fail(&format!("{} values changed on a XYZ roundtrip", n_errors));
+        return Err("XYZ encoding is not reversible");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,515 @@
+use rs_cms::{
+    state::{Context, DEFAULT_CONTEXT},
+    types::{CurveSegment, ToneCurve},
+    Result,
+};
+
+use crate::helpers::{fail, is_good_val, is_good_word};
+
+/// Parameters exercising every built-in parametric curve type.
+const PARAMETRIC_SAMPLES: &[(i32, &[f64])] = &[
+    (1, &[2.2]),
+    (2, &[2.2, 1.5, -0.5]),
+    (3, &[2.2, 1.5, -0.5, 0.1]),
+    (4, &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045]),
+    (5, &[2.2, 0.9, 0.1, 0.5, 0.105, 0.05, 0.02]),
+    (6, &[2.2, 0.9, 0.1, 0.05]),
+    (7, &[2.2, 0.5, 2.0, 1.0, 0.1]),
+    (8, &[0.5, 2.0, 1.5, 0.0, 0.1]),
+    (108, &[2.0]),
+    (109, &[1.9]),
+];
+
+fn eval_parametric(ctx: &Context, r#type: i32, params: &[f64], r: f64) -> Result<f64> {
+    match ctx.get_parametric_curve(r#type) {
+        Some((eval, _)) => Ok(eval(r#type, params, r)),
+        None => {
+            fail(&format!(
+                "Parametric curve type {} is not registered",
+                r#type
+            ));
+            Err("Missing parametric curve")
+        }
+    }
+}
+
+pub fn check_parametric_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // Known values
+    is_good_val(
+        "Gamma 2.2",
+        0.5f64.powf(2.2),
+        eval_parametric(ctx, 1, &[2.2], 0.5)?,
+        1e-12,
+    )?;
+    is_good_val(
+        "sRGB",
+        0.214041140482232,
+        eval_parametric(ctx, 4, PARAMETRIC_SAMPLES[3].1, 0.5)?,
+        1e-12,
+    )?;
+    is_good_val(
+        "Sigmoidal midpoint",
+        0.5,
+        eval_parametric(ctx, 109, &[1.9], 0.5)?,
+        1e-12,
+    )?;
+
+    // Out of domain values
+    is_good_val(
+        "Negative gamma 1",
+        -0.5,
+        eval_parametric(ctx, 1, &[1.0], -0.5)?,
+        0.0,
+    )?;
+    is_good_val(
+        "Negative gamma 2.2",
+        0.0,
+        eval_parametric(ctx, 1, &[2.2], -0.5)?,
+        0.0,
+    )?;
+    is_good_val(
+        "Negative type 2 inverse",
+        0.0,
+        eval_parametric(ctx, -2, &[2.2, 1.5, -0.5], -0.5)?,
+        0.0,
+    )?;
+    if eval_parametric(ctx, -1, &[0.0], 0.5)? < 1e21 {
+        fail("Inverse of gamma 0 must be infinite");
+        return Err("Parametric curve mismatch");
+    }
+
+    // Every type, followed by its inverse
+    for (r#type, params) in PARAMETRIC_SAMPLES {
+        for i in 0..=100 {
+            let y = eval_parametric(ctx, *r#type, params, i as f64 / 100.0)?;
+            let x = eval_parametric(ctx, -r#type, params, y)?;
+
+            is_good_val(
+                &format!("Type {} at {}", r#type, i),
+                y,
+                eval_parametric(ctx, *r#type, params, x)?,
+                1e-9,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_gamma_estimation(curve: &ToneCurve, g: f64) -> Result<()> {
+    match curve.estimate_gamma(0.001) {
+        Some(est) if (est - g).abs() <= 0.001 => Ok(()),
+        est => {
+            fail(&format!(
+                "Gamma not properly estimated, {} instead of {}",
+                est.unwrap_or(-1.0),
+                g
+            ));
+            Err("Gamma estimation mismatch")
+        }
+    }
+}
+
+/// 16 bit table of `x ^ g`, or of `1 - x ^ g` if `descending`.
+fn build_gamma_table(n: usize, g: f64, descending: bool) -> Vec<u16> {
+    (0..n)
+        .map(|i| {
+            let val = ((i as f64 / (n - 1) as f64).powf(g) * 65535.0 + 0.5).floor() as u16;
+
+            if descending {
+                0xffff - val
+            } else {
+                val
+            }
+        })
+        .collect()
+}
+
+pub fn check_gamma_creation_16() -> Result<()> {
+    let lin_gamma = ToneCurve::build_gamma(&DEFAULT_CONTEXT, 1.0)?;
+
+    for i in 0..=0xffff {
+        let r#in = i as u16;
+        is_good_word("Linear gamma", r#in, lin_gamma.eval_u16(r#in))?;
+    }
+
+    check_gamma_estimation(&lin_gamma, 1.0)
+}
+
+pub fn check_gamma_creation_f32() -> Result<()> {
+    let lin_gamma = ToneCurve::build_gamma(&DEFAULT_CONTEXT, 1.0)?;
+
+    for i in 0..=0xffff {
+        let r#in = i as f32 / 65535.0;
+        is_good_val(
+            "Linear gamma",
+            r#in as f64,
+            lin_gamma.eval_f32(r#in) as f64,
+            1.0 / 65535.0,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn check_gamma_f32(g: f64) -> Result<()> {
+    let curve = ToneCurve::build_gamma(&DEFAULT_CONTEXT, g)?;
+
+    for i in 0..=0xffff {
+        let r#in = i as f32 / 65535.0;
+        let val = (r#in as f64).powf(g);
+
+        is_good_val(
+            &format!("Gamma {}", g),
+            val,
+            curve.eval_f32(r#in) as f64,
+            1e-6,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn check_gamma_1_8() -> Result<()> {
+    check_gamma_f32(1.8)
+}
+
+pub fn check_gamma_2_2() -> Result<()> {
+    check_gamma_f32(2.2)
+}
+
+pub fn check_gamma_3_0() -> Result<()> {
+    check_gamma_f32(3.0)
+}
+
+pub fn check_gamma_2_2_table_16() -> Result<()> {
+    let curve =
+        ToneCurve::build_tabulated_16(&DEFAULT_CONTEXT, &build_gamma_table(4096, 2.2, false))?;
+
+    for i in 0..=0xffff {
+        let r#in = i as f32 / 65535.0;
+        let val = (r#in as f64).powf(2.2);
+
+        is_good_val(
+            "Gamma 2.2 16 bit table",
+            val,
+            curve.eval_f32(r#in) as f64,
+            3.0 / 65535.0,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn check_gamma_2_2_table_f32() -> Result<()> {
+    let values = (0..4096)
+        .map(|i| (i as f64 / 4095.0).powf(2.2) as f32)
+        .collect::<Vec<_>>();
+    let curve = ToneCurve::build_tabulated_f32(&DEFAULT_CONTEXT, &values)?;
+
+    for i in 0..=0xffff {
+        let r#in = i as f32 / 65535.0;
+        let val = (r#in as f64).powf(2.2);
+
+        is_good_val(
+            "Gamma 2.2 float table",
+            val,
+            curve.eval_f32(r#in) as f64,
+            1e-5,
+        )?;
+    }
+
+    // Out of domain values are clamped to the first and last samples
+    is_good_val("Below domain", 0.0, curve.eval_f32(-0.5) as f64, 0.0)?;
+    is_good_val("Above domain", 1.0, curve.eval_f32(1.5) as f64, 0.0)?;
+
+    Ok(())
+}
+
+pub fn check_segmented_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // Linear up to 0.5, sampled quadratic up to 1
+    let mut linear = CurveSegment {
+        x0: -1e22,
+        x1: 0.5,
+        r#type: 6,
+        params: [0.0; 10],
+        sampled_points: Vec::new(),
+    };
+    linear.params[0] = 1.0;
+    linear.params[1] = 1.0;
+
+    let sampled = CurveSegment {
+        x0: 0.5,
+        x1: 1.0,
+        r#type: 0,
+        params: [0.0; 10],
+        sampled_points: (0..=100)
+            .map(|i| {
+                let x = 0.5 + i as f32 / 200.0;
+                x * x + 0.25
+            })
+            .collect(),
+    };
+
+    let curve = ToneCurve::build_segmented(ctx, &[linear, sampled])?;
+
+    is_good_val("Linear segment", 0.25, curve.eval_f32(0.25) as f64, 1e-7)?;
+    is_good_val("Linear segment", -0.25, curve.eval_f32(-0.25) as f64, 1e-7)?;
+    is_good_val(
+        "Sampled segment",
+        0.75 * 0.75 + 0.25,
+        curve.eval_f32(0.75) as f64,
+        1e-4,
+    )?;
+
+    // The 16 bit approximation follows the segments
+    is_good_word("16 bit approximation", 0x8000, curve.eval_u16(0x8000))?;
+    is_good_word("16 bit approximation", 0xffff, curve.eval_u16(0xffff))?;
+
+    Ok(())
+}
+
+pub fn check_parametric_tone_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    for (r#type, params) in PARAMETRIC_SAMPLES {
+        let curve = ToneCurve::build_parametric(ctx, *r#type, params)?;
+
+        for i in 0..=100 {
+            let r#in = i as f32 / 100.0;
+            let val = eval_parametric(ctx, *r#type, params, r#in as f64)?;
+
+            is_good_val(
+                &format!("Parametric type {}", r#type),
+                val,
+                curve.eval_f32(r#in) as f64,
+                1e-6,
+            )?;
+            is_good_val(
+                &format!("Parametric type {} in 16 bits", r#type),
+                val.clamp(0.0, 1.0),
+                curve.eval_u16((r#in as f64 * 65535.0 + 0.5) as u16) as f64 / 65535.0,
+                2.0 / 65535.0,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_reverse_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // Parametric curves are reversed analytically
+    let gamma = ToneCurve::build_gamma(ctx, 2.2)?;
+    let reverse = gamma.reverse()?;
+    if reverse.get_parametric_type() != -1 {
+        fail("Parametric curve was not reversed analytically");
+        return Err("Reverse mismatch");
+    }
+
+    for i in 0..=100 {
+        let r#in = i as f32 / 100.0;
+        is_good_val(
+            "Reversed gamma",
+            r#in as f64,
+            reverse.eval_f32(gamma.eval_f32(r#in)) as f64,
+            1e-6,
+        )?;
+    }
+
+    // Tables are reversed on tables, either way
+    for descending in [false, true] {
+        let table = ToneCurve::build_tabulated_16(ctx, &build_gamma_table(4096, 2.2, descending))?;
+        let reverse = table.reverse()?;
+
+        if reverse.is_descending() != descending {
+            fail("Reversed table has the wrong direction");
+            return Err("Reverse mismatch");
+        }
+
+        for i in (0..=0xffff).step_by(0x101) {
+            let r#in = i as u16;
+            let out = table.eval_u16(reverse.eval_u16(r#in));
+
+            if (out as i32).abs_diff(r#in as i32) > 0x30 {
+                fail(&format!(
+                    "Reversed table: Must be {:x}, but is {:x}",
+                    r#in, out
+                ));
+                return Err("Reverse mismatch");
+            }
+        }
+    }
+
+    // Flat regions are collapsed
+    let degenerated = ToneCurve::build_tabulated_16(
+        ctx,
+        &[
+            0, 0, 0, 0, 0, 0x5f5f, 0x6000, 0x6000, 0x6000, 0x6000, 0x6000, 0x6000, 0x6000, 0x6000,
+            0xffff, 0xffff,
+        ],
+    )?;
+    let reverse = degenerated.reverse()?;
+    if !reverse.is_monotonic() {
+        fail("Reversed degenerated table is not monotonic");
+        return Err("Reverse mismatch");
+    }
+    is_good_word("Degenerated top", 0xffff, reverse.eval_u16(0xffff))?;
+
+    Ok(())
+}
+
+pub fn check_joint_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let forward = ToneCurve::build_gamma(ctx, 3.0)?;
+    let reverse = ToneCurve::build_gamma(ctx, 3.0)?;
+
+    let result = ToneCurve::join(ctx, &forward, &reverse, 256)?;
+    if !result.is_linear() {
+        fail("Joining same curve twice does not result in a linear ramp");
+        return Err("Join mismatch");
+    }
+
+    // A ramp needs both of its ends
+    if ToneCurve::join(ctx, &forward, &reverse, 1).is_ok() || reverse.reverse_ex(0).is_ok() {
+        fail("Joining or reversing curves on less than 2 points was accepted");
+        return Err("Join mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_joint_curves_descending() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let forward = ToneCurve::build_tabulated_16(ctx, &build_gamma_table(4096, 2.2, true))?;
+    let reverse = forward.reverse()?;
+
+    let result = ToneCurve::join(ctx, &reverse, &reverse, 256)?;
+    if !result.is_linear() {
+        fail("Joining descending curves does not result in a linear ramp");
+        return Err("Join mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_curve_properties() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let linear = ToneCurve::build_gamma(ctx, 1.0)?;
+    let gamma = ToneCurve::build_gamma(ctx, 2.2)?;
+    let descending = ToneCurve::build_tabulated_16(ctx, &build_gamma_table(256, 2.2, true))?;
+    let bumpy = ToneCurve::build_tabulated_16(ctx, &[0, 0x4000, 0x3000, 0xffff])?;
+    let rippled = ToneCurve::build_tabulated_16(ctx, &[0, 0x4000, 0x3ffe, 0xffff])?;
+
+    if !linear.is_linear() || gamma.is_linear() || descending.is_linear() {
+        fail("Wrong linearity");
+        return Err("Curve property mismatch");
+    }
+    if !gamma.is_monotonic()
+        || !descending.is_monotonic()
+        || bumpy.is_monotonic()
+        || !rippled.is_monotonic()
+    {
+        fail("Wrong monotonicity");
+        return Err("Curve property mismatch");
+    }
+    if gamma.is_descending() || !descending.is_descending() {
+        fail("Wrong direction");
+        return Err("Curve property mismatch");
+    }
+    if gamma.is_multisegment()
+        || !ToneCurve::build_tabulated_f32(ctx, &[0.0, 1.0])?.is_multisegment()
+    {
+        fail("Wrong segment count");
+        return Err("Curve property mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_gamma_estimation_deviation() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // sRGB is close to, but not exactly, a 2.2 gamma
+    let srgb = ToneCurve::build_parametric(ctx, 4, PARAMETRIC_SAMPLES[3].1)?;
+    let (gamma, std) = match srgb.estimate_gamma_with_deviation() {
+        Some(estimation) => estimation,
+        None => {
+            fail("sRGB gamma could not be estimated");
+            return Err("Gamma estimation mismatch");
+        }
+    };
+
+    is_good_val("sRGB gamma", 2.2, gamma, 0.1)?;
+    if std < 0.001 || srgb.estimate_gamma(0.001).is_some() || srgb.estimate_gamma(std).is_none() {
+        fail("sRGB is not an exact gamma");
+        return Err("Gamma estimation mismatch");
+    }
+
+    // Flat curves are far from any gamma, black curves have no samples to estimate from
+    if ToneCurve::build_tabulated_16(ctx, &[0x8000, 0x8000])?
+        .estimate_gamma(0.1)
+        .is_some()
+        || ToneCurve::build_tabulated_16(ctx, &[0, 0])?
+            .estimate_gamma_with_deviation()
+            .is_some()
+    {
+        fail("Flat curve has a gamma");
+        return Err("Gamma estimation mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_smooth_curves() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    // Gamma 2.2 with some noise on it
+    let mut noisy = build_gamma_table(256, 2.2, false);
+    for (i, value) in noisy.iter_mut().enumerate().skip(16) {
+        *value = if i % 2 == 0 {
+            value.saturating_add(600)
+        } else {
+            value.saturating_sub(600)
+        };
+    }
+
+    let mut curve = ToneCurve::build_tabulated_16(ctx, &noisy)?;
+    if curve.is_monotonic() {
+        fail("Noisy curve should not be monotonic");
+        return Err("Smoothing mismatch");
+    }
+
+    curve.smooth(50.0)?;
+    if !curve.is_monotonic() {
+        fail("Smoothed curve is not monotonic");
+        return Err("Smoothing mismatch");
+    }
+    for i in 0..=100 {
+        let r#in = i as f32 / 100.0;
+        is_good_val(
+            "Smoothed gamma",
+            (r#in as f64).powf(2.2),
+            curve.eval_f32(r#in) as f64,
+            0.01,
+        )?;
+    }
+
+    // Linear curves are left alone
+    let mut linear = ToneCurve::build_tabulated_16(ctx, &[0, 0x5555, 0xaaaa, 0xffff])?;
+    linear.smooth(50.0)?;
+    if linear.get_estimated_table() != [0, 0x5555, 0xaaaa, 0xffff] {
+        fail("Linear curve was smoothed");
+        return Err("Smoothing mismatch");
+    }
+
+    Ok(())
+}
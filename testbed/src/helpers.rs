@@ -9,7 +9,8 @@ use std::{
 
 use log::{error, info, Level};
 use rs_cms::{
-    f64_to_s15_fixed16_number, f64_to_u8_fixed8_number, s15_fixed16_number_to_f64,
+    f64_to_s15_fixed16_number, f64_to_u8_fixed8_number, float_to_half, half_to_float,
+    s15_fixed16_number_to_f64,
     state::{Context, ErrorCode, DEFAULT_CONTEXT},
     types::{Signature, XYZ},
     u8_fixed8_number_to_f64, Result, S15Fixed16Number, U16Fixed16Number, U8Fixed8Number, D50,
@@ -224,6 +225,79 @@ pub fn check_fixed_point_8_8() -> Result<()> {
     test_single_fixed_8_8(0.1234567890123456789099999)?;
     test_single_fixed_8_8(255.1234567890123456789099999)?;
 
+    // The fraction counts 256ths, so the 0x01CD of a 1.8 gamma is slightly above 1.8
+    for (fixed, expected) in [
+        (0x0000, 0.0),
+        (0x0100, 1.0),
+        (0x0180, 1.5),
+        (0x01CD, 1.80078125),
+        (0x0233, 2.19921875),
+        (0xFFFF, 255.99609375),
+    ] {
+        let actual = u8_fixed8_number_to_f64(fixed);
+        if actual != expected {
+            fail(&format!("0x{:04x} is {}, not {}", fixed, actual, expected));
+            return Err("Wrong u8Fixed8 decoding");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_half_float() -> Result<()> {
+    // Every half value, but NaNs, survives the round trip bit by bit
+    for h in 0..=0xffffu16 {
+        let f = half_to_float(h);
+        let back = float_to_half(f);
+
+        if f.is_nan() {
+            if back & 0x7c00 != 0x7c00 || back & 0x03ff == 0 {
+                fail(&format!("NaN 0x{:04x} became 0x{:04x}", h, back));
+                return Err("Half float NaN lost");
+            }
+        } else if back != h {
+            fail(&format!("0x{:04x} -> {} -> 0x{:04x}", h, f, back));
+            return Err("Half float roundtrip error");
+        }
+    }
+
+    let known: &[(f32, u16)] = &[
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (65504.0, 0x7bff),
+        (f32::INFINITY, 0x7c00),
+        (f32::NEG_INFINITY, 0xfc00),
+        // Smallest and largest denormals, and the smallest normal
+        (5.960_464_5e-8, 0x0001),
+        (6.097_555e-5, 0x03ff),
+        (6.103_515_6e-5, 0x0400),
+        // Overflow and underflow
+        (65520.0, 0x7c00),
+        (1e-9, 0x0000),
+        // Ties round to even
+        (1.0 + 1.0 / 2048.0, 0x3c00),
+        (1.0 + 3.0 / 2048.0, 0x3c02),
+        (2.980_232_2e-8, 0x0000),
+        (8.940_697e-8, 0x0002),
+    ];
+
+    for (f, h) in known {
+        let back = float_to_half(*f);
+        if back != *h {
+            fail(&format!(
+                "{} should be 0x{:04x}, but is 0x{:04x}",
+                f, h, back
+            ));
+            return Err("Half float encoding error");
+        }
+    }
+
+    if !half_to_float(0x7e00).is_nan() || float_to_half(f32::NAN) & 0x03ff == 0 {
+        return Err("Half float NaN error");
+    }
+
     Ok(())
 }
 
@@ -282,7 +356,7 @@ pub fn check_d50_roundtrip() -> Result<()> {
     if euc > 1e-5 {
         fail(&format!("D50 roundtrip |{}|", euc));
         return Err("D50 roundtrip error outside allowed range");
-    } 
+    }
 
     Ok(())
 }
@@ -8,7 +8,7 @@ use rs_cms::{
     Result,
 };
 
-use crate::helpers::{fail, is_good_fixed_15_16, is_good_word, MAX_ERR};
+use crate::helpers::{fail, is_good_fixed_15_16, is_good_val, is_good_word, MAX_ERR};
 
 fn build_table(n: usize, tab: &mut [u16], descending: bool) {
     for i in 0..n {
@@ -467,3 +467,134 @@ pub fn exhaustive_check_3d_interpolation_u16_trilinear() -> Result<()> {
         return Err("Invalid interpolation function");
     }
 }
+
+/// Points lcms checks its multidimensional interpolations on. Only the first inputs are used
+/// on fewer dimensions.
+const LINEAR_CHECK_POINTS: [[u16; 4]; 8] = [
+    [0, 0, 0, 0],
+    [0xffff, 0xffff, 0xffff, 0xffff],
+    [0x8080, 0x8080, 0x8080, 0x8080],
+    [0x0000, 0xFE00, 0x80FF, 0x8888],
+    [0x1111, 0x2222, 0x3333, 0x4444],
+    [0x0000, 0x0012, 0x0013, 0x0014],
+    [0x3141, 0x1415, 0x1592, 0x9261],
+    [0xFF00, 0xFF01, 0xFF12, 0xFF13],
+];
+
+/// Three linear functions of up to four inputs, which any interpolation has to reproduce.
+fn linear_functions(r#in: &[u16]) -> [u16; 3] {
+    let m = r#in.len() as u32;
+    let a = |i: usize| r#in.get(i).copied().unwrap_or(0) as u32;
+
+    [
+        (a(0) + a(1) + a(2) + a(3)) / m,
+        (a(0) + 3 * a(1) + 3 * a(2) + a(3)) / (m + 4),
+        (3 * a(0) + 2 * a(1) + 3 * a(2) + a(3)) / (m + 5),
+    ]
+    .map(|v| v as u16)
+}
+
+/// Samples `linear_functions` on a 9 points grid of `n_inputs` dimensions.
+fn build_linear_table(n_inputs: usize) -> Vec<u16> {
+    const N_GRID_POINTS: usize = 9;
+    let n_nodes = N_GRID_POINTS.pow(n_inputs as u32);
+    let mut table = Vec::with_capacity(n_nodes * 3);
+
+    for node in 0..n_nodes {
+        // The last input varies fastest
+        let r#in = (0..n_inputs)
+            .map(|i| {
+                let coord = node / N_GRID_POINTS.pow((n_inputs - i - 1) as u32) % N_GRID_POINTS;
+                ((65535.0 * coord as f64) / (N_GRID_POINTS - 1) as f64 + 0.5).floor() as u16
+            })
+            .collect::<Vec<_>>();
+
+        table.extend(linear_functions(&r#in));
+    }
+
+    table
+}
+
+fn check_linear_interpolation_u16(n_inputs: usize) -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let table = build_linear_table(n_inputs);
+
+    let p = InterpParams::compute(ctx, 9, n_inputs, 3, &table, lerp_flags::BITS_16)?;
+    let lerp = match p.interpolation {
+        InterpFunction::U16(lerp) => lerp,
+        _ => return Err("Invalid interpolation function"),
+    };
+
+    for point in LINEAR_CHECK_POINTS {
+        let r#in = &point[..n_inputs];
+        let expected = linear_functions(r#in);
+        let mut out = [0u16; 3];
+
+        lerp(r#in, &mut out, &p);
+
+        for (chan, (expected, out)) in expected.iter().zip(out).enumerate() {
+            is_good_val(
+                &format!("Channel {}", chan + 1),
+                *expected as f64,
+                out as f64,
+                2.0,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_linear_interpolation_f32(n_inputs: usize) -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let table = build_linear_table(n_inputs)
+        .iter()
+        .map(|v| *v as f32 / 65535.0)
+        .collect::<Vec<_>>();
+
+    let p = InterpParams::compute(ctx, 9, n_inputs, 3, &table, lerp_flags::FLOAT)?;
+    let lerp = match p.interpolation {
+        InterpFunction::F32(lerp) => lerp,
+        _ => return Err("Invalid interpolation function"),
+    };
+
+    for point in LINEAR_CHECK_POINTS {
+        let r#in = &point[..n_inputs];
+        let expected = linear_functions(r#in);
+        let mut out = [0f32; 3];
+
+        let r#in = r#in.iter().map(|v| *v as f32 / 65535.0).collect::<Vec<_>>();
+        lerp(&r#in, &mut out, &p);
+
+        for (chan, (expected, out)) in expected.iter().zip(out).enumerate() {
+            is_good_val(
+                &format!("Channel {}", chan + 1),
+                *expected as f64,
+                out as f64 * 65535.0,
+                2.0,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_1d_interpolation_f32_3_outputs() -> Result<()> {
+    check_linear_interpolation_f32(1)
+}
+
+pub fn check_3d_interpolation_u16_9pt() -> Result<()> {
+    check_linear_interpolation_u16(3)
+}
+
+pub fn check_3d_interpolation_f32_9pt() -> Result<()> {
+    check_linear_interpolation_f32(3)
+}
+
+pub fn check_4d_interpolation_u16() -> Result<()> {
+    check_linear_interpolation_u16(4)
+}
+
+pub fn check_4d_interpolation_f32() -> Result<()> {
+    check_linear_interpolation_f32(4)
+}
@@ -1,14 +1,16 @@
 use std::{process::exit, sync::atomic::Ordering};
 
-use clap::{
-    crate_authors, crate_version, value_parser, Arg, ArgAction,
-    Command,
-};
+use clap::{crate_authors, crate_version, value_parser, Arg, ArgAction, Command};
 use log::{error, info, Level};
 use rs_cms::state::DEFAULT_CONTEXT;
 
+use colorspace::*;
+use curves::*;
 use helpers::*;
 use lerp::*;
+use pipeline::*;
+use profile::*;
+use stage::*;
 
 pub fn main() {
     #[allow(non_upper_case_globals)]
@@ -74,7 +76,57 @@ pub fn main() {
     check("Quick floor word", check_quick_floor_word);
     check("Fixed point 15.16 representation", check_fixed_point_15_16);
     check("Fixed point 8.8 representation", check_fixed_point_8_8);
+    check("Half float representation", check_half_float);
     check("D50 roundtrip", check_d50_roundtrip);
+    check("Lab to LCh and back (float only)", check_lab_to_lch);
+    check("Lab to XYZ and back (float only)", check_lab_to_xyz);
+    check("Lab to xyY and back (float only)", check_lab_to_xyy);
+    check("XYZ to Luv and back (float only)", check_xyz_to_luv);
+    check("XYZ to OKLab and back (float only)", check_xyz_to_oklab);
+    check("XYZ to Jzazbz and back (float only)", check_xyz_to_jzazbz);
+    check(
+        "BT.2020 to ICtCp and back (float only)",
+        check_bt2020_to_ictcp,
+    );
+    check("PQ out of range (float only)", check_pq_out_of_range);
+    check("Lab V2 encoding", check_lab_v2_encoding);
+    check("Lab V4 encoding", check_lab_v4_encoding);
+    check("XYZ encoding", check_xyz_encoding);
+    check("Profile header", check_profile_header);
+    check("Profile tag directory", check_profile_tag_directory);
+    check("Profile saving", check_profile_save);
+    check("Profile TRC tags", check_profile_trc);
+    check("Parametric curves", check_parametric_curves);
+    check("Gamma creation 16 bits", check_gamma_creation_16);
+    check("Gamma creation float", check_gamma_creation_f32);
+    check("Gamma 1.8", check_gamma_1_8);
+    check("Gamma 2.2", check_gamma_2_2);
+    check("Gamma 3.0", check_gamma_3_0);
+    check("Gamma 2.2 16 bit table", check_gamma_2_2_table_16);
+    check("Gamma 2.2 float table", check_gamma_2_2_table_f32);
+    check("Segmented curves", check_segmented_curves);
+    check("Parametric tone curves", check_parametric_tone_curves);
+    check("Curve reversal", check_reverse_curves);
+    check("Join curves", check_joint_curves);
+    check("Join curves descending", check_joint_curves_descending);
+    check("Curve properties", check_curve_properties);
+    check(
+        "Gamma estimation deviation",
+        check_gamma_estimation_deviation,
+    );
+    check("Curve smoothing", check_smooth_curves);
+    check("Identity stage", check_identity_stage);
+    check("Curve set stage", check_curves_stage);
+    check("Matrix stage", check_matrix_stage);
+    check("CLUT stages", check_clut_stages);
+    check("LUT creation", check_lut_creation);
+    check("1 stage LUT", check_1_stage_lut);
+    check("2 stage LUT", check_2_stage_lut);
+    check("3 stage LUT", check_3_stage_lut);
+    check("LUT channel mismatch", check_lut_channel_mismatch);
+    check("LUT concatenation", check_lut_concat);
+    check("LUT duplication", check_lut_duplicate);
+    check("LUT reverse evaluation", check_reverse_lut);
 
     if *args.get_one("checks").unwrap() {
         check("1D interpolation in 2pt tables", check_1d_lerp_2);
@@ -127,6 +179,20 @@ pub fn main() {
             "3D interpolation Trilinear (u16)",
             check_3d_interpolation_u16_trilinear,
         );
+        check(
+            "1D interpolation with 3 outputs (f32)",
+            check_1d_interpolation_f32_3_outputs,
+        );
+        check(
+            "3D interpolation in 9pt tables (f32)",
+            check_3d_interpolation_f32_9pt,
+        );
+        check(
+            "3D interpolation in 9pt tables (u16)",
+            check_3d_interpolation_u16_9pt,
+        );
+        check("4D interpolation (f32)", check_4d_interpolation_f32);
+        check("4D interpolation (u16)", check_4d_interpolation_u16);
 
         if *args.get_one("exhaustive").unwrap() {
             check(
@@ -184,5 +250,10 @@ fn print_supported_intents() {
     }
 }
 
+mod colorspace;
+mod curves;
 mod helpers;
 mod lerp;
+mod pipeline;
+mod profile;
+mod stage;
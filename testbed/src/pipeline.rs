@@ -0,0 +1,361 @@
+use rs_cms::{
+    sig,
+    state::{Context, DEFAULT_CONTEXT},
+    types::{Pipeline, Signature, Stage, StageLoc, ToneCurve},
+    Result,
+};
+
+use crate::helpers::{fail, is_good_val, is_good_word_prec};
+
+const IDENTITY_MATRIX: [f64; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+/// Checks that a 3 channel pipeline leaves both its 16 bit and float inputs alone.
+fn check_full_lut(lut: &Pipeline, expected_stages: usize) -> Result<()> {
+    if lut.stage_count() != expected_stages {
+        fail(&format!(
+            "Expected {} stages, but found {}",
+            expected_stages,
+            lut.stage_count()
+        ));
+        return Err("Stage count mismatch");
+    }
+
+    for i in (0..=0xffffu32).step_by(0x3ff).chain([0xffff]) {
+        let r#in = [i as u16, (0xffff - i) as u16, (i / 2) as u16];
+        let mut out = [0u16; 3];
+
+        lut.eval_16(&r#in, &mut out);
+
+        for chan in 0..3 {
+            is_good_word_prec("16 bit pipeline", r#in[chan], out[chan], 2)?;
+        }
+    }
+
+    for i in 0..=100 {
+        let r#in = [i as f32 / 100.0, 1.0 - i as f32 / 100.0, i as f32 / 200.0];
+        let mut out = [0f32; 3];
+
+        lut.eval_f32(&r#in, &mut out);
+
+        for chan in 0..3 {
+            is_good_val("Float pipeline", r#in[chan] as f64, out[chan] as f64, 1e-4)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn identity_curves(ctx: &Context) -> Result<Stage> {
+    Stage::alloc_tone_curves(ctx, 3, None)
+}
+
+fn identity_matrix(ctx: &Context) -> Result<Stage> {
+    Stage::alloc_matrix(ctx, 3, 3, &IDENTITY_MATRIX, None)
+}
+
+fn identity_clut(ctx: &Context) -> Result<Stage> {
+    let mut table = Vec::new();
+    for r in 0..2 {
+        for g in 0..2 {
+            for b in 0..2 {
+                table.extend([r * 0xffff, g * 0xffff, b * 0xffff]);
+            }
+        }
+    }
+
+    Stage::alloc_clut_16bit(ctx, 2, 3, 3, Some(&table))
+}
+
+pub fn check_lut_creation() -> Result<()> {
+    let lut = Pipeline::new(&DEFAULT_CONTEXT, 4, 4)?;
+
+    if lut.stage_count() != 0
+        || lut.get_input_channels() != 4
+        || lut.get_output_channels() != 4
+        || lut.first_stage().is_some()
+    {
+        fail("Wrong empty pipeline");
+        return Err("Pipeline mismatch");
+    }
+
+    // Empty pipelines pass values through
+    let mut out = [0u16; 4];
+    lut.eval_16(&[1, 2, 3, 4], &mut out);
+    if out != [1, 2, 3, 4] {
+        fail("Empty pipeline changed its input");
+        return Err("Pipeline mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_1_stage_lut() -> Result<()> {
+    let mut lut = Pipeline::new(&DEFAULT_CONTEXT, 3, 3)?;
+    lut.insert_stage(StageLoc::AtBegin, identity_matrix(&DEFAULT_CONTEXT)?)?;
+
+    check_full_lut(&lut, 1)
+}
+
+pub fn check_2_stage_lut() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut lut = Pipeline::new(ctx, 3, 3)?;
+    lut.insert_stage(StageLoc::AtBegin, identity_matrix(ctx)?)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_curves(ctx)?)?;
+
+    check_full_lut(&lut, 2)
+}
+
+pub fn check_3_stage_lut() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut lut = Pipeline::new(ctx, 3, 3)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_clut(ctx)?)?;
+    lut.insert_stage(StageLoc::AtBegin, identity_matrix(ctx)?)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_curves(ctx)?)?;
+
+    check_full_lut(&lut, 3)
+}
+
+pub fn check_lut_channel_mismatch() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut lut = Pipeline::new(ctx, 3, 3)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_curves(ctx)?)?;
+
+    // 3 -> 1
+    lut.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(ctx, 1, 3, &[1.0 / 3.0; 3], None)?,
+    )?;
+    if lut.get_input_channels() != 3 || lut.get_output_channels() != 1 {
+        fail("Pipeline channels were not updated");
+        return Err("Pipeline mismatch");
+    }
+
+    if lut
+        .insert_stage(StageLoc::AtEnd, identity_curves(ctx)?)
+        .is_ok()
+        || lut
+            .insert_stage(StageLoc::AtBegin, Stage::alloc_identity(ctx, 4)?)
+            .is_ok()
+    {
+        fail("Stages with mismatched channels were accepted");
+        return Err("Pipeline mismatch");
+    }
+
+    if lut.stage_count() != 2 || lut.get_output_channels() != 1 {
+        fail("Rejected stages changed the pipeline");
+        return Err("Pipeline mismatch");
+    }
+
+    let mut out = [0f32];
+    lut.eval_f32(&[0.3, 0.6, 0.9], &mut out);
+    is_good_val("Mean", 0.6, out[0] as f64, 1e-6)?;
+
+    // Unlinking updates channels too
+    lut.unlink_stage(StageLoc::AtEnd);
+    if lut.get_output_channels() != 3 {
+        fail("Unlinking did not update the pipeline channels");
+        return Err("Pipeline mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_lut_concat() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut lut1 = Pipeline::new(ctx, 3, 3)?;
+    lut1.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_tone_curves(
+            ctx,
+            3,
+            Some(&[
+                ToneCurve::build_gamma(ctx, 2.2)?,
+                ToneCurve::build_gamma(ctx, 2.2)?,
+                ToneCurve::build_gamma(ctx, 2.2)?,
+            ]),
+        )?,
+    )?;
+
+    let mut lut2 = Pipeline::new(ctx, 3, 3)?;
+    lut2.insert_stage(StageLoc::AtEnd, identity_matrix(ctx)?)?;
+    lut2.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_tone_curves(
+            ctx,
+            3,
+            Some(&[
+                ToneCurve::build_gamma(ctx, 1.0 / 2.2)?,
+                ToneCurve::build_gamma(ctx, 1.0 / 2.2)?,
+                ToneCurve::build_gamma(ctx, 1.0 / 2.2)?,
+            ]),
+        )?,
+    )?;
+
+    lut1.cat(&lut2)?;
+    check_full_lut(&lut1, 3)?;
+
+    // The source pipeline is left alone
+    if lut2.stage_count() != 2 {
+        fail("Concatenation changed its source");
+        return Err("Pipeline mismatch");
+    }
+
+    // Empty pipelines inherit the channels
+    let mut empty = Pipeline::new(ctx, 0, 0)?;
+    empty.cat(&Pipeline::new(ctx, 4, 2)?)?;
+    if empty.get_input_channels() != 4 || empty.get_output_channels() != 2 {
+        fail("Empty pipeline did not inherit its channels");
+        return Err("Pipeline mismatch");
+    }
+
+    let mut mismatch = Pipeline::new(ctx, 4, 4)?;
+    mismatch.insert_stage(StageLoc::AtEnd, Stage::alloc_identity(ctx, 4)?)?;
+    if mismatch.cat(&lut2).is_ok() || mismatch.stage_count() != 1 {
+        fail("Concatenation of mismatched pipelines was accepted");
+        return Err("Pipeline mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_lut_duplicate() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut lut = Pipeline::new(ctx, 3, 3)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_matrix(ctx)?)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_clut(ctx)?)?;
+    lut.insert_stage(StageLoc::AtEnd, identity_curves(ctx)?)?;
+
+    let dup = lut.duplicate();
+    drop(lut);
+
+    check_full_lut(&dup, 3)?;
+
+    let types = dup
+        .stages()
+        .map(|stage| stage.get_type())
+        .collect::<Vec<_>>();
+    let expected: [Signature; 3] = [
+        sig::mpe_stage::MATRIX,
+        sig::mpe_stage::CLUT,
+        sig::mpe_stage::CURVE_SET,
+    ];
+    if types != expected {
+        fail("Wrong stages in duplicated pipeline");
+        return Err("Pipeline mismatch");
+    }
+
+    Ok(())
+}
+
+/// A non linear, invertible 3 -> 3 pipeline.
+fn build_reversible_lut(ctx: &Context) -> Result<Pipeline> {
+    #[rustfmt::skip]
+    let matrix = [
+        0.6, 0.3, 0.1,
+        0.2, 0.7, 0.1,
+        0.1, 0.1, 0.8,
+    ];
+
+    let mut lut = Pipeline::new(ctx, 3, 3)?;
+    lut.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_tone_curves(
+            ctx,
+            3,
+            Some(&[
+                ToneCurve::build_gamma(ctx, 2.2)?,
+                ToneCurve::build_gamma(ctx, 1.8)?,
+                ToneCurve::build_gamma(ctx, 1.0)?,
+            ]),
+        )?,
+    )?;
+    lut.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(ctx, 3, 3, &matrix, None)?,
+    )?;
+
+    Ok(lut)
+}
+
+pub fn check_reverse_lut() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+    let lut = build_reversible_lut(ctx)?;
+
+    for r#in in [[0.2f32, 0.5, 0.8], [0.9, 0.1, 0.4], [0.5, 0.5, 0.5]] {
+        let mut target = [0f32; 3];
+        lut.eval_f32(&r#in, &mut target);
+
+        let mut result = [0f32; 3];
+        let residual = lut.eval_reverse_f32(&target, &mut result, None)?;
+
+        is_good_val("Residual", 0.0, residual, 1e-4)?;
+        for chan in 0..3 {
+            is_good_val("Reversed", r#in[chan] as f64, result[chan] as f64, 1e-3)?;
+        }
+
+        // Starting next to the solution needs fewer iterations
+        let residual = lut.eval_reverse_f32_ex(&target, &mut result, Some(&r#in), 1e-6, 1)?;
+        is_good_val("Residual from hint", 0.0, residual, 1e-6)?;
+    }
+
+    // Targets out of reach are reported
+    let mut result = [0f32; 3];
+    let residual = lut.eval_reverse_f32(&[1.5, -0.5, 1.5], &mut result, None)?;
+    if residual < 0.1 {
+        fail(&format!(
+            "Unreachable target has a residual of {}",
+            residual
+        ));
+        return Err("Pipeline mismatch");
+    }
+
+    // The fourth channel is fixed
+    let mut lut4 = Pipeline::new(ctx, 4, 3)?;
+    lut4.insert_stage(
+        StageLoc::AtEnd,
+        Stage::alloc_matrix(
+            ctx,
+            3,
+            4,
+            &[0.5, 0.0, 0.0, 0.5, 0.0, 0.5, 0.0, 0.5, 0.0, 0.0, 0.5, 0.5],
+            None,
+        )?,
+    )?;
+
+    let mut result = [0f32; 4];
+    let residual = lut4.eval_reverse_f32(&[0.6, 0.4, 0.5, 0.4], &mut result, None)?;
+    is_good_val("Residual", 0.0, residual, 1e-4)?;
+    for (expected, actual) in [0.8, 0.4, 0.6, 0.4].iter().zip(result) {
+        is_good_val(
+            "Reversed with fixed channel",
+            *expected,
+            actual as f64,
+            1e-3,
+        )?;
+    }
+
+    // Hints hold the 3 first input channels
+    if lut
+        .eval_reverse_f32(&[0.5; 3], &mut [0.0; 3], Some(&[0.5, 0.5]))
+        .is_ok()
+    {
+        fail("Reversed a pipeline from a 2 channel hint");
+        return Err("Pipeline mismatch");
+    }
+
+    // Only 3 output channels are supported
+    if Pipeline::new(ctx, 3, 4)?
+        .eval_reverse_f32(&[0.0; 4], &mut [0.0; 4], None)
+        .is_ok()
+    {
+        fail("Reversed a pipeline with 4 outputs");
+        return Err("Pipeline mismatch");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,408 @@
+use std::{
+    any::Any,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rs_cms::{
+    io::{FileNull, IoHandler},
+    plugin::{Plugin, TagDescriptor, TagTypeHandler},
+    sig,
+    state::{Context, Tag, DEFAULT_CONTEXT},
+    types::{Profile, Signature, ToneCurve},
+    Result,
+};
+
+use crate::helpers::fail;
+
+fn build_header(size: u32, version: u32) -> Vec<u8> {
+    // Header plus an empty tag directory
+    let mut header = vec![0u8; 132];
+
+    header[0..4].copy_from_slice(&size.to_be_bytes());
+    header[8..12].copy_from_slice(&version.to_be_bytes());
+    header[12..16].copy_from_slice(&u32::from(sig::class::DISPLAY).to_be_bytes());
+    header[16..20].copy_from_slice(&u32::from(sig::colorspace::RGB).to_be_bytes());
+    header[20..24].copy_from_slice(&u32::from(sig::colorspace::XYZ).to_be_bytes());
+    header[24..36].copy_from_slice(&[0x07, 0xE8, 0, 3, 0, 16, 0, 12, 0, 30, 0, 0]);
+    header[36..40].copy_from_slice(&u32::from(sig::MAGIC_NUMBER).to_be_bytes());
+    header[64..68].copy_from_slice(&1u32.to_be_bytes());
+    // D50 illuminant
+    header[68..80].copy_from_slice(&[
+        0x00, 0x00, 0xF6, 0xD6, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xD3, 0x2D,
+    ]);
+
+    header
+}
+
+pub fn check_profile_header() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let header = build_header(132, 0x04300000);
+    let profile = Profile::open_from_mem(ctx, &header)?;
+
+    if profile.get_device_class() != sig::class::DISPLAY
+        || profile.get_color_space() != sig::colorspace::RGB
+        || profile.get_pcs() != sig::colorspace::XYZ
+        || profile.get_header_rendering_intent() != 1
+    {
+        fail("Header fields were not read back");
+        return Err("Header mismatch");
+    }
+
+    if (profile.get_version() - 4.3).abs() > 1e-9 {
+        fail(&format!(
+            "Version must be 4.3, but is {}",
+            profile.get_version()
+        ));
+        return Err("Header mismatch");
+    }
+
+    if (profile.get_header_illuminant().x - 0.9642).abs() > 1e-4 {
+        fail("Illuminant was not read back");
+        return Err("Header mismatch");
+    }
+
+    match profile.get_header_creation_date_time() {
+        Some(date) if date.to_rfc3339() == "2024-03-16T12:30:00+00:00" => {}
+        _ => {
+            fail("Creation date was not read back");
+            return Err("Header mismatch");
+        }
+    }
+
+    // Bad versions are clamped to the spec
+    let header = build_header(132, 0x0AFF1234);
+    let profile = Profile::open_from_mem(ctx, &header)?;
+    if profile.get_encoded_icc_version() != 0x09990000 {
+        fail("Version was not validated");
+        return Err("Header mismatch");
+    }
+
+    // A header without 'acsp' must be rejected
+    let mut header = build_header(132, 0x04300000);
+    header[36] = 0;
+    if Profile::open_from_mem(ctx, &header).is_ok() {
+        fail("Bad magic number accepted");
+        return Err("Header mismatch");
+    }
+
+    Ok(())
+}
+
+const TEST_TYPE: Signature = Signature(0x74657374); // 'test'
+const TEST_TAG_A: Signature = Signature(0x74737441); // 'tstA'
+const TEST_TAG_B: Signature = Signature(0x74737442); // 'tstB'
+const TEST_TAG_C: Signature = Signature(0x74737443); // 'tstC'
+
+static TEST_TYPE_READS: AtomicUsize = AtomicUsize::new(0);
+
+fn read_test_type(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    n_items: &mut usize,
+    _tag_size: usize,
+) -> Result<Box<dyn Any>> {
+    TEST_TYPE_READS.fetch_add(1, Ordering::SeqCst);
+
+    let value = io.read_u32().map_err(|_| "Read error")?;
+    *n_items = 1;
+
+    Ok(Box::new(value))
+}
+
+fn write_test_type(
+    _handler: &TagTypeHandler,
+    io: &mut dyn IoHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<()> {
+    match data.downcast_ref::<u32>() {
+        Some(value) => io.write_u32(*value).map_err(|_| "Write error"),
+        None => Err("Wrong data type"),
+    }
+}
+
+fn dup_test_type(
+    _handler: &TagTypeHandler,
+    data: &dyn Any,
+    _n_items: usize,
+) -> Result<Box<dyn Any>> {
+    match data.downcast_ref::<u32>() {
+        Some(value) => Ok(Box::new(*value)),
+        None => Err("Wrong data type"),
+    }
+}
+
+static TEST_TYPE_HANDLERS: &[TagTypeHandler] = &[TagTypeHandler {
+    sig: TEST_TYPE,
+    read: read_test_type,
+    write: write_test_type,
+    dup: dup_test_type,
+}];
+static TEST_TYPE_PLUGIN: Plugin = Plugin::create_tag_type_plugin(&TEST_TYPE_HANDLERS);
+
+static TEST_TAG_DESCRIPTOR: TagDescriptor = TagDescriptor {
+    elem_count: 1,
+    decide_type: None,
+    supported_types: &[TEST_TYPE],
+};
+static TEST_TAGS: &[Tag] = &[
+    Tag {
+        sig: TEST_TAG_A,
+        desc: &TEST_TAG_DESCRIPTOR,
+    },
+    Tag {
+        sig: TEST_TAG_B,
+        desc: &TEST_TAG_DESCRIPTOR,
+    },
+    Tag {
+        sig: TEST_TAG_C,
+        desc: &TEST_TAG_DESCRIPTOR,
+    },
+];
+static TEST_TAG_PLUGIN: Plugin = Plugin::create_tag_plugin(&TEST_TAGS);
+
+/// Builds a profile whose directory holds `entries`, followed by two 'test' typed tags holding
+/// 0xCAFE and 0xBEEF.
+fn build_tagged_profile(entries: &[(Signature, u32, u32)]) -> Vec<u8> {
+    let data_offset = 132 + 12 * entries.len() as u32;
+    let size = data_offset + 24;
+
+    let mut profile = build_header(size, 0x04300000);
+    profile[128..132].copy_from_slice(&(entries.len() as u32).to_be_bytes());
+
+    for (sig, offset, size) in entries {
+        profile.extend_from_slice(&u32::from(*sig).to_be_bytes());
+        profile.extend_from_slice(&(data_offset + offset).to_be_bytes());
+        profile.extend_from_slice(&size.to_be_bytes());
+    }
+
+    for value in [0xCAFEu32, 0xBEEF] {
+        profile.extend_from_slice(&u32::from(TEST_TYPE).to_be_bytes());
+        profile.extend_from_slice(&[0u8; 4]);
+        profile.extend_from_slice(&value.to_be_bytes());
+    }
+
+    profile
+}
+
+fn read_test_tag(profile: &Profile, sig: Signature) -> Result<u32> {
+    match profile.read_tag(sig)?.downcast_ref::<u32>() {
+        Some(value) => Ok(*value),
+        None => Err("Tag has the wrong type"),
+    }
+}
+
+pub fn check_profile_tag_directory() -> Result<()> {
+    let ctx = DEFAULT_CONTEXT.register_plugins(&[&TEST_TYPE_PLUGIN, &TEST_TAG_PLUGIN])?;
+
+    // 'tstB' shares the block of 'tstA'
+    let buffer = build_tagged_profile(&[
+        (TEST_TAG_A, 0, 12),
+        (TEST_TAG_B, 0, 12),
+        (TEST_TAG_C, 12, 12),
+    ]);
+
+    TEST_TYPE_READS.store(0, Ordering::SeqCst);
+    let profile = Profile::open_from_mem(&ctx, &buffer)?;
+
+    if profile.get_tag_count() != 3 || profile.get_tag_linked_to(TEST_TAG_B) != Some(TEST_TAG_A) {
+        fail("Tag directory was not read back");
+        return Err("Tag directory mismatch");
+    }
+    if TEST_TYPE_READS.load(Ordering::SeqCst) != 0 {
+        fail("Tags were loaded eagerly");
+        return Err("Tag directory mismatch");
+    }
+
+    if read_test_tag(&profile, TEST_TAG_A)? != 0xCAFE
+        || read_test_tag(&profile, TEST_TAG_B)? != 0xCAFE
+        || read_test_tag(&profile, TEST_TAG_A)? != 0xCAFE
+    {
+        fail("Linked tag was not read back");
+        return Err("Tag mismatch");
+    }
+    if TEST_TYPE_READS.load(Ordering::SeqCst) != 1 {
+        fail("Linked tag was deserialized more than once");
+        return Err("Tag mismatch");
+    }
+
+    if read_test_tag(&profile, TEST_TAG_C)? != 0xBEEF {
+        fail("Tag was not read back");
+        return Err("Tag mismatch");
+    }
+
+    // Overlapping tags and tags outside of the profile are dropped
+    let buffer = build_tagged_profile(&[
+        (TEST_TAG_A, 0, 12),
+        (TEST_TAG_B, 4, 12),
+        (TEST_TAG_C, 12, 0x1000),
+    ]);
+    let profile = Profile::open_from_mem(&ctx, &buffer)?;
+    if profile.get_tag_count() != 1 || !profile.is_tag(TEST_TAG_A) {
+        fail("Invalid tags were accepted");
+        return Err("Tag directory mismatch");
+    }
+
+    // Too many tags
+    let mut buffer = build_header(132, 0x04300000);
+    buffer[128..132].copy_from_slice(&101u32.to_be_bytes());
+    if Profile::open_from_mem(&ctx, &buffer).is_ok() {
+        fail("Too many tags accepted");
+        return Err("Tag directory mismatch");
+    }
+
+    Ok(())
+}
+
+pub fn check_profile_save() -> Result<()> {
+    let ctx = DEFAULT_CONTEXT.register_plugins(&[&TEST_TYPE_PLUGIN, &TEST_TAG_PLUGIN])?;
+
+    let buffer = build_tagged_profile(&[
+        (TEST_TAG_A, 0, 12),
+        (TEST_TAG_B, 0, 12),
+        (TEST_TAG_C, 12, 12),
+    ]);
+    let mut profile = Profile::open_from_mem(&ctx, &buffer)?;
+
+    // A raw tag with an odd size needs padding
+    let raw = [0x74u8, 0x65, 0x73, 0x74, 0, 0, 0, 0, 1, 2, 3, 4, 5];
+    let raw_sig = Signature(0x72617720); // 'raw '
+    profile.write_raw_tag(raw_sig, &raw)?;
+
+    let needed = profile.save_to_io(&FileNull::open(&ctx))?;
+    let saved = profile.save_to_mem()?;
+    if needed != saved.len() {
+        fail(&format!(
+            "Dry run reported {} bytes, but {} were written",
+            needed,
+            saved.len()
+        ));
+        return Err("Save size mismatch");
+    }
+
+    // Header, 4 entries, 2 shared 12 byte tags and a 13 byte tag aligned to 16
+    if saved.len() != 132 + 4 * 12 + 12 + 12 + 16 {
+        fail(&format!("Unexpected profile size {}", saved.len()));
+        return Err("Save size mismatch");
+    }
+    if u32::from_be_bytes([saved[0], saved[1], saved[2], saved[3]]) as usize != saved.len() {
+        fail("Header size was not patched");
+        return Err("Save size mismatch");
+    }
+
+    let reloaded = Profile::open_from_mem(&ctx, &saved)?;
+    if reloaded.get_tag_count() != 4
+        || reloaded.get_tag_linked_to(TEST_TAG_B) != Some(TEST_TAG_A)
+        || read_test_tag(&reloaded, TEST_TAG_B)? != 0xCAFE
+        || read_test_tag(&reloaded, TEST_TAG_C)? != 0xBEEF
+        || reloaded.read_raw_tag(raw_sig)? != raw
+        || reloaded.get_device_class() != profile.get_device_class()
+    {
+        fail("Saved profile was not read back");
+        return Err("Save mismatch");
+    }
+
+    // Saving again must be byte stable
+    if reloaded.save_to_mem()? != saved {
+        fail("Profile saving is not stable");
+        return Err("Save mismatch");
+    }
+
+    // Cooked tags go through the type handler
+    let mut profile = reloaded.duplicate()?;
+    profile.write_tag(TEST_TAG_C, Box::new(0xF00Du32))?;
+    let copy = profile.duplicate()?;
+    profile.write_tag(TEST_TAG_C, Box::new(0xD00Du32))?;
+
+    let reloaded_copy = Profile::open_from_mem(&ctx, &copy.save_to_mem()?)?;
+    if read_test_tag(&profile, TEST_TAG_C)? != 0xD00D
+        || read_test_tag(&reloaded_copy, TEST_TAG_C)? != 0xF00D
+        || read_test_tag(&reloaded_copy, TEST_TAG_A)? != 0xCAFE
+        || copy.read_raw_tag(TEST_TAG_C)? != [0x74, 0x65, 0x73, 0x74, 0, 0, 0, 0, 0, 0, 0xF0, 0x0D]
+    {
+        fail("Written tag was not read back");
+        return Err("Save mismatch");
+    }
+
+    let path = std::env::temp_dir().join("rs-cms-testbed-profile.icc");
+    let written = reloaded.save_to_file(&path)?;
+    let from_file = Profile::open_from_file(&ctx, &path)?;
+    _ = std::fs::remove_file(&path);
+    if written != saved.len() || read_test_tag(&from_file, TEST_TAG_C)? != 0xBEEF {
+        fail("Profile was not saved to file");
+        return Err("Save mismatch");
+    }
+
+    Ok(())
+}
+
+fn saved_tag_type(profile: &Profile, sig: Signature) -> Result<Signature> {
+    let reloaded = Profile::open_from_mem(profile.context_id(), &profile.save_to_mem()?)?;
+    let raw = reloaded.read_raw_tag(sig)?;
+
+    Ok(Signature(u32::from_be_bytes([
+        raw[0], raw[1], raw[2], raw[3],
+    ])))
+}
+
+pub fn check_profile_trc() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let mut profile = Profile::new(ctx);
+    profile.write_tag(
+        sig::tags::RED_TRC,
+        Box::new(ToneCurve::build_gamma(ctx, 2.2)?),
+    )?;
+    profile.write_tag(
+        sig::tags::GREEN_TRC,
+        Box::new(ToneCurve::build_tabulated_16(ctx, &[0, 0x1000, 0xFFFF])?),
+    )?;
+
+    // v4 profiles store single parametric curves as such
+    if saved_tag_type(&profile, sig::tags::RED_TRC)? != sig::types::PARAMETRIC_CURVE
+        || saved_tag_type(&profile, sig::tags::GREEN_TRC)? != sig::types::CURVE
+    {
+        fail("Wrong tag type chosen for a v4 TRC");
+        return Err("TRC type mismatch");
+    }
+
+    // The type is decided when the tag is written
+    profile.set_version(2.1);
+    profile.write_tag(
+        sig::tags::RED_TRC,
+        Box::new(ToneCurve::build_gamma(ctx, 2.2)?),
+    )?;
+    if saved_tag_type(&profile, sig::tags::RED_TRC)? != sig::types::CURVE {
+        fail("Wrong tag type chosen for a v2 TRC");
+        return Err("TRC type mismatch");
+    }
+
+    let reloaded = Profile::open_from_mem(ctx, &profile.save_to_mem()?)?;
+    let red = match reloaded
+        .read_tag(sig::tags::RED_TRC)?
+        .downcast_ref::<ToneCurve>()
+    {
+        Some(curve) => curve,
+        None => return Err("Tag has the wrong type"),
+    };
+    let green = match reloaded
+        .read_tag(sig::tags::GREEN_TRC)?
+        .downcast_ref::<ToneCurve>()
+    {
+        Some(curve) => curve,
+        None => return Err("Tag has the wrong type"),
+    };
+
+    // 2.2 stored as u8Fixed8
+    if red.get_parametric_type() != 1 || (red.get_params().unwrap()[0] - 2.2).abs() > 1.0 / 256.0 {
+        fail("Gamma was not read back");
+        return Err("TRC mismatch");
+    }
+    if green.get_parametric_type() != 0 || green.get_estimated_table() != [0, 0x1000, 0xFFFF] {
+        fail("Table was not read back");
+        return Err("TRC mismatch");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use rs_cms::{
+    sig,
+    state::{Context, DEFAULT_CONTEXT},
+    types::{Stage, ToneCurve},
+    Result,
+};
+
+use crate::helpers::{fail, is_good_val};
+
+/// Table of a CLUT mapping every node to its own coordinates.
+fn build_identity_table(n_grid_points: usize, n_chans: usize) -> Vec<f64> {
+    let n_nodes = n_grid_points.pow(n_chans as u32);
+    let mut table = Vec::with_capacity(n_nodes * n_chans);
+
+    for node in 0..n_nodes {
+        // The last channel varies fastest
+        for chan in 0..n_chans {
+            let coord = node / n_grid_points.pow((n_chans - chan - 1) as u32) % n_grid_points;
+            table.push(coord as f64 / (n_grid_points - 1) as f64);
+        }
+    }
+
+    table
+}
+
+fn check_stage_identity_eval(title: &str, stage: &Stage, max: f64) -> Result<()> {
+    let n_chans = stage.get_input_channels();
+
+    for i in 0..=20 {
+        let r#in = (0..n_chans)
+            .map(|chan| ((i * (chan + 3) + chan) % 21) as f32 / 20.0)
+            .collect::<Vec<_>>();
+        let mut out = vec![0f32; stage.get_output_channels()];
+
+        stage.eval(&r#in, &mut out);
+
+        for chan in 0..n_chans {
+            is_good_val(title, r#in[chan] as f64, out[chan] as f64, max)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_identity_stage() -> Result<()> {
+    let stage = Stage::alloc_identity(&DEFAULT_CONTEXT, 4)?;
+
+    if stage.get_type() != sig::mpe_stage::IDENTITY {
+        fail("Wrong stage type");
+        return Err("Stage mismatch");
+    }
+
+    check_stage_identity_eval("Identity stage", &stage, 0.0)?;
+    check_stage_identity_eval("Duplicated identity stage", &stage.duplicate(), 0.0)
+}
+
+pub fn check_curves_stage() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    let curves = [
+        ToneCurve::build_gamma(ctx, 1.0)?,
+        ToneCurve::build_gamma(ctx, 2.2)?,
+        ToneCurve::build_gamma(ctx, 3.0)?,
+    ];
+    let stage = Stage::alloc_tone_curves(ctx, 3, Some(&curves))?;
+
+    if stage.get_type() != sig::mpe_stage::CURVE_SET
+        || stage.get_curves().map(|curves| curves.len()) != Some(3)
+    {
+        fail("Wrong curve set stage");
+        return Err("Stage mismatch");
+    }
+
+    for stage in [&stage, &stage.duplicate()] {
+        let mut out = [0f32; 3];
+        stage.eval(&[0.5, 0.5, 0.5], &mut out);
+
+        is_good_val("Curve 0", 0.5, out[0] as f64, 1e-6)?;
+        is_good_val("Curve 1", 0.5f64.powf(2.2), out[1] as f64, 1e-6)?;
+        is_good_val("Curve 2", 0.125, out[2] as f64, 1e-6)?;
+    }
+
+    // Without curves, channels are left alone
+    check_stage_identity_eval(
+        "Default curve set",
+        &Stage::alloc_tone_curves(ctx, 3, None)?,
+        1e-6,
+    )
+}
+
+pub fn check_matrix_stage() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    #[rustfmt::skip]
+    let matrix = [
+        0.5, 0.0, 0.0,
+        0.0, 0.25, 0.25,
+        1.0, -1.0, 0.0,
+    ];
+    let offset = [0.1, 0.2, 0.3];
+    let stage = Stage::alloc_matrix(ctx, 3, 3, &matrix, Some(&offset))?;
+
+    if stage.get_type() != sig::mpe_stage::MATRIX {
+        fail("Wrong stage type");
+        return Err("Stage mismatch");
+    }
+
+    for stage in [&stage, &stage.duplicate()] {
+        let mut out = [0f32; 3];
+        stage.eval(&[0.2, 0.4, 0.8], &mut out);
+
+        is_good_val("Row 0", 0.2, out[0] as f64, 1e-6)?;
+        is_good_val("Row 1", 0.5, out[1] as f64, 1e-6)?;
+        is_good_val("Row 2", 0.1, out[2] as f64, 1e-6)?;
+    }
+
+    // Non square matrices change the channel count
+    let stage = Stage::alloc_matrix(ctx, 1, 3, &[1.0 / 3.0; 3], None)?;
+    let mut out = [0f32];
+    stage.eval(&[0.3, 0.6, 0.9], &mut out);
+
+    if stage.get_input_channels() != 3 || stage.get_output_channels() != 1 {
+        fail("Wrong matrix channels");
+        return Err("Stage mismatch");
+    }
+    is_good_val("Mean", 0.6, out[0] as f64, 1e-6)
+}
+
+pub fn check_clut_stages() -> Result<()> {
+    let ctx: &Context = &DEFAULT_CONTEXT;
+
+    for n_chans in 1..=8 {
+        let n_grid_points = if n_chans > 5 { 3 } else { 5 };
+        let table = build_identity_table(n_grid_points, n_chans);
+
+        let table16 = table
+            .iter()
+            .map(|v| (v * 65535.0 + 0.5).floor() as u16)
+            .collect::<Vec<_>>();
+        let stage = Stage::alloc_clut_16bit(ctx, n_grid_points, n_chans, n_chans, Some(&table16))?;
+
+        if stage.get_type() != sig::mpe_stage::CLUT {
+            fail("Wrong stage type");
+            return Err("Stage mismatch");
+        }
+
+        let title = format!("{} channel 16 bit CLUT", n_chans);
+        check_stage_identity_eval(&title, &stage, 2.0 / 65535.0)?;
+        check_stage_identity_eval(&title, &stage.duplicate(), 2.0 / 65535.0)?;
+
+        let table32 = table.iter().map(|v| *v as f32).collect::<Vec<_>>();
+        let stage = Stage::alloc_clut_f32(ctx, n_grid_points, n_chans, n_chans, Some(&table32))?;
+
+        let title = format!("{} channel float CLUT", n_chans);
+        check_stage_identity_eval(&title, &stage, 1e-5)?;
+        check_stage_identity_eval(&title, &stage.duplicate(), 1e-5)?;
+    }
+
+    // Different grid sizes per channel
+    let mut table = Vec::new();
+    for x in 0..2 {
+        for y in 0..3 {
+            for z in 0..9 {
+                table.extend([x as f32, y as f32 / 2.0, z as f32 / 8.0]);
+            }
+        }
+    }
+    let stage = Stage::alloc_clut_f32_granular(ctx, &[2, 3, 9], 3, 3, Some(&table))?;
+    check_stage_identity_eval("Granular CLUT", &stage, 1e-5)?;
+
+    // Degenerated grids are refused
+    if Stage::alloc_clut_16bit(ctx, 1, 3, 3, None).is_ok() {
+        fail("CLUT with a single grid point was accepted");
+        return Err("Stage mismatch");
+    }
+
+    Ok(())
+}